@@ -37,6 +37,14 @@ if errorlevel 1 (
     exit /b 1
 )
 
+REM === Build plugin_wifi ===
+echo Building plugin_wifi...
+cargo build --manifest-path plugins\plugin_wifi\Cargo.toml %CARGO_FLAG%
+if errorlevel 1 (
+    echo Failed to build plugin_wifi.
+    exit /b 1
+)
+
 REM === Build plugin_execplan ===
 echo Building plugin_execplan...
 cargo build --manifest-path plugins\plugin_execplan\Cargo.toml %CARGO_FLAG%
@@ -149,6 +157,12 @@ if errorlevel 1 (
     exit /b 1
 )
 
+xcopy /E /I /Y plugins\plugin_wifi\web %TARGET%\wifi\web
+if errorlevel 1 (
+    echo Failed to copy plugin_wifi web folder.
+    exit /b 1
+)
+
 echo Copying plugins web folder to engine output directory...
 xcopy /E /I /Y plugins\plugin_execplan\web %TARGET%\execution\web
 if errorlevel 1 (
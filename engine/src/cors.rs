@@ -0,0 +1,230 @@
+//! CORS module deciding which browser origins may call the engine from another origin.
+//! The global policy covers every route; plugins can override it for their own `/api/<route>` paths.
+
+// Standard library imports
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+// Third-party imports
+use axum::{
+    body::Body,
+    http::{ header, HeaderValue, Method, Request, StatusCode },
+    middleware::Next,
+    response::{ IntoResponse, Response },
+};
+use once_cell::sync::Lazy;
+
+/// How long browsers may cache a preflight result, in seconds.
+const PREFLIGHT_MAX_AGE_SECS: u32 = 600;
+
+/// Headers cross-origin clients may send.
+const ALLOWED_HEADERS: &str = "authorization, content-type, if-match, if-none-match, x-request-id";
+
+/// Origins and methods allowed for cross-origin requests.
+#[derive(Debug, Clone)]
+pub struct CorsPolicy {
+    /// Allowed origins, e.g. `http://localhost:8080`. `*` allows any origin.
+    pub allowed_origins: Vec<String>,
+    /// Methods a preflight may ask for.
+    pub allowed_methods: Vec<Method>,
+}
+
+impl Default for CorsPolicy {
+    /// Allows no cross-origin callers; the engine replaces this with its own origin on startup.
+    fn default() -> Self {
+        Self { allowed_origins: Vec::new(), allowed_methods: default_methods() }
+    }
+}
+
+impl CorsPolicy {
+    /// Builds a policy from the strings used in `EngineConfig` and the execution plan.
+    /// Unknown method names are reported and skipped; no methods means the defaults.
+    pub fn from_strings(origins: &[String], methods: Option<&[String]>) -> Self {
+        let allowed_methods = match methods {
+            Some(methods) if !methods.is_empty() => methods
+                .iter()
+                .filter_map(|m| {
+                    Method::from_bytes(m.trim().to_ascii_uppercase().as_bytes())
+                        .map_err(|_| eprintln!("[engine] Ignoring unknown CORS method '{}'", m))
+                        .ok()
+                })
+                .collect(),
+            _ => default_methods(),
+        };
+        Self {
+            allowed_origins: origins.iter().map(|o| o.trim().trim_end_matches('/').to_string()).collect(),
+            allowed_methods,
+        }
+    }
+
+    fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.allows_any_origin() || self.allowed_origins.iter().any(|o| o == origin)
+    }
+
+    fn allows_method(&self, method: &str) -> bool {
+        self.allowed_methods.iter().any(|m| m.as_str().eq_ignore_ascii_case(method))
+    }
+
+    fn allow_origin_value(&self, origin: &str) -> HeaderValue {
+        if self.allows_any_origin() {
+            HeaderValue::from_static("*")
+        } else {
+            HeaderValue::from_str(origin).unwrap_or_else(|_| HeaderValue::from_static("null"))
+        }
+    }
+}
+
+fn default_methods() -> Vec<Method> {
+    vec![Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE]
+}
+
+/// Policy for every route without a plugin override.
+static GLOBAL_POLICY: Lazy<RwLock<CorsPolicy>> = Lazy::new(|| RwLock::new(CorsPolicy::default()));
+
+/// Per-plugin overrides keyed by plugin route.
+static PLUGIN_POLICIES: Lazy<RwLock<HashMap<String, CorsPolicy>>> = Lazy::new(|| {
+    RwLock::new(HashMap::new())
+});
+
+/// Replaces the global CORS policy.
+pub fn set_cors_policy(policy: CorsPolicy) {
+    *GLOBAL_POLICY.write().unwrap() = policy;
+}
+
+/// Sets or, with `None`, removes the policy for `/api/<plugin_route>/...`.
+pub fn set_plugin_cors_policy(plugin_route: &str, policy: Option<CorsPolicy>) {
+    let mut policies = PLUGIN_POLICIES.write().unwrap();
+    match policy {
+        Some(policy) => {
+            policies.insert(plugin_route.to_string(), policy);
+        }
+        None => {
+            policies.remove(plugin_route);
+        }
+    }
+}
+
+/// Returns the policy for a request path: the plugin's own for `/api/<route>/...`, else the global one.
+fn policy_for(path: &str) -> CorsPolicy {
+    let plugin_route = path
+        .strip_prefix("/api/")
+        .and_then(|rest| rest.split('/').next())
+        .filter(|route| !route.is_empty());
+
+    if let Some(route) = plugin_route {
+        if let Some(policy) = PLUGIN_POLICIES.read().unwrap().get(route) {
+            return policy.clone();
+        }
+    }
+    GLOBAL_POLICY.read().unwrap().clone()
+}
+
+/// Axum middleware answering CORS preflights and tagging responses for allowed origins.
+///
+/// A preflight from a disallowed origin, or asking for a disallowed method, gets 403.
+/// Other requests always reach the handler; without `Access-Control-Allow-Origin`
+/// the browser withholds the response from a disallowed page.
+pub async fn cors(req: Request<Body>, next: Next) -> Response {
+    let Some(origin) = req.headers().get(header::ORIGIN).and_then(|o| o.to_str().ok()).map(str::to_string) else {
+        // Not a browser cross-origin request
+        return next.run(req).await;
+    };
+
+    let policy = policy_for(req.uri().path());
+    let origin_allowed = policy.allows_origin(&origin);
+
+    let requested_method = req
+        .headers()
+        .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|m| m.to_str().ok())
+        .map(str::to_string);
+
+    if req.method() == Method::OPTIONS {
+        if let Some(requested_method) = requested_method {
+            if !origin_allowed || !policy.allows_method(&requested_method) {
+                println!(
+                    "[engine] Rejected CORS preflight from {} for {} {}",
+                    origin, requested_method, req.uri().path()
+                );
+                return (StatusCode::FORBIDDEN, "CORS origin or method not allowed").into_response();
+            }
+            return preflight_response(&policy, &origin);
+        }
+    }
+
+    let mut response = next.run(req).await;
+    if origin_allowed {
+        let headers = response.headers_mut();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, policy.allow_origin_value(&origin));
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+    response
+}
+
+fn preflight_response(policy: &CorsPolicy, origin: &str) -> Response {
+    let methods = policy.allowed_methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, policy.allow_origin_value(origin))
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, methods)
+        .header(header::ACCESS_CONTROL_ALLOW_HEADERS, ALLOWED_HEADERS)
+        .header(header::ACCESS_CONTROL_MAX_AGE, PREFLIGHT_MAX_AGE_SECS)
+        .header(header::VARY, "Origin")
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{ routing::get, Router };
+    use tower::ServiceExt;
+
+    // Each test sets a policy for its own plugin route so the global policy stays untouched
+    fn app() -> Router {
+        Router::new()
+            .route("/api/:plugin/status", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(cors))
+    }
+
+    fn preflight(route: &str, origin: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri(format!("/api/{}/status", route))
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn allow_localhost(route: &str) {
+        let origins = vec!["http://localhost:8080".to_string()];
+        set_plugin_cors_policy(route, Some(CorsPolicy::from_strings(&origins, None)));
+    }
+
+    #[tokio::test]
+    async fn preflight_from_a_disallowed_origin_is_rejected() {
+        allow_localhost("cors_rejects");
+
+        let response = app().oneshot(preflight("cors_rejects", "http://evil.example")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[tokio::test]
+    async fn preflight_from_an_allowed_origin_gets_the_cors_headers() {
+        allow_localhost("cors_allows");
+
+        let response = app().oneshot(preflight("cors_allows", "http://localhost:8080")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:8080");
+        assert!(response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS].to_str().unwrap().contains("GET"));
+    }
+}
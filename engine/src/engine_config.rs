@@ -0,0 +1,159 @@
+//! Engine Config module for the addresses the engine's servers listen on.
+//! Hosts embedding the engine can move it off the default localhost ports.
+
+// Standard library imports
+use std::env;
+use std::net::SocketAddr;
+
+use libws::{OverflowPolicy, QueueConfig};
+
+/// Environment variable overriding the HTTP listen address, e.g. `127.0.0.1:9080`.
+pub const HTTP_ADDR_ENV: &str = "OOBE_HTTP_ADDR";
+
+/// Environment variable overriding the WebSocket listen address, e.g. `127.0.0.1:9081`.
+pub const WS_ADDR_ENV: &str = "OOBE_WS_ADDR";
+
+/// Environment variable listing the origins allowed to make cross-origin requests,
+/// comma-separated, e.g. `http://localhost:3000,http://10.0.0.5:8080`. `*` allows any.
+pub const CORS_ORIGINS_ENV: &str = "OOBE_CORS_ORIGINS";
+
+/// Environment variable turning response compression off with `0` or `false`.
+pub const COMPRESSION_ENV: &str = "OOBE_COMPRESSION";
+
+/// Environment variable loading the real WiFi plugin instead of the mock with `1` or `true`.
+pub const REAL_WIFI_ENV: &str = "OOBE_REAL_WIFI";
+
+/// Environment variable setting how many messages each WebSocket connection may have
+/// queued before the overflow policy applies, e.g. `64`.
+pub const WS_QUEUE_CAPACITY_ENV: &str = "OOBE_WS_QUEUE_CAPACITY";
+
+/// Environment variable choosing what happens to a WebSocket connection whose queue
+/// is full: `drop_oldest` (the default) or `disconnect`.
+pub const WS_OVERFLOW_ENV: &str = "OOBE_WS_OVERFLOW";
+
+/// Addresses used by `start_server_async`.
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    /// Address of the HTTP server (API, plugin web content and webapp).
+    pub http_addr: SocketAddr,
+    /// Address of the WebSocket server; clients connect to `ws://<ws_addr>/ws`.
+    pub ws_addr: SocketAddr,
+    /// Origins allowed to make cross-origin requests. `None` allows only the HTTP
+    /// server's own origin; see [`EngineConfig::cors_origins`].
+    pub cors_origins: Option<Vec<String>>,
+    /// Whether HTTP responses are gzip/deflate compressed for clients that send
+    /// `Accept-Encoding`. On by default.
+    pub compression: bool,
+    /// Whether the WiFi step uses `plugin_wifi` (real hardware) rather than
+    /// `plugin_mockwifi`. Off by default, so machines without WiFi work out of the box.
+    pub real_wifi: bool,
+    /// Bounds on each WebSocket connection's outgoing queue, and what to do with a
+    /// subscriber that falls that far behind. Defaults to 256 messages, dropping the oldest.
+    pub ws_queue: QueueConfig,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            http_addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            ws_addr: SocketAddr::from(([127, 0, 0, 1], 8081)),
+            cors_origins: None,
+            compression: true,
+            real_wifi: false,
+            ws_queue: QueueConfig::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Starts from the defaults and applies `OOBE_HTTP_ADDR`, `OOBE_WS_ADDR`,
+    /// `OOBE_CORS_ORIGINS`, `OOBE_COMPRESSION`, `OOBE_REAL_WIFI`,
+    /// `OOBE_WS_QUEUE_CAPACITY` and `OOBE_WS_OVERFLOW` when set.
+    /// Unparseable values are reported and ignored.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            http_addr: addr_from_env(HTTP_ADDR_ENV, defaults.http_addr),
+            ws_addr: addr_from_env(WS_ADDR_ENV, defaults.ws_addr),
+            cors_origins: env::var(CORS_ORIGINS_ENV).ok().map(|value| {
+                value.split(',').map(str::trim).filter(|o| !o.is_empty()).map(str::to_string).collect()
+            }),
+            compression: env::var(COMPRESSION_ENV)
+                .map(|value| !matches!(value.trim().to_ascii_lowercase().as_str(), "0" | "false" | "off"))
+                .unwrap_or(defaults.compression),
+            real_wifi: env::var(REAL_WIFI_ENV)
+                .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "on"))
+                .unwrap_or(defaults.real_wifi),
+            ws_queue: QueueConfig {
+                capacity: capacity_from_env(WS_QUEUE_CAPACITY_ENV, defaults.ws_queue.capacity),
+                policy: overflow_from_env(WS_OVERFLOW_ENV, defaults.ws_queue.policy),
+            },
+        }
+    }
+
+    /// Origins the global CORS policy allows: the configured list, or else the HTTP
+    /// server's own origin (plus its `localhost` spelling for loopback addresses).
+    pub fn cors_origins(&self) -> Vec<String> {
+        if let Some(origins) = &self.cors_origins {
+            return origins.clone();
+        }
+
+        let mut origins = vec![format!("http://{}", self.http_addr)];
+        if self.http_addr.ip().is_loopback() {
+            origins.push(format!("http://localhost:{}", self.http_addr.port()));
+        }
+        origins
+    }
+
+    /// Core plugin handling the WiFi step, selected by `real_wifi`.
+    pub fn wifi_plugin(&self) -> &'static str {
+        if self.real_wifi { "plugin_wifi" } else { "plugin_mockwifi" }
+    }
+
+    /// Web route the welcome step hands off to, matching `wifi_plugin`.
+    pub fn wifi_web_route(&self) -> &'static str {
+        if self.real_wifi { "/wifi/web" } else { "/mwifi/web" }
+    }
+
+    /// URL the engine and its plugins use to reach the WebSocket server.
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}/ws", self.ws_addr)
+    }
+}
+
+fn addr_from_env(name: &str, default: SocketAddr) -> SocketAddr {
+    match env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|e| {
+            eprintln!("[engine] Ignoring {}={}: {}", name, value, e);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+fn capacity_from_env(name: &str, default: usize) -> usize {
+    match env::var(name) {
+        Ok(value) => match value.trim().parse::<usize>() {
+            Ok(capacity) if capacity > 0 => capacity,
+            _ => {
+                eprintln!("[engine] Ignoring {}={}: expected a positive number", name, value);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+fn overflow_from_env(name: &str, default: OverflowPolicy) -> OverflowPolicy {
+    match env::var(name) {
+        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
+            "drop_oldest" => OverflowPolicy::DropOldest,
+            "disconnect" => OverflowPolicy::Disconnect,
+            _ => {
+                eprintln!("[engine] Ignoring {}={}: expected drop_oldest or disconnect", name, value);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
@@ -3,25 +3,26 @@
 //
 
 // ===== Standard library imports =====
-use std::{ net::SocketAddr, sync::{ Arc, Mutex } }; // For network sockets and thread-safe shared state
+use std::{ net::SocketAddr, sync::{ Arc, Mutex, RwLock } }; // For network sockets and thread-safe shared state
 use std::fs; // For file system operations
 use std::path::PathBuf; // For path manipulation
 use std::ffi::CString; // For C-compatible strings used in FFI
-use std::time::Duration; // For time-based operations
-use std::sync::atomic::{ AtomicPtr, Ordering }; // For atomic operations
+use std::collections::HashMap; // For tracking plugin routes claimed by the execution plan
+use std::sync::atomic::{ AtomicPtr, AtomicUsize, Ordering }; // For atomic operations
+use std::time::{ Duration, Instant }; // For timing plugin preparation and bounding downloads
 
 // ===== Async runtime imports =====
 use tokio::net::TcpListener; // For asynchronous TCP socket listening
+use tokio::sync::watch; // For signalling shutdown to both servers
 
 // ===== Web framework imports =====
 use axum::Router; // For HTTP routing
-use axum::routing::{ any }; // For route handler definitions
+use axum::routing::{ any, get }; // For route handler definitions
 use axum::response::Response; // For HTTP responses
 use axum::body::Body; // For HTTP body content
 use axum::http::StatusCode; // For HTTP status codes
-use axum::http::{ Method, header };
-use tower_http::cors::{ Any, CorsLayer }; // For CORS support
 use tower_http::trace::TraceLayer; // For HTTP request tracing
+use tower_http::compression::CompressionLayer; // For gzip/deflate responses
 
 // ===== Authentication =====
 use libjwt::{ JwtManager, create_auth_router_with_cache }; // JWT authentication
@@ -31,17 +32,26 @@ use liblogger;
 use plugin_core::{ log_debug, log_info, log_warn, log_error }; // Logging utilities
 use liblogger_macros::*; // Logging macro extensions
 use ctor::ctor; // Constructor attribute for initialization
+use once_cell::sync::Lazy; // For lazily created globals
 
 // ===== Local module declarations =====
 mod router_manager;
 mod websocket_manager;
 mod plugin_manager;
+mod rate_limiter;
+mod engine_config;
+mod cors;
+mod request_id;
 
 // ===== Local module imports =====
-use plugin_manager::PluginManager;
+use plugin_manager::{ PluginManager, retain_library, run_plugin, unload_plugins, with_ws_url };
 use router_manager::RouterManager;
+pub use rate_limiter::{ RateLimitConfig, RouteLimit, set_rate_limit_config };
+pub use engine_config::EngineConfig;
+pub use cors::{ CorsPolicy, set_cors_policy, set_plugin_cors_policy };
 use websocket_manager::{
     WS_SUBSCRIBERS,
+    ws_stats,
     ENGINE_WS_CLIENT,
     WELCOME_COMPLETED,
     WIFI_COMPLETED,
@@ -56,27 +66,30 @@ use websocket_manager::{
 use engine_core::{
     plugin_loader::load_plugin,
     plugin_registry::PluginRegistry,
-    handlers::dispatch_plugin_api,
+    handlers::{ dispatch_plugin_api, health_check },
     execution_plan_updater::{ ExecutionPlanUpdater, PlanLoadSource },
-    execution_plan::ExecutionPlanLoader,
+    execution_plan::{ ExecutionPlanLoader, sort_by_dependencies },
     plugin_metadata::PluginMetadata,
     plugin_utils::prepare_plugin_binary,
 };
 
 // ===== Plugin core types =====
 use plugin_core::{ HttpMethod, ApiRequest };
+use plugin_core::events::{ decode_payload, WifiCompletedPayload };
 
 // ===== WebSocket functionality =====
-use libws::handle_socket;
-use libws::ws_client::WsClient;
+use libws::handle_socket_with_jwt;
+use libws::ws_client::{ ReconnectPolicy, WsClient, service_token_provider };
 
 // ===== Global variables =====
 // Registry pointer to maintain plugins across the application lifetime
 static REGISTRY_PTR: AtomicPtr<Arc<PluginRegistry>> = AtomicPtr::new(std::ptr::null_mut());
-// Plugin libraries pointer to prevent dynamic libraries from being unloaded
-static PLUGIN_LIBRARIES_PTR: AtomicPtr<Vec<libloading::Library>> = AtomicPtr::new(
-    std::ptr::null_mut()
-);
+
+// Set to true by stop_server(); the HTTP and WebSocket servers drain and exit when it flips
+static SHUTDOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+// WebSocket URL of the running engine, handed to execution plan plugins when they are run
+static WS_URL: RwLock<String> = RwLock::new(String::new());
 
 // Initialize logger attributes
 initialize_logger_attributes!();
@@ -115,45 +128,23 @@ fn initialize_custom_logger() {
 //
 
 // Creates and initializes the WebSocket client for the engine
-pub async fn create_ws_engine_client() {
+// `wifi_route` is where the welcome step hands off to, e.g. "/mwifi/web"
+pub async fn create_ws_engine_client(url: &str, wifi_route: &'static str) {
     log_debug!("Creating ws client for the engine");
-    let url = "ws://127.0.0.1:8081/ws";
-
-    // Connect to the WebSocket server with retries.
-    let mut retries = 0;
-    const MAX_RETRIES: u8 = 5;
-    let mut client = None;
-
-    while retries < MAX_RETRIES {
-        match WsClient::connect("engine", url).await {
-            Ok(connected_client) => {
-                client = Some(connected_client);
-                break;
-            }
-            Err(_err) => {
-                retries += 1;
-                // Actually use the error variable by directly printing it with its display implementation
-                log_debug!(
-                    format!(
-                        "Failed to connect WsClient (attempt {}/{}): {}",
-                        retries,
-                        MAX_RETRIES,
-                        _err
-                    ).as_str()
-                );
-                if retries < MAX_RETRIES {
-                    tokio::time::sleep(Duration::from_millis(500)).await;
-                }
-            }
-        }
-    }
 
-    // Check if we successfully connected after retries
-    let client = match client {
-        Some(c) => c,
-        None => {
+    // Connect to the WebSocket server; the client reconnects, with a fresh service
+    // token, and resubscribes on its own if the connection drops later.
+    const MAX_RETRIES: u32 = 5;
+    let policy = ReconnectPolicy {
+        max_attempts: Some(MAX_RETRIES),
+        ..ReconnectPolicy::default()
+    };
+    let token_provider = service_token_provider("engine");
+    let client = match WsClient::connect_authenticated_with_policy("engine", url, token_provider, policy).await {
+        Ok(c) => c,
+        Err(_err) => {
             log_error!(
-                format!("Failed to connect to WebSocket server after {} attempts. Exiting.", MAX_RETRIES).as_str()
+                format!("Failed to connect to WebSocket server after {} attempts: {}. Exiting.", MAX_RETRIES, _err).as_str()
             );
             return;
         }
@@ -179,17 +170,15 @@ pub async fn create_ws_engine_client() {
     }
 
     // Subscribe to WELCOME_COMPLETED topic
-    // Route next to /wifi/web
+    // Route next to the selected WiFi plugin's page
     if let Some(client_arc) = ENGINE_WS_CLIENT.get() {
-        // subscribe_and_handle(client_arc.clone(), WELCOME_COMPLETED, "/wifi/web").await;
-        subscribe_and_handle(client_arc.clone(), WELCOME_COMPLETED, "/mwifi/web").await;
+        subscribe_and_handle(client_arc.clone(), WELCOME_COMPLETED, wifi_route).await;
     }
 
     // Subscribe to WIFI_COMPLETED topic
     // Route next to /execution/web
-    // Fix: Initialize registry and plugin_libraries variables before using them
+    // Fix: Initialize the registry before using it
     let registry = Arc::new(PluginRegistry::new());
-    let plugin_libraries = Arc::new(Mutex::new(Vec::new()));
 
     // Fix: Access client_arc from ENGINE_WS_CLIENT
     // Execution plan plugins are loaded dynamically through the execution plan
@@ -199,8 +188,7 @@ pub async fn create_ws_engine_client() {
             client_arc.clone(),
             WIFI_COMPLETED,
             "/execution/web",
-            registry.clone(),
-            plugin_libraries.clone()
+            registry.clone()
         ).await;
     }
 
@@ -249,8 +237,7 @@ async fn subscribe_and_handle_with_registry(
     client_arc: Arc<Mutex<WsClient>>,
     topic: &'static str,
     _route: &'static str,
-    registry: Arc<PluginRegistry>,
-    plugin_libraries: Arc<Mutex<Vec<libloading::Library>>>
+    registry: Arc<PluginRegistry>
 ) {
     // First, set up the message handler directly without holding locks across await points
     {
@@ -265,7 +252,6 @@ async fn subscribe_and_handle_with_registry(
 
         // Create clones of necessary objects before entering the message handler
         let registry_clone = registry.clone();
-        let plugin_libraries_clone = plugin_libraries.clone();
 
         // Set up a separate message handler for WIFI_COMPLETED
         if topic == WIFI_COMPLETED {
@@ -276,11 +262,20 @@ async fn subscribe_and_handle_with_registry(
             if let Ok(mut client) = client_arc.lock() {
                 client.on_message(topic, move |msg| {
                     log_debug!(format!("[engine] => {}: {}", topic, msg).as_str());
+                    match decode_payload::<WifiCompletedPayload>(&msg) {
+                        Ok(_wifi) => log_debug!(
+                            format!(
+                                "[engine] WiFi step finished: status={}, ssid={}",
+                                _wifi.status,
+                                _wifi.ssid.as_deref().unwrap_or("<none>")
+                            ).as_str()
+                        ),
+                        Err(_e) => log_warn!(format!("[engine] Unexpected {} payload: {}", topic, _e).as_str()),
+                    }
 
                     // Create a dedicated thread for handling this specific message occurrence
                     // This avoids crossing thread boundaries with mutexes
                     let registry_for_thread = registry_clone.clone();
-                    let libs_for_thread = plugin_libraries_clone.clone();
                     let client_for_thread = msg_client_arc.clone();
 
                     // Spawn a standard thread instead of using tokio::spawn
@@ -294,25 +289,12 @@ async fn subscribe_and_handle_with_registry(
 
                         // Execute the async block in the runtime
                         rt.block_on(async {
-                            // Create a new Vec to hold the libraries
-                            let mut new_libraries = Vec::new();
-
                             // Load the plugins
-                            if
-                                !load_execution_plan_plugins(
-                                    &registry_for_thread,
-                                    &mut new_libraries
-                                ).await
-                            {
+                            if !load_execution_plan_plugins(&registry_for_thread).await {
                                 log_error!("Failed to load plugins from execution plan");
                                 return;
                             }
 
-                            // Update the original vector with the new libraries
-                            if let Ok(mut guard) = libs_for_thread.lock() {
-                                guard.extend(new_libraries.into_iter());
-                            }
-
                             // Send the navigation message
                             publish_ws_message(
                                 client_for_thread,
@@ -353,7 +335,7 @@ pub async fn publish_ws_message(
             if let Ok(mut client) = client_arc.lock() {
                 let rt = tokio::runtime::Handle::current();
                 let _ = rt.block_on(
-                    client.publish(&client_name, &topic_name, &payload, &timestamp)
+                    client.publish(&client_name, &topic_name, payload.as_str(), &timestamp, false)
                 );
                 log_debug!(
                     format!(
@@ -372,8 +354,31 @@ pub async fn publish_ws_message(
 // ===== Execution Plan Management =====
 //
 
-// Loads and processes the execution plan that controls plugin loading
-pub async fn run_exection_plan_updater() -> Option<(PlanLoadSource, Vec<PluginMetadata>)> {
+/// A plan plugin skipped because an earlier plugin in the plan already claimed its route.
+#[derive(Debug, Clone)]
+pub struct RouteConflict {
+    pub route: String,
+    /// Plugin that keeps the route
+    pub kept_plugin: String,
+    /// Plugin that was skipped
+    pub skipped_plugin: String,
+}
+
+/// Execution plan resolved by `run_exection_plan_updater`.
+pub struct ExecutionPlanLoad {
+    pub source: PlanLoadSource,
+    /// Plugins to load, in plan order, without those skipped for route conflicts
+    pub plugins: Vec<PluginMetadata>,
+    pub route_conflicts: Vec<RouteConflict>,
+    /// Plugin binaries prepared at the same time, from the plan's `[general]` section
+    pub max_parallel_downloads: usize,
+    /// Time a single plugin binary download may take, from the plan's `[general]` section
+    pub fetch_timeout: Duration,
+}
+
+// Loads and processes the execution plan that controls plugin loading.
+// Plugins whose route is already taken are left out of the returned list and reported as conflicts.
+pub async fn run_exection_plan_updater() -> Option<ExecutionPlanLoad> {
     let local_path = "execution_plan.toml";
 
     // Move the Error-returning code inside this function to avoid Send issues
@@ -381,14 +386,17 @@ pub async fn run_exection_plan_updater() -> Option<(PlanLoadSource, Vec<PluginMe
         Ok(plan_status) => {
             let plan_path = match &plan_status {
                 PlanLoadSource::Remote(path) => path,
-                PlanLoadSource::LocalFallback(path) => path,
+                PlanLoadSource::LocalFallback(path, _reason) => {
+                    log_warn!(format!("Using local execution plan: {}", _reason).as_str());
+                    path
+                }
             };
 
             match ExecutionPlanLoader::load_from_file(plan_path) {
                 Ok(plan) => {
                     let _plan_type = match plan_status {
                         PlanLoadSource::Remote(_) => "remote",
-                        PlanLoadSource::LocalFallback(_) => "local fallback",
+                        PlanLoadSource::LocalFallback(..) => "local fallback",
                     };
 
                     log_debug!(
@@ -445,8 +453,32 @@ pub async fn run_exection_plan_updater() -> Option<(PlanLoadSource, Vec<PluginMe
                         }
                     }
 
+                    // Route -> plugin that claimed it first
+                    let mut claimed_routes: HashMap<String, String> = HashMap::new();
+                    let mut route_conflicts = Vec::new();
+                    let mut accepted_plugins = Vec::with_capacity(plan.plugins.len());
+
                     // Log details for each plugin in the execution plan
-                    for (idx, plugin) in plan.plugins.iter().enumerate() {
+                    for (idx, plugin) in plan.plugins.into_iter().enumerate() {
+                        let route_key = plugin.plugin_route.trim_matches('/').to_string();
+                        if let Some(kept_plugin) = claimed_routes.get(&route_key) {
+                            log_error!(
+                                format!(
+                                    "Plugin '{}' claims route '{}' already taken by '{}'; skipping it",
+                                    plugin.name,
+                                    route_key,
+                                    kept_plugin
+                                ).as_str()
+                            );
+                            route_conflicts.push(RouteConflict {
+                                route: route_key,
+                                kept_plugin: kept_plugin.clone(),
+                                skipped_plugin: plugin.name.clone(),
+                            });
+                            continue;
+                        }
+                        claimed_routes.insert(route_key, plugin.name.clone());
+
                         let run_after_event_name = plugin.run_after_event_name
                             .as_deref()
                             .unwrap_or("None");
@@ -504,9 +536,17 @@ pub async fn run_exection_plan_updater() -> Option<(PlanLoadSource, Vec<PluginMe
 
                         // To add a new route at runtime:
                         RouterManager::add_plugin_route(&plugin_route, route).await;
+
+                        accepted_plugins.push(plugin);
                     }
 
-                    Some((plan_status, plan.plugins))
+                    Some(ExecutionPlanLoad {
+                        source: plan_status,
+                        plugins: accepted_plugins,
+                        route_conflicts,
+                        max_parallel_downloads: plan.general.max_parallel_downloads,
+                        fetch_timeout: Duration::from_secs(plan.general.fetch_timeout_secs),
+                    })
                 }
                 Err(_e) => {
                     log_debug!(
@@ -527,17 +567,38 @@ pub async fn run_exection_plan_updater() -> Option<(PlanLoadSource, Vec<PluginMe
 // ===== Plugin Management =====
 //
 
-// Loads and registers a plugin from the given path
+// Loads a plugin from the given path, runs it with its `[plugins.config]` table and registers it
 #[measure_time]
 fn load_and_register(
     path: PathBuf,
-    registry: &Arc<PluginRegistry>,
-    lib_holder: &mut Vec<libloading::Library>
+    plugin_meta: &PluginMetadata,
+    registry: &Arc<PluginRegistry>
 ) {
+    let config_json = match plugin_meta.config_json() {
+        Ok(config_json) => config_json,
+        Err(_e) => {
+            log_warn!(format!("Skipping plugin '{}': {}", plugin_meta.name, _e).as_str());
+            return;
+        }
+    };
+
     match load_plugin(&path) {
-        Ok((plugin, lib)) => {
+        Ok((mut plugin, lib)) => {
+            plugin.max_body_bytes = plugin_meta.max_body_bytes;
+            plugin.max_concurrent_requests = plugin_meta.max_concurrent_requests;
+            plugin.request_timeout = plugin_meta.request_timeout_ms.map(Duration::from_millis);
+            if let Some(origins) = &plugin_meta.cors_allowed_origins {
+                let policy = CorsPolicy::from_strings(origins, plugin_meta.cors_allowed_methods.as_deref());
+                set_plugin_cors_policy(&plugin.plugin_route, Some(policy));
+            }
+
+            let ws_url = WS_URL.read().unwrap().clone();
+            let config = with_ws_url("", &ws_url);
+            run_plugin(&plugin, &config, &ws_url, config_json.as_deref());
+
+            // retain library to avoid drop
+            retain_library(&plugin.name, &path, Some((&config, &ws_url)), config_json.as_deref(), lib);
             registry.register(plugin);
-            lib_holder.push(lib); // retain library to avoid drop
         }
         Err(_e) => {
             log_debug!(format!("Failed to load plugin from {}: {}", path.display(), _e).as_str());
@@ -545,30 +606,132 @@ fn load_and_register(
     }
 }
 
+/// Reloads a single plugin from disk without restarting the server.
+///
+/// See `plugin_manager::reload_plugin` for how the old library is kept loaded while
+/// anything may still call into it.
+pub async fn reload_plugin(name: &str) -> Result<(), String> {
+    let registry = unsafe { REGISTRY_PTR.load(Ordering::Relaxed).as_ref() }
+        .ok_or_else(|| "Engine is not running".to_string())?
+        .clone();
+
+    plugin_manager::reload_plugin(&registry, name).await.map(|_| ())
+}
+
+// Prepares every plugin binary on up to `max_parallel` worker threads.
+// Results come back in plan order, so registration never depends on which download finished first.
+fn prepare_plugin_binaries(
+    plugins: &[PluginMetadata],
+    allow_write: bool,
+    max_parallel: usize,
+    fetch_timeout: Duration
+) -> Vec<Result<PathBuf, String>> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<PathBuf, String>>>> = Mutex::new(
+        plugins.iter().map(|_| None).collect()
+    );
+    let workers = max_parallel.clamp(1, plugins.len().max(1));
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(plugin) = plugins.get(idx) else {
+                        break;
+                    };
+                    let result = prepare_plugin_binary(plugin, allow_write, fetch_timeout).map_err(|e| e.to_string());
+                    results.lock().unwrap()[idx] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err("Plugin was not prepared".to_string())))
+        .collect()
+}
+
 // Loads plugins defined in the execution plan
-async fn load_execution_plan_plugins(
-    registry: &Arc<PluginRegistry>,
-    plugin_libraries: &mut Vec<libloading::Library>
-) -> bool {
+async fn load_execution_plan_plugins(registry: &Arc<PluginRegistry>) -> bool {
     log_debug!("Loading the execution plan");
 
-    let Some((plan_status, plugins)) = run_exection_plan_updater().await else {
+    let Some(plan) = run_exection_plan_updater().await else {
         log_debug!("Execution plan loading failed. Cannot continue.");
         return false;
     };
+    let plan_status = plan.source;
+
+    if !plan.route_conflicts.is_empty() {
+        log_warn!(
+            format!("{} plugin(s) skipped because of duplicate routes", plan.route_conflicts.len()).as_str()
+        );
+    }
 
     let allow_write = matches!(plan_status, PlanLoadSource::Remote(_));
 
-    // Modify the error handler for plugin preparation
-    for plugin_meta in plugins {
-        match prepare_plugin_binary(&plugin_meta, allow_write) {
-            Ok(local_path) => load_and_register(local_path, &registry, plugin_libraries),
-            Err(_e) => {
-                let _source = match plan_status {
-                    PlanLoadSource::Remote(_) => "remote plan",
-                    PlanLoadSource::LocalFallback(_) => "local fallback plan",
-                };
+    // Dependencies first; plugins the engine loaded itself count as satisfied
+    let plugins = match sort_by_dependencies(plan.plugins, |name| registry.get(name).is_some()) {
+        Ok(plugins) => plugins,
+        Err(e) => {
+            log_error!(format!("Execution plan rejected: {}", e).as_str());
+            return false;
+        }
+    };
 
+    // Binaries are prepared in parallel; loading and registering stays sequential and in dependency order
+    let prep_started = Instant::now();
+    let prepared = prepare_plugin_binaries(
+        &plugins,
+        allow_write,
+        plan.max_parallel_downloads,
+        plan.fetch_timeout
+    );
+    log_info!(
+        format!(
+            "Prepared {} plugin binaries in {:?} (up to {} at a time)",
+            plugins.len(),
+            prep_started.elapsed(),
+            plan.max_parallel_downloads
+        ).as_str()
+    );
+
+    let source = match plan_status {
+        PlanLoadSource::Remote(_) => "remote plan",
+        PlanLoadSource::LocalFallback(..) => "local fallback plan",
+    };
+    register_prepared_plugins(&plugins, prepared, source, registry, load_and_register);
+
+    log_debug!("Execution plan load completed");
+    true
+}
+
+// Loads and registers prepared plugins one at a time, in plan order, using `load`.
+// Plugins that failed to prepare are logged and skipped, and so are their dependents.
+fn register_prepared_plugins<L>(
+    plugins: &[PluginMetadata],
+    prepared: Vec<Result<PathBuf, String>>,
+    _source: &str,
+    registry: &Arc<PluginRegistry>,
+    load: L
+)
+    where L: Fn(PathBuf, &PluginMetadata, &Arc<PluginRegistry>)
+{
+    for (plugin_meta, prepared_path) in plugins.iter().zip(prepared) {
+        // A dependency that failed to prepare or load takes its dependents down with it
+        if let Some(missing) = plugin_meta.depends_on.iter().find(|d| registry.get(d).is_none()) {
+            log_warn!(
+                format!("Skipping plugin '{}': dependency '{}' is not loaded", plugin_meta.name, missing).as_str()
+            );
+            continue;
+        }
+
+        match prepared_path {
+            Ok(local_path) => load(local_path, plugin_meta, registry),
+            Err(_e) => {
                 log_debug!(
                     format!(
                         "[WARN] Plugin '{}' failed to prepare from '{}' ({}): {}",
@@ -581,31 +744,72 @@ async fn load_execution_plan_plugins(
             }
         }
     }
-
-    log_debug!("Execution plan load completed");
-    true
 }
 
 //
 // ===== Server Entry Points =====
 //
 
-// FFI-safe entry point for non-Rust platforms
+// FFI-safe entry point for non-Rust platforms; addresses come from the environment
 #[no_mangle]
 pub extern "C" fn start_oobe_server() {
     std::thread::spawn(|| {
         let rt = tokio::runtime::Runtime::new().unwrap();
-        rt.block_on(start_server_async());
+        rt.block_on(start_server_async(EngineConfig::from_env()));
     });
 }
 
-// Main async entry point for Rust applications
-pub async fn start_server_async() {
+// FFI-safe counterpart of stop_server()
+#[no_mangle]
+pub extern "C" fn stop_oobe_server() {
+    stop_server();
+}
+
+/// Asks a running engine to shut down gracefully.
+///
+/// Both servers stop accepting connections, in-flight HTTP requests finish, WebSocket
+/// connections are closed, plugins are unloaded and the logger is flushed, after which
+/// `start_server_async` returns. Ctrl-C does the same. Safe to call from any thread.
+pub fn stop_server() {
+    SHUTDOWN.send_replace(true);
+}
+
+// Resolves once stop_server() is called or Ctrl-C is pressed
+async fn shutdown_signal() {
+    let mut stop = SHUTDOWN.subscribe();
+    tokio::select! {
+        _ = stop.wait_for(|stopped| *stopped) => {}
+        _ = tokio::signal::ctrl_c() => {
+            log_info!("Ctrl-C received, shutting down");
+            // Let the other server follow
+            stop_server();
+        }
+    }
+}
+
+// Compresses plugin API responses and static files for clients that accept it. Small
+// bodies and already-compressed content types such as images are sent as-is.
+fn compression_layer(enabled: bool) -> CompressionLayer {
+    if enabled {
+        CompressionLayer::new()
+    } else {
+        CompressionLayer::new().no_gzip().no_deflate()
+    }
+}
+
+// Main async entry point for Rust applications; returns after stop_server()
+pub async fn start_server_async(config: EngineConfig) {
     initialize_custom_logger();
+    // A previous run may have left the flag set
+    SHUTDOWN.send_replace(false);
+    let ws_url = config.ws_url();
+    *WS_URL.write().unwrap() = ws_url.clone();
+    WS_SUBSCRIBERS.lock().unwrap().set_queue_config(config.ws_queue);
 
     // WebSocket Server Initialization
-    tokio::spawn({
+    let ws_server = tokio::spawn({
         let subs = WS_SUBSCRIBERS.clone();
+        let ws_addr = config.ws_addr;
         async move {
             use axum::{ Router, routing::get };
             use axum::extract::connect_info::ConnectInfo;
@@ -618,38 +822,46 @@ pub async fn start_server_async() {
 
             let ws_app = Router::new().route(
                 "/ws",
-                get(move |ws, ConnectInfo(addr)| {
-                    handle_socket(ws, ConnectInfo(addr), subs.clone())
+                get({
+                    let subs = subs.clone();
+                    move |ws, ConnectInfo(addr), query| {
+                        // Clients pass their JWT as `?token=`; connections without a valid one are closed
+                        handle_socket_with_jwt(ws, ConnectInfo(addr), query, subs.clone())
+                    }
                 })
             );
 
-            let listener = TcpListener::bind("127.0.0.1:8081").await.unwrap();
-            log_debug!("[engine] WebSocket server listening at ws://127.0.0.1:8081/ws");
+            let listener = TcpListener::bind(ws_addr).await.unwrap();
+            log_debug!(format!("[engine] WebSocket server listening at ws://{}/ws", ws_addr).as_str());
 
             axum::serve(
                 listener,
                 ws_app.into_make_service_with_connect_info::<SocketAddr>()
-            ).await.unwrap();
+            )
+                .with_graceful_shutdown(async move {
+                    shutdown_signal().await;
+                    // Upgraded connections outlive the server, so close them explicitly
+                    let closed = subs.lock().unwrap().close_all();
+                    log_debug!(format!("[engine] Closed {} WebSocket connections", closed).as_str());
+                })
+                .await
+                .unwrap();
         }
     });
 
     // WebSocket Client Initialization
-    create_ws_engine_client().await;
+    create_ws_engine_client(&ws_url, config.wifi_web_route()).await;
 
     // Plugin Registry Initialization
     let registry = Arc::new(PluginRegistry::new());
     REGISTRY_PTR.store(Box::into_raw(Box::new(registry.clone())), Ordering::Relaxed);
 
-    // Initialize plugin_libraries as Vec type
-    let plugin_libraries = Vec::new();
-    PLUGIN_LIBRARIES_PTR.store(Box::into_raw(Box::new(plugin_libraries)), Ordering::Relaxed);
-
-    let mut plugin_manager = PluginManager::new(registry.clone());
+    let mut plugin_manager = PluginManager::new(registry.clone(), &ws_url);
 
     // Core Plugin Loading
     let plugins_to_load = [
         ("plugin_welcome", "continue=false"),
-        ("plugin_mockwifi", "connected=false"),
+        (config.wifi_plugin(), "connected=false"),
         ("plugin_execplan", "hasupdate=true"),
         ("plugin_login", "isloggedin=false"),
         ("plugin_provisioning", "isprovisioned=false"),
@@ -699,24 +911,13 @@ pub async fn start_server_async() {
                 }
             }
         } else {
-            log_debug!(format!("Failed to load {}", plugin_name).as_str());
-            return;
+            // Keep serving the steps that did load; the missing one answers 404
+            log_error!(format!("Failed to load {}, continuing without it", plugin_name).as_str());
         }
     }
 
     log_debug!("Core plugins loaded", None);
 
-    // Move plugin libraries to holder
-    let mut plugin_libs = plugin_manager
-        .get_plugin_libraries()
-        .drain(..)
-        .collect::<Vec<_>>();
-    unsafe {
-        if let Some(libs_ptr) = PLUGIN_LIBRARIES_PTR.load(Ordering::Relaxed).as_mut() {
-            libs_ptr.extend(plugin_libs.drain(..));
-        }
-    }
-
     // JWT Authentication Setup
     log_debug!("********** JWT AUTHENTICATION SETUP - BEGIN **********");
 
@@ -732,10 +933,9 @@ pub async fn start_server_async() {
     let auth_router = create_auth_router_with_cache(jwt_manager.token_cache.clone());
 
     log_debug!("Creating plugin API router...");
-    let plugin_api_router = Router::new().route(
-        "/:plugin/:resource",
-        any(dispatch_plugin_api).with_state(registry.clone())
-    );
+    let plugin_api_router = Router::new()
+        .route("/health", get(health_check).with_state(registry.clone()))
+        .route("/:plugin/*resource", any(dispatch_plugin_api).with_state(registry.clone()));
 
     // Step 3: Combine all API routers into a single API router
     log_debug!("Combining all API routers...");
@@ -743,7 +943,7 @@ pub async fn start_server_async() {
 
     // Step 4: Nest the combined API router under /api
     log_debug!("Nesting combined API router under /api path...");
-    base_router = base_router.nest("/api", api_router);
+    base_router = base_router.nest("/api", api_router).route("/ws/stats", get(ws_stats));
 
     log_debug!("********** JWT AUTHENTICATION SETUP - COMPLETE **********");
 
@@ -779,22 +979,334 @@ pub async fn start_server_async() {
         }
     }
 
-    // Create CORS layer
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
-        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
-        .allow_origin(Any);
+    // Only the configured origins (by default the engine's own) may call the API cross-origin
+    set_cors_policy(CorsPolicy::from_strings(&config.cors_origins(), None));
+
+    let compression = compression_layer(config.compression);
 
     // Get the router for serving
     let app = RouterManager::shared_router_service();
 
     // Apply middleware layers
-    let app = app.layer(cors).layer(TraceLayer::new_for_http());
+    let app = app
+        .layer(axum::middleware::from_fn(rate_limiter::rate_limit))
+        .layer(compression)
+        .layer(axum::middleware::from_fn(cors::cors))
+        .layer(TraceLayer::new_for_http())
+        .layer(axum::middleware::from_fn(request_id::request_id));
 
     // Start the HTTP server
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+    let addr = config.http_addr;
     log_debug!(format!("Listening at http://{}", addr).as_str());
 
     let listener = TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+
+    // Graceful shutdown: in-flight requests have finished at this point
+    log_info!("HTTP server stopped, shutting down the engine");
+    let _ = ws_server.await;
+    unload_plugins(&registry).await;
+    if let Err(e) = liblogger::shutdown_logger() {
+        eprintln!("[engine] Failed to flush logger: {}", e);
+    }
+    println!("[engine] Shutdown complete");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_core::PluginBinding;
+    use engine_core::plugin_utils::{ resolve_plugin_binary_path, resolve_plugin_exe_path };
+    use plugin_core::resource_utils::static_resource;
+    use plugin_core::response_utils::json_response;
+    use plugin_core::{ cleanup_response, ApiResponse, PluginContext, Resource };
+
+    extern "C" fn run(_ctx: *const PluginContext) {}
+
+    extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
+        static METHODS: [HttpMethod; 1] = [HttpMethod::Get];
+        let slice = static_resource("status", &METHODS);
+        unsafe {
+            *out_len = slice.len();
+        }
+        slice.as_ptr()
+    }
+
+    extern "C" fn handle_request(_req: *const ApiRequest) -> *mut ApiResponse {
+        json_response(200, "{}")
+    }
+
+    // Stands in for a plugin whose handle_request logs under the engine's request id
+    extern "C" fn logging_handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+        let request = unsafe { &*req };
+        plugin_core::with_request_context(request, || {
+            log_info!("mock plugin handled the request");
+            json_response(200, "{}")
+        })
+    }
+
+    extern "C" fn cleanup(resp: *mut ApiResponse) {
+        cleanup_response(resp);
+    }
+
+    /// Serializes tests that reconfigure or shut down the process-wide logger.
+    fn logger_turn() -> std::sync::MutexGuard<'static, ()> {
+        static LOGGER: Mutex<()> = Mutex::new(());
+        LOGGER.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn mock_binding(plugin_meta: &PluginMetadata) -> PluginBinding {
+        PluginBinding {
+            name: plugin_meta.name.clone(),
+            plugin_route: plugin_meta.plugin_route.clone(),
+            static_path: "web".to_string(),
+            get_api_resources,
+            handle_request,
+            cleanup,
+            run,
+            run_workflow: None,
+            on_progress: None,
+            on_complete: None,
+            health_check: None,
+            max_body_bytes: None,
+            max_concurrent_requests: None,
+            request_timeout: None,
+            in_flight: Default::default(),
+            library: None,
+        }
+    }
+
+    /// Plan metadata for a local plugin in `folder`; the binary is written there unless `missing`.
+    fn mock_plugin(folder: &std::path::Path, name: &str, depends_on: &[&str], missing: bool) -> PluginMetadata {
+        if !missing {
+            fs::write(resolve_plugin_binary_path(folder.to_str().unwrap(), name), name).unwrap();
+        }
+        toml::from_str(
+            &format!(
+                r#"
+                name = "{name}"
+                plugin_route = "{name}_route"
+                version = "1.0"
+                plugin_location_type = "local"
+                plugin_base_path = "{folder}"
+                team_name = "Test"
+                engineering_contact_email = "eng@example.com"
+                operation_contact_email = "ops@example.com"
+                depends_on = {depends_on:?}
+                "#,
+                name = name,
+                folder = folder.display(),
+                depends_on = depends_on
+            )
+        ).unwrap()
+    }
+
+    /// Removes the plan folder and the copies prepared next to the test binary.
+    fn remove_mock_plugins(folder: &std::path::Path, plugins: &[PluginMetadata]) {
+        for plugin in plugins {
+            let _ = fs::remove_file(resolve_plugin_exe_path(&plugin.name).unwrap());
+        }
+        let _ = fs::remove_dir_all(folder);
+    }
+
+    #[test]
+    fn prepared_mock_plugins_are_all_registered_in_plan_order() {
+        let folder = std::env::temp_dir().join(format!("engine_prepare_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let plugins = vec![
+            mock_plugin(&folder, "engine_mock_a", &[], false),
+            mock_plugin(&folder, "engine_mock_b", &["engine_mock_a"], false),
+            mock_plugin(&folder, "engine_mock_c", &[], false),
+            mock_plugin(&folder, "engine_mock_d", &["engine_mock_b", "engine_mock_c"], false),
+            mock_plugin(&folder, "engine_mock_e", &[], false),
+        ];
+
+        let prepared = prepare_plugin_binaries(&plugins, true, 2, Duration::from_secs(1));
+        assert!(prepared.iter().all(|p| p.is_ok()), "preparation failed: {:?}", prepared);
+
+        let registry = Arc::new(PluginRegistry::new());
+        let order = Mutex::new(Vec::new());
+        register_prepared_plugins(&plugins, prepared, "test plan", &registry, |path, plugin_meta, registry| {
+            // Each plugin gets its own binary back, however the workers interleaved
+            assert_eq!(fs::read_to_string(&path).unwrap(), plugin_meta.name);
+            order.lock().unwrap().push(plugin_meta.name.clone());
+            registry.register(mock_binding(plugin_meta));
+        });
+        remove_mock_plugins(&folder, &plugins);
+
+        let names: Vec<&str> = plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(*order.lock().unwrap(), names);
+        for name in names {
+            let plugin = registry.get(name).unwrap_or_else(|| panic!("{} was not registered", name));
+            assert_eq!(plugin.plugin_route, format!("{}_route", name));
+        }
+    }
+
+    #[test]
+    fn plugin_that_fails_to_prepare_skips_its_dependents() {
+        let folder = std::env::temp_dir().join(format!("engine_prepare_missing_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let plugins = vec![
+            mock_plugin(&folder, "engine_mock_missing", &[], true),
+            mock_plugin(&folder, "engine_mock_dependent", &["engine_mock_missing"], false),
+            mock_plugin(&folder, "engine_mock_standalone", &[], false),
+        ];
+
+        let prepared = prepare_plugin_binaries(&plugins, true, 3, Duration::from_secs(1));
+        assert!(prepared[0].is_err());
+
+        let registry = Arc::new(PluginRegistry::new());
+        register_prepared_plugins(&plugins, prepared, "test plan", &registry, |_path, plugin_meta, registry| {
+            registry.register(mock_binding(plugin_meta));
+        });
+        remove_mock_plugins(&folder, &plugins);
+
+        assert!(registry.get("engine_mock_missing").is_none());
+        assert!(registry.get("engine_mock_dependent").is_none());
+        assert!(registry.get("engine_mock_standalone").is_some());
+    }
+
+    async fn fetch_with_gzip(compression: bool) -> (Option<String>, Vec<u8>) {
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route("/api/mock/status", get(|| async { "engine status ".repeat(64) }))
+            .layer(compression_layer(compression));
+        let request = axum::http::Request::builder()
+            .uri("/api/mock/status")
+            .header(axum::http::header::ACCEPT_ENCODING, "gzip")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        let encoding = response
+            .headers()
+            .get(axum::http::header::CONTENT_ENCODING)
+            .map(|e| e.to_str().unwrap().to_string());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (encoding, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn gzip_clients_get_a_compressed_response() {
+        let (encoding, body) = fetch_with_gzip(true).await;
+
+        assert_eq!(encoding.as_deref(), Some("gzip"));
+        // gzip magic number, and much smaller than the 896 repetitive plain bytes
+        assert_eq!(&body[..2], &[0x1f, 0x8b]);
+        assert!(body.len() < 200, "compressed body is {} bytes", body.len());
+    }
+
+    #[tokio::test]
+    async fn disabled_compression_sends_the_plain_body() {
+        let (encoding, body) = fetch_with_gzip(false).await;
+
+        assert_eq!(encoding, None);
+        assert_eq!(body, "engine status ".repeat(64).into_bytes());
+    }
+
+    fn free_addr() -> SocketAddr {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+    }
+
+    fn health_status(addr: SocketAddr) -> Option<String> {
+        use std::io::{ Read, Write };
+
+        let mut stream = std::net::TcpStream::connect(addr).ok()?;
+        stream.set_read_timeout(Some(Duration::from_secs(2))).ok()?;
+        write!(stream, "GET /api/health HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", addr).ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        response.lines().next().map(str::to_string)
+    }
+
+    #[test]
+    fn started_server_stops_cleanly() {
+        // The engine shuts the logger down on its way out
+        let _turn = logger_turn();
+        // JwtManager and the logger read app_config.toml from the working directory
+        let dir = std::env::temp_dir().join(format!("engine_stop_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("app_config.toml"),
+            "[logging]\ntype = \"console\"\nthreshold = \"warn\"\n\n[jwt_storage]\nstorage_type = \"in_memory\"\n"
+        ).unwrap();
+        std::env::set_current_dir(&dir).unwrap();
+
+        let config = EngineConfig { http_addr: free_addr(), ws_addr: free_addr(), ..EngineConfig::default() };
+        let http_addr = config.http_addr;
+        // Same runtime setup as start_oobe_server, but reporting when the engine returns
+        let (stopped_tx, stopped) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(start_server_async(config));
+            let _ = stopped_tx.send(());
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+        let mut status = None;
+        while status.is_none() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(100));
+            status = health_status(http_addr);
+        }
+        let status = status.expect("engine never answered /api/health");
+        assert!(status.starts_with("HTTP/1.1 "), "unexpected status line: {}", status);
+
+        stop_oobe_server();
+        stopped.recv_timeout(Duration::from_secs(10)).expect("engine did not stop after stop_oobe_server");
+
+        // Both listeners are closed, so the HTTP port can be bound again
+        assert!(std::net::TcpListener::bind(http_addr).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn request_id_shows_up_in_engine_and_plugin_logs() {
+        use tower::ServiceExt;
+
+        let _turn = logger_turn();
+        let dir = std::env::temp_dir().join(format!("engine_request_id_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // File outputs take their settings from the config file, not the struct passed in
+        let config_path = dir.join("logging.toml");
+        fs::write(
+            &config_path,
+            format!(
+                "[logging]\ntype = \"file\"\nthreshold = \"debug\"\nlog_folder = {:?}\nfile_path = \"requests.log\"\nasync_logging = false\nforce_flush = true\n",
+                dir.display().to_string()
+            )
+        ).unwrap();
+        liblogger::Logger::init_with_config_file(config_path.to_str().unwrap()).unwrap();
+
+        let plugin_meta = mock_plugin(&dir, "engine_mock_logging", &[], false);
+        let registry = Arc::new(PluginRegistry::new());
+        registry.register(PluginBinding { handle_request: logging_handle_request, ..mock_binding(&plugin_meta) });
+
+        let app = Router::new()
+            .route("/api/:plugin/*resource", any(dispatch_plugin_api).with_state(registry))
+            .layer(axum::middleware::from_fn(request_id::request_id));
+        let request = axum::http::Request::builder()
+            .uri(format!("/api/{}/status", plugin_meta.plugin_route))
+            .header(request_id::REQUEST_ID_HEADER, "engine-test-591")
+            .body(Body::empty())
+            .unwrap();
+        // The logger turn is held throughout, so drive the request from a runtime of its own
+        let response = tokio::runtime::Runtime::new().unwrap().block_on(app.oneshot(request)).unwrap();
+
+        let log = fs::read_to_string(dir.join("requests.log")).unwrap();
+        // Put the console logger from the ctor back for the other tests
+        liblogger::Logger::init_with_config(liblogger::LogConfig::default()).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[request_id::REQUEST_ID_HEADER], "engine-test-591");
+        let tagged = |message: &str| {
+            log.lines().any(|line| line.contains(message) && line.contains("request_id=engine-test-591"))
+        };
+        assert!(tagged("dispatch_plugin_api called"), "engine line missing from:\n{}", log);
+        assert!(tagged("mock plugin handled the request"), "plugin line missing from:\n{}", log);
+    }
 }
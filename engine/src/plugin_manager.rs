@@ -4,11 +4,16 @@
 //! It handles plugin lifecycle, registration, and resource management.
 
 // Standard library imports
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicUsize, Ordering };
 use std::ffi::CString;
 
 // Internal crate imports
 use plugin_core::PluginContext;
+use plugin_core::plugin_context::WS_URL_CONFIG_KEY;
 use engine_core::{
     plugin_loader::load_plugin,
     plugin_registry::PluginRegistry,
@@ -18,14 +23,194 @@ use engine_core::{
 
 // External crate imports
 use libloading::Library;
+use once_cell::sync::Lazy;
+
+// Local imports
+use crate::router_manager::RouterManager;
+
+/// A plugin library kept alive by the engine, along with what is needed to load it again.
+struct LoadedPlugin {
+    path: PathBuf,
+    /// Configuration passed to `run()`, or `None` if the engine never ran the plugin
+    config: Option<String>,
+    /// WebSocket URL passed to `run()` alongside `config`
+    ws_url: Option<String>,
+    /// Structured configuration passed to `run()` as `PluginContext::config_json`
+    config_json: Option<String>,
+    library: Arc<Library>,
+}
+
+/// Every plugin library the engine has loaded, keyed by plugin name.
+///
+/// An entry is only replaced by a newer load of the same plugin; see `retire_library`
+/// for what happens to the one it replaces.
+static LOADED_PLUGINS: Lazy<Mutex<HashMap<String, LoadedPlugin>>> = Lazy::new(|| {
+    Mutex::new(HashMap::new())
+});
+
+/// Appends the engine's WebSocket URL to a plugin's `key=value;...` config string,
+/// for plugins that predate `PluginContext::ws_url`.
+pub(crate) fn with_ws_url(config: &str, ws_url: &str) -> String {
+    if config.is_empty() {
+        format!("{}={}", WS_URL_CONFIG_KEY, ws_url)
+    } else {
+        format!("{};{}={}", config, WS_URL_CONFIG_KEY, ws_url)
+    }
+}
+
+/// Calls the plugin's `run()` with its config, the engine's WebSocket URL and, for
+/// execution plan plugins with a `[plugins.config]` table, that table as JSON.
+pub(crate) fn run_plugin(plugin: &PluginBinding, config: &str, ws_url: &str, config_json: Option<&str>) {
+    let plugin_config = CString::new(config).unwrap();
+    let plugin_ws_url = CString::new(ws_url).unwrap();
+    let plugin_config_json = config_json.map(|json| CString::new(json).unwrap());
+    let ctx = PluginContext {
+        config: plugin_config.as_ptr(),
+        ws_url: plugin_ws_url.as_ptr(),
+        config_json: plugin_config_json.as_ref().map_or(std::ptr::null(), |json| json.as_ptr()),
+    };
+    (plugin.run)(&ctx);
+}
+
+/// Keeps a plugin's library loaded so its function pointers stay valid.
+///
+/// `run_with` is the `(config, ws_url)` pair the plugin was run with, if any, and
+/// `config_json` its structured configuration, so a reload can run it the same way.
+pub(crate) fn retain_library(
+    name: &str,
+    path: &Path,
+    run_with: Option<(&str, &str)>,
+    config_json: Option<&str>,
+    library: Arc<Library>
+) {
+    let loaded = LoadedPlugin {
+        path: path.to_path_buf(),
+        config: run_with.map(|(config, _)| config.to_string()),
+        ws_url: run_with.map(|(_, ws_url)| ws_url.to_string()),
+        config_json: config_json.map(str::to_string),
+        library,
+    };
+
+    let previous = LOADED_PLUGINS.lock().unwrap().insert(name.to_string(), loaded);
+    if let Some(previous) = previous {
+        retire_library(previous);
+    }
+}
+
+/// Lets go of a library that a newer load of the same plugin has replaced.
+///
+/// A plugin the engine ran may have started threads in `run()` (e.g. its WebSocket
+/// client) that keep executing its code, and nothing tracks them, so its library is
+/// leaked and stays mapped for the life of the process. A plugin that was never run
+/// is only reachable through its bindings, whose own references keep the library
+/// mapped until the last one, including any request still running, is dropped.
+fn retire_library(previous: LoadedPlugin) {
+    if previous.config.is_some() {
+        std::mem::forget(previous.library);
+    }
+}
+
+/// Source of unique file names for `fresh_copy`.
+static RELOAD_COUNTER: AtomicUsize = AtomicUsize::new(1);
+
+/// Copies a plugin library to a new file in the temp directory.
+///
+/// Opening a path that is already loaded returns the library that is already mapped,
+/// so a reload must load the new build from a path the process hasn't opened before.
+fn fresh_copy(path: &Path) -> Result<PathBuf, String> {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("plugin");
+    let extension = path.extension().and_then(|e| e.to_str()).map_or(String::new(), |e| format!(".{}", e));
+    let copy = std::env::temp_dir().join(format!(
+        "{}-reload-{}-{}{}",
+        stem,
+        std::process::id(),
+        RELOAD_COUNTER.fetch_add(1, Ordering::SeqCst),
+        extension
+    ));
+    fs::copy(path, &copy).map_err(|e| format!("Failed to copy {} for reload: {}", path.display(), e))?;
+    Ok(copy)
+}
+
+/// Loads a plugin again from the file it was first loaded from and swaps it in.
+///
+/// The new build is loaded and run first, from a fresh copy of the file. Only then is
+/// the old binding unregistered, its web folder route replaced and the new binding
+/// registered. If loading fails, the running plugin is left untouched.
+///
+/// # Safety constraints
+/// The old library is never unmapped while something may still call into it:
+/// - requests already inside the old plugin, including ones that timed out, hold
+///   its binding and finish against the old code; their `ApiResponse` is handed to
+///   the old `cleanup`
+/// - a plugin the engine ran may have threads started in `run()` (e.g. a WebSocket
+///   client), so its old library is kept loaded for the life of the process
+///
+/// The caller must still make sure that nothing outside the engine kept raw
+/// pointers into the old plugin, and that the old and new instances can run side by
+/// side (both WebSocket clients stay connected, for example).
+pub async fn reload_plugin(registry: &Arc<PluginRegistry>, name: &str) -> Result<PluginBinding, String> {
+    let (path, run_with, config_json) = {
+        let loaded = LOADED_PLUGINS.lock().unwrap();
+        let loaded = loaded.get(name).ok_or_else(|| format!("Plugin '{}' is not loaded", name))?;
+        let run_with = loaded.config.clone().zip(loaded.ws_url.clone());
+        (loaded.path.clone(), run_with, loaded.config_json.clone())
+    };
+
+    let copy = fresh_copy(&path)?;
+    let loaded = load_plugin(&copy);
+    // Once mapped, the copy is no longer needed (removing it fails harmlessly on Windows)
+    let _ = fs::remove_file(&copy);
+    let (mut plugin, library) = loaded.map_err(|e| format!("Failed to reload {} plugin: {}", name, e))?;
+
+    if plugin.name != name {
+        return Err(format!("Reloaded library for '{}' reports itself as '{}'", name, plugin.name));
+    }
+
+    let previous = registry.get(name);
+    if let Some(previous) = &previous {
+        plugin.max_body_bytes = previous.max_body_bytes;
+        plugin.max_concurrent_requests = previous.max_concurrent_requests;
+        plugin.request_timeout = previous.request_timeout;
+    }
+
+    if let Some((config, ws_url)) = &run_with {
+        run_plugin(&plugin, config, ws_url, config_json.as_deref());
+    }
+
+    // Swap: from here on nothing can fail
+    if let Some(previous) = registry.unregister(name) {
+        RouterManager::remove_plugin_route(&previous.plugin_route).await;
+    }
+    registry.register(plugin.clone());
+    RouterManager::add_plugin_route(&plugin.plugin_route, &plugin.static_path).await;
+    let run_with = run_with.as_ref().map(|(config, ws_url)| (config.as_str(), ws_url.as_str()));
+    retain_library(&plugin.name, &path, run_with, config_json.as_deref(), library);
+
+    println!("[engine] Reloaded plugin {}", plugin.name);
+    Ok(plugin)
+}
+
+/// Takes every plugin out of the registry and stops serving its web folder and CORS
+/// override. Called once the HTTP server has drained, so no request is in flight.
+///
+/// The libraries themselves stay mapped: plugins' WebSocket clients run on threads
+/// started in `run()`, and the engine has no way to stop them before unmapping their code.
+pub async fn unload_plugins(registry: &Arc<PluginRegistry>) {
+    for plugin in registry.all() {
+        registry.unregister(&plugin.name);
+        RouterManager::remove_plugin_route(&plugin.plugin_route).await;
+        crate::cors::set_plugin_cors_policy(&plugin.plugin_route, None);
+        println!("[engine] Unloaded plugin {}", plugin.name);
+    }
+}
 
 /// Manages the lifecycle of plugins including loading, registration, and cleanup.
 /// 
-/// The `PluginManager` maintains a registry of loaded plugins and their associated
-/// dynamic libraries to ensure proper resource management.
+/// The `PluginManager` registers loaded plugins and hands their dynamic libraries
+/// to the engine-wide store so they stay loaded until explicitly reloaded.
 pub struct PluginManager {
     registry: Arc<PluginRegistry>,
-    pub(crate) plugin_libraries: Vec<Library>,
+    ws_url: String,
 }
 
 impl PluginManager {
@@ -33,11 +218,9 @@ impl PluginManager {
     /// 
     /// # Arguments
     /// * `registry` - A thread-safe reference to the plugin registry
-    pub fn new(registry: Arc<PluginRegistry>) -> Self {
-        Self {
-            registry,
-            plugin_libraries: Vec::new(),
-        }
+    /// * `ws_url` - WebSocket URL handed to every plugin in its config
+    pub fn new(registry: Arc<PluginRegistry>, ws_url: &str) -> Self {
+        Self { registry, ws_url: ws_url.to_string() }
     }
 
     /// Loads and initializes a plugin from a dynamic library.
@@ -51,7 +234,8 @@ impl PluginManager {
     pub fn load_plugin(&mut self, plugin_name: &str, config: &str) -> Option<PluginBinding> {
         println!("Loading the {} plugin", plugin_name);
 
-        let (plugin, lib) = match load_plugin(plugin_utils::resolve_plugin_filename(plugin_name)) {
+        let path = PathBuf::from(plugin_utils::resolve_plugin_filename(plugin_name));
+        let (plugin, lib) = match load_plugin(&path) {
             Ok(p) => p,
             Err(e) => {
                 eprintln!("Failed to load {} plugin: {}", plugin_name, e);
@@ -79,24 +263,14 @@ impl PluginManager {
             println!("[engine] Plugin returned no resources");
         }
 
-        // Run plugin with config
-        let plugin_config = CString::new(config).unwrap();
-        let ctx = PluginContext {
-            config: plugin_config.as_ptr(),
-        };
-        (plugin.run)(&ctx);
+        // Run plugin with config, telling it where the WebSocket server is
+        let config = with_ws_url(config, &self.ws_url);
+        run_plugin(&plugin, &config, &self.ws_url, None);
 
         // Store and register
-        self.plugin_libraries.push(lib);
+        retain_library(&plugin.name, &path, Some((&config, &self.ws_url)), None, lib);
         self.registry.register(plugin.clone());
 
         Some(plugin)
     }
-
-    /// Returns a mutable reference to the collection of loaded plugin libraries.
-    /// 
-    /// This method is primarily used for internal crate access to manage plugin cleanup.
-    pub fn get_plugin_libraries(&mut self) -> &mut Vec<Library> {
-        &mut self.plugin_libraries
-    }
 }
@@ -0,0 +1,229 @@
+//! Rate Limiter module for throttling HTTP requests per client IP and route.
+//! Protects plugins that do expensive work (e.g. WiFi scans) from clients stuck in a request loop.
+
+// Standard library imports
+use std::collections::HashMap;
+use std::net::{ IpAddr, SocketAddr };
+use std::sync::{ Mutex, RwLock };
+use std::time::Instant;
+
+// Third-party imports
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{ Request, StatusCode },
+    middleware::Next,
+    response::{ IntoResponse, Response },
+};
+use once_cell::sync::Lazy;
+
+/// Buckets that haven't been touched for this long are dropped when the map is pruned.
+const IDLE_BUCKET_SECS: u64 = 300;
+
+/// Pruning only runs once the number of tracked buckets grows past this.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Token bucket parameters for a route.
+#[derive(Debug, Clone, Copy)]
+pub struct RouteLimit {
+    /// Maximum number of requests that can be made in a burst.
+    pub burst: u32,
+    /// Number of requests added back to the bucket per second.
+    pub per_second: f64,
+}
+
+/// Rate limit settings applied by the engine's HTTP server.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Limit used for any route without an override.
+    pub default_limit: RouteLimit,
+    /// Per-route overrides keyed by path prefix (e.g. "/api/wifi/network").
+    /// The longest matching prefix wins.
+    pub overrides: Vec<(String, RouteLimit)>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            default_limit: RouteLimit { burst: 50, per_second: 20.0 },
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    fn limit_for(&self, path: &str) -> RouteLimit {
+        self.overrides
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, limit)| *limit)
+            .unwrap_or(self.default_limit)
+    }
+}
+
+/// Classic token bucket: starts full, spends one token per request and refills continuously.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RouteLimit, now: Instant) -> Self {
+        Self { tokens: limit.burst as f64, last_refill: now }
+    }
+
+    /// Refills based on elapsed time, then takes a token if one is available.
+    fn try_acquire(&mut self, limit: RouteLimit, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Active rate limit settings.
+static RATE_LIMIT_CONFIG: Lazy<RwLock<RateLimitConfig>> = Lazy::new(|| {
+    RwLock::new(RateLimitConfig::default())
+});
+
+/// Token buckets per client IP and request path.
+static BUCKETS: Lazy<Mutex<HashMap<(IpAddr, String), TokenBucket>>> = Lazy::new(|| {
+    Mutex::new(HashMap::new())
+});
+
+/// Replaces the active rate limit settings. Existing buckets keep their current tokens.
+pub fn set_rate_limit_config(config: RateLimitConfig) {
+    *RATE_LIMIT_CONFIG.write().unwrap() = config;
+}
+
+/// Returns true if the client at `ip` may make another request to `path` right now.
+fn check_rate_limit(ip: IpAddr, path: &str) -> bool {
+    let limit = RATE_LIMIT_CONFIG.read().unwrap().limit_for(path);
+    let now = Instant::now();
+
+    let mut buckets = BUCKETS.lock().unwrap();
+    if buckets.len() > PRUNE_THRESHOLD {
+        buckets.retain(|_, bucket| {
+            now.saturating_duration_since(bucket.last_refill).as_secs() < IDLE_BUCKET_SECS
+        });
+    }
+
+    buckets
+        .entry((ip, path.to_string()))
+        .or_insert_with(|| TokenBucket::new(limit, now))
+        .try_acquire(limit, now)
+}
+
+/// Axum middleware rejecting requests with 429 once a client exceeds the route's limit.
+///
+/// Requires the server to be started with `into_make_service_with_connect_info::<SocketAddr>()`.
+pub async fn rate_limit(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request<Body>,
+    next: Next
+) -> Response {
+    let path = req.uri().path().to_string();
+
+    if !check_rate_limit(addr.ip(), &path) {
+        println!("[engine] Rate limit exceeded for {} on {}", addr.ip(), path);
+        return (StatusCode::TOO_MANY_REQUESTS, "Too many requests").into_response();
+    }
+
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const LIMIT: RouteLimit = RouteLimit { burst: 3, per_second: 2.0 };
+
+    #[test]
+    fn new_bucket_allows_a_full_burst_then_denies() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(LIMIT, now);
+
+        for _ in 0..LIMIT.burst {
+            assert!(bucket.try_acquire(LIMIT, now));
+        }
+        assert!(!bucket.try_acquire(LIMIT, now));
+    }
+
+    #[test]
+    fn tokens_refill_at_the_configured_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(LIMIT, start);
+        for _ in 0..LIMIT.burst {
+            bucket.try_acquire(LIMIT, start);
+        }
+
+        // A quarter second at 2/s is half a token: not enough yet
+        assert!(!bucket.try_acquire(LIMIT, start + Duration::from_millis(250)));
+        // Another quarter second completes it
+        assert!(bucket.try_acquire(LIMIT, start + Duration::from_millis(500)));
+        assert!(!bucket.try_acquire(LIMIT, start + Duration::from_millis(500)));
+
+        // One second later two more requests are allowed
+        let later = start + Duration::from_millis(1500);
+        assert!(bucket.try_acquire(LIMIT, later));
+        assert!(bucket.try_acquire(LIMIT, later));
+        assert!(!bucket.try_acquire(LIMIT, later));
+    }
+
+    #[test]
+    fn refill_is_capped_at_the_burst_size() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(LIMIT, start);
+        for _ in 0..LIMIT.burst {
+            bucket.try_acquire(LIMIT, start);
+        }
+
+        // An hour idle still only refills to `burst`
+        let later = start + Duration::from_secs(3600);
+        for _ in 0..LIMIT.burst {
+            assert!(bucket.try_acquire(LIMIT, later));
+        }
+        assert!(!bucket.try_acquire(LIMIT, later));
+    }
+
+    #[test]
+    fn longest_matching_override_wins() {
+        let wifi = RouteLimit { burst: 5, per_second: 1.0 };
+        let network = RouteLimit { burst: 1, per_second: 0.5 };
+        let config = RateLimitConfig {
+            overrides: vec![
+                ("/api/wifi".to_string(), wifi),
+                ("/api/wifi/network".to_string(), network),
+            ],
+            ..RateLimitConfig::default()
+        };
+
+        assert_eq!(config.limit_for("/api/wifi/network").burst, network.burst);
+        assert_eq!(config.limit_for("/api/wifi/status").burst, wifi.burst);
+        assert_eq!(config.limit_for("/api/settings").burst, config.default_limit.burst);
+    }
+
+    #[test]
+    fn buckets_are_kept_per_client_and_path() {
+        let burst = RATE_LIMIT_CONFIG.read().unwrap().limit_for("/rate-limit-test").burst;
+        let client: IpAddr = "192.0.2.10".parse().unwrap();
+        let other_client: IpAddr = "192.0.2.11".parse().unwrap();
+
+        for _ in 0..burst {
+            assert!(check_rate_limit(client, "/rate-limit-test"));
+        }
+        assert!(!check_rate_limit(client, "/rate-limit-test"));
+
+        assert!(check_rate_limit(other_client, "/rate-limit-test"));
+        assert!(check_rate_limit(client, "/rate-limit-test/other"));
+    }
+}
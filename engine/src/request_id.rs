@@ -0,0 +1,70 @@
+//! Request ID module tagging every HTTP request with an `X-Request-Id`.
+//! The id is kept in the logger's thread-local context while the request is handled,
+//! forwarded to plugins as a request header and echoed on the response.
+
+// Standard library imports
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ Context, Poll };
+
+// Third-party imports
+use axum::{
+    body::Body,
+    http::{ HeaderName, HeaderValue, Request },
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+/// Header carrying the request id, in both directions.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied id accepted; longer ones are replaced with a fresh id.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Axum middleware reusing the client's `X-Request-Id` or generating one.
+///
+/// The id is written back into the request headers, so `dispatch_plugin_api` hands
+/// it to the plugin, and set as the logger's `request_id` for every poll of the
+/// handler, so engine logs for the request carry it.
+pub async fn request_id(mut req: Request<Body>, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    // Valid ids and UUIDs are plain visible ASCII, so this cannot fail
+    let value = HeaderValue::from_str(&id).unwrap();
+    req.headers_mut().insert(REQUEST_ID_HEADER, value.clone());
+
+    let mut response = WithRequestId { id, inner: Box::pin(next.run(req)) }.await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    response
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Runs a future with the logger's `request_id` set.
+///
+/// The handler may resume on a different worker thread after each `.await`, so the
+/// thread-local is set around every poll and the previous value restored afterwards.
+struct WithRequestId<F> {
+    id: String,
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for WithRequestId<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let previous = liblogger::set_context_value(liblogger::REQUEST_ID_KEY, Some(&self.id));
+        let result = self.inner.as_mut().poll(cx);
+        liblogger::set_context_value(liblogger::REQUEST_ID_KEY, previous.as_deref());
+        result
+    }
+}
@@ -2,14 +2,17 @@
 //! Provides functionality for managing HTTP routes and serving static files.
 
 // Standard library imports
+use std::collections::HashMap;
 use std::fs;
 use std::sync::{ Arc, RwLock };
 use std::convert::Infallible;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{ Hash, Hasher };
 
 // Third-party imports
 use axum::{
     body::Body,
-    http::StatusCode,
+    http::{ header, HeaderValue, StatusCode },
     http::Request,
     response::Response,
     routing::{ any, get },
@@ -20,7 +23,8 @@ use tower_http::{ cors::{ CorsLayer, Any }, services::ServeDir };
 use tower::util::{ ServiceExt, service_fn };
 
 // Local imports
-use engine_core::{ handlers::dispatch_plugin_api, plugin_registry::PluginRegistry };
+use engine_core::{ handlers::{ dispatch_plugin_api, health_check }, plugin_registry::PluginRegistry };
+use crate::websocket_manager::ws_stats;
 
 /// Global router manager for handling dynamic routes.
 /// Uses a lazy-initialized RwLock to allow runtime modifications.
@@ -28,6 +32,77 @@ static ROUTER_MANAGER: Lazy<Arc<RwLock<Router>>> = Lazy::new(|| {
     Arc::new(RwLock::new(Router::new()))
 });
 
+/// Static folders served by each mounted plugin web path (e.g. "/wifi/web"), or
+/// `None` for a path that is mounted but currently disabled.
+/// Plugin web routes look their folder up here on every request, so a route can
+/// be repointed or disabled after it has been mounted (axum can't unmount a service).
+/// Entries are never removed: a path stays mounted in the router once nested.
+static PLUGIN_WEB_ROUTES: Lazy<RwLock<HashMap<String, Option<String>>>> = Lazy::new(|| {
+    RwLock::new(HashMap::new())
+});
+
+/// `Cache-Control` for HTML pages: always revalidate, so a reloaded plugin's new page
+/// shows up on the next navigation.
+const HTML_CACHE_CONTROL: &str = "no-cache";
+
+/// `Cache-Control` for every other static file (scripts, styles, images).
+const ASSET_CACHE_CONTROL: &str = "public, max-age=60";
+
+/// Serves `req` from `dir` with caching headers.
+///
+/// ServeDir already sends `Last-Modified` and answers `If-Modified-Since` with 304. This
+/// adds a `Cache-Control` header and a weak `ETag` built from the file's modification
+/// time and size, and answers a matching `If-None-Match` with 304 as well.
+async fn serve_static(dir: &str, req: Request<Body>) -> Response {
+    let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+    let mut response = ServeDir::new(dir).oneshot(req).await.unwrap().map(Body::new);
+
+    if !(response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED) {
+        return response;
+    }
+
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/html"));
+    let cache_control = if is_html { HTML_CACHE_CONTROL } else { ASSET_CACHE_CONTROL };
+
+    let etag = static_etag(&response);
+    if let (Some(etag), Some(if_none_match)) = (&etag, &if_none_match) {
+        // If-None-Match uses weak comparison, so the W/ prefix is ignored on both sides
+        let etag_value = etag.trim_start_matches("W/");
+        let matches = if_none_match.to_str().is_ok_and(|value| {
+            value.split(',').map(str::trim).any(|c| c == "*" || c.trim_start_matches("W/") == etag_value)
+        });
+        if matches {
+            response = Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Body::empty())
+                .unwrap();
+        }
+    }
+
+    let headers = response.headers_mut();
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    if let Some(etag) = etag.and_then(|etag| HeaderValue::from_str(&etag).ok()) {
+        headers.insert(header::ETAG, etag);
+    }
+    response
+}
+
+/// Weak ETag for a ServeDir response, from its `Last-Modified` and `Content-Length`.
+/// `None` when ServeDir didn't report a modification time.
+fn static_etag(response: &Response) -> Option<String> {
+    let last_modified = response.headers().get(header::LAST_MODIFIED)?;
+    let mut hasher = DefaultHasher::new();
+    last_modified.as_bytes().hash(&mut hasher);
+    if let Some(length) = response.headers().get(header::CONTENT_LENGTH) {
+        length.as_bytes().hash(&mut hasher);
+    }
+    Some(format!("W/\"{:016x}\"", hasher.finish()))
+}
+
 /// RouterManager handles the creation and management of HTTP routes for the plugin system.
 /// It maintains routes for API endpoints, static files, and plugin-specific web content.
 #[allow(dead_code)]
@@ -77,12 +152,14 @@ impl RouterManager {
         app = app.layer(cors);
 
         // API routes
-        let plugin_api_router = Router::new().route(
-            "/:plugin/:resource",
-            any(dispatch_plugin_api).with_state(self.registry.clone())
-        );
+        let plugin_api_router = Router::new()
+            .route("/health", get(health_check).with_state(self.registry.clone()))
+            .route("/:plugin/*resource", any(dispatch_plugin_api).with_state(self.registry.clone()));
         app = app.nest("/api", plugin_api_router);
 
+        // WebSocket subscriber counts
+        app = app.route("/ws/stats", get(ws_stats));
+
         // Plugin web routes - preserve the exact path structure
         for plugin in self.registry.all() {
             let web_path = format!("/{}/web", plugin.plugin_route);
@@ -97,19 +174,65 @@ impl RouterManager {
         app
     }
 
+    /// Mounts a plugin's static folder at `/<route>/web`.
+    ///
+    /// Calling this again for a route that is already mounted just repoints it at `path`.
     pub async fn add_plugin_route(route: &str, path: &str) {
-        let web_path = if route.starts_with('/') {
-            format!("{}/web", route)
-        } else {
-            format!("/{}/web", route)
-        };
+        let web_path = Self::plugin_web_path(route);
 
         println!("Adding plugin route: '{}' -> '{}'", web_path, path);
+        let already_mounted = PLUGIN_WEB_ROUTES.write()
+            .unwrap()
+            .insert(web_path.clone(), Some(path.to_string()))
+            .is_some();
+
+        if already_mounted {
+            println!("Updated plugin route: {} -> {}", web_path, path);
+            return;
+        }
+
+        let lookup_path = web_path.clone();
+        let service = service_fn(move |req: Request<Body>| {
+            let lookup_path = lookup_path.clone();
+            async move {
+                let static_path = PLUGIN_WEB_ROUTES.read().unwrap().get(&lookup_path).cloned().flatten();
+                let response = match static_path {
+                    Some(dir) => serve_static(&dir, req).await,
+                    None =>
+                        Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .body(Body::from("Plugin not loaded"))
+                            .unwrap(),
+                };
+
+                Ok::<_, Infallible>(response)
+            }
+        });
+
         let mut router = ROUTER_MANAGER.write().unwrap();
-        *router = router.clone().nest_service(&web_path, ServeDir::new(path));
+        *router = router.clone().nest_service(&web_path, service);
         println!("Added plugin route: {} -> {}", web_path, path);
     }
 
+    /// Stops serving a plugin's static folder. Requests to its web path return 404
+    /// until `add_plugin_route` is called for the route again.
+    pub async fn remove_plugin_route(route: &str) {
+        let web_path = Self::plugin_web_path(route);
+        if let Some(static_path) = PLUGIN_WEB_ROUTES.write().unwrap().get_mut(&web_path) {
+            if static_path.take().is_some() {
+                println!("Removed plugin route: {}", web_path);
+            }
+        }
+    }
+
+    fn plugin_web_path(route: &str) -> String {
+        if route.starts_with('/') {
+            format!("{}/web", route)
+        } else {
+            format!("/{}/web", route)
+        }
+    }
+
     /// Adds a static file route at runtime.
     ///
     /// # Arguments
@@ -119,8 +242,14 @@ impl RouterManager {
         // Ensure route starts with /
         let route = if !route.starts_with('/') { format!("/{}", route) } else { route.to_string() };
 
+        let dir = path.to_string();
+        let service = service_fn(move |req: Request<Body>| {
+            let dir = dir.clone();
+            async move { Ok::<_, Infallible>(serve_static(&dir, req).await) }
+        });
+
         let mut router = ROUTER_MANAGER.write().unwrap();
-        *router = router.clone().nest_service(&route, ServeDir::new(path));
+        *router = router.clone().nest_service(&route, service);
         println!("Added static route: {}", route);
     }
 
@@ -1,36 +1,41 @@
-use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
-use tokio::sync::mpsc::UnboundedSender;
+use std::sync::{Arc, Mutex};
+use axum::Json;
 use once_cell::sync::{Lazy, OnceCell};
 use libws::ws_client::WsClient;
+use plugin_core::EngineEvent;
 
-pub type Subscribers = Arc<Mutex<HashMap<String, Vec<UnboundedSender<String>>>>>;
+pub use libws::Subscribers;
 
 /// WebSocket subscribers for the engine.
-pub static WS_SUBSCRIBERS: Lazy<Subscribers> = Lazy::new(|| {
-    Arc::new(Mutex::new(HashMap::new()))
-});
+pub static WS_SUBSCRIBERS: Lazy<Subscribers> = Lazy::new(Subscribers::default);
+
+/// Handler for `/ws/stats`: live subscriber counts per topic, for diagnosing
+/// messages that were published before anyone subscribed.
+pub async fn ws_stats() -> Json<HashMap<String, usize>> {
+    Json(libws::subscriber_counts(&WS_SUBSCRIBERS))
+}
 
 /// WebSocket client for the engine.
 pub static ENGINE_WS_CLIENT: OnceCell<Arc<Mutex<WsClient>>> = OnceCell::new();
 
 /// Topic for receiving network connected messages.
-pub static NETWORK_CONNECTED: &str = "NetworkConnected";
+pub static NETWORK_CONNECTED: &str = EngineEvent::NetworkConnected.to_topic();
 
 /// Topic for receiving switch route messages.
-pub static SWITCH_ROUTE: &str = "SwitchRoute";
+pub static SWITCH_ROUTE: &str = EngineEvent::SwitchRoute.to_topic();
 
 /// Topic for receiving welcome completed messages.
-pub static WELCOME_COMPLETED: &str = "WelcomeCompleted";
+pub static WELCOME_COMPLETED: &str = EngineEvent::WelcomeCompleted.to_topic();
 
 /// Topic for receiving network connected messages.
-pub static WIFI_COMPLETED : &str = "WifiCompleted";
+pub static WIFI_COMPLETED : &str = EngineEvent::WifiCompleted.to_topic();
 
 /// Topic for receiving execution plan completed messages.
-pub static EXECPLAN_COMPLETED : &str = "ExecutionPlanCompleted";
+pub static EXECPLAN_COMPLETED : &str = EngineEvent::ExecutionPlanCompleted.to_topic();
 
 /// Topic for receiving login completed messages.
-// pub static LOGIN_COMPLETED : &str = "LoginCompleted";
+// pub static LOGIN_COMPLETED : &str = EngineEvent::LoginCompleted.to_topic();
 
 /// Topic for provision completed messages.
-pub static PROVISION_COMPLETED : &str = "ProvisionCompleted";
\ No newline at end of file
+pub static PROVISION_COMPLETED : &str = EngineEvent::ProvisionCompleted.to_topic();
\ No newline at end of file
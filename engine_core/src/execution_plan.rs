@@ -1,9 +1,17 @@
 use crate::plugin_metadata::PluginMetadata;
 use serde::Deserialize;
-use std::{fs, path::Path, error::Error};
+use std::{collections::HashSet, fs, path::Path, error::Error};
+
+/// Newest execution plan schema this engine understands.
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// Schema assumed for plans written before `schema_version` existed.
+const LEGACY_SCHEMA_VERSION: u32 = 1;
 
 #[derive(Debug, Deserialize)]
 pub struct PluginExecutionPlan {
+    /// Format version of the plan file. Unversioned plans are migrated as v1.
+    pub schema_version: u32,
     pub general: GeneralConfig,
     pub plugins: Vec<PluginMetadata>,
     pub handoffs: Handoffs,
@@ -15,6 +23,30 @@ pub struct GeneralConfig {
     pub execution_plan_version: String,
     pub update_from: String,
     pub update_path_root: String,
+    /// Plugin binaries prepared (copied or downloaded) at the same time. Default: 4.
+    #[serde(default = "default_max_parallel_downloads")]
+    pub max_parallel_downloads: usize,
+    /// Seconds a single remote fetch (plan, signature or plugin binary) may take. Default: 10.
+    #[serde(default = "default_fetch_timeout_secs")]
+    pub fetch_timeout_secs: u64,
+    /// Seconds the whole remote plan update may take before the local plan is used. Default: 30.
+    #[serde(default = "default_update_budget_secs")]
+    pub update_budget_secs: u64,
+}
+
+/// Default value for `max_parallel_downloads` (4).
+fn default_max_parallel_downloads() -> usize {
+    4
+}
+
+/// Default value for `fetch_timeout_secs` (10).
+fn default_fetch_timeout_secs() -> u64 {
+    10
+}
+
+/// Default value for `update_budget_secs` (30).
+fn default_update_budget_secs() -> u64 {
+    30
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,7 +59,14 @@ pub struct ExecutionPlanLoader;
 impl ExecutionPlanLoader {
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<PluginExecutionPlan, Box<dyn Error>> {
         let content = fs::read_to_string(path)?;
-        let plan: PluginExecutionPlan = toml::from_str(&content)?;
+        Self::load_from_str(&content)
+    }
+
+    /// Parses, migrates and validates a plan from its TOML text.
+    pub fn load_from_str(content: &str) -> Result<PluginExecutionPlan, Box<dyn Error>> {
+        let mut document: toml::Table = toml::from_str(content)?;
+        Self::migrate(&mut document)?;
+        let plan: PluginExecutionPlan = document.try_into()?;
 
         // general section validation
         Self::validate_general(&plan.general)?;
@@ -43,6 +82,36 @@ impl ExecutionPlanLoader {
         Ok(plan)
     }
 
+    /// Brings a parsed plan up to `CURRENT_SCHEMA_VERSION`.
+    ///
+    /// Plans without `schema_version` are treated as v1. Plans newer than this engine
+    /// are rejected instead of failing later with a confusing parse error.
+    fn migrate(document: &mut toml::Table) -> Result<(), Box<dyn Error>> {
+        let version = match document.get("schema_version") {
+            None => {
+                println!("Execution plan has no schema_version; treating it as v{}", LEGACY_SCHEMA_VERSION);
+                LEGACY_SCHEMA_VERSION
+            }
+            Some(toml::Value::Integer(version)) if *version >= 1 => *version as u32,
+            Some(other) => {
+                return Err(format!("Invalid 'schema_version' {}: must be a positive integer", other).into());
+            }
+        };
+
+        if version > CURRENT_SCHEMA_VERSION {
+            return Err(format!(
+                "Execution plan schema v{} requires a newer engine; engine_core {} supports up to v{}",
+                version,
+                env!("CARGO_PKG_VERSION"),
+                CURRENT_SCHEMA_VERSION
+            ).into());
+        }
+
+        // Step-wise upgrades (v1 -> v2, ...) go here once the format changes
+        document.insert("schema_version".to_string(), toml::Value::Integer(CURRENT_SCHEMA_VERSION as i64));
+        Ok(())
+    }
+
     fn validate_handoffs(handoffs: &Handoffs) -> Result<(), Box<dyn Error>> {
         for event in &handoffs.handoff_events {
             println!("Handoff event: {}", event);
@@ -63,6 +132,12 @@ impl ExecutionPlanLoader {
         if general.update_path_root.trim().is_empty() {
             return Err("Missing 'update_path_root' in [general] section".into());
         }
+        if general.fetch_timeout_secs == 0 {
+            return Err("Invalid 'fetch_timeout_secs' in [general] section: must be at least 1".into());
+        }
+        if general.max_parallel_downloads == 0 {
+            return Err("Invalid 'max_parallel_downloads' in [general] section: must be at least 1".into());
+        }
 
         Ok(())
     }
@@ -111,6 +186,26 @@ impl ExecutionPlanLoader {
             }
         }
 
+        if plugin.max_concurrent_requests == Some(0) {
+            return Err(format!(
+                "Plugin at index {} has 'max_concurrent_requests' of 0; omit it for no limit",
+                index
+            ).into());
+        }
+
+        if plugin.request_timeout_ms == Some(0) {
+            return Err(format!("Plugin at index {} has 'request_timeout_ms' of 0", index).into());
+        }
+
+        for dependency in &plugin.depends_on {
+            if dependency.trim().is_empty() {
+                return Err(format!("Plugin at index {} has an empty 'depends_on' entry", index).into());
+            }
+            if dependency == &plugin.name {
+                return Err(format!("Plugin '{}' lists itself in 'depends_on'", plugin.name).into());
+            }
+        }
+
         // Validate optional completed_event_name if present
         if let Some(event_name) = &plugin.completed_event_name {
             if event_name.trim().is_empty() {
@@ -124,3 +219,176 @@ impl ExecutionPlanLoader {
         Ok(())
     }
 }
+
+/// Orders plugins so that each comes after everything in its `depends_on`.
+///
+/// Plugins keep their plan order wherever their dependencies allow it. A dependency
+/// may also be a plugin the engine has already loaded, as reported by `is_loaded`.
+/// Fails if a dependency is neither, or if dependencies form a cycle.
+pub fn sort_by_dependencies<F>(plugins: Vec<PluginMetadata>, is_loaded: F) -> Result<Vec<PluginMetadata>, String>
+where
+    F: Fn(&str) -> bool,
+{
+    let in_plan: HashSet<String> = plugins.iter().map(|p| p.name.clone()).collect();
+    for plugin in &plugins {
+        if let Some(missing) = plugin.depends_on.iter().find(|d| !in_plan.contains(*d) && !is_loaded(d)) {
+            return Err(format!(
+                "Plugin '{}' depends on '{}', which is not in the execution plan",
+                plugin.name, missing
+            ));
+        }
+    }
+
+    let mut placed: HashSet<String> = HashSet::new();
+    let mut remaining = plugins;
+    let mut ordered = Vec::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        // First plugin, in plan order, whose plan dependencies are all placed
+        let ready = remaining.iter().position(|p| {
+            p.depends_on.iter().all(|d| placed.contains(d) || !in_plan.contains(d))
+        });
+        let Some(index) = ready else {
+            return Err(format!("Plugin dependency cycle: {}", describe_cycle(&remaining, &placed)));
+        };
+        let plugin = remaining.remove(index);
+        placed.insert(plugin.name.clone());
+        ordered.push(plugin);
+    }
+
+    Ok(ordered)
+}
+
+/// Follows unplaced dependencies from the first remaining plugin until a name
+/// repeats, returning the loop as `a -> b -> a`.
+fn describe_cycle(remaining: &[PluginMetadata], placed: &HashSet<String>) -> String {
+    let find = |name: &str| remaining.iter().find(|p| p.name == name);
+    let mut path: Vec<&str> = Vec::new();
+    let mut current = remaining[0].name.as_str();
+
+    while !path.contains(&current) {
+        path.push(current);
+        let next = find(current)
+            .and_then(|p| p.depends_on.iter().find(|d| !placed.contains(*d) && find(d).is_some()));
+        match next {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+
+    let start = path.iter().position(|name| *name == current).unwrap_or(0);
+    let mut cycle = path[start..].to_vec();
+    cycle.push(current);
+    cycle.join(" -> ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN_BODY: &str = r#"
+plugins = []
+
+[general]
+product_family = "Echo"
+execution_plan_version = "1.0"
+update_from = "local"
+update_path_root = "./ext_plan"
+
+[handoffs]
+handoff_events = []
+"#;
+
+    /// Writes a plan with the given top-level header lines to a temp file and loads it.
+    fn load(test_name: &str, header: &str) -> Result<PluginExecutionPlan, Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("{}-{}.toml", test_name, std::process::id()));
+        fs::write(&path, format!("{}\n{}", header, PLAN_BODY)).unwrap();
+        let result = ExecutionPlanLoader::load_from_file(&path);
+        let _ = fs::remove_file(&path);
+        result
+    }
+
+    #[test]
+    fn supported_schema_version_loads() {
+        let plan = load("plan_schema_supported", &format!("schema_version = {}", CURRENT_SCHEMA_VERSION)).unwrap();
+        assert_eq!(plan.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(plan.general.product_family, "Echo");
+    }
+
+    #[test]
+    fn newer_schema_version_is_rejected() {
+        let too_new = CURRENT_SCHEMA_VERSION + 2;
+        let err = load("plan_schema_too_new", &format!("schema_version = {}", too_new)).unwrap_err().to_string();
+        let expected = format!("Execution plan schema v{} requires a newer engine", too_new);
+        assert!(err.starts_with(&expected), "unexpected error: {}", err);
+        assert!(err.contains(&format!("supports up to v{}", CURRENT_SCHEMA_VERSION)), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn unversioned_plan_is_migrated_as_v1() {
+        let plan = load("plan_schema_legacy", "").unwrap();
+        assert_eq!(plan.schema_version, CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn invalid_schema_version_is_rejected() {
+        for header in ["schema_version = 0", "schema_version = \"1\""] {
+            let err = load("plan_schema_invalid", header).unwrap_err().to_string();
+            assert!(err.starts_with("Invalid 'schema_version'"), "{}: unexpected error: {}", header, err);
+        }
+    }
+
+    fn plugin(name: &str, depends_on: &[&str]) -> PluginMetadata {
+        toml::from_str(&format!(
+            r#"
+            name = "{name}"
+            plugin_route = "{name}"
+            version = "1.0.0"
+            plugin_location_type = "local"
+            plugin_base_path = "./"
+            team_name = "Test"
+            engineering_contact_email = "eng@example.com"
+            operation_contact_email = "ops@example.com"
+            depends_on = {depends_on:?}
+            "#
+        ))
+        .unwrap()
+    }
+
+    fn names(plugins: &[PluginMetadata]) -> Vec<&str> {
+        plugins.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn dependencies_come_first_and_plan_order_is_kept_otherwise() {
+        let plan = vec![
+            plugin("d", &["b", "c"]),
+            plugin("a", &[]),
+            plugin("b", &["a"]),
+            plugin("c", &[]),
+        ];
+        let sorted = sort_by_dependencies(plan, |_| false).unwrap();
+        assert_eq!(names(&sorted), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn already_loaded_plugins_satisfy_dependencies() {
+        let plan = vec![plugin("summary", &["plugin_login"]), plugin("a", &[])];
+        let sorted = sort_by_dependencies(plan, |name| name == "plugin_login").unwrap();
+        assert_eq!(names(&sorted), ["summary", "a"]);
+    }
+
+    #[test]
+    fn missing_dependency_is_rejected() {
+        let plan = vec![plugin("a", &[]), plugin("b", &["nowhere"])];
+        let err = sort_by_dependencies(plan, |_| false).unwrap_err();
+        assert_eq!(err, "Plugin 'b' depends on 'nowhere', which is not in the execution plan");
+    }
+
+    #[test]
+    fn dependency_cycle_is_rejected_and_named() {
+        let plan = vec![plugin("c", &["a"]), plugin("a", &["b"]), plugin("b", &["a"])];
+        let err = sort_by_dependencies(plan, |_| false).unwrap_err();
+        assert_eq!(err, "Plugin dependency cycle: a -> b -> a");
+    }
+}
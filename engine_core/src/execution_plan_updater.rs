@@ -1,17 +1,69 @@
 use crate::execution_plan::ExecutionPlanLoader;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::signature::{UnparsedPublicKey, ED25519};
 use std::{fs, io::Write, path::PathBuf, error::Error};
+use std::time::{Duration, Instant};
+
+/// Base64 Ed25519 public key that remote execution plans must be signed with.
+///
+/// Pinned into the engine at build time through the `OOBE_PLAN_PUBLIC_KEY` environment
+/// variable. Without it no remote plan can be verified, so the local plan is always used.
+pub const PINNED_PLAN_PUBLIC_KEY: Option<&str> = option_env!("OOBE_PLAN_PUBLIC_KEY");
+
+/// Suffix of the detached signature published next to each remote plan
+/// (`execution_plan.toml.sig`), holding the base64 Ed25519 signature of the plan's bytes.
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+
+/// File in the exe folder holding the last remote plan that was verified and parsed.
+/// An offline device boots with it instead of the bundled plan.
+pub const CACHED_PLAN_FILE: &str = "execution_plan.cache.toml";
 
 pub enum PlanLoadSource {
     Remote(PathBuf),
-    LocalFallback(PathBuf),
+    LocalFallback(PathBuf, FallbackReason),
 }
 
+/// Why `fetch_and_prepare_latest` used a local plan instead of a remote one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FallbackReason {
+    /// Fetching the remote plan exceeded `fetch_timeout_secs` or `update_budget_secs`.
+    TimedOut,
+    /// The remote plan was missing, unreachable, unsigned, tampered with or invalid.
+    Unavailable(String),
+}
+
+impl std::fmt::Display for FallbackReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FallbackReason::TimedOut => write!(f, "remote fetch timed out"),
+            FallbackReason::Unavailable(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Error for a fetch that ran into its timeout or the overall update budget.
+#[derive(Debug)]
+struct FetchTimedOut(String);
+
+impl std::fmt::Display for FetchTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Timed out fetching {}", self.0)
+    }
+}
+
+impl Error for FetchTimedOut {}
+
 /// Responsible for resolving and downloading updated execution plans.
 pub struct ExecutionPlanUpdater;
 
 impl ExecutionPlanUpdater {
     /// Downloads the latest execution plan if available.
     /// Returns the path to the file that should be used (either updated or original).
+    ///
+    /// A remote plan is only accepted if its detached signature verifies against
+    /// `PINNED_PLAN_PUBLIC_KEY`. Accepted plans are cached as `CACHED_PLAN_FILE`;
+    /// when no remote plan can be used, the cache is returned as the `LocalFallback`
+    /// if it still parses, and the bundled `plan_path` otherwise.
     pub fn fetch_and_prepare_latest(plan_path: &str) -> Result<PlanLoadSource, Box<dyn Error>> {
         // Step 1: Load the base plan first (always required)
         let base_plan = ExecutionPlanLoader::load_from_file(plan_path)?;
@@ -28,30 +80,94 @@ impl ExecutionPlanUpdater {
         match general.update_from.as_str() {
             "s3" => {
                 println!("Checking S3 for updated execution plan: {}", remote_path);
-                match Self::download_from_https_url(&remote_path) {
+                let fetch_timeout = Duration::from_secs(general.fetch_timeout_secs);
+                let deadline = Instant::now() + Duration::from_secs(general.update_budget_secs);
+                match Self::download_verified_plan(&remote_path, fetch_timeout, deadline) {
                     Ok(updated_path) => {
                         println!("Downloaded execution plan from: {}", updated_path.display());
                         Ok(PlanLoadSource::Remote(updated_path))
                     }
                     Err(err) => {
                         eprintln!("S3 update failed. Using local plan. Error: {}", err);
-                        Ok(PlanLoadSource::LocalFallback(PathBuf::from(plan_path)))
+                        let reason = if err.downcast_ref::<FetchTimedOut>().is_some() {
+                            FallbackReason::TimedOut
+                        } else {
+                            FallbackReason::Unavailable(err.to_string())
+                        };
+                        Ok(Self::fallback(plan_path, reason))
                     }
                 }
             }
             "local" | "unc" => {
                 println!("Using override path: {}", remote_path);
                 if PathBuf::from(&remote_path).exists() {
-                    Ok(PlanLoadSource::Remote(PathBuf::from(remote_path)))
+                    match Self::verify_local_plan(&remote_path) {
+                        Ok(()) => Ok(PlanLoadSource::Remote(PathBuf::from(remote_path))),
+                        Err(err) => {
+                            eprintln!("Override plan rejected: {}. Using fallback.", err);
+                            Ok(Self::fallback(plan_path, FallbackReason::Unavailable(err.to_string())))
+                        }
+                    }
                 } else {
                     eprintln!("Override path not found: {}. Using fallback.", remote_path);
-                    Ok(PlanLoadSource::LocalFallback(PathBuf::from(plan_path)))
+                    let reason = FallbackReason::Unavailable(format!("Override path not found: {}", remote_path));
+                    Ok(Self::fallback(plan_path, reason))
                 }
             }
             other => Err(format!("Unsupported update_from value: '{}'", other).into()),
         }
     }
 
+    /// Picks the plan to use when no remote plan is available: the last good remote
+    /// plan if it is cached and still parses, otherwise the bundled one.
+    fn fallback(plan_path: &str, reason: FallbackReason) -> PlanLoadSource {
+        if let Ok(cache_path) = Self::cached_plan_path() {
+            if cache_path.exists() {
+                match ExecutionPlanLoader::load_from_file(&cache_path) {
+                    Ok(_) => {
+                        println!("Using last good execution plan: {}", cache_path.display());
+                        return PlanLoadSource::LocalFallback(cache_path, reason);
+                    }
+                    Err(err) => eprintln!("Ignoring cached execution plan {}: {}", cache_path.display(), err),
+                }
+            }
+        }
+        PlanLoadSource::LocalFallback(PathBuf::from(plan_path), reason)
+    }
+
+    fn cached_plan_path() -> Result<PathBuf, Box<dyn Error>> {
+        let mut exe_path = std::env::current_exe()?;
+        exe_path.pop();
+        Ok(exe_path.join(CACHED_PLAN_FILE))
+    }
+
+    /// Stores a verified and parsed plan as the last good one.
+    ///
+    /// Written to a temp file next to the cache and renamed over it, so a crash mid-write
+    /// leaves the previous cache intact.
+    fn store_last_good(content: &str) -> Result<(), Box<dyn Error>> {
+        let cache_path = Self::cached_plan_path()?;
+        let tmp_path = cache_path.with_extension("toml.tmp");
+
+        let mut tmp_file = fs::File::create(&tmp_path)?;
+        tmp_file.write_all(content.as_bytes())?;
+        tmp_file.sync_all()?;
+        drop(tmp_file);
+
+        fs::rename(&tmp_path, &cache_path)?;
+        Ok(())
+    }
+
+    /// Parses a verified plan and, if it is valid, caches it as the last good plan.
+    fn accept_plan(content: &str) -> Result<(), Box<dyn Error>> {
+        ExecutionPlanLoader::load_from_str(content)
+            .map_err(|e| format!("Remote execution plan is invalid: {}", e))?;
+        if let Err(err) = Self::store_last_good(content) {
+            eprintln!("Failed to cache execution plan: {}", err);
+        }
+        Ok(())
+    }
+
     /// Constructs the full remote path to the new execution_plan.toml
     fn build_remote_path(root: &str, product: &str, version: &str) -> String {
         let mut fixed = root.trim_end_matches('/').to_string();
@@ -63,12 +179,53 @@ impl ExecutionPlanUpdater {
         fixed
     }
 
-    /// Downloads the execution plan from an HTTPS URL and copies the file to the exe location.
-    /// Returns the path to the downloaded file.
-    fn download_from_https_url(url: &str) -> Result<PathBuf, Box<dyn Error>> {
-        let response = ureq::get(url).call();
+    /// Verifies plan bytes against its detached base64 Ed25519 signature and a base64 public key.
+    pub fn verify_signature(plan: &[u8], signature_b64: &str, public_key_b64: &str) -> Result<(), Box<dyn Error>> {
+        let public_key = BASE64.decode(public_key_b64.trim())
+            .map_err(|e| format!("Invalid plan public key: {}", e))?;
+        let signature = BASE64.decode(signature_b64.trim())
+            .map_err(|e| format!("Invalid plan signature encoding: {}", e))?;
+
+        UnparsedPublicKey::new(&ED25519, &public_key)
+            .verify(plan, &signature)
+            .map_err(|_| "Execution plan signature does not match".into())
+    }
+
+    /// Verifies a plan against the pinned key.
+    fn verify_with_pinned_key(plan: &[u8], signature_b64: &str) -> Result<(), Box<dyn Error>> {
+        let public_key = PINNED_PLAN_PUBLIC_KEY
+            .ok_or("No plan signing key pinned (build with OOBE_PLAN_PUBLIC_KEY)")?;
+        Self::verify_signature(plan, signature_b64, public_key)
+    }
+
+    /// Verifies an override plan on a local or UNC path against its `.sig` file and
+    /// caches it once it parses.
+    fn verify_local_plan(path: &str) -> Result<(), Box<dyn Error>> {
+        let plan = fs::read_to_string(path)?;
+        let signature_path = format!("{}{}", path, SIGNATURE_SUFFIX);
+        let signature = fs::read_to_string(&signature_path)
+            .map_err(|e| format!("Missing plan signature {}: {}", signature_path, e))?;
+        Self::verify_with_pinned_key(plan.as_bytes(), &signature)?;
+        Self::accept_plan(&plan)
+    }
+
+    /// Fetches a text resource over HTTPS, giving up after `fetch_timeout` or at `deadline`,
+    /// whichever comes first.
+    fn fetch_https(url: &str, fetch_timeout: Duration, deadline: Instant) -> Result<String, Box<dyn Error>> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(FetchTimedOut(url.to_string()).into());
+        }
+        let timeout = fetch_timeout.min(remaining);
+
+        let started = Instant::now();
+        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        let response = agent.get(url).call();
     
         if let Err(err) = response {
+            if started.elapsed() >= timeout {
+                return Err(FetchTimedOut(url.to_string()).into());
+            }
             return Err(format!("Failed to reach URL: {}", err).into());
         }
     
@@ -77,7 +234,18 @@ impl ExecutionPlanUpdater {
             return Err(format!("HTTP GET failed with status {}", response.status()).into());
         }
     
-        let content = response.into_string()?;
+        Ok(response.into_string()?)
+    }
+
+    /// Downloads the execution plan and its signature from an HTTPS URL and, once the
+    /// signature verifies, copies the plan to the exe location.
+    /// Returns the path to the downloaded file.
+    fn download_verified_plan(url: &str, fetch_timeout: Duration, deadline: Instant) -> Result<PathBuf, Box<dyn Error>> {
+        let content = Self::fetch_https(url, fetch_timeout, deadline)?;
+        let signature = Self::fetch_https(&format!("{}{}", url, SIGNATURE_SUFFIX), fetch_timeout, deadline)?;
+        Self::verify_with_pinned_key(content.as_bytes(), &signature)?;
+        Self::accept_plan(&content)?;
+
         let tmp_path = std::env::temp_dir().join("execution_plan.override.toml");
         let mut tmp_file = fs::File::create(&tmp_path)?;
         tmp_file.write_all(content.as_bytes())?;
@@ -100,3 +268,126 @@ impl ExecutionPlanUpdater {
     }
     
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const PLAN: &str = "schema_version = 1\n\n[general]\nproduct_family = \"Echo\"\n";
+
+    /// Returns a fresh key pair's base64 public key and the base64 signature of `plan`.
+    fn sign(plan: &[u8]) -> (String, String) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let signature = key_pair.sign(plan);
+        (BASE64.encode(key_pair.public_key().as_ref()), BASE64.encode(signature.as_ref()))
+    }
+
+    #[test]
+    fn good_plan_verifies() {
+        let (public_key, signature) = sign(PLAN.as_bytes());
+        assert!(ExecutionPlanUpdater::verify_signature(PLAN.as_bytes(), &signature, &public_key).is_ok());
+        // Signature files usually end with a newline
+        let signature = format!("{}\n", signature);
+        assert!(ExecutionPlanUpdater::verify_signature(PLAN.as_bytes(), &signature, &public_key).is_ok());
+    }
+
+    #[test]
+    fn tampered_plan_is_rejected() {
+        let (public_key, signature) = sign(PLAN.as_bytes());
+        let tampered = PLAN.replace("Echo", "Evil");
+
+        let err = ExecutionPlanUpdater::verify_signature(tampered.as_bytes(), &signature, &public_key).unwrap_err();
+        assert_eq!(err.to_string(), "Execution plan signature does not match");
+    }
+
+    #[test]
+    fn plan_signed_with_another_key_is_rejected() {
+        let (_, signature) = sign(PLAN.as_bytes());
+        let (other_key, _) = sign(PLAN.as_bytes());
+        assert!(ExecutionPlanUpdater::verify_signature(PLAN.as_bytes(), &signature, &other_key).is_err());
+    }
+
+    #[test]
+    fn malformed_signature_is_rejected() {
+        let (public_key, _) = sign(PLAN.as_bytes());
+        let err = ExecutionPlanUpdater::verify_signature(PLAN.as_bytes(), "not base64!", &public_key).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid plan signature encoding"), "unexpected error: {}", err);
+    }
+
+    /// Accepts connections on a free port but never answers them, and returns its URL.
+    fn start_hung_server() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for stream in listener.incoming().flatten() {
+                held.push(stream);
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    fn assert_timed_out(result: Result<String, Box<dyn Error>>) {
+        let err = result.unwrap_err();
+        assert!(err.downcast_ref::<FetchTimedOut>().is_some(), "expected a timeout, got: {}", err);
+    }
+
+    #[test]
+    fn hung_fetch_gives_up_after_the_fetch_timeout() {
+        let url = format!("{}/execution_plan.toml", start_hung_server());
+        let started = Instant::now();
+        let far_deadline = started + Duration::from_secs(30);
+
+        assert_timed_out(ExecutionPlanUpdater::fetch_https(&url, Duration::from_millis(200), far_deadline));
+        assert!(started.elapsed() < Duration::from_secs(2), "fetch took {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn hung_fetch_gives_up_at_the_budget_deadline() {
+        let url = format!("{}/execution_plan.toml", start_hung_server());
+        let started = Instant::now();
+
+        assert_timed_out(ExecutionPlanUpdater::fetch_https(&url, Duration::from_secs(30), started + Duration::from_millis(200)));
+        assert!(started.elapsed() < Duration::from_secs(2), "fetch took {:?}", started.elapsed());
+
+        // Once the budget is spent, later fetches are not even attempted
+        assert_timed_out(ExecutionPlanUpdater::fetch_https(&url, Duration::from_secs(30), started));
+    }
+
+    #[test]
+    fn hung_remote_plan_falls_back_within_the_budget() {
+        let plan = format!(
+            r#"
+plugins = []
+
+[general]
+product_family = "Echo"
+execution_plan_version = "1.0"
+update_from = "s3"
+update_path_root = "{}"
+fetch_timeout_secs = 1
+update_budget_secs = 1
+
+[handoffs]
+handoff_events = []
+"#,
+            start_hung_server()
+        );
+        let plan_path = std::env::temp_dir().join(format!("plan_hung_remote-{}.toml", std::process::id()));
+        fs::write(&plan_path, plan).unwrap();
+
+        let started = Instant::now();
+        let source = ExecutionPlanUpdater::fetch_and_prepare_latest(plan_path.to_str().unwrap());
+        let elapsed = started.elapsed();
+        let _ = fs::remove_file(&plan_path);
+
+        assert!(elapsed < Duration::from_secs(3), "fallback took {:?}", elapsed);
+        match source.unwrap() {
+            PlanLoadSource::LocalFallback(_, reason) => assert_eq!(reason, FallbackReason::TimedOut),
+            PlanLoadSource::Remote(path) => panic!("hung server produced a plan: {}", path.display()),
+        }
+    }
+}
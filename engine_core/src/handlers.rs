@@ -1,18 +1,82 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, RawQuery, State},
 };
 use axum::body::Bytes;
 use axum::response::IntoResponse;
-use http::{Method, HeaderMap, StatusCode, HeaderValue};
+use axum::Json;
+use http::{Method, HeaderMap, HeaderName, StatusCode, HeaderValue};
 use std::sync::Arc;
-use crate::PluginRegistry;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use crate::{PluginBinding, PluginRegistry};
 
 use std::ffi::{CString, CStr};
-use plugin_core::{ApiRequest, HttpMethod};
+use plugin_core::{ApiRequest, ApiResponse, HttpMethod};
+use liblogger_macros::{initialize_logger_attributes, request_context};
 
+// Helpers used by the logging attributes below
+initialize_logger_attributes!();
+
+/// Default upper bound on request bodies forwarded to plugins (1 MiB).
+///
+/// Plugins can raise or lower this through `max_body_bytes` in the execution plan.
+/// Note that axum's own `DefaultBodyLimit` (2 MiB) still applies in front of this check.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Default time the engine waits for a plugin's `handle_request` before answering 504.
+///
+/// Plugins can change this through `request_timeout_ms` in the execution plan.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A slot in a plugin's in-flight count, given back when dropped.
+struct InFlightSlot(Arc<AtomicUsize>);
+
+impl InFlightSlot {
+    /// Takes a slot, or returns `None` if `limit` requests are already in flight.
+    fn acquire(counter: &Arc<AtomicUsize>, limit: Option<usize>) -> Option<Self> {
+        counter
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                limit.is_none_or(|limit| n < limit).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(counter.clone()))
+    }
+}
+
+impl Drop for InFlightSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Response pointer handed back from the blocking thread that called the plugin.
+///
+/// Only one side ever touches it: the handler if it is still waiting, otherwise the
+/// blocking thread, which returns it to the plugin's `cleanup` itself.
+struct PluginResponse(*mut ApiResponse);
+
+unsafe impl Send for PluginResponse {}
+
+/// Forwards `/api/<plugin_route>/<resource>` to the plugin registered for the route.
+///
+/// Logs the call with the request's `X-Request-Id`; the header itself is passed on
+/// to the plugin with the others.
+///
+/// `handle_request` runs on a blocking thread. If it takes longer than the plugin's
+/// timeout the client gets 504 and the thread is left to finish on its own: its
+/// in-flight slot stays taken until then, and the late `ApiResponse` goes straight
+/// to the plugin's `cleanup`, so the plugin must keep it valid until that call.
+///
+/// That cleanup calls into the plugin, so it only works while the plugin's library is
+/// still mapped. The blocking thread holds a clone of the binding, and with it the
+/// binding's `library`, so unregistering or reloading the plugin meanwhile does not
+/// unmap the code (see the safety constraints on the engine's `reload_plugin`). A binding
+/// built without a `library` has to be kept loaded by whoever created it.
+#[request_context]
 pub async fn dispatch_plugin_api(
     State(registry): State<Arc<PluginRegistry>>,
     Path((plugin_route, resource_path)): Path<(String, String)>,
+    RawQuery(query): RawQuery,
     method: Method,
     headers: HeaderMap,
     body: Bytes,
@@ -35,6 +99,7 @@ pub async fn dispatch_plugin_api(
         "POST" => HttpMethod::Post,
         "PUT" => HttpMethod::Put,
         "DELETE" => HttpMethod::Delete,
+        "PATCH" => HttpMethod::Patch,
         _ => return (StatusCode::METHOD_NOT_ALLOWED, "Unsupported method").into_response(),
     };
 
@@ -48,71 +113,150 @@ pub async fn dispatch_plugin_api(
 
     let supported = unsafe { std::slice::from_raw_parts(ptr, count) };
 
-    // Search for matching resource
-    let Some(resource) = supported.iter().find(|r| {
-        let cstr = unsafe { CStr::from_ptr(r.path) };
-        let plugin_path = cstr.to_string_lossy();
-        println!("Comparing resource: '{}' == '{}'", plugin_path, resource_path);
-        plugin_path == resource_path
-    }) else {
+    // Search for matching resource. A declared resource matches the path itself or
+    // any sub-path below it (e.g. "user" handles "user/123/sessions"); the longest
+    // declared match wins.
+    let Some(resource) = supported
+        .iter()
+        .filter(|r| {
+            let cstr = unsafe { CStr::from_ptr(r.path) };
+            let plugin_path = cstr.to_string_lossy();
+            println!("Comparing resource: '{}' == '{}'", plugin_path, resource_path);
+            resource_matches(&plugin_path, &resource_path)
+        })
+        .max_by_key(|r| unsafe { CStr::from_ptr(r.path) }.to_bytes().len())
+    else {
         println!("Resource '{}' not found in plugin '{}'", resource_path,  binding.name);
         return (StatusCode::NOT_FOUND, "Resource not found").into_response();
     };
 
-    // Check method support
-    let method_supported = {
-        let mut i = 0;
-        loop {
-            let m = unsafe { *resource.supported_methods.add(i) };
-            if m == method_enum {
-                break true;
-            } else if m == HttpMethod::Get && method_enum != HttpMethod::Get {
-                i += 1;
-            } else {
-                break false;
-            }
-        }
-    };
-
-    if !method_supported {
+    // Check method support against the full list the plugin declared
+    if !resource.methods().contains(&method_enum) {
         return (StatusCode::METHOD_NOT_ALLOWED, "Method not allowed").into_response();
     }
 
-    // Convert headers
-    let headers_vec = headers
+    // Reject oversized bodies before anything is handed to the plugin
+    let max_body_bytes = binding.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+    if body.len() > max_body_bytes {
+        println!(
+            "Request body of {} bytes exceeds limit of {} for plugin '{}'",
+            body.len(), max_body_bytes, binding.name
+        );
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").into_response();
+    }
+
+    // Held until the plugin returns, even if the engine stops waiting for it first
+    let Some(slot) = InFlightSlot::acquire(&binding.in_flight, binding.max_concurrent_requests) else {
+        println!(
+            "Plugin '{}' is busy: {} requests in flight",
+            binding.name, binding.in_flight_requests()
+        );
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(http::header::RETRY_AFTER, "1")],
+            "Plugin is busy"
+        ).into_response();
+    };
+
+    // Copy everything the plugin reads into owned strings the blocking call can take along
+    let header_strings = headers
         .iter()
         .map(|(k, v)| {
-            let key = CString::new(k.as_str()).unwrap().into_raw();
-            let val = CString::new(v.to_str().unwrap_or("")).unwrap().into_raw();
-            plugin_core::ApiHeader { key, value: val }
+            (CString::new(k.as_str()).unwrap(), CString::new(v.to_str().unwrap_or("")).unwrap())
         })
         .collect::<Vec<_>>();
 
-    let path_cstr = CString::new(resource_path).unwrap();
-    let content_type_cstr = CString::new("application/json").unwrap();
-
-    let request = ApiRequest {
-        path: path_cstr.as_ptr(),
-        method: method_enum,
-        headers: headers_vec.as_ptr(),
-        header_count: headers_vec.len(),
-        content_type: content_type_cstr.as_ptr(),
-        query: std::ptr::null(),
-        body_ptr: body.as_ptr(),
-        body_len: body.len(),
+    let path_cstr = CString::new(resource_path.as_str()).unwrap();
+    // Forward the client's Content-Type so plugins can reject bodies they can't parse
+    let content_type_cstr = headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| CString::new(v).ok());
+    let query_cstr = query.and_then(|q| CString::new(q).ok());
+
+    let timeout = binding.request_timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+    let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+    let call_binding = binding.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let _slot = slot;
+        let headers_vec = header_strings
+            .iter()
+            .map(|(key, value)| plugin_core::ApiHeader { key: key.as_ptr(), value: value.as_ptr() })
+            .collect::<Vec<_>>();
+
+        let request = ApiRequest {
+            path: path_cstr.as_ptr(),
+            method: method_enum,
+            headers: headers_vec.as_ptr(),
+            header_count: headers_vec.len(),
+            content_type: content_type_cstr.as_ref().map_or(std::ptr::null(), |c| c.as_ptr()),
+            query: query_cstr.as_ref().map_or(std::ptr::null(), |q| q.as_ptr()),
+            body_ptr: body.as_ptr(),
+            body_len: body.len(),
+        };
+
+        // Nothing can catch a panic here: unwinding out of an `extern "C"` call aborts the
+        // process. `declare_plugin!` runs every handler inside `guard_handle_request`, so a
+        // panicking plugin answers 500 itself.
+        let response = PluginResponse((call_binding.handle_request)(&request));
+
+        if let Err(PluginResponse(late)) = response_tx.send(response) {
+            // The request already timed out, so nothing else will hand this back to the plugin
+            println!("Plugin '{}' returned after its request timed out", call_binding.name);
+            if !late.is_null() {
+                (call_binding.cleanup)(late);
+            }
+        }
+    });
+
+    let response_ptr = match tokio::time::timeout(timeout, response_rx).await {
+        Ok(Ok(PluginResponse(ptr))) => ptr,
+        Ok(Err(_)) => {
+            println!("Plugin '{}' call ended without a response", binding.name);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Plugin error").into_response();
+        }
+        Err(_) => {
+            println!(
+                "Plugin '{}' timed out after {:?} handling {} {}",
+                binding.name, timeout, method, resource_path
+            );
+            return (StatusCode::GATEWAY_TIMEOUT, "Plugin timed out").into_response();
+        }
     };
 
-    // Call plugin handler
-    let response_ptr = (binding.handle_request)(&request);
     if response_ptr.is_null() {
         return (StatusCode::INTERNAL_SERVER_ERROR, "Plugin error").into_response();
     }
 
     let response = unsafe { &*response_ptr };
-    let body_slice = unsafe { std::slice::from_raw_parts(response.body_ptr, response.body_len) };
-    let content_type = unsafe { CStr::from_ptr(response.content_type) }
-        .to_str()
-        .unwrap_or("application/octet-stream");
+
+    // Anything outside the valid 100..=599 range is treated as a plugin fault
+    let status = match StatusCode::from_u16(response.status) {
+        Ok(code) if code.as_u16() <= 599 => code,
+        _ => {
+            println!("Plugin '{}' returned invalid status {}", binding.name, response.status);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+
+    // 204 and 304 must not carry a body, regardless of what the plugin returned
+    let body = if status == StatusCode::NO_CONTENT || status == StatusCode::NOT_MODIFIED
+        || response.body_ptr.is_null() || response.body_len == 0
+    {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(response.body_ptr, response.body_len) }.to_vec()
+    };
+
+    let content_type = if response.content_type.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(response.content_type) }
+            .to_str()
+            .ok()
+            .map(str::to_owned)
+    };
 
     let headers = response.headers;
     let header_count = response.header_count;
@@ -124,17 +268,115 @@ pub async fn dispatch_plugin_api(
         };
 
         for h in header_slice {
-            let k = unsafe { CStr::from_ptr(h.key) }.to_str().unwrap_or("");
-            let v = unsafe { CStr::from_ptr(h.value) }.to_str().unwrap_or("");
-            axum_headers.insert(
-                k,
-                v.parse().unwrap_or_else(|_| HeaderValue::from_static("")),
-            );
+            if h.key.is_null() || h.value.is_null() {
+                continue;
+            }
+            let k = unsafe { CStr::from_ptr(h.key) }.to_bytes();
+            let v = unsafe { CStr::from_ptr(h.value) }.to_bytes();
+            // Skip headers that aren't valid HTTP rather than failing the whole response
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(k), HeaderValue::from_bytes(v)) {
+                axum_headers.append(name, value);
+            }
         }
     }
 
-    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK);
-    let body = body_slice.to_vec();
+    // Everything has been copied out, so hand the response back to the plugin to free
+    (binding.cleanup)(response_ptr);
 
-    (status, [(axum::http::header::CONTENT_TYPE, content_type)], body).into_response()
+    if !body.is_empty() {
+        let content_type = content_type.unwrap_or_else(|| "application/octet-stream".to_string());
+        if let Ok(value) = HeaderValue::from_str(&content_type) {
+            axum_headers.insert(axum::http::header::CONTENT_TYPE, value);
+        }
+    }
+
+    (status, axum_headers, body).into_response()
 }
+
+/// Returns true if `request_path` is the declared resource or a sub-path of it.
+fn resource_matches(declared: &str, request_path: &str) -> bool {
+    match request_path.strip_prefix(declared) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Aggregates the health of every registered plugin for `/api/health`.
+///
+/// Plugins without a `health_check` function are reported as "ok". The overall
+/// status is 200 when every plugin is healthy and 503 otherwise.
+pub async fn health_check(State(registry): State<Arc<PluginRegistry>>) -> impl IntoResponse {
+    let mut plugins = registry.all();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut all_ok = true;
+    let mut report = Vec::with_capacity(plugins.len());
+    for plugin in &plugins {
+        let (ok, details) = probe_plugin_health(plugin).await;
+        all_ok &= ok;
+        report.push(serde_json::json!({
+            "name": plugin.name,
+            "route": plugin.plugin_route,
+            "status": if ok { "ok" } else { "unhealthy" },
+            "in_flight": plugin.in_flight_requests(),
+            "details": details,
+        }));
+    }
+
+    let status = if all_ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let body = serde_json::json!({
+        "status": if all_ok { "ok" } else { "degraded" },
+        "plugins": report,
+    });
+
+    (status, Json(body)).into_response()
+}
+
+/// Calls a plugin's optional `health_check` and returns whether it is healthy plus
+/// the JSON it reported (or `null` if it reported nothing usable).
+///
+/// Like `dispatch_plugin_api`, the plugin runs on a blocking thread and gets the
+/// binding's request timeout; a check that doesn't answer in time is unhealthy.
+async fn probe_plugin_health(plugin: &PluginBinding) -> (bool, serde_json::Value) {
+    let Some(health_fn) = plugin.health_check else {
+        return (true, serde_json::Value::Null);
+    };
+
+    let timeout = plugin.request_timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+    let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+    let call_binding = plugin.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let response = PluginResponse(health_fn());
+        if let Err(PluginResponse(late)) = response_tx.send(response) {
+            println!("Plugin '{}' health check returned after it timed out", call_binding.name);
+            if !late.is_null() {
+                (call_binding.cleanup)(late);
+            }
+        }
+    });
+
+    let response_ptr = match tokio::time::timeout(timeout, response_rx).await {
+        Ok(Ok(PluginResponse(ptr))) if !ptr.is_null() => ptr,
+        Err(_) => {
+            println!("Plugin '{}' health check timed out after {:?}", plugin.name, timeout);
+            return (false, serde_json::Value::Null);
+        }
+        _ => {
+            println!("Plugin '{}' health check failed", plugin.name);
+            return (false, serde_json::Value::Null);
+        }
+    };
+
+    let response = unsafe { &*response_ptr };
+    let ok = (200..300).contains(&response.status);
+    let details = if response.body_ptr.is_null() || response.body_len == 0 {
+        serde_json::Value::Null
+    } else {
+        let body = unsafe { std::slice::from_raw_parts(response.body_ptr, response.body_len) };
+        serde_json::from_slice(body).unwrap_or(serde_json::Value::Null)
+    };
+
+    (plugin.cleanup)(response_ptr);
+    (ok, details)
+}
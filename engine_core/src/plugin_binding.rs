@@ -1,3 +1,9 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use libloading::Library;
+
 use plugin_core::{ApiRequest, ApiResponse, Resource, PluginContext};
 
 /// Represents a loaded plugin and the engine's active binding to it.
@@ -54,6 +60,36 @@ pub struct PluginBinding {
     pub run_workflow: Option<extern "C" fn(input: *const ApiRequest) -> *mut ApiResponse>,
     pub on_progress: Option<extern "C" fn() -> *mut ApiResponse>,
     pub on_complete: Option<extern "C" fn() -> *mut ApiResponse>,
+
+    /// Optional health probe reported by `/api/health`. `None` means the plugin is always "ok".
+    pub health_check: Option<extern "C" fn() -> *mut ApiResponse>,
+
+    /// Per-plugin override for the maximum request body size, taken from
+    /// `PluginMetadata::max_body_bytes`. `None` uses the engine default.
+    pub max_body_bytes: Option<usize>,
+
+    /// Per-plugin cap on concurrent `handle_request` calls, taken from
+    /// `PluginMetadata::max_concurrent_requests`. `None` means no limit.
+    pub max_concurrent_requests: Option<usize>,
+
+    /// Per-plugin override for how long a request may take, taken from
+    /// `PluginMetadata::request_timeout_ms`. `None` uses the engine default.
+    pub request_timeout: Option<Duration>,
+
+    /// Requests currently inside the plugin. Shared by every clone of the binding.
+    pub in_flight: Arc<AtomicUsize>,
+
+    /// The library the function pointers above point into. Every clone holds it, so
+    /// the code stays mapped while any binding, including one held by a request still
+    /// running after its timeout, can call into it.
+    pub library: Option<Arc<Library>>,
+}
+
+impl PluginBinding {
+    /// Number of requests `dispatch_plugin_api` is currently running in this plugin.
+    pub fn in_flight_requests(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
 }
 
 impl Clone for PluginBinding {
@@ -69,6 +105,12 @@ impl Clone for PluginBinding {
             run_workflow: self.run_workflow,
             on_progress: self.on_progress,
             on_complete: self.on_complete,
+            health_check: self.health_check,
+            max_body_bytes: self.max_body_bytes,
+            max_concurrent_requests: self.max_concurrent_requests,
+            request_timeout: self.request_timeout,
+            in_flight: self.in_flight.clone(),
+            library: self.library.clone(),
         }
     }
 }
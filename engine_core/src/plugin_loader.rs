@@ -1,24 +1,42 @@
 use std::ffi::CStr;
 use std::path::Path;
 use std::ptr;
+use std::sync::Arc;
 
 use libloading::{Library, Symbol};
 use plugin_core::Resource;
 use crate::plugin_binding::PluginBinding;
-use plugin_core::Plugin;
+use plugin_core::{Plugin, PLUGIN_ABI_VERSION};
 
 // Updated: now stores a real slice instead of raw pointer
 static mut STATIC_RESOURCES: Option<&'static [Resource]> = None;
 
 /// Loads a plugin from a shared library file and returns a PluginBinding.
 /// This assumes the plugin exports a `create_plugin()` function.
-pub fn load_plugin<P: AsRef<Path>>(path: P) -> Result<(PluginBinding, Library), String> {
+///
+/// The binding holds a reference to the returned library, so the library is only
+/// unloaded once it and every clone of the binding have been dropped.
+pub fn load_plugin<P: AsRef<Path>>(path: P) -> Result<(PluginBinding, Arc<Library>), String> {
     unsafe {
         println!("[engine] Loading plugin from: {:?}", path.as_ref().canonicalize());
 
         // Load the shared library
-        let lib = Library::new(path.as_ref())
-            .map_err(|e| format!("Failed to load plugin: {}", e))?;
+        let lib = Arc::new(Library::new(path.as_ref())
+            .map_err(|e| format!("Failed to load plugin: {}", e))?);
+
+        // Refuse plugins built against a different `Plugin` layout before reading it
+        let abi_version: Symbol<unsafe extern "C" fn() -> u32> = lib.get(b"plugin_abi_version")
+            .map_err(|_| format!(
+                "Plugin does not export plugin_abi_version; rebuild it with declare_plugin! (engine plugin ABI v{})",
+                PLUGIN_ABI_VERSION
+            ))?;
+        let plugin_abi = abi_version();
+        if plugin_abi != PLUGIN_ABI_VERSION {
+            return Err(format!(
+                "Plugin was built for plugin ABI v{}, but this engine requires v{}; rebuild it against a matching plugin_core",
+                plugin_abi, PLUGIN_ABI_VERSION
+            ));
+        }
 
         // Load the create_plugin symbol
         let constructor: Symbol<unsafe extern "C" fn() -> *const Plugin> =
@@ -82,6 +100,12 @@ pub fn load_plugin<P: AsRef<Path>>(path: P) -> Result<(PluginBinding, Library),
             run_workflow: plugin.run_workflow,
             on_progress: plugin.on_progress,
             on_complete: plugin.on_complete,
+            health_check: plugin.health_check,
+            max_body_bytes: None,
+            max_concurrent_requests: None,
+            request_timeout: None,
+            in_flight: Default::default(),
+            library: Some(lib.clone()),
         };
 
         Ok((binding, lib))
@@ -47,6 +47,44 @@ pub struct PluginMetadata {
     /// Specify this in the toml so that the engine knows that you are done
     pub completed_event_name: Option<String>,
 
+    /// Largest request body, in bytes, the engine will forward to this plugin.
+    /// Falls back to the engine default (1 MiB) when not set.
+    #[serde(default)]
+    pub max_body_bytes: Option<usize>,
+
+    /// Most requests the engine hands to this plugin at once; further requests
+    /// get 503 until one finishes. No limit when not set.
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+
+    /// How long, in milliseconds, the engine waits for this plugin to answer a
+    /// request before returning 504. Falls back to the engine default (30 s) when not set.
+    #[serde(default)]
+    pub request_timeout_ms: Option<u64>,
+
+    /// Hex SHA-256 digest the prepared plugin binary must match.
+    #[serde(default)]
+    pub sha256: Option<String>,
+
+    /// Origins allowed to call this plugin's API cross-origin, replacing the engine's
+    /// global list. `["*"]` allows any origin.
+    #[serde(default)]
+    pub cors_allowed_origins: Option<Vec<String>>,
+
+    /// Methods a cross-origin caller may use on this plugin's API, e.g. `["GET"]`.
+    /// Defaults to GET, POST, PUT, PATCH and DELETE.
+    #[serde(default)]
+    pub cors_allowed_methods: Option<Vec<String>>,
+
+    /// Names of plugins that must be loaded before this one. Each must be in the plan
+    /// or already loaded by the engine; see `execution_plan::sort_by_dependencies`.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+
+    /// Plugin-specific settings from a `[plugins.config]` table, handed to the plugin's
+    /// `run()` as JSON in `PluginContext::config_json`.
+    #[serde(default)]
+    pub config: Option<toml::Table>,
 }
 
 /// Default value for `visible_in_ui` field (true).
@@ -59,4 +97,15 @@ impl PluginMetadata {
         use crate::plugin_utils::resolve_plugin_binary_path;
         resolve_plugin_binary_path(&self.plugin_base_path, &self.name)
     }
+
+    /// Returns the `[plugins.config]` table serialized as a JSON object, if the plan has one.
+    pub fn config_json(&self) -> Result<Option<String>, String> {
+        self.config
+            .as_ref()
+            .map(|config| {
+                serde_json::to_string(config)
+                    .map_err(|e| format!("Invalid config for plugin '{}': {}", self.name, e))
+            })
+            .transpose()
+    }
 }
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::sync::RwLock;
 
+use plugin_core::{ApiRequest, ApiResponse};
+
 use crate::plugin_binding::PluginBinding;
 
 /// Central registry holding all plugins loaded into the engine.
@@ -30,6 +32,19 @@ impl PluginRegistry {
         route_map.insert(plugin.plugin_route.clone(), plugin);
     }
 
+    /// Removes a plugin from both the name and route maps, returning its binding.
+    ///
+    /// This only forgets the binding. The caller still owns the plugin's `Library`
+    /// and must not unload it while any pointer returned by the plugin is in use.
+    pub fn unregister(&self, name: &str) -> Option<PluginBinding> {
+        let mut name_map = self.name_map.write().unwrap();
+        let mut route_map = self.route_map.write().unwrap();
+
+        let plugin = name_map.remove(name)?;
+        route_map.remove(&plugin.plugin_route);
+        Some(plugin)
+    }
+
     /// Gets a plugin by internal name.
     pub fn get(&self, name: &str) -> Option<PluginBinding> {
         let map = self.name_map.read().unwrap();
@@ -47,4 +62,24 @@ impl PluginRegistry {
         let map = self.name_map.read().unwrap();
         map.values().cloned().collect()
     }
+
+    /// Calls a registered plugin's `handle_request` in-process, without going through HTTP.
+    ///
+    /// This is how one plugin's work can synchronously trigger another's, e.g. the
+    /// engine posting to the task agent's `jobs` resource once provisioning is done.
+    /// Unlike `dispatch_plugin_api`, the plugin's declared resources, methods and body
+    /// limit are not checked, and no JWT is added, so `request` must carry whatever
+    /// headers the plugin expects.
+    ///
+    /// Returns `None` if no plugin named `plugin_name` is registered. Otherwise the
+    /// response (which may be null) is owned by that plugin: the caller copies out what
+    /// it needs, then frees it with the same plugin's `cleanup`, before the plugin can
+    /// be unregistered or reloaded.
+    ///
+    /// Panics are not caught here, since one cannot unwind out of the `extern "C"` call.
+    /// Plugins built with `declare_plugin!` guard their own handler and answer 500.
+    pub fn invoke(&self, plugin_name: &str, request: &ApiRequest) -> Option<*mut ApiResponse> {
+        let plugin = self.get(plugin_name)?;
+        Some((plugin.handle_request)(request))
+    }
 }
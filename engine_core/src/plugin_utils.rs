@@ -1,6 +1,7 @@
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::io::{Read, Write};
+use std::time::Duration;
 use crate::plugin_metadata::PluginMetadata;
 
 /// Returns the filename of the plugin based on platform (e.g., libfoo.so, foo.dll, libfoo.dylib)
@@ -47,9 +48,18 @@ fn write_to_exe_dir(filename: &str, content: &[u8]) -> Result<PathBuf, Box<dyn s
 }
 
 /// Downloads a plugin binary from an S3 HTTPS URL and stores it in the exe folder.
+/// The whole download, body included, is abandoned after `timeout`.
 /// Returns the final local path to the copied file.
-pub fn download_plugin_from_s3(url: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let response = ureq::get(url).call();
+pub fn download_plugin_from_s3(url: &str, timeout: Duration) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (filename, bytes) = fetch_plugin_from_s3(url, timeout)?;
+    write_to_exe_dir(&filename, &bytes)
+}
+
+/// Downloads a plugin binary from an S3 HTTPS URL into memory.
+/// Returns the file name taken from the URL and the downloaded bytes.
+fn fetch_plugin_from_s3(url: &str, timeout: Duration) -> Result<(String, Vec<u8>), Box<dyn std::error::Error>> {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let response = agent.get(url).call();
 
     if let Err(err) = response {
         return Err(format!("Network error: {}", err).into());
@@ -76,12 +86,19 @@ pub fn download_plugin_from_s3(url: &str) -> Result<PathBuf, Box<dyn std::error:
         .last()
         .ok_or("Invalid URL: no filename found")?;
 
-    write_to_exe_dir(filename, &bytes)
+    Ok((filename.to_string(), bytes))
 }
 
 /// Copies a plugin binary from a known source (UNC or local folder) into the exe directory.
 /// Returns the full local path where the file was copied.
 pub fn copy_plugin_to_exe_dir(source: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (filename, bytes) = read_plugin_source(source)?;
+    write_to_exe_dir(&filename, &bytes)
+}
+
+/// Reads a plugin binary from a known source (UNC or local folder) into memory.
+/// Returns the source file name and its bytes.
+fn read_plugin_source(source: &str) -> Result<(String, Vec<u8>), Box<dyn std::error::Error>> {
     let source_path = Path::new(source);
     if !source_path.exists() {
         return Err(format!("Plugin binary not found at: {}", source).into());
@@ -90,29 +107,188 @@ pub fn copy_plugin_to_exe_dir(source: &str) -> Result<PathBuf, Box<dyn std::erro
     let filename = source_path
         .file_name()
         .ok_or("Missing filename from source path")?
-        .to_string_lossy();
+        .to_string_lossy()
+        .into_owned();
 
     let bytes = fs::read(source_path)?;
-    write_to_exe_dir(&filename, &bytes)
+    Ok((filename, bytes))
 }
 
 /// Resolves and prepares the plugin binary locally before load.
 /// Handles download or copy depending on plugin_location_type.
-pub fn prepare_plugin_binary(plugin: &PluginMetadata, allow_write: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+///
+/// The binary is checked against the plan's `sha256`, if any, and refused on a mismatch.
+/// Copied or downloaded bytes are checked in memory and only written to the exe
+/// directory once they pass, so a bad fetch never replaces the last good copy.
+/// S3 downloads give up after `fetch_timeout`.
+pub fn prepare_plugin_binary(
+    plugin: &PluginMetadata,
+    allow_write: bool,
+    fetch_timeout: Duration
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
     if !allow_write {
-        return resolve_plugin_exe_path(&plugin.name);
+        let prepared_path = resolve_plugin_exe_path(&plugin.name)?;
+        verify_plugin_checksum(plugin, &prepared_path)?;
+        return Ok(prepared_path);
     }
 
+    let (filename, bytes) = fetch_plugin_binary(plugin, fetch_timeout)?;
+    verify_plugin_bytes(plugin, &bytes, &plugin.resolved_local_path())?;
+    write_to_exe_dir(&filename, &bytes)
+}
+
+/// Copies or downloads the plugin binary into memory, returning its file name and bytes.
+fn fetch_plugin_binary(plugin: &PluginMetadata, fetch_timeout: Duration) -> Result<(String, Vec<u8>), Box<dyn std::error::Error>> {
     match plugin.plugin_location_type.as_str() {
-        "local" | "unc" => {
-            let resolved_path = plugin.resolved_local_path();
-            copy_plugin_to_exe_dir(&resolved_path)
-        }
-        "s3" => {
-            let remote_url = plugin.resolved_local_path();
-            download_plugin_from_s3(&remote_url)
-        }
+        "local" | "unc" => read_plugin_source(&plugin.resolved_local_path()),
+        "s3" => fetch_plugin_from_s3(&plugin.resolved_local_path(), fetch_timeout),
         other => Err(format!("Unsupported plugin location type '{}'", other).into()),
     }
 }
 
+/// Compares the binary at `path` with the plan's `sha256` for the plugin.
+///
+/// Plugins without a `sha256` are accepted as before, with a warning.
+pub fn verify_plugin_checksum(plugin: &PluginMetadata, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    verify_plugin_bytes(plugin, &fs::read(path)?, &path.display().to_string())
+}
+
+/// Compares `bytes`, fetched from `origin`, with the plan's `sha256` for the plugin.
+fn verify_plugin_bytes(plugin: &PluginMetadata, bytes: &[u8], origin: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(expected) = &plugin.sha256 else {
+        eprintln!("[WARN] Plugin '{}' has no sha256 in the execution plan; loading it unverified", plugin.name);
+        return Ok(());
+    };
+
+    let actual = sha256_hex(bytes);
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        eprintln!(
+            "[ERROR] Checksum mismatch for plugin '{}' at {}: expected {}, actual {}",
+            plugin.name, origin, expected.trim(), actual
+        );
+        return Err(format!(
+            "Checksum mismatch for plugin '{}': expected {}, actual {}",
+            plugin.name, expected.trim(), actual
+        ).into());
+    }
+
+    Ok(())
+}
+
+/// Returns the lowercase hex SHA-256 digest of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, bytes)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const BINARY: &[u8] = b"not really a shared library";
+
+    /// Writes a fake plugin binary into its own temp folder and returns plan metadata
+    /// pointing at it, with the given `sha256` line (if any).
+    fn plugin_in_temp_dir(name: &str, sha256: Option<&str>) -> PluginMetadata {
+        let folder = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(resolve_plugin_binary_path(folder.to_str().unwrap(), name), BINARY).unwrap();
+
+        let sha256_line = sha256.map(|digest| format!("sha256 = \"{}\"", digest)).unwrap_or_default();
+        toml::from_str(&format!(
+            r#"
+            name = "{name}"
+            plugin_route = "test"
+            version = "1.0"
+            plugin_location_type = "local"
+            plugin_base_path = "{folder}"
+            team_name = "Test"
+            engineering_contact_email = "eng@example.com"
+            operation_contact_email = "ops@example.com"
+            {sha256_line}
+            "#,
+            name = name,
+            folder = folder.display(),
+            sha256_line = sha256_line,
+        ))
+        .unwrap()
+    }
+
+    fn prepare(plugin: &PluginMetadata) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let result = prepare_plugin_binary(plugin, true, Duration::from_secs(1));
+        let _ = fs::remove_dir_all(Path::new(&plugin.plugin_base_path));
+        result
+    }
+
+    #[test]
+    fn matching_digest_is_prepared() {
+        // Plans may write the digest in upper case
+        let digest = sha256_hex(BINARY).to_uppercase();
+        let plugin = plugin_in_temp_dir("checksum_match", Some(&digest));
+
+        let path = prepare(&plugin).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), BINARY);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn mismatching_digest_is_refused() {
+        let digest = sha256_hex(b"some other binary");
+        let plugin = plugin_in_temp_dir("checksum_mismatch", Some(&digest));
+
+        let err = prepare(&plugin).unwrap_err().to_string();
+        assert!(!resolve_plugin_exe_path("checksum_mismatch").unwrap().exists(), "refused binary was written");
+        assert!(err.starts_with("Checksum mismatch for plugin 'checksum_mismatch'"), "unexpected error: {}", err);
+        assert!(err.contains(&digest) && err.contains(&sha256_hex(BINARY)), "expected and actual missing: {}", err);
+    }
+
+    #[test]
+    fn mismatching_digest_keeps_the_last_good_copy() {
+        let exe_copy = resolve_plugin_exe_path("checksum_keep").unwrap();
+        fs::write(&exe_copy, b"last good copy").unwrap();
+
+        let plugin = plugin_in_temp_dir("checksum_keep", Some(&sha256_hex(b"last good copy")));
+        let result = prepare(&plugin);
+        let on_disk = fs::read(&exe_copy).unwrap();
+        fs::remove_file(&exe_copy).unwrap();
+
+        assert!(result.is_err(), "tampered binary was accepted");
+        assert_eq!(on_disk, b"last good copy");
+    }
+
+    #[test]
+    fn missing_digest_is_accepted() {
+        let plugin = plugin_in_temp_dir("checksum_absent", None);
+
+        let path = prepare(&plugin).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn hung_s3_download_gives_up_after_the_fetch_timeout() {
+        // Accepts the connection but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/plugins/libplugin_hung.so", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for stream in listener.incoming().flatten() {
+                held.push(stream);
+            }
+        });
+
+        let started = Instant::now();
+        let result = download_plugin_from_s3(&url, Duration::from_millis(200));
+        assert!(result.is_err(), "hung server produced a binary");
+        assert!(started.elapsed() < Duration::from_secs(2), "download took {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn sha256_hex_is_lowercase_hex() {
+        assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+}
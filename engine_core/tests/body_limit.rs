@@ -0,0 +1,104 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{Path, RawQuery, State};
+use axum::response::IntoResponse;
+use http::{HeaderMap, Method, StatusCode};
+
+use engine_core::handlers::DEFAULT_MAX_BODY_BYTES;
+use engine_core::{dispatch_plugin_api, PluginBinding, PluginRegistry};
+use plugin_core::resource_utils::static_resource;
+use plugin_core::response_utils::json_response;
+use plugin_core::{cleanup_response, declare_plugin, ApiRequest, ApiResponse, HttpMethod, Plugin, PluginContext, Resource};
+
+/// Requests that reached the plugin's `handle_request`.
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn run(_ctx: *const PluginContext) {}
+
+extern "C" fn get_static_content_path() -> *const c_char {
+    CString::new("upload/web").unwrap().into_raw()
+}
+
+extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
+    static METHODS: [HttpMethod; 1] = [HttpMethod::Post];
+    let slice = static_resource("blob", &METHODS);
+    unsafe { *out_len = slice.len(); }
+    slice.as_ptr()
+}
+
+fn handle_request(_req: *const ApiRequest) -> *mut ApiResponse {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+    json_response(200, r#"{"stored": true}"#)
+}
+
+extern "C" fn cleanup(resp: *mut ApiResponse) {
+    cleanup_response(resp);
+}
+
+declare_plugin! {
+    name: "plugin_upload",
+    route: "upload",
+    run: run,
+    static_content_path: get_static_content_path,
+    api_resources: get_api_resources,
+    handle_request: handle_request,
+    cleanup: cleanup,
+}
+
+fn upload_binding(route: &str, max_body_bytes: Option<usize>) -> PluginBinding {
+    let plugin: &Plugin = unsafe { &*create_plugin() };
+    PluginBinding {
+        name: format!("plugin_{}", route),
+        plugin_route: route.to_string(),
+        static_path: "upload/web".to_string(),
+        get_api_resources: plugin.get_api_resources,
+        handle_request: plugin.handle_request,
+        cleanup: plugin.cleanup,
+        run: plugin.run,
+        run_workflow: plugin.run_workflow,
+        on_progress: plugin.on_progress,
+        on_complete: plugin.on_complete,
+        health_check: plugin.health_check,
+        max_body_bytes,
+        max_concurrent_requests: None,
+        request_timeout: None,
+        in_flight: Default::default(),
+        library: None,
+    }
+}
+
+async fn post(registry: &Arc<PluginRegistry>, route: &str, len: usize) -> StatusCode {
+    dispatch_plugin_api(
+        State(registry.clone()),
+        Path((route.to_string(), "blob".to_string())),
+        RawQuery(None),
+        Method::POST,
+        HeaderMap::new(),
+        Bytes::from(vec![b'x'; len]),
+    )
+    .await
+    .into_response()
+    .status()
+}
+
+#[tokio::test]
+async fn oversized_body_is_rejected_before_the_plugin_sees_it() {
+    let registry = Arc::new(PluginRegistry::new());
+    registry.register(upload_binding("upload", None));
+    registry.register(upload_binding("small", Some(16)));
+
+    // Over the engine default
+    assert_eq!(post(&registry, "upload", DEFAULT_MAX_BODY_BYTES + 1).await, StatusCode::PAYLOAD_TOO_LARGE);
+    // Over a per-plugin limit from the execution plan
+    assert_eq!(post(&registry, "small", 17).await, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(CALLS.load(Ordering::SeqCst), 0, "handle_request ran for an oversized body");
+
+    // Bodies at the limit still go through
+    assert_eq!(post(&registry, "upload", DEFAULT_MAX_BODY_BYTES).await, StatusCode::OK);
+    assert_eq!(post(&registry, "small", 16).await, StatusCode::OK);
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+}
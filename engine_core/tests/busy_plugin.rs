@@ -0,0 +1,125 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::{Path, RawQuery, State};
+use axum::response::{IntoResponse, Response};
+use http::{HeaderMap, Method, StatusCode};
+
+use engine_core::{dispatch_plugin_api, PluginBinding, PluginRegistry};
+use plugin_core::resource_utils::static_resource;
+use plugin_core::response_utils::json_response;
+use plugin_core::{cleanup_response, declare_plugin, ApiRequest, ApiResponse, HttpMethod, Plugin, PluginContext, Resource};
+
+/// Concurrent `handle_request` calls the binding allows.
+const MAX_IN_FLIGHT: usize = 2;
+
+// A plugin that holds its slot for a while on `work/slow`
+extern "C" fn run(_ctx: *const PluginContext) {}
+
+extern "C" fn get_static_content_path() -> *const c_char {
+    CString::new("busy/web").unwrap().into_raw()
+}
+
+extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
+    static METHODS: [HttpMethod; 1] = [HttpMethod::Get];
+    let slice = static_resource("work", &METHODS);
+    unsafe { *out_len = slice.len(); }
+    slice.as_ptr()
+}
+
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+    let path = unsafe { CStr::from_ptr((*req).path) }.to_string_lossy();
+    if path == "work/slow" {
+        std::thread::sleep(Duration::from_millis(300));
+    }
+    json_response(200, r#"{"done": true}"#)
+}
+
+extern "C" fn cleanup(resp: *mut ApiResponse) {
+    cleanup_response(resp);
+}
+
+declare_plugin! {
+    name: "plugin_busy",
+    route: "busy",
+    run: run,
+    static_content_path: get_static_content_path,
+    api_resources: get_api_resources,
+    handle_request: handle_request,
+    cleanup: cleanup,
+}
+
+fn busy_binding() -> PluginBinding {
+    let plugin: &Plugin = unsafe { &*create_plugin() };
+    PluginBinding {
+        name: "plugin_busy".to_string(),
+        plugin_route: "busy".to_string(),
+        static_path: "busy/web".to_string(),
+        get_api_resources: plugin.get_api_resources,
+        handle_request: plugin.handle_request,
+        cleanup: plugin.cleanup,
+        run: plugin.run,
+        run_workflow: plugin.run_workflow,
+        on_progress: plugin.on_progress,
+        on_complete: plugin.on_complete,
+        health_check: plugin.health_check,
+        max_body_bytes: None,
+        max_concurrent_requests: Some(MAX_IN_FLIGHT),
+        request_timeout: None,
+        in_flight: Default::default(),
+        library: None,
+    }
+}
+
+async fn get(registry: Arc<PluginRegistry>, resource: &str) -> Response {
+    dispatch_plugin_api(
+        State(registry),
+        Path(("busy".to_string(), resource.to_string())),
+        RawQuery(None),
+        Method::GET,
+        HeaderMap::new(),
+        Bytes::new(),
+    )
+    .await
+    .into_response()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn saturated_plugin_answers_503_until_a_slot_frees() {
+    let registry = Arc::new(PluginRegistry::new());
+    registry.register(busy_binding());
+    let binding = registry.get("plugin_busy").unwrap();
+
+    // Fill every slot with a slow call
+    let slow_calls = (0..MAX_IN_FLIGHT)
+        .map(|_| tokio::spawn(get(registry.clone(), "work/slow")))
+        .collect::<Vec<_>>();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while binding.in_flight_requests() < MAX_IN_FLIGHT {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("slow calls never started");
+
+    let rejected = get(registry.clone(), "work").await;
+    assert_eq!(rejected.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(rejected.headers()[http::header::RETRY_AFTER], "1");
+    assert_eq!(binding.in_flight_requests(), MAX_IN_FLIGHT, "a rejected call took a slot");
+
+    for call in slow_calls {
+        assert_eq!(call.await.unwrap().status(), StatusCode::OK);
+    }
+    // Slots are given back once the blocking threads finish, just after they answer
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while binding.in_flight_requests() > 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("slots were never freed");
+    assert_eq!(get(registry.clone(), "work").await.status(), StatusCode::OK);
+}
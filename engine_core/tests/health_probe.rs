@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::body::to_bytes;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use http::StatusCode;
+
+use engine_core::handlers::health_check;
+use engine_core::{PluginBinding, PluginRegistry};
+use plugin_core::resource_utils::static_resource;
+use plugin_core::response_utils::{health_response, json_response};
+use plugin_core::{cleanup_response, ApiRequest, ApiResponse, HttpMethod, PluginContext, Resource};
+
+/// How long the hanging health check takes, well past the binding's timeout.
+const SLOW_CHECK: Duration = Duration::from_millis(500);
+
+extern "C" fn run(_ctx: *const PluginContext) {}
+
+extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
+    static METHODS: [HttpMethod; 1] = [HttpMethod::Get];
+    let slice = static_resource("work", &METHODS);
+    unsafe { *out_len = slice.len(); }
+    slice.as_ptr()
+}
+
+extern "C" fn handle_request(_req: *const ApiRequest) -> *mut ApiResponse {
+    json_response(200, "{}")
+}
+
+extern "C" fn cleanup(resp: *mut ApiResponse) {
+    cleanup_response(resp);
+}
+
+extern "C" fn healthy() -> *mut ApiResponse {
+    health_response(true, true)
+}
+
+extern "C" fn hanging() -> *mut ApiResponse {
+    std::thread::sleep(SLOW_CHECK);
+    health_response(true, true)
+}
+
+fn binding(name: &str, health_check: extern "C" fn() -> *mut ApiResponse) -> PluginBinding {
+    PluginBinding {
+        name: name.to_string(),
+        plugin_route: name.to_string(),
+        static_path: "web".to_string(),
+        get_api_resources,
+        handle_request,
+        cleanup,
+        run,
+        run_workflow: None,
+        on_progress: None,
+        on_complete: None,
+        health_check: Some(health_check),
+        max_body_bytes: None,
+        max_concurrent_requests: None,
+        request_timeout: Some(Duration::from_millis(100)),
+        in_flight: Default::default(),
+        library: None,
+    }
+}
+
+#[tokio::test]
+async fn hanging_health_check_times_out_instead_of_blocking() {
+    let registry = Arc::new(PluginRegistry::new());
+    registry.register(binding("plugin_healthy", healthy));
+    registry.register(binding("plugin_hanging", hanging));
+
+    let started = Instant::now();
+    let response = health_check(State(registry)).await.into_response();
+    assert!(started.elapsed() < SLOW_CHECK, "health endpoint waited for the plugin: {:?}", started.elapsed());
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["plugins"][0]["name"], "plugin_hanging");
+    assert_eq!(body["plugins"][0]["status"], "unhealthy");
+    assert_eq!(body["plugins"][1]["name"], "plugin_healthy");
+    assert_eq!(body["plugins"][1]["status"], "ok");
+}
+
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::body::{to_bytes, Bytes};
+use axum::extract::{Path, RawQuery, State};
+use axum::response::IntoResponse;
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+
+use engine_core::{dispatch_plugin_api, PluginBinding, PluginRegistry};
+use plugin_core::Plugin;
+
+/// Builds the binding `load_plugin` would for the real `plugin_mockwifi`.
+fn mockwifi_binding() -> PluginBinding {
+    let plugin: &Plugin = unsafe { &*plugin_mockwifi::create_plugin() };
+    PluginBinding {
+        name: "plugin_mockwifi".to_string(),
+        plugin_route: "mwifi".to_string(),
+        static_path: "mwifi/web".to_string(),
+        get_api_resources: plugin.get_api_resources,
+        handle_request: plugin.handle_request,
+        cleanup: plugin.cleanup,
+        run: plugin.run,
+        run_workflow: plugin.run_workflow,
+        on_progress: plugin.on_progress,
+        on_complete: plugin.on_complete,
+        health_check: plugin.health_check,
+        max_body_bytes: None,
+        max_concurrent_requests: None,
+        request_timeout: None,
+        in_flight: Default::default(),
+        library: None,
+    }
+}
+
+fn json_headers() -> HeaderMap {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let token = libjwt::generate_jwt("test-key", "session", now, now + 3600).unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token)).unwrap());
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers
+}
+
+async fn post(registry: &Arc<PluginRegistry>, resource: &str, body: &'static str) -> (StatusCode, String) {
+    let response = dispatch_plugin_api(
+        State(registry.clone()),
+        Path(("mwifi".to_string(), resource.to_string())),
+        RawQuery(None),
+        Method::POST,
+        json_headers(),
+        Bytes::from_static(body.as_bytes()),
+    )
+    .await
+    .into_response();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+#[tokio::test]
+async fn invalid_json_to_mockwifi_is_a_400() {
+    let registry = Arc::new(PluginRegistry::new());
+    registry.register(mockwifi_binding());
+
+    let (status, body) = post(&registry, "network", "{not json").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "body: {}", body);
+    assert!(body.contains("Invalid data"), "unexpected body: {}", body);
+
+    // A valid body goes through with the plugin's own 201
+    let valid = r#"{"ssid": "TestNet", "bssid": "00:11:22:33:44:55", "signal": -40, "channel": 6, "security": "WPA2", "frequency": 2437.0}"#;
+    let (status, body) = post(&registry, "network", valid).await;
+    assert_eq!(status, StatusCode::CREATED, "body: {}", body);
+}
@@ -0,0 +1,106 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+use axum::body::{to_bytes, Bytes};
+use axum::extract::{Path, RawQuery, State};
+use axum::response::IntoResponse;
+use http::{HeaderMap, Method, StatusCode};
+
+use engine_core::{dispatch_plugin_api, PluginBinding, PluginRegistry};
+use plugin_core::resource_utils::static_resource;
+use plugin_core::response_utils::json_response;
+use plugin_core::{cleanup_response, declare_plugin, ApiRequest, ApiResponse, HttpMethod, Plugin, PluginContext, Resource};
+
+// A plugin whose handler panics on `work/boom` and answers normally on `work`
+extern "C" fn run(_ctx: *const PluginContext) {}
+
+extern "C" fn get_static_content_path() -> *const c_char {
+    CString::new("panicky/web").unwrap().into_raw()
+}
+
+extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
+    static METHODS: [HttpMethod; 1] = [HttpMethod::Get];
+    let slice = static_resource("work", &METHODS);
+    unsafe { *out_len = slice.len(); }
+    slice.as_ptr()
+}
+
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+    let request = unsafe { &*req };
+    let path = unsafe { CStr::from_ptr(request.path) }.to_string_lossy();
+    if path == "work/boom" {
+        panic!("plugin bug");
+    }
+    json_response(200, r#"{"ok": true}"#)
+}
+
+extern "C" fn cleanup(resp: *mut ApiResponse) {
+    cleanup_response(resp);
+}
+
+declare_plugin! {
+    name: "plugin_panicky",
+    route: "panicky",
+    run: run,
+    static_content_path: get_static_content_path,
+    api_resources: get_api_resources,
+    handle_request: handle_request,
+    cleanup: cleanup,
+}
+
+/// Builds the binding `load_plugin` would, straight from this binary's `create_plugin`.
+fn panicky_binding() -> PluginBinding {
+    let plugin: &Plugin = unsafe { &*create_plugin() };
+    PluginBinding {
+        name: "plugin_panicky".to_string(),
+        plugin_route: "panicky".to_string(),
+        static_path: "panicky/web".to_string(),
+        get_api_resources: plugin.get_api_resources,
+        handle_request: plugin.handle_request,
+        cleanup: plugin.cleanup,
+        run: plugin.run,
+        run_workflow: plugin.run_workflow,
+        on_progress: plugin.on_progress,
+        on_complete: plugin.on_complete,
+        health_check: plugin.health_check,
+        max_body_bytes: None,
+        max_concurrent_requests: None,
+        request_timeout: None,
+        in_flight: Default::default(),
+        library: None,
+    }
+}
+
+async fn get(registry: &Arc<PluginRegistry>, resource: &str) -> (StatusCode, String) {
+    let response = dispatch_plugin_api(
+        State(registry.clone()),
+        Path(("panicky".to_string(), resource.to_string())),
+        RawQuery(None),
+        Method::GET,
+        HeaderMap::new(),
+        Bytes::new(),
+    )
+    .await
+    .into_response();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+#[tokio::test]
+async fn panicking_handler_answers_500_and_engine_keeps_serving() {
+    let registry = Arc::new(PluginRegistry::new());
+    registry.register(panicky_binding());
+
+    let (status, body) = get(&registry, "work/boom").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("Internal plugin error"), "unexpected body: {}", body);
+
+    // The same plugin still answers, and its in-flight slot was released
+    let (status, body) = get(&registry, "work").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, r#"{"ok": true}"#);
+    assert_eq!(registry.get("plugin_panicky").unwrap().in_flight_requests(), 0);
+}
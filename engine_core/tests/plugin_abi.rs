@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use engine_core::load_plugin;
+use plugin_core::PLUGIN_ABI_VERSION;
+
+/// Compiles `source` into a shared library and returns its path.
+///
+/// `declare_plugin!` always exports the current ABI version, so stubs reporting
+/// anything else are built here with plain `rustc`.
+fn build_stub(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("plugin_abi_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source_path = dir.join(format!("{}.rs", name));
+    fs::write(&source_path, source).unwrap();
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(rustc)
+        .args(["--crate-type", "cdylib", "--crate-name", name, "--edition", "2021", "--out-dir"])
+        .arg(&dir)
+        .arg(&source_path)
+        .output()
+        .expect("failed to run rustc");
+    assert!(output.status.success(), "stub failed to build: {}", String::from_utf8_lossy(&output.stderr));
+
+    dir.join(format!("{}{}{}", std::env::consts::DLL_PREFIX, name, std::env::consts::DLL_SUFFIX))
+}
+
+fn load_error(path: &Path) -> String {
+    let result = load_plugin(path);
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+    match result {
+        Ok(_) => panic!("{} loaded despite its ABI", path.display()),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn plugin_built_for_another_abi_version_is_refused() {
+    let stale = PLUGIN_ABI_VERSION + 1;
+    let path = build_stub(
+        "stale_abi_stub",
+        &format!("#[no_mangle]\npub extern \"C\" fn plugin_abi_version() -> u32 {{ {} }}\n", stale),
+    );
+
+    let err = load_error(&path);
+
+    assert_eq!(
+        err,
+        format!(
+            "Plugin was built for plugin ABI v{}, but this engine requires v{}; rebuild it against a matching plugin_core",
+            stale, PLUGIN_ABI_VERSION
+        )
+    );
+}
+
+#[test]
+fn plugin_without_an_abi_version_is_refused() {
+    let path = build_stub("unversioned_stub", "#[no_mangle]\npub extern \"C\" fn create_plugin() -> usize { 0 }\n");
+
+    let err = load_error(&path);
+
+    assert!(err.starts_with("Plugin does not export plugin_abi_version"), "unexpected error: {}", err);
+    assert!(err.contains(&format!("plugin ABI v{}", PLUGIN_ABI_VERSION)), "unexpected error: {}", err);
+}
@@ -0,0 +1,116 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::{Arc, OnceLock};
+
+use engine_core::{PluginBinding, PluginRegistry};
+use plugin_core::resource_utils::static_resource;
+use plugin_core::response_utils::json_response;
+use plugin_core::{cleanup_response, declare_plugin, error_response, ApiRequest, ApiResponse, HttpMethod, Plugin, PluginContext, Resource};
+
+// The registry the caller plugin reaches the target through, as the engine's would be
+static REGISTRY: OnceLock<Arc<PluginRegistry>> = OnceLock::new();
+
+extern "C" fn run(_ctx: *const PluginContext) {}
+
+extern "C" fn get_static_content_path() -> *const c_char {
+    CString::new("web").unwrap().into_raw()
+}
+
+extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
+    static METHODS: [HttpMethod; 1] = [HttpMethod::Get];
+    let slice = static_resource("echo", &METHODS);
+    unsafe { *out_len = slice.len(); }
+    slice.as_ptr()
+}
+
+extern "C" fn cleanup(resp: *mut ApiResponse) {
+    cleanup_response(resp);
+}
+
+fn request_for(path: &CStr) -> ApiRequest {
+    ApiRequest {
+        path: path.as_ptr(),
+        method: HttpMethod::Get,
+        headers: std::ptr::null(),
+        header_count: 0,
+        content_type: std::ptr::null(),
+        query: std::ptr::null(),
+        body_ptr: std::ptr::null(),
+        body_len: 0,
+    }
+}
+
+fn response_body(response: *mut ApiResponse) -> (u16, String) {
+    let response = unsafe { &*response };
+    let body = unsafe { std::slice::from_raw_parts(response.body_ptr, response.body_len) };
+    (response.status, String::from_utf8_lossy(body).into_owned())
+}
+
+// Target plugin: answers with the path it was called on
+extern "C" fn target_handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+    let path = unsafe { CStr::from_ptr((*req).path) }.to_string_lossy();
+    json_response(200, &format!(r#"{{"target_saw": "{}"}}"#, path))
+}
+
+// Caller plugin: forwards to the target in-process and wraps what it got back
+fn handle_request(_req: *const ApiRequest) -> *mut ApiResponse {
+    let registry = REGISTRY.get().unwrap();
+    let path = CString::new("echo").unwrap();
+
+    let Some(response) = registry.invoke("plugin_target", &request_for(&path)) else {
+        return error_response(502, "target not registered");
+    };
+    let (status, body) = response_body(response);
+    (registry.get("plugin_target").unwrap().cleanup)(response);
+
+    json_response(200, &format!(r#"{{"status": {}, "body": {}}}"#, status, body))
+}
+
+declare_plugin! {
+    name: "plugin_caller",
+    route: "caller",
+    run: run,
+    static_content_path: get_static_content_path,
+    api_resources: get_api_resources,
+    handle_request: handle_request,
+    cleanup: cleanup,
+}
+
+fn binding(name: &str, route: &str, handle_request: extern "C" fn(*const ApiRequest) -> *mut ApiResponse) -> PluginBinding {
+    PluginBinding {
+        name: name.to_string(),
+        plugin_route: route.to_string(),
+        static_path: "web".to_string(),
+        get_api_resources,
+        handle_request,
+        cleanup,
+        run,
+        run_workflow: None,
+        on_progress: None,
+        on_complete: None,
+        health_check: None,
+        max_body_bytes: None,
+        max_concurrent_requests: None,
+        request_timeout: None,
+        in_flight: Default::default(),
+        library: None,
+    }
+}
+
+#[test]
+fn plugin_invokes_another_registered_plugin() {
+    let registry = REGISTRY.get_or_init(|| Arc::new(PluginRegistry::new()));
+    let caller: &Plugin = unsafe { &*create_plugin() };
+    registry.register(binding("plugin_caller", "caller", caller.handle_request));
+    registry.register(binding("plugin_target", "target", target_handle_request));
+
+    let path = CString::new("echo").unwrap();
+    let response = registry.invoke("plugin_caller", &request_for(&path)).expect("caller is registered");
+    let (status, body) = response_body(response);
+    (registry.get("plugin_caller").unwrap().cleanup)(response);
+
+    assert_eq!(status, 200);
+    assert_eq!(body, r#"{"status": 200, "body": {"target_saw": "echo"}}"#);
+
+    assert!(registry.invoke("plugin_missing", &request_for(&path)).is_none());
+}
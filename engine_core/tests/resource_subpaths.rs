@@ -0,0 +1,101 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+use axum::body::{to_bytes, Bytes};
+use axum::extract::{Path, RawQuery, State};
+use axum::response::IntoResponse;
+use http::{HeaderMap, Method, StatusCode};
+
+use engine_core::{dispatch_plugin_api, PluginBinding, PluginRegistry};
+use plugin_core::resource_utils::{parse_resource_path, static_resource};
+use plugin_core::response_utils::json_response;
+use plugin_core::{cleanup_response, declare_plugin, ApiRequest, ApiResponse, HttpMethod, Plugin, PluginContext, Resource};
+
+// A plugin declaring `user` that echoes the path it was given and its segments
+extern "C" fn run(_ctx: *const PluginContext) {}
+
+extern "C" fn get_static_content_path() -> *const c_char {
+    CString::new("echo/web").unwrap().into_raw()
+}
+
+extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
+    static METHODS: [HttpMethod; 1] = [HttpMethod::Get];
+    let slice = static_resource("user", &METHODS);
+    unsafe { *out_len = slice.len(); }
+    slice.as_ptr()
+}
+
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+    let path = unsafe { CStr::from_ptr((*req).path) }.to_string_lossy();
+    let body = serde_json::json!({ "path": path, "segments": parse_resource_path(&path) });
+    json_response(200, &body.to_string())
+}
+
+extern "C" fn cleanup(resp: *mut ApiResponse) {
+    cleanup_response(resp);
+}
+
+declare_plugin! {
+    name: "plugin_echo",
+    route: "echo",
+    run: run,
+    static_content_path: get_static_content_path,
+    api_resources: get_api_resources,
+    handle_request: handle_request,
+    cleanup: cleanup,
+}
+
+fn echo_binding() -> PluginBinding {
+    let plugin: &Plugin = unsafe { &*create_plugin() };
+    PluginBinding {
+        name: "plugin_echo".to_string(),
+        plugin_route: "echo".to_string(),
+        static_path: "echo/web".to_string(),
+        get_api_resources: plugin.get_api_resources,
+        handle_request: plugin.handle_request,
+        cleanup: plugin.cleanup,
+        run: plugin.run,
+        run_workflow: plugin.run_workflow,
+        on_progress: plugin.on_progress,
+        on_complete: plugin.on_complete,
+        health_check: plugin.health_check,
+        max_body_bytes: None,
+        max_concurrent_requests: None,
+        request_timeout: None,
+        in_flight: Default::default(),
+        library: None,
+    }
+}
+
+async fn get(registry: &Arc<PluginRegistry>, resource: &str) -> (StatusCode, serde_json::Value) {
+    let response = dispatch_plugin_api(
+        State(registry.clone()),
+        Path(("echo".to_string(), resource.to_string())),
+        RawQuery(None),
+        Method::GET,
+        HeaderMap::new(),
+        Bytes::new(),
+    )
+    .await
+    .into_response();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
+#[tokio::test]
+async fn three_segment_path_reaches_the_plugin_intact() {
+    let registry = Arc::new(PluginRegistry::new());
+    registry.register(echo_binding());
+
+    let (status, body) = get(&registry, "user/123/sessions").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["path"], "user/123/sessions");
+    assert_eq!(body["segments"], serde_json::json!(["user", "123", "sessions"]));
+
+    // Shorter paths still resolve to the same resource
+    assert_eq!(get(&registry, "user").await.1["segments"], serde_json::json!(["user"]));
+    assert_eq!(get(&registry, "user/123").await.1["segments"], serde_json::json!(["user", "123"]));
+}
@@ -0,0 +1,125 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::body::{to_bytes, Bytes};
+use axum::extract::{Path, RawQuery, State};
+use axum::response::IntoResponse;
+use http::{HeaderMap, Method, StatusCode};
+
+use engine_core::{dispatch_plugin_api, PluginBinding, PluginRegistry};
+use plugin_core::resource_utils::static_resource;
+use plugin_core::response_utils::json_response;
+use plugin_core::{cleanup_response, declare_plugin, ApiRequest, ApiResponse, HttpMethod, Plugin, PluginContext, Resource};
+
+/// How long the plugin takes on `work/slow`, well past the binding's timeout.
+const SLOW_CALL: Duration = Duration::from_millis(500);
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Responses the engine has handed back to this plugin's `cleanup`.
+static CLEANED_UP: AtomicUsize = AtomicUsize::new(0);
+
+// A plugin that hangs on `work/slow` and answers at once on `work`
+extern "C" fn run(_ctx: *const PluginContext) {}
+
+extern "C" fn get_static_content_path() -> *const c_char {
+    CString::new("slow/web").unwrap().into_raw()
+}
+
+extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
+    static METHODS: [HttpMethod; 1] = [HttpMethod::Get];
+    let slice = static_resource("work", &METHODS);
+    unsafe { *out_len = slice.len(); }
+    slice.as_ptr()
+}
+
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+    let path = unsafe { CStr::from_ptr((*req).path) }.to_string_lossy();
+    if path == "work/slow" {
+        std::thread::sleep(SLOW_CALL);
+    }
+    json_response(200, r#"{"done": true}"#)
+}
+
+extern "C" fn cleanup(resp: *mut ApiResponse) {
+    CLEANED_UP.fetch_add(1, Ordering::SeqCst);
+    cleanup_response(resp);
+}
+
+declare_plugin! {
+    name: "plugin_slow",
+    route: "slow",
+    run: run,
+    static_content_path: get_static_content_path,
+    api_resources: get_api_resources,
+    handle_request: handle_request,
+    cleanup: cleanup,
+}
+
+fn slow_binding() -> PluginBinding {
+    let plugin: &Plugin = unsafe { &*create_plugin() };
+    PluginBinding {
+        name: "plugin_slow".to_string(),
+        plugin_route: "slow".to_string(),
+        static_path: "slow/web".to_string(),
+        get_api_resources: plugin.get_api_resources,
+        handle_request: plugin.handle_request,
+        cleanup: plugin.cleanup,
+        run: plugin.run,
+        run_workflow: plugin.run_workflow,
+        on_progress: plugin.on_progress,
+        on_complete: plugin.on_complete,
+        health_check: plugin.health_check,
+        max_body_bytes: None,
+        max_concurrent_requests: None,
+        request_timeout: Some(REQUEST_TIMEOUT),
+        in_flight: Default::default(),
+        library: None,
+    }
+}
+
+async fn get(registry: &Arc<PluginRegistry>, resource: &str) -> (StatusCode, String) {
+    let response = dispatch_plugin_api(
+        State(registry.clone()),
+        Path(("slow".to_string(), resource.to_string())),
+        RawQuery(None),
+        Method::GET,
+        HeaderMap::new(),
+        Bytes::new(),
+    )
+    .await
+    .into_response();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+#[tokio::test]
+async fn slow_plugin_times_out_and_its_late_response_is_cleaned_up() {
+    let registry = Arc::new(PluginRegistry::new());
+    registry.register(slow_binding());
+    let binding = registry.get("plugin_slow").unwrap();
+
+    let started = Instant::now();
+    let (status, body) = get(&registry, "work/slow").await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(body, "Plugin timed out");
+    assert!(started.elapsed() < SLOW_CALL, "engine waited for the plugin: {:?}", started.elapsed());
+
+    // The plugin is still working, so its slot stays taken, but other requests get through
+    assert_eq!(binding.in_flight_requests(), 1);
+    assert_eq!(get(&registry, "work").await, (StatusCode::OK, r#"{"done": true}"#.to_string()));
+    assert_eq!(CLEANED_UP.load(Ordering::SeqCst), 1);
+
+    // Once it returns, the late response goes to the plugin's cleanup and the slot is freed
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while CLEANED_UP.load(Ordering::SeqCst) < 2 || binding.in_flight_requests() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("late response was never cleaned up");
+}
@@ -1,4 +1,4 @@
-use engine::start_server_async;
+use engine::{ start_server_async, stop_server, EngineConfig };
 use std::{ thread, time::Duration };
 
 use tao::event::{ Event, StartCause, WindowEvent };
@@ -12,10 +12,10 @@ use wry::WebViewBuilder;
 // pub static RUST_INVOKED_EVENT: &str = "RustInvokedEvent";
 // pub static PLUGIN_TASK_AGENT_INVOKED_EVENT: &str = "PluginTaskAgentInvokedEvent";
 
-fn wait_for_server() {
+fn wait_for_server(addr: std::net::SocketAddr) {
     use std::net::TcpStream;
     for _ in 0..20 {
-        if TcpStream::connect("127.0.0.1:8080").is_ok() {
+        if TcpStream::connect(addr).is_ok() {
             return;
         }
         thread::sleep(Duration::from_millis(1000));
@@ -24,14 +24,28 @@ fn wait_for_server() {
     eprintln!("Warning: Server did not become available in time.");
 }
 
+fn wait_for_shutdown(handle: thread::JoinHandle<()>) {
+    for _ in 0..50 {
+        if handle.is_finished() {
+            let _ = handle.join();
+            return;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    eprintln!("Warning: Server did not shut down in time.");
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Start Axum plugin engine
-    thread::spawn(|| {
+    let config = EngineConfig::from_env();
+    let http_addr = config.http_addr;
+    let server_thread = thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
-        rt.block_on(start_server_async());
+        rt.block_on(start_server_async(config));
     });
 
-    wait_for_server();
+    wait_for_server(http_addr);
 
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
@@ -40,7 +54,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build(&event_loop)?;
 
     let webview = WebViewBuilder::new(&window)
-        .with_url("http://localhost:8080")?
+        .with_url(&format!("http://{}", http_addr))?
         .with_devtools(true)
         .with_initialization_script(r#"
             console.log("WebView initialized");
@@ -49,6 +63,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Wrap in Option so we can move it out cleanly
     let mut webview_opt = Some(webview);
+    let mut server_thread = Some(server_thread);
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Wait;
@@ -58,6 +73,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
                 *control_flow = ControlFlow::Exit;
 
+                // Let the engine drain requests and flush its logs before the process exits
+                stop_server();
+                if let Some(handle) = server_thread.take() {
+                    wait_for_shutdown(handle);
+                }
+
                 // Take ownership and drop explicitly
                 if let Some(wv) = webview_opt.take() {
                     drop(wv);
@@ -1,9 +1,12 @@
 use config::Config;
 use crate::SharedTokenCache;
+use crate::models::{Claims, TokenRotation};
 use crate::renewal::start_renewal_task;
+use crate::token::{validate_jwt_with_options, TokenError, ValidationOptions, DEFAULT_LEEWAY_SECONDS};
 
 pub struct JwtManager {
     pub token_cache: SharedTokenCache,
+    validation: ValidationOptions,
 }
 
 impl JwtManager {
@@ -31,12 +34,33 @@ impl JwtManager {
             "in_memory" | _ => SharedTokenCache::new(),
         };
 
+        // Clock skew tolerated when validating tokens, e.g. from plugins in other processes
+        let leeway_secs = config.get_int("jwt_validation.leeway_secs")
+            .map(|secs| secs.max(0) as u64)
+            .unwrap_or(DEFAULT_LEEWAY_SECONDS);
+        let validation = ValidationOptions { leeway_secs, ..ValidationOptions::default() };
+
         // Start the token renewal background task
         let renewal_cache = token_cache.clone();
         tokio::spawn(async move {
             start_renewal_task(renewal_cache).await;
         });
 
-        Ok(Self { token_cache })
+        Ok(Self { token_cache, validation })
+    }
+
+    /// Subscribe to tokens replaced by the renewal task, see `SharedTokenCache::subscribe_rotations`
+    pub fn subscribe_rotations(&self) -> tokio::sync::broadcast::Receiver<TokenRotation> {
+        self.token_cache.subscribe_rotations()
+    }
+
+    /// Seconds past expiry a token is still accepted by `validate_token`
+    pub fn leeway_secs(&self) -> u64 {
+        self.validation.leeway_secs
+    }
+
+    /// Validates a token with the configured leeway
+    pub fn validate_token(&self, token: &str) -> Result<Claims, TokenError> {
+        validate_jwt_with_options(token, &self.validation)
     }
 }
\ No newline at end of file
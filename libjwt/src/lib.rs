@@ -10,9 +10,9 @@ pub mod storage;
 pub mod jwt_manager;
 
 // Re-export important items for easier usage
-pub use token::{generate_jwt, validate_jwt, get_api_key_from_token};
+pub use token::{generate_jwt, generate_jwt_with_scopes, generate_service_token, validate_jwt, validate_jwt_with_options, get_api_key_from_token, ValidationOptions, FULL_ACCESS_SCOPE};
 pub use models::Claims;
-pub use models::SharedTokenCache;
+pub use models::{SharedTokenCache, TokenRotation};
 pub use routes::{create_auth_router, create_auth_router_with_cache};
 pub use test_route::create_test_router;
 pub use auth::{JwtAuth, AuthClaims, AuthErrorResponse};
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 use std::time::Instant;
 
 /// JWT Claims structure
@@ -15,6 +15,21 @@ pub struct Claims {
     pub exp: usize,
     /// Issued at time (as Unix timestamp)
     pub iat: usize,
+    /// Scopes granted to the bearer, e.g. `wifi:connect`; see `Claims::has_scope`
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl Claims {
+    /// Returns true if the token grants `scope`
+    ///
+    /// A granted `*` matches every scope and `wifi:*` matches every `wifi:` scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| match granted.strip_suffix('*') {
+            Some(prefix) => scope.starts_with(prefix),
+            None => granted == scope,
+        })
+    }
 }
 
 /// Request parameters for token generation
@@ -73,6 +88,19 @@ pub struct TokenCacheEntry {
     pub last_renewed: Instant,
 }
 
+/// Sent to rotation subscribers whenever the renewal task replaces a session's token
+#[derive(Debug, Clone)]
+pub struct TokenRotation {
+    pub cache_key: String,
+    pub api_key: String,
+    pub session_id: String,
+    /// The freshly issued token
+    pub token: String,
+}
+
+/// Rotations buffered per subscriber; slower subscribers skip to the newest ones
+const ROTATION_CHANNEL_CAPACITY: usize = 64;
+
 /// Type alias for the in-memory token cache
 type TokenCache = HashMap<String, TokenCacheEntry>;
 
@@ -81,6 +109,7 @@ type TokenCache = HashMap<String, TokenCacheEntry>;
 pub struct SharedTokenCache {
     pub memory_cache: Arc<Mutex<TokenCache>>,
     pub sqlite_storage: Option<Arc<crate::storage::SqliteSessionStorage>>,
+    rotations: broadcast::Sender<TokenRotation>,
 }
 
 impl SharedTokenCache {
@@ -89,6 +118,7 @@ impl SharedTokenCache {
         Self {
             memory_cache: Arc::new(Mutex::new(HashMap::new())),
             sqlite_storage: None,
+            rotations: broadcast::channel(ROTATION_CHANNEL_CAPACITY).0,
         }
     }
 
@@ -98,8 +128,22 @@ impl SharedTokenCache {
         Ok(Self {
             memory_cache: Arc::new(Mutex::new(HashMap::new())),
             sqlite_storage: Some(Arc::new(sqlite)),
+            rotations: broadcast::channel(ROTATION_CHANNEL_CAPACITY).0,
         })
     }
+
+    /// Subscribe to token rotations performed by the renewal task
+    ///
+    /// Holders of a long-lived credential (e.g. a WebSocket client that connected with
+    /// a session token) use this to pick up the replacement before the old token expires.
+    pub fn subscribe_rotations(&self) -> broadcast::Receiver<TokenRotation> {
+        self.rotations.subscribe()
+    }
+
+    /// Notify rotation subscribers; a no-op when nobody is listening
+    pub(crate) fn notify_rotation(&self, rotation: TokenRotation) {
+        let _ = self.rotations.send(rotation);
+    }
 }
 
 impl Default for SharedTokenCache {
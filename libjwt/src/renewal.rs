@@ -1,4 +1,4 @@
-use crate::models::SharedTokenCache;
+use crate::models::{SharedTokenCache, TokenRotation};
 use crate::token::generate_jwt;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::time;
@@ -9,7 +9,8 @@ const TOKEN_EXPIRY_SECONDS: u64 = 10;
 /// Start the token renewal background task
 ///
 /// This function spawns a background task that periodically checks for tokens
-/// that need renewal and generates fresh tokens.
+/// that need renewal and generates fresh tokens. Every replacement is announced to
+/// `SharedTokenCache::subscribe_rotations` subscribers.
 pub async fn start_renewal_task(token_cache: SharedTokenCache) {
     println!("Starting token renewal background task");
     
@@ -55,6 +56,14 @@ async fn renew_expiring_tokens(token_cache: &SharedTokenCache) {
                             eprintln!("Error updating token in SQLite during renewal: {}", e);
                         }
                     }
+
+                    // Let holders of the old token switch over
+                    token_cache.notify_rotation(TokenRotation {
+                        cache_key: cache_key.clone(),
+                        api_key: entry.api_key.clone(),
+                        session_id: entry.session_id.clone(),
+                        token: new_token,
+                    });
                 }
                 Err(e) => {
                     eprintln!("Failed to renew token: {}", e);
@@ -62,4 +71,51 @@ async fn renew_expiring_tokens(token_cache: &SharedTokenCache) {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TokenCacheEntry;
+    use crate::token::validate_jwt;
+
+    const TICK: Duration = Duration::from_secs(TOKEN_RENEWAL_INTERVAL_SECONDS);
+
+    #[tokio::test(start_paused = true)]
+    async fn renewal_announces_a_valid_replacement_token() {
+        let cache = SharedTokenCache::new();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let old_token = generate_jwt("test-key", "session-1", now - 60, now + TOKEN_EXPIRY_SECONDS).unwrap();
+        cache.memory_cache.lock().await.insert("test-key:session-1".to_string(), TokenCacheEntry {
+            api_key: "test-key".to_string(),
+            api_secret: "secret".to_string(),
+            session_id: "session-1".to_string(),
+            token: old_token.clone(),
+            created_at: Instant::now(),
+            last_renewed: Instant::now(),
+        });
+        let mut rotations = cache.subscribe_rotations();
+        tokio::spawn(start_renewal_task(cache.clone()));
+
+        // A freshly renewed token is left alone
+        time::advance(TICK).await;
+        tokio::task::yield_now().await;
+        assert!(rotations.try_recv().is_err());
+
+        // Token age is measured on the wall clock, so age the entry past half its
+        // lifetime before the next tick instead of waiting for it
+        let aged = Instant::now() - Duration::from_secs(TOKEN_EXPIRY_SECONDS);
+        cache.memory_cache.lock().await.get_mut("test-key:session-1").unwrap().last_renewed = aged;
+        time::advance(TICK).await;
+
+        let rotation = time::timeout(TICK, rotations.recv()).await.expect("no rotation").unwrap();
+        assert_eq!(rotation.cache_key, "test-key:session-1");
+        assert_eq!(rotation.session_id, "session-1");
+        assert_ne!(rotation.token, old_token);
+
+        let claims = validate_jwt(&rotation.token).unwrap();
+        assert_eq!(claims.sub, "test-key");
+        assert_eq!(claims.session_id, "session-1");
+        assert_eq!(cache.memory_cache.lock().await["test-key:session-1"].token, rotation.token);
+    }
+}
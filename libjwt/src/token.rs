@@ -1,6 +1,35 @@
 use crate::models::Claims;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lifetime of tokens minted for in-process services (the engine and its plugins)
+pub const SERVICE_TOKEN_EXPIRY_SECONDS: u64 = 24 * 60 * 60;
+
+/// Scope granted to session and service tokens; matches every scope a plugin requires
+pub const FULL_ACCESS_SCOPE: &str = "*";
+
+/// Clock skew tolerated by `validate_jwt`, in seconds
+pub const DEFAULT_LEEWAY_SECONDS: u64 = 60;
+
+/// Options for `validate_jwt_with_options`
+#[derive(Debug, Clone)]
+pub struct ValidationOptions {
+    /// How many seconds past `exp` a token is still accepted, to absorb clock skew
+    /// between the engine and plugins running in other processes
+    pub leeway_secs: u64,
+    /// Registered claims that must be present (`exp`, `nbf`, `sub`, `iss` or `aud`)
+    pub required_claims: Vec<String>,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            leeway_secs: DEFAULT_LEEWAY_SECONDS,
+            required_claims: vec!["exp".to_string()],
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct TokenError(String);
@@ -21,6 +50,9 @@ impl From<Box<dyn Error>> for TokenError {
 
 /// Generates a JWT token with the specified parameters
 ///
+/// The token is granted `FULL_ACCESS_SCOPE`, since holders of an API key and secret
+/// may use every plugin.
+///
 /// # Arguments
 ///
 /// * `api_key` - The API key to use as subject in the JWT
@@ -32,6 +64,23 @@ impl From<Box<dyn Error>> for TokenError {
 ///
 /// A Result containing the JWT token string or an error
 pub fn generate_jwt(api_key: &str, session_id: &str, iat: u64, exp: u64) -> Result<String, TokenError> {
+    generate_jwt_with_scopes(api_key, session_id, &[FULL_ACCESS_SCOPE], iat, exp)
+}
+
+/// Generates a JWT token restricted to the given scopes
+///
+/// # Arguments
+///
+/// * `api_key` - The API key to use as subject in the JWT
+/// * `session_id` - The session ID to include in the claims
+/// * `scopes` - Scopes granted to the bearer, e.g. `wifi:connect` or `wifi:*`
+/// * `iat` - Issued at timestamp
+/// * `exp` - Expiration timestamp
+///
+/// # Returns
+///
+/// A Result containing the JWT token string or an error
+pub fn generate_jwt_with_scopes(api_key: &str, session_id: &str, scopes: &[&str], iat: u64, exp: u64) -> Result<String, TokenError> {
     // Check if the supplied api_key is not empty
     if api_key.is_empty() || session_id.is_empty() {
         return Err(TokenError("API key and session ID cannot be empty".to_string()));
@@ -43,6 +92,7 @@ pub fn generate_jwt(api_key: &str, session_id: &str, iat: u64, exp: u64) -> Resu
         session_id: session_id.to_string(),
         exp: exp as usize,
         iat: iat as usize,
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
     };
     
     // Generate the JWT using a hardcoded secret for demonstration
@@ -56,6 +106,24 @@ pub fn generate_jwt(api_key: &str, session_id: &str, iat: u64, exp: u64) -> Resu
     ).map_err(|e| TokenError(e.to_string()))
 }
 
+/// Generates a token for an in-process service such as the engine or one of its plugins
+///
+/// # Arguments
+///
+/// * `service_name` - The service name to use as subject in the JWT
+///
+/// # Returns
+///
+/// A Result containing the JWT token string or an error
+pub fn generate_service_token(service_name: &str) -> Result<String, TokenError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| TokenError(e.to_string()))?
+        .as_secs();
+
+    generate_jwt(service_name, "service", now, now + SERVICE_TOKEN_EXPIRY_SECONDS)
+}
+
 /// Validates a JWT token and returns the claims
 ///
 /// # Arguments
@@ -66,12 +134,31 @@ pub fn generate_jwt(api_key: &str, session_id: &str, iat: u64, exp: u64) -> Resu
 ///
 /// A Result containing the Claims if the token is valid
 pub fn validate_jwt(token: &str) -> Result<Claims, TokenError> {
+    validate_jwt_with_options(token, &ValidationOptions::default())
+}
+
+/// Validates a JWT token with a custom expiry leeway and set of required claims
+///
+/// # Arguments
+///
+/// * `token` - The JWT token to validate
+/// * `options` - Leeway and required claims to apply
+///
+/// # Returns
+///
+/// A Result containing the Claims if the token is valid. A token expired by at most
+/// `options.leeway_secs` seconds is still valid.
+pub fn validate_jwt_with_options(token: &str, options: &ValidationOptions) -> Result<Claims, TokenError> {
     let secret = "jwt_secret_do_not_use_in_production";
-    
+
+    let mut validation = Validation::default();
+    validation.leeway = options.leeway_secs;
+    validation.set_required_spec_claims(&options.required_claims);
+
     decode::<Claims>(
         token,
         &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default()
+        &validation
     )
     .map(|token_data| token_data.claims)
     .map_err(|e| TokenError(e.to_string()))
@@ -89,4 +176,47 @@ pub fn validate_jwt(token: &str) -> Result<Claims, TokenError> {
 pub fn get_api_key_from_token(token: &str) -> Result<String, TokenError> {
     let claims = validate_jwt(token)?;
     Ok(claims.sub)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn token_expiring_at(exp: u64) -> String {
+        generate_jwt("test-key", "session", exp.saturating_sub(3600), exp).unwrap()
+    }
+
+    #[test]
+    fn token_exactly_at_expiry_is_valid() {
+        let exp = now();
+        let claims = validate_jwt(&token_expiring_at(exp)).unwrap();
+        assert_eq!(claims.exp, exp as usize);
+    }
+
+    #[test]
+    fn token_expired_within_the_leeway_is_valid() {
+        let token = token_expiring_at(now() - DEFAULT_LEEWAY_SECONDS / 2);
+        assert!(validate_jwt(&token).is_ok());
+    }
+
+    #[test]
+    fn token_expired_beyond_the_leeway_is_rejected() {
+        let token = token_expiring_at(now() - DEFAULT_LEEWAY_SECONDS * 2);
+        let err = validate_jwt(&token).unwrap_err();
+        assert!(err.to_string().contains("ExpiredSignature"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn custom_leeway_moves_the_cut_off() {
+        let token = token_expiring_at(now() - 10);
+        let strict = ValidationOptions { leeway_secs: 0, ..ValidationOptions::default() };
+        let lenient = ValidationOptions { leeway_secs: 30, ..ValidationOptions::default() };
+
+        assert!(validate_jwt_with_options(&token, &strict).is_err());
+        assert!(validate_jwt_with_options(&token, &lenient).is_ok());
+    }
+}
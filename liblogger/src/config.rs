@@ -5,6 +5,7 @@
  * - Parsing configuration from TOML files (app_config.toml)
  * - Defining the LogType enum for output destinations (Console, File, Http)
  * - Defining the LogLevel enum for severity levels (Debug, Info, Warn, Error)
+ * - Defining the LogFormat enum for line formats (Text, Json)
  * - Implementing methods for level comparison and string conversion
  * - Providing default configuration values for all settings
  * 
@@ -57,6 +58,16 @@
              LogLevel::Error => "ERROR",
          }
      }
+ 
+     /// Inverse of `level as usize`; out-of-range values map to Error
+     pub(crate) fn from_usize(value: usize) -> Self {
+         match value {
+             0 => LogLevel::Debug,
+             1 => LogLevel::Info,
+             2 => LogLevel::Warn,
+             _ => LogLevel::Error,
+         }
+     }
  }
  
  /// Supported output types for logging
@@ -86,6 +97,34 @@
      }
  }
  
+ /// Formatting applied to each log line before it reaches an output
+ #[derive(Debug, Clone, Default, PartialEq, Serialize)]
+ pub enum LogFormat {
+     /// `timestamp [LEVEL] [file:line] [module] msg | ctx`
+     #[default]
+     Text,
+     /// Newline-delimited JSON objects
+     Json,
+ }
+ 
+ // Separate implementation of Deserialize to handle case-insensitive values
+ impl<'de> Deserialize<'de> for LogFormat {
+     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+     where
+         D: serde::Deserializer<'de>,
+     {
+         let s = String::deserialize(deserializer)?;
+         match s.to_lowercase().as_str() {
+             "text" => Ok(LogFormat::Text),
+             "json" => Ok(LogFormat::Json),
+             _ => Err(serde::de::Error::unknown_variant(
+                 &s,
+                 &["text", "json"],
+             )),
+         }
+     }
+ }
+ 
  static CONFIG_INSTANCE: OnceCell<LogConfig> = OnceCell::new();
  
  /// Configuration for the logger
@@ -98,6 +137,10 @@
      /// Minimum log level to record
      pub threshold: LogLevel,
      
+     /// Line format (text, json). HTTP output always uses json.
+     #[serde(default)]
+     pub format: LogFormat,
+     
      /// File path for file-based logging
      #[serde(default)]
      pub file_path: Option<String>,
@@ -118,10 +161,34 @@
      #[serde(default)]
      pub http_timeout_seconds: Option<u64>,
      
+     /// Retries for transient HTTP failures (5xx, 429, timeouts) with exponential backoff (default: 0)
+     #[serde(default)]
+     pub http_max_retries: Option<u32>,
+     
+     /// File that receives HTTP log records still undelivered after all retries
+     #[serde(default)]
+     pub http_fallback_file: Option<String>,
+     
+     /// Maximum number of log records per HTTP request (default: 1, no batching)
+     #[serde(default)]
+     pub http_batch_size: Option<usize>,
+     
+     /// Maximum time in milliseconds a partial HTTP batch waits before being sent (default: 1000)
+     #[serde(default)]
+     pub http_batch_interval_ms: Option<u64>,
+     
      /// Whether to use async logging (default: true)
      #[serde(default = "default_async_logging")]
      pub async_logging: bool,
      
+     /// Capacity of the async logging channel (default: 100)
+     /// 
+     /// Larger values absorb bursts at the cost of memory held by queued messages;
+     /// smaller values bound memory but overflow sooner, dropping to synchronous
+     /// writes on the calling thread and incrementing the dropped-log counter.
+     #[serde(default)]
+     pub async_queue_depth: Option<usize>,
+     
      /// Whether to force flush after every write (default: false)
      #[serde(default = "default_force_flush")]
      pub force_flush: bool,
@@ -131,6 +198,12 @@
      true
  }
  
+ /// Default capacity of the async logging channel
+ pub const DEFAULT_ASYNC_QUEUE_DEPTH: usize = 100;
+ 
+ /// Default time a partial HTTP batch waits before being sent
+ pub const DEFAULT_HTTP_BATCH_INTERVAL_MS: u64 = 1000;
+ 
  fn default_force_flush() -> bool {
      false  // Default to false for better performance
  }
@@ -140,12 +213,18 @@
          LogConfig {
              log_type: LogType::Console,
              threshold: LogLevel::Info,
+             format: LogFormat::Text,
              file_path: None,
              log_folder: None,
              max_file_size_mb: None,
              http_endpoint: None,
              http_timeout_seconds: None,
+             http_max_retries: None,
+             http_fallback_file: None,
+             http_batch_size: None,
+             http_batch_interval_ms: None,
              async_logging: true,
+             async_queue_depth: None,
              force_flush: false,
          }
      }
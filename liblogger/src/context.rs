@@ -0,0 +1,73 @@
+/*
+ * Logging context module for Rusty Logger v2
+ * 
+ * Holds per-thread key/value pairs, such as the id of the HTTP request the
+ * thread is currently handling. While a request id is set, every log line
+ * written from that thread carries it in its context.
+ */
+
+ use std::cell::RefCell;
+ use std::collections::HashMap;
+ use std::env;
+ 
+ /// Context key for the id of the request being handled on this thread
+ pub const REQUEST_ID_KEY: &str = "request_id";
+ 
+ /// Context key overriding the level `#[log_result]` logs successes at on this thread
+ pub const RESULT_SUCCESS_LEVEL_KEY: &str = "log_result_success_level";
+ 
+ /// Context key overriding the level `#[log_result]` logs errors at on this thread
+ pub const RESULT_ERROR_LEVEL_KEY: &str = "log_result_error_level";
+ 
+ /// Environment variable overriding `#[log_result]`'s success level process-wide
+ pub const RESULT_SUCCESS_LEVEL_ENV: &str = "LOG_RESULT_SUCCESS_LEVEL";
+ 
+ /// Environment variable overriding `#[log_result]`'s error level process-wide
+ pub const RESULT_ERROR_LEVEL_ENV: &str = "LOG_RESULT_ERROR_LEVEL";
+ 
+ thread_local! {
+     static CONTEXT: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+ }
+ 
+ /// Sets a context value for the current thread, or removes it when `value` is `None`
+ /// 
+ /// Returns the previous value so callers can restore it when they are done.
+ pub fn set_context_value(key: &str, value: Option<&str>) -> Option<String> {
+     CONTEXT.with(|context| {
+         let mut context = context.borrow_mut();
+         match value {
+             Some(value) => context.insert(key.to_string(), value.to_string()),
+             None => context.remove(key),
+         }
+     })
+ }
+ 
+ /// Returns the current thread's value for `key`
+ pub fn context_value(key: &str) -> Option<String> {
+     CONTEXT.with(|context| context.borrow().get(key).cloned())
+ }
+ 
+ /// Returns the level `#[log_result]` should use: the thread's context value for
+ /// `key`, else the environment variable `env_var`, else the compile-time `default`
+ /// 
+ /// Overrides are trimmed and lower-cased; empty ones are ignored.
+ pub fn result_level(key: &str, env_var: &str, default: &str) -> String {
+     context_value(key)
+         .or_else(|| env::var(env_var).ok())
+         .map(|level| level.trim().to_ascii_lowercase())
+         .filter(|level| !level.is_empty())
+         .unwrap_or_else(|| default.to_string())
+ }
+ 
+ /// Appends the thread's request id to a log line's context, unless it already names one
+ pub(crate) fn with_request_id(context: Option<String>) -> Option<String> {
+     let Some(request_id) = context_value(REQUEST_ID_KEY) else {
+         return context;
+     };
+     let tag = format!("{}={}", REQUEST_ID_KEY, request_id);
+     match context {
+         Some(ctx) if ctx.contains(&tag) => Some(ctx),
+         Some(ctx) => Some(format!("{}, {}", ctx, tag)),
+         None => Some(tag),
+     }
+ }
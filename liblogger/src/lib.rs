@@ -14,6 +14,8 @@
  mod config;
  mod outputs;
  mod logger;
+ mod context;
+ mod sampling;
  
  /// Main logger class that handles initialization and log operations
  /// 
@@ -34,6 +36,29 @@
  /// - Http: Sends logs to a remote endpoint
  pub use config::LogType;
  
+ /// Enum defining how each log line is rendered
+ /// 
+ /// - Text: `timestamp [LEVEL] [file:line] [module] msg | ctx` (default)
+ /// - Json: One JSON object per line, suitable for log aggregators
+ pub use config::LogFormat;
+ 
+ /// Per-thread context attached to every log line, e.g. the current request id
+ /// 
+ /// - set_context_value: Set or clear a value, returning the previous one
+ /// - context_value: Read a value back
+ /// - REQUEST_ID_KEY: Key under which the engine stores the `X-Request-Id` it is handling
+ /// - RESULT_*_LEVEL_KEY / RESULT_*_LEVEL_ENV: Runtime overrides for `#[log_result]` levels
+ /// - result_level: Resolves a `#[log_result]` level from those overrides
+ pub use context::{
+     set_context_value, context_value, result_level, REQUEST_ID_KEY,
+     RESULT_SUCCESS_LEVEL_KEY, RESULT_ERROR_LEVEL_KEY, RESULT_SUCCESS_LEVEL_ENV, RESULT_ERROR_LEVEL_ENV,
+ };
+ 
+ /// Percentage sampling used by `#[throttle_log(sample_pct = ...)]`
+ /// 
+ /// - sample_percent: Returns true for roughly the given percentage of calls
+ pub use sampling::sample_percent;
+ 
  /// Log a debug-level message
  /// 
  /// # Example
@@ -132,4 +157,22 @@
  pub fn shutdown_logger() -> Result<(), String> {
      Logger::shutdown()
  }
+ 
+ /// Flushes pending log messages, giving up after `timeout`
+ /// 
+ /// Unlike `shutdown_logger`, the caller chooses how long to wait. Intended for
+ /// UI threads that must not hang on exit. Returns `Err` if the timeout expires.
+ /// Calling it more than once is a no-op.
+ /// 
+ /// # Example
+ /// ```
+ /// use std::time::Duration;
+ /// 
+ /// if let Err(e) = liblogger::try_shutdown_logger(Duration::from_millis(500)) {
+ ///     eprintln!("Logger did not flush in time: {}", e);
+ /// }
+ /// ```
+ pub fn try_shutdown_logger(timeout: std::time::Duration) -> Result<(), String> {
+     Logger::shutdown_with_timeout(timeout)
+ }
  
\ No newline at end of file
@@ -13,21 +13,28 @@
  */
 
  use once_cell::sync::OnceCell;
- use std::sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}};
+ use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}};
+ use std::sync::mpsc::{self as std_mpsc, RecvTimeoutError, SyncSender};
  use std::path::Path;
+ use std::time::{Duration, Instant};
  use chrono::Utc;
  use std::io::{self, Write};
- use tokio::sync::{mpsc::{self, Sender, Receiver}, oneshot};
+ use tokio::sync::mpsc::{self, Sender, Receiver, error::TrySendError};
  use tokio::runtime::Runtime;
- use tokio::time::{timeout, Duration as TokioDuration};
+ use tokio::time::timeout_at;
  
- use crate::config::{LogConfig, LogLevel};
- use crate::outputs::{LogOutput, create_log_output, create_async_log_output, AsyncLogOutputTrait};
+ use crate::config::{LogConfig, LogFormat, LogLevel, LogType, DEFAULT_ASYNC_QUEUE_DEPTH, DEFAULT_HTTP_BATCH_INTERVAL_MS};
+ use crate::outputs::{LogPayload, LogOutput, create_log_output, create_async_log_output, AsyncLogOutputTrait};
  use crate::outputs::AsyncLogOutput;
  
  // Global logger instance
  static LOGGER_INSTANCE: OnceCell<Arc<Mutex<LoggerInner>>> = OnceCell::new();
  static RUNTIME: OnceCell<Runtime> = OnceCell::new();
+ // Set once the first shutdown has been requested
+ static SHUTDOWN_STARTED: AtomicBool = AtomicBool::new(false);
+ 
+ // Default time to wait for the async worker to drain on shutdown
+ const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
  
  // Message structure for async logging channel
  struct LogMessage {
@@ -43,22 +50,45 @@
  // Command enum for controlling the background worker
  enum LogCommand {
      Entry(LogMessage),
-     Shutdown(oneshot::Sender<()>),
+     Shutdown(SyncSender<()>),
+ }
+ 
+ /// Callback invoked with the number of newly dropped log messages
+ type DroppedLogCallback = Arc<dyn Fn(u64) + Send + Sync>;
+ 
+ // Dropped-log count waiting to be handed to the registered callback
+ struct DroppedNotice {
+     callback: DroppedLogCallback,
+     dropped: u64,
+ }
+ 
+ impl DroppedNotice {
+     fn deliver(self) {
+         (self.callback)(self.dropped);
+     }
  }
  
  struct LoggerInner {
      initialized: bool,
      config: Option<LogConfig>,
      output: Option<Box<dyn LogOutput>>,
+     /// Line format used when rendering messages for the output
+     format: LogFormat,
+     /// Active threshold (`LogLevel as usize`), can be changed at runtime via `Logger::set_level`
+     threshold: AtomicUsize,
      // Channel sender for async logging
      async_sender: Option<Sender<LogCommand>>,
      /// Flag to indicate if asynchronous logging is enabled
      /// When false, all logging operations will be synchronous
      async_enabled: bool,
-     /// Counter for messages dropped due to channel backpressure
+     /// Messages dropped due to channel backpressure since the last report
      dropped_logs: AtomicU64,
+     /// Messages dropped due to channel backpressure since initialization, never reset
+     total_dropped_logs: AtomicU64,
      /// Counter to track when to report dropped logs
      log_counter: AtomicU64,
+     /// Optional sink for dropped-log counts, replaces the in-stream warning
+     dropped_callback: Option<DroppedLogCallback>,
  }
  
  impl LoggerInner {
@@ -68,10 +98,14 @@
              initialized: false,
              config: None,
              output: None,
+             format: LogFormat::Text,
+             threshold: AtomicUsize::new(LogLevel::Info as usize),
              async_sender: None,
              async_enabled: false,
              dropped_logs: AtomicU64::new(0),
+             total_dropped_logs: AtomicU64::new(0),
              log_counter: AtomicU64::new(0),
+             dropped_callback: None,
          }
      }
  
@@ -82,6 +116,7 @@
          // Create the appropriate log output based on configuration
          let output = create_log_output(&config.log_type)?;
          self.output = Some(output);
+         self.format = effective_format(&config);
          
          // Set up async logging if enabled
          if config.async_logging {
@@ -91,20 +126,24 @@
              });
              
              // Create channel for async logging with LogCommand instead of LogMessage
-             let (tx, rx) = mpsc::channel::<LogCommand>(100);
+             let depth = config.async_queue_depth.unwrap_or(DEFAULT_ASYNC_QUEUE_DEPTH).max(1);
+             let (tx, rx) = mpsc::channel::<LogCommand>(depth);
              self.async_sender = Some(tx);
              
              // Create the async output
              let async_output = create_async_log_output(&config.log_type)?;
+             let format = self.format.clone();
+             let batching = batch_settings(&config);
              
              // Spawn a task to process log messages
              runtime.spawn(async move {
-                 process_log_commands(rx, async_output).await
+                 process_log_commands(rx, async_output, format, batching).await
                      .unwrap_or_else(|e| eprintln!("Async logging failed: {}", e));
              });
          }
          
          // Store the configuration
+         self.threshold.store(config.threshold.clone() as usize, Ordering::Relaxed);
          self.config = Some(config.clone());
          self.async_enabled = config.async_logging;
          self.initialized = true;
@@ -113,12 +152,17 @@
      }
  
      /// Log a message with the configured output
-     fn log(&mut self, level: LogLevel, message: &str, context: Option<&str>, file: &str, line: u32, module: &str) {
+     /// 
+     /// Returns a pending dropped-log notification that the caller must deliver
+     /// after releasing the logger mutex.
+     fn log(&mut self, level: LogLevel, message: &str, context: Option<&str>, file: &str, line: u32, module: &str) -> Option<DroppedNotice> {
+         let mut notice = None;
+         
          // Check if we're initialized with a configuration
-         if let Some(ref config) = self.config {
+         if self.config.is_some() {
              // Skip logging if level is below threshold
-             if (level.clone() as usize) < (config.threshold.clone() as usize) {
-                 return;
+             if (level.clone() as usize) < self.threshold.load(Ordering::Relaxed) {
+                 return None;
              }
              
              // Format timestamp
@@ -127,9 +171,10 @@
              // Increment log counter
              let count = self.log_counter.fetch_add(1, Ordering::Relaxed) + 1;
              
-             // Check if we need to report dropped logs (every 100 logs)
-             if count % 100 == 0 {
-                 self.report_dropped_logs();
+             // Check if we need to report dropped logs (every log when a callback is
+             // registered, otherwise every 100 logs)
+             if self.dropped_callback.is_some() || count % 100 == 0 {
+                 notice = self.report_dropped_logs();
              }
              
              // Try async logging first if enabled
@@ -148,8 +193,9 @@
                      
                      // Send to the async channel as a LogCommand::Entry, fallback to sync if channel is full
                      if let Err(_) = sender.try_send(LogCommand::Entry(log_message)) {
-                         // Increment dropped logs counter before falling back to sync
+                         // Increment dropped logs counters before falling back to sync
                          self.dropped_logs.fetch_add(1, Ordering::Relaxed);
+                         self.total_dropped_logs.fetch_add(1, Ordering::Relaxed);
                          
                          // Channel full or closed, fallback to sync logging
                          self.log_sync(&timestamp, &level, message, context, file, line, module);
@@ -167,28 +213,42 @@
              let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
              self.log_sync(&timestamp, &level, message, context, file, line, module);
          }
+         
+         notice
      }
      
      /// Report dropped logs if any
-     fn report_dropped_logs(&mut self) {
-         let dropped = self.dropped_logs.load(Ordering::Relaxed);
-         if dropped > 0 {
-             // Reset the counter first to avoid multiple reports of the same drops
-             let actual_dropped = self.dropped_logs.swap(0, Ordering::Relaxed);
-             
-             // Log a warning about dropped messages
-             let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
-             let warning_message = format!("WARNING: {} log messages were dropped due to backpressure", actual_dropped);
-             self.log_sync(
-                 &timestamp, 
-                 &LogLevel::Warn, 
-                 &warning_message, 
-                 None,
-                 "logger.rs",
-                 0,
-                 "liblogger"
-             );
+     /// 
+     /// With a registered callback, returns the notification to deliver instead of
+     /// logging; otherwise falls back to writing a warning into the log stream.
+     fn report_dropped_logs(&mut self) -> Option<DroppedNotice> {
+         // Reset the counter first to avoid multiple reports of the same drops;
+         // the cumulative total behind `get_dropped_log_count` is left alone
+         let actual_dropped = self.dropped_logs.swap(0, Ordering::Relaxed);
+         if actual_dropped == 0 {
+             return None;
          }
+         
+         if let Some(ref callback) = self.dropped_callback {
+             return Some(DroppedNotice {
+                 callback: Arc::clone(callback),
+                 dropped: actual_dropped,
+             });
+         }
+         
+         // Log a warning about dropped messages
+         let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+         let warning_message = format!("WARNING: {} log messages were dropped due to backpressure", actual_dropped);
+         self.log_sync(
+             &timestamp, 
+             &LogLevel::Warn, 
+             &warning_message, 
+             None,
+             "logger.rs",
+             0,
+             "liblogger"
+         );
+         None
      }
  
      /// Synchronous logging fallback
@@ -196,7 +256,7 @@
                  context: Option<&str>, file: &str, line: u32, module: &str) {
          if let Some(ref mut output) = self.output {
              // Format the log message
-             let formatted_message = format_log_message(timestamp, level, message, context, file, line, module);
+             let formatted_message = format_log_message(&self.format, timestamp, level, message, context, file, line, module);
              
              // Write the log
              if let Err(e) = output.write_log(&formatted_message) {
@@ -218,25 +278,118 @@
  }
  
  // Format a log message for output
- fn format_log_message(timestamp: &str, level: &LogLevel, message: &str, 
+ #[allow(clippy::too_many_arguments)]
+ fn format_log_message(format: &LogFormat, timestamp: &str, level: &LogLevel, message: &str, 
                      context: Option<&str>, file: &str, line: u32, module: &str) -> String {
      let level_str = level.as_str();
-     match context {
-         Some(ctx) => format!("{} [{}] [{}:{}] [{}] {} | {}", 
-             timestamp, level_str, file, line, module, message, ctx),
-         None => format!("{} [{}] [{}:{}] [{}] {}",
-             timestamp, level_str, file, line, module, message),
+     match format {
+         LogFormat::Json => to_payload(timestamp, level, message, context, file, line, module).to_json_line(),
+         LogFormat::Text => match context {
+             Some(ctx) => format!("{} [{}] [{}:{}] [{}] {} | {}", 
+                 timestamp, level_str, file, line, module, message, ctx),
+             None => format!("{} [{}] [{}:{}] [{}] {}",
+                 timestamp, level_str, file, line, module, message),
+         },
+     }
+ }
+ 
+ // Resolve the line format for a configuration; HTTP output always consumes JSON
+ fn effective_format(config: &LogConfig) -> LogFormat {
+     match config.log_type {
+         LogType::Http => LogFormat::Json,
+         _ => config.format.clone(),
+     }
+ }
+ 
+ // Batching parameters for outputs that accept multiple payloads per request
+ #[derive(Clone, Copy)]
+ struct BatchSettings {
+     size: usize,
+     interval: Duration,
+ }
+ 
+ // Batching only applies to HTTP output with a batch size greater than one
+ fn batch_settings(config: &LogConfig) -> Option<BatchSettings> {
+     match (&config.log_type, config.http_batch_size) {
+         (LogType::Http, Some(size)) if size > 1 => Some(BatchSettings {
+             size,
+             interval: Duration::from_millis(
+                 config.http_batch_interval_ms.unwrap_or(DEFAULT_HTTP_BATCH_INTERVAL_MS)),
+         }),
+         _ => None,
      }
  }
  
+ // Build the structured payload for a queued message
+ fn to_payload(timestamp: &str, level: &LogLevel, message: &str,
+               context: Option<&str>, file: &str, line: u32, module: &str) -> LogPayload {
+     LogPayload {
+         ts: timestamp.to_string(),
+         level: level.as_str().to_string(),
+         file: file.to_string(),
+         line,
+         module: module.to_string(),
+         msg: message.to_string(),
+         ctx: context.map(|s| s.to_string()),
+     }
+ }
+ 
+ // Send any accumulated payloads as a single batch
+ async fn flush_batch(output: &mut AsyncLogOutput, batch: &mut Vec<LogPayload>) {
+     if batch.is_empty() {
+         return;
+     }
+     if let Err(e) = output.write_batch_async(batch).await {
+         eprintln!("Async logging error: {}", e);
+     }
+     batch.clear();
+ }
+ 
  // Async function to process log commands from the channel
- async fn process_log_commands(mut receiver: Receiver<LogCommand>, mut output: AsyncLogOutput) -> Result<(), String> {
-     while let Some(cmd) = receiver.recv().await {
+ async fn process_log_commands(mut receiver: Receiver<LogCommand>, mut output: AsyncLogOutput,
+                               format: LogFormat, batching: Option<BatchSettings>) -> Result<(), String> {
+     let mut batch: Vec<LogPayload> = Vec::new();
+     // When the current partial batch must be sent, even if not full
+     let mut batch_deadline: Option<Instant> = None;
+     
+     loop {
+         let next = match batch_deadline {
+             Some(deadline) => match timeout_at(deadline.into(), receiver.recv()).await {
+                 Ok(next) => next,
+                 Err(_) => {
+                     // Interval elapsed, send the partial batch
+                     flush_batch(&mut output, &mut batch).await;
+                     batch_deadline = None;
+                     continue;
+                 }
+             },
+             None => receiver.recv().await,
+         };
+         
+         let cmd = match next {
+             Some(cmd) => cmd,
+             None => break,
+         };
+         
          match cmd {
              LogCommand::Entry(msg) => {
+                 if let Some(settings) = batching {
+                     batch.push(to_payload(
+                         &msg.timestamp, &msg.level, &msg.message,
+                         msg.context.as_deref(), &msg.file, msg.line, &msg.module));
+                     
+                     if batch.len() >= settings.size {
+                         flush_batch(&mut output, &mut batch).await;
+                         batch_deadline = None;
+                     } else if batch_deadline.is_none() {
+                         batch_deadline = Some(Instant::now() + settings.interval);
+                     }
+                     continue;
+                 }
+                 
                  // Format the log message
                  let formatted_message = format_log_message(
-                     &msg.timestamp, &msg.level, &msg.message, 
+                     &format, &msg.timestamp, &msg.level, &msg.message, 
                      msg.context.as_deref(), &msg.file, msg.line, &msg.module);
                  
                  // Write using the async output
@@ -248,12 +401,20 @@
                  // Final log message before shutdown
                  let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
                  let message = "Logger shutdown initiated, ensuring all logs are flushed";
-                 let formatted_message = format_log_message(
-                     &timestamp, &LogLevel::Info, message, None, "logger.rs", 0, "liblogger");
                  
-                 // Final flush before shutdown
-                 if let Err(e) = output.write_log_async(&formatted_message).await {
-                     eprintln!("Error writing final log message: {}", e);
+                 if batching.is_some() {
+                     // Send the partial batch together with the final message
+                     batch.push(to_payload(
+                         &timestamp, &LogLevel::Info, message, None, "logger.rs", 0, "liblogger"));
+                     flush_batch(&mut output, &mut batch).await;
+                 } else {
+                     let formatted_message = format_log_message(
+                         &format, &timestamp, &LogLevel::Info, message, None, "logger.rs", 0, "liblogger");
+                     
+                     // Final flush before shutdown
+                     if let Err(e) = output.write_log_async(&formatted_message).await {
+                         eprintln!("Error writing final log message: {}", e);
+                     }
                  }
                  
                  // Notify that shutdown is complete
@@ -265,6 +426,9 @@
          }
      }
      
+     // Channel closed without a shutdown command; don't lose the partial batch
+     flush_batch(&mut output, &mut batch).await;
+     
      Ok(())
  }
  
@@ -307,6 +471,39 @@
          }
      }
  
+     /// Change the active threshold without re-initializing outputs or the async worker
+     pub fn set_level(level: LogLevel) {
+         let logger = LOGGER_INSTANCE.get_or_init(|| Arc::new(Mutex::new(LoggerInner::new())));
+         let logger_guard = match logger.lock() {
+             Ok(guard) => guard,
+             Err(poisoned) => poisoned.into_inner(),
+         };
+         logger_guard.threshold.store(level as usize, Ordering::Relaxed);
+     }
+ 
+     /// Register a callback receiving the number of newly dropped messages
+     /// 
+     /// Replaces the default behaviour of writing a backpressure warning into the
+     /// log stream. The callback runs on the logging thread, outside the logger lock.
+     pub fn on_dropped(callback: Box<dyn Fn(u64) + Send + Sync>) {
+         let logger = LOGGER_INSTANCE.get_or_init(|| Arc::new(Mutex::new(LoggerInner::new())));
+         let mut logger_guard = match logger.lock() {
+             Ok(guard) => guard,
+             Err(poisoned) => poisoned.into_inner(),
+         };
+         logger_guard.dropped_callback = Some(Arc::from(callback));
+     }
+ 
+     /// Get the currently active threshold
+     pub fn current_level() -> LogLevel {
+         if let Some(logger) = LOGGER_INSTANCE.get() {
+             if let Ok(logger_guard) = logger.lock() {
+                 return LogLevel::from_usize(logger_guard.threshold.load(Ordering::Relaxed));
+             }
+         }
+         LogLevel::Info
+     }
+ 
      /// Log a debug message
      pub fn debug(message: &str, context: Option<String>, file: &'static str, line: u32, module: &'static str) {
          Self::log_with_metadata(LogLevel::Debug, message, context, file, line, module)
@@ -335,11 +532,13 @@
              .unwrap_or(file);
  
          let logger = LOGGER_INSTANCE.get_or_init(|| Arc::new(Mutex::new(LoggerInner::new())));
+         // Tag the line with the request this thread is handling, if any
+         let context = crate::context::with_request_id(context);
          
          // Use a block to limit the scope of the mutex lock
-         {
+         let notice = {
              if let Ok(mut logger) = logger.lock() {
-                 logger.log(level, message, context.as_deref(), file_name, line, module);
+                 logger.log(level, message, context.as_deref(), file_name, line, module)
              } else {
                  // If the mutex is poisoned, log to stderr
                  let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
@@ -347,91 +546,114 @@
                  let log_line = format!("{} [{}] [{}:{}] [{}] {} | MUTEX POISONED\n",
                      timestamp, level_str, file_name, line, module, message);
                  let _ = io::stderr().write_all(log_line.as_bytes());
+                 None
              }
+         };
+         
+         // Deliver outside the lock so the callback may itself log
+         if let Some(notice) = notice {
+             notice.deliver();
          }
      }
  
      /// Shutdown the logger gracefully, ensuring all pending logs are written
+     /// 
+     /// Waits up to 5 seconds for the async worker to drain. See `shutdown_with_timeout`.
      pub fn shutdown() -> Result<(), String> {
-         // Try to get the runtime
-         if let Some(rt) = RUNTIME.get() {
-             // Check if we have an async logger initialized
-             if let Some(logger) = LOGGER_INSTANCE.get() {
-                 if let Ok(mut logger_guard) = logger.lock() {
-                     // Report any dropped logs before shutdown
-                     logger_guard.report_dropped_logs();
-                     
-                     if logger_guard.async_enabled {
-                         if let Some(sender) = &logger_guard.async_sender {
-                             // Create a oneshot channel for completion notification
-                             let (completion_tx, completion_rx) = oneshot::channel();
-                             
-                             // Clone sender outside of task to avoid reference issues
-                             let sender_clone = sender.clone();
-                             
-                             // Send shutdown command
-                             // Use block to release the mutex guard before the blocking operation
-                             drop(logger_guard);
-                             
-                             // Spawn a Tokio task to send the shutdown command
-                             let handle = rt.spawn(async move {
-                                 if let Err(e) = sender_clone.send(LogCommand::Shutdown(completion_tx)).await {
-                                     eprintln!("Failed to send shutdown command: {}", e);
-                                     return false;
-                                 }
-                                 
-                                 // Wait for completion with timeout
-                                 match timeout(TokioDuration::from_secs(5), completion_rx).await {
-                                     Ok(Ok(())) => {
-                                         println!("Logger shutdown completed successfully");
-                                         true
-                                     },
-                                     Ok(Err(_)) => {
-                                         eprintln!("Shutdown completion channel was closed");
-                                         false
-                                     },
-                                     Err(_) => {
-                                         eprintln!("Logger shutdown timed out after 5 seconds");
-                                         false
-                                     }
-                                 }
-                             });
-                             
-                             // Wait for the shutdown to complete
-                             match rt.block_on(handle) {
-                                 Ok(true) => return Ok(()),
-                                 Ok(false) => return Err("Logger shutdown failed".to_string()),
-                                 Err(e) => return Err(format!("Logger shutdown task panicked: {}", e)),
-                             }
-                         }
+         Self::shutdown_with_timeout(DEFAULT_SHUTDOWN_TIMEOUT)
+     }
+ 
+     /// Shutdown the logger, waiting at most `wait` for pending logs to be written
+     /// 
+     /// Never drives the Tokio runtime from the calling thread, so it is safe to call
+     /// from UI threads and from within async contexts. Returns `Err` if the worker does
+     /// not confirm completion before the timeout. Only the first call does any work;
+     /// subsequent calls are no-ops returning `Ok`.
+     pub fn shutdown_with_timeout(wait: Duration) -> Result<(), String> {
+         if SHUTDOWN_STARTED.swap(true, Ordering::SeqCst) {
+             return Ok(());
+         }
+ 
+         // No runtime means no async logging was initialized
+         if RUNTIME.get().is_none() {
+             println!("No async logger to shutdown");
+             return Ok(());
+         }
+ 
+         // Detach the async sender so later log calls go straight to the sync output
+         let (sender, notice) = match LOGGER_INSTANCE.get().map(|logger| logger.lock()) {
+             Some(Ok(mut logger_guard)) => {
+                 // Report any dropped logs before shutdown
+                 let notice = logger_guard.report_dropped_logs();
+                 logger_guard.async_enabled = false;
+                 let sender = logger_guard.async_sender.take();
+ 
+                 if sender.is_none() {
+                     if let Some(ref mut output) = logger_guard.output {
+                         // For non-async loggers, write an empty message which will trigger a flush
+                         let _ = output.write_log("");
                      }
                  }
+                 (sender, notice)
+             },
+             _ => (None, None),
+         };
+         
+         if let Some(notice) = notice {
+             notice.deliver();
+         }
+ 
+         let sender = match sender {
+             Some(sender) => sender,
+             None => {
+                 println!("Logger shutdown completed");
+                 return Ok(());
              }
-             
-             // If we can't do an async shutdown, still try to flush any file outputs
-             if let Some(logger) = LOGGER_INSTANCE.get() {
-                 if let Ok(mut guard) = logger.lock() {
-                     if let Some(ref mut output) = guard.output {
-                         // For non-async loggers, write an empty message which will trigger a flush
-                         let _ = output.write_log("");
+         };
+ 
+         let deadline = Instant::now() + wait;
+         let (completion_tx, completion_rx) = std_mpsc::sync_channel(1);
+         let mut command = LogCommand::Shutdown(completion_tx);
+ 
+         // Queue the shutdown command behind any pending entries without blocking on the runtime
+         loop {
+             match sender.try_send(command) {
+                 Ok(()) => break,
+                 Err(TrySendError::Full(returned)) => {
+                     if Instant::now() >= deadline {
+                         return Err(format!("Logger shutdown timed out after {:?} (queue full)", wait));
                      }
+                     command = returned;
+                     std::thread::sleep(Duration::from_millis(1));
+                 },
+                 Err(TrySendError::Closed(_)) => {
+                     return Err("Failed to send shutdown command: channel closed".to_string());
                  }
              }
-             
-             println!("Logger shutdown completed");
-             Ok(())
-         } else {
-             // No runtime means no async logging was initialized
-             println!("No async logger to shutdown");
-             Ok(())
+         }
+ 
+         match completion_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+             Ok(()) => {
+                 println!("Logger shutdown completed successfully");
+                 Ok(())
+             },
+             Err(RecvTimeoutError::Timeout) => {
+                 Err(format!("Logger shutdown timed out after {:?}", wait))
+             },
+             Err(RecvTimeoutError::Disconnected) => {
+                 Err("Shutdown completion channel was closed".to_string())
+             }
          }
      }
      
      /// Get the number of dropped log messages due to backpressure
+     /// 
+     /// The count is cumulative; reporting drops through `on_dropped` or the
+     /// periodic warning does not reset it.
      pub fn get_dropped_log_count() -> u64 {
          if let Some(logger) = LOGGER_INSTANCE.get() {
              if let Ok(logger_guard) = logger.lock() {
-                 return logger_guard.dropped_logs.load(Ordering::Relaxed);
+                 return logger_guard.total_dropped_logs.load(Ordering::Relaxed);
              }
          }
          0
@@ -17,7 +17,7 @@
  use std::sync::{Arc, Mutex};
  use std::time::Duration;
  use tokio::io::{AsyncWriteExt, stdout};
- use reqwest::{Client, blocking::Client as BlockingClient};
+ use reqwest::{Client, StatusCode, blocking::Client as BlockingClient};
  use serde::{Serialize, Deserialize};
  use crate::config::{LogConfig, LogType};
  use async_trait::async_trait;
@@ -215,23 +215,41 @@
      Ok((file_output, async_file_output))
  }
  
+ // Structured log record shared by the JSON line formatter and the HTTP output
  #[derive(Serialize, Deserialize)]
- struct LogPayload<'a> {
-     timestamp: &'a str,
-     level: &'a str,
-     message: &'a str,
-     file: &'a str,
-     line: u32,
-     module: &'a str,
-     #[serde(skip_serializing_if = "Option::is_none")]
-     context: Option<&'a str>,
+ pub struct LogPayload {
+     pub ts: String,
+     pub level: String,
+     pub file: String,
+     pub line: u32,
+     pub module: String,
+     pub msg: String,
+     #[serde(skip_serializing_if = "Option::is_none", default)]
+     pub ctx: Option<String>,
  }
  
+ impl LogPayload {
+     /// Serializes the payload as a single JSON line (without trailing newline)
+     pub fn to_json_line(&self) -> String {
+         serde_json::to_string(self)
+             .unwrap_or_else(|e| format!("{{\"level\":\"ERROR\",\"msg\":\"Failed to serialize log payload: {}\"}}", e))
+     }
+ }
+ 
+ // Initial delay before the first HTTP retry; doubles on each further attempt
+ const HTTP_RETRY_BASE_DELAY_MS: u64 = 100;
+ 
  // HTTP output implementation - updated to support async operations
  pub struct HttpOutput {
      blocking_client: BlockingClient,
      async_client: Client,
      endpoint: String,
+     /// Additional attempts after the first failed async send
+     max_retries: u32,
+     /// Upper bound for the backoff delay between attempts
+     max_backoff: Duration,
+     /// File receiving payloads that could not be delivered after all retries
+     fallback_file: Option<String>,
  }
  
  impl HttpOutput {
@@ -250,8 +268,70 @@
              blocking_client,
              async_client,
              endpoint: endpoint.to_string(),
+             max_retries: 0,
+             max_backoff: Duration::from_secs(timeout_seconds.max(1)),
+             fallback_file: None,
          })
      }
+ 
+     /// Retry transient failures up to `max_retries` times, then spill to `fallback_file` if set
+     pub fn with_retry_policy(mut self, max_retries: u32, fallback_file: Option<String>) -> Self {
+         self.max_retries = max_retries;
+         self.fallback_file = fallback_file;
+         self
+     }
+ 
+     // POST a JSON body, retrying 5xx, 429 and connection/timeout errors with exponential backoff
+     async fn post_with_retry<T: Serialize + ?Sized>(&self, body: &T) -> Result<(), String> {
+         let mut attempt: u32 = 0;
+         loop {
+             let (error, transient) = match self.async_client.post(&self.endpoint)
+                 .json(body)
+                 .send()
+                 .await {
+                     Ok(resp) if resp.status().is_success() => return Ok(()),
+                     Ok(resp) => {
+                         let status = resp.status();
+                         (format!("HTTP log failed with status: {}", status),
+                          status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS)
+                     },
+                     Err(e) => {
+                         let transient = e.is_timeout() || e.is_connect() || e.is_request();
+                         (format!("Failed to send HTTP log: {}", e), transient)
+                     }
+                 };
+ 
+             if !transient || attempt >= self.max_retries {
+                 return Err(error);
+             }
+ 
+             let delay = Duration::from_millis(HTTP_RETRY_BASE_DELAY_MS.saturating_mul(1u64 << attempt.min(16)))
+                 .min(self.max_backoff);
+             tokio::time::sleep(delay).await;
+             attempt += 1;
+         }
+     }
+ 
+     // Append undeliverable payloads to the fallback file as JSON lines
+     fn spill_to_fallback(&self, payloads: &[&LogPayload], error: String) -> Result<(), String> {
+         let path = match &self.fallback_file {
+             Some(path) => path,
+             None => return Err(error),
+         };
+ 
+         let mut file = OpenOptions::new()
+             .create(true)
+             .append(true)
+             .open(path)
+             .map_err(|e| format!("{}; failed to open fallback log file '{}': {}", error, path, e))?;
+ 
+         for payload in payloads {
+             writeln!(file, "{}", payload.to_json_line())
+                 .map_err(|e| format!("{}; failed to write fallback log file '{}': {}", error, path, e))?;
+         }
+ 
+         Ok(())
+     }
  }
  
  impl LogOutput for HttpOutput {
@@ -282,19 +362,38 @@
          let payload: LogPayload = serde_json::from_str(formatted_message)
              .map_err(|e| format!("Failed to parse log payload: {}", e))?;
          
-         let response = match self.async_client.post(&self.endpoint)
-             .json(&payload)
-             .send()
-             .await {
-                 Ok(resp) => resp,
-                 Err(e) => return Err(format!("Failed to send HTTP log: {}", e))
-             };
-         
-         if !response.status().is_success() {
-             return Err(format!("HTTP log failed with status: {}", response.status()));
+         match self.post_with_retry(&payload).await {
+             Ok(()) => Ok(()),
+             Err(e) => self.spill_to_fallback(&[&payload], e),
+         }
+     }
+ }
+ 
+ impl HttpOutput {
+     /// Posts several payloads as a single JSON array
+     pub async fn write_batch_async(&mut self, payloads: &[LogPayload]) -> Result<(), String> {
+         match self.post_with_retry(payloads).await {
+             Ok(()) => Ok(()),
+             Err(e) => {
+                 let refs: Vec<&LogPayload> = payloads.iter().collect();
+                 self.spill_to_fallback(&refs, e)
+             }
+         }
+     }
+ }
+ 
+ impl AsyncLogOutput {
+     /// Writes a batch of payloads; outputs without native batching write one JSON line each
+     pub async fn write_batch_async(&mut self, payloads: &[LogPayload]) -> Result<(), String> {
+         match self {
+             AsyncLogOutput::Http(output) => output.write_batch_async(payloads).await,
+             _ => {
+                 for payload in payloads {
+                     self.write_log_async(&payload.to_json_line()).await?;
+                 }
+                 Ok(())
+             }
          }
-         
-         Ok(())
      }
  }
  
@@ -391,7 +490,9 @@
              let endpoint = &config.http_endpoint.as_ref().ok_or_else(|| 
                  "HTTP endpoint not specified in configuration".to_string())?;
              let timeout = config.http_timeout_seconds.unwrap_or(30);
-             Ok(AsyncLogOutput::Http(HttpOutput::new(endpoint, timeout)?))
+             let output = HttpOutput::new(endpoint, timeout)?
+                 .with_retry_policy(config.http_max_retries.unwrap_or(0), config.http_fallback_file.clone());
+             Ok(AsyncLogOutput::Http(output))
          },
      }
  }
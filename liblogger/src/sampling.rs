@@ -0,0 +1,39 @@
+/*
+ * Log sampling module for Rusty Logger v2
+ * 
+ * Decides which calls of a hot function get logged when only a percentage of
+ * them should be, as used by `#[throttle_log(sample_pct = ...)]`.
+ */
+
+ use std::sync::atomic::{AtomicU64, Ordering};
+ use std::time::{SystemTime, UNIX_EPOCH};
+ 
+ use once_cell::sync::Lazy;
+ 
+ /// SplitMix64 state, seeded once per process from the clock and process id
+ static SAMPLE_STATE: Lazy<AtomicU64> = Lazy::new(|| {
+     let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+     AtomicU64::new(nanos ^ ((std::process::id() as u64) << 32))
+ });
+ 
+ /// Returns true for roughly `pct` percent of calls
+ /// 
+ /// `pct` of 0 or less never samples and 100 or more always does. Draws are shared
+ /// by every caller in the process, so concurrent callers still see independent outcomes.
+ pub fn sample_percent(pct: f64) -> bool {
+     if pct <= 0.0 {
+         return false;
+     }
+     if pct >= 100.0 {
+         return true;
+     }
+ 
+     let mut z = SAMPLE_STATE.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed);
+     z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+     z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+     z ^= z >> 31;
+ 
+     // Top 53 bits as a uniform value in [0, 100)
+     let draw = (z >> 11) as f64 / (1u64 << 53) as f64 * 100.0;
+     draw < pct
+ }
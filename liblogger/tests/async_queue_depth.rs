@@ -0,0 +1,15 @@
+use liblogger::{LogConfig, Logger};
+
+#[test]
+fn tiny_queue_depth_drops_under_a_burst() {
+    let config = LogConfig { async_queue_depth: Some(1), ..LogConfig::default() };
+    Logger::init_with_config(config).unwrap();
+    assert_eq!(Logger::get_dropped_log_count(), 0);
+
+    for i in 0..2000 {
+        Logger::info(&format!("burst message {}", i), None, file!(), line!(), module_path!());
+    }
+
+    // Overflowed messages fell back to synchronous writes and were counted
+    assert!(Logger::get_dropped_log_count() > 0, "a one-slot queue dropped nothing");
+}
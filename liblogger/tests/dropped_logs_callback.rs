@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use liblogger::{LogConfig, Logger};
+
+// Enough messages to overflow a one-slot queue many times over
+const BURST: usize = 2000;
+
+fn burst() {
+    for i in 0..BURST {
+        Logger::info(&format!("burst message {}", i), None, file!(), line!(), module_path!());
+    }
+}
+
+#[test]
+fn dropped_count_keeps_growing_while_a_callback_is_registered() {
+    let config = LogConfig { async_queue_depth: Some(1), ..LogConfig::default() };
+    Logger::init_with_config(config).unwrap();
+
+    let reported = Arc::new(AtomicU64::new(0));
+    let sink = reported.clone();
+    Logger::on_dropped(Box::new(move |dropped| {
+        sink.fetch_add(dropped, Ordering::SeqCst);
+    }));
+
+    burst();
+    let after_first = Logger::get_dropped_log_count();
+    assert!(after_first > 0, "a one-slot queue dropped nothing");
+    assert!(reported.load(Ordering::SeqCst) > 0, "callback never saw the drops");
+
+    // Reporting to the callback must not reset the cumulative count
+    burst();
+    let after_second = Logger::get_dropped_log_count();
+    assert!(after_second > after_first, "count went from {} to {}", after_first, after_second);
+    assert!(reported.load(Ordering::SeqCst) <= after_second);
+}
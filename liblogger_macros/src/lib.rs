@@ -18,7 +18,9 @@
  use syn::{parse_macro_input, parse_quote, ItemFn};
  
  // Import helpers from our utils module
- use crate::macro_utils::{get_fn_name, IdList, MacroArgs, define_helper_functions};
+ use crate::macro_utils::{
+     get_fn_name, log_macro_for, non_default_return, returns_result, IdList, MacroArgs, define_helper_functions
+ };
  
  /// Initialization macro that must be called at the module level to enable attribute macros
  ///
@@ -149,6 +151,109 @@
      TokenStream::from(quote!(#input_fn))
  }
  
+ /// Log entry and exit, and optionally timing and errors, in a single wrapper
+ /// 
+ /// Does the work of stacking `#[log_entry_exit]`, `#[measure_time]` and `#[log_errors]`
+ /// with one closure around the body instead of one per attribute.
+ /// 
+ /// - `timing`: adds the elapsed time to the exit log and to panic logs
+ /// - `errors`: logs an `Err` return at ERROR; the function must return a `Result`
+ /// - `level`: level for the entry and exit logs (default "info")
+ /// 
+ /// Panics are always logged at ERROR and then resumed.
+ /// 
+ /// # Example
+ /// ```ignore
+ /// #[instrument(timing, errors, level = "debug")]
+ /// fn connect(ssid: &str) -> Result<(), String> {
+ ///     // Implementation
+ /// }
+ /// ```
+ /// 
+ /// # Generated logs
+ /// - "ENTRY: connect"
+ /// - "EXIT: connect after 12 ms"
+ /// - "connect returned error: \"timeout\"" (on `Err`)
+ #[proc_macro_attribute]
+ pub fn instrument(args: TokenStream, input: TokenStream) -> TokenStream {
+     let args = parse_macro_input!(args as MacroArgs);
+     let mut input_fn = parse_macro_input!(input as ItemFn);
+     
+     for flag in &args.flags {
+         if flag != "timing" && flag != "errors" {
+             let message = format!("instrument: unknown option '{}', expected 'timing' or 'errors'", flag);
+             return syn::Error::new(proc_macro2::Span::call_site(), message).to_compile_error().into();
+         }
+     }
+     let timing = args.flags.iter().any(|f| f == "timing");
+     let errors = args.flags.iter().any(|f| f == "errors");
+     if errors && !returns_result(&input_fn) {
+         return syn::Error::new_spanned(&input_fn.sig, "instrument(errors) requires a function returning Result")
+             .to_compile_error()
+             .into();
+     }
+     
+     let fn_name = get_fn_name(&input_fn);
+     let orig_block = input_fn.block.clone();
+     let log_macro = log_macro_for(args.level.as_deref());
+     let return_type = match &input_fn.sig.output {
+         syn::ReturnType::Type(_, ty) => quote! { #ty },
+         syn::ReturnType::Default => quote! { () },
+     };
+     
+     let start = if timing {
+         quote! { let start_time = std::time::Instant::now(); }
+     } else {
+         quote! {}
+     };
+     let elapsed = if timing {
+         quote! { format!(" after {} ms", start_time.elapsed().as_millis()) }
+     } else {
+         quote! { String::new() }
+     };
+     let error_check = if errors {
+         quote! {
+             if let Err(err) = &output {
+                 liblogger::log_error!(&format!("{} returned error: {:?}", #fn_name, err), None);
+             }
+         }
+     } else {
+         quote! {}
+     };
+     
+     *input_fn.block = parse_quote!({
+         use std::panic::{catch_unwind, AssertUnwindSafe};
+         
+         #log_macro!(&format!("ENTRY: {}", #fn_name), None);
+         #start
+         
+         let result = catch_unwind(AssertUnwindSafe(|| -> #return_type #orig_block));
+         let elapsed = #elapsed;
+         
+         match result {
+             Ok(output) => {
+                 #error_check
+                 #log_macro!(&format!("EXIT: {}{}", #fn_name, elapsed), None);
+                 output
+             },
+             Err(panic_err) => {
+                 let panic_msg = if let Some(s) = panic_err.downcast_ref::<&str>() {
+                     s.to_string()
+                 } else if let Some(s) = panic_err.downcast_ref::<String>() {
+                     s.clone()
+                 } else {
+                     "Unknown panic".to_string()
+                 };
+                 
+                 liblogger::log_error!(&format!("{} panicked{}: {}", #fn_name, elapsed, panic_msg), None);
+                 std::panic::resume_unwind(panic_err);
+             }
+         }
+     });
+     
+     TokenStream::from(quote!(#input_fn))
+ }
+ 
  /// Log specified function arguments
  #[proc_macro_attribute]
  pub fn log_args(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -353,14 +458,34 @@
  }
  
  /// Throttle logs to avoid flooding during incidents
+ /// 
+ /// `rate` caps logged calls per minute (5 by default). `sample_pct` logs a random
+ /// percentage of calls instead; with both set, sampled calls are still capped by `rate`.
+ /// 
+ /// `level` ("debug", "info", "warn" or "error", default "info") is used for both the
+ /// logged calls and the skipped-count summary. `message` replaces the default
+ /// `"{fn} executed"`; `{fn}` in it is replaced with the function name.
  #[proc_macro_attribute]
  pub fn throttle_log(args: TokenStream, input: TokenStream) -> TokenStream {
      let args = parse_macro_input!(args as MacroArgs);
-     let rate = args.rate.unwrap_or(5);
+     let log_macro = log_macro_for(args.level.as_deref());
+     let sampled = match args.sample_pct {
+         Some(pct) => quote! { liblogger::sample_percent(#pct) },
+         None => quote! { true },
+     };
+     // Sampling alone has no per-minute cap; otherwise the rate defaults to 5
+     let within_rate = match (args.rate, args.sample_pct) {
+         (None, Some(_)) => quote! { true },
+         (rate, _) => {
+             let rate = rate.unwrap_or(5) as usize;
+             quote! { COUNTER.fetch_add(1, Ordering::SeqCst) < #rate }
+         }
+     };
      
      let mut input_fn = parse_macro_input!(input as ItemFn);
      let fn_name = get_fn_name(&input_fn);
      let orig_block = input_fn.block.clone();
+     let message = args.message.as_deref().unwrap_or("{fn} executed").replace("{fn}", &fn_name);
      
      input_fn.block = Box::new(parse_quote!({
          use std::sync::atomic::{AtomicUsize, Ordering};
@@ -374,7 +499,7 @@
          let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
          let current_minute = (now.as_secs() / 60) as usize;
          
-         // Check if we're in a new minute or still in the rate limit
+         // Check if we're in a new minute, then whether this call is sampled and within the rate limit
          let should_log = {
              let last_minute = LAST_MINUTE.load(Ordering::SeqCst);
              if last_minute != current_minute {
@@ -382,23 +507,20 @@
                  LAST_MINUTE.store(current_minute, Ordering::SeqCst);
                  let skipped = SKIPPED_COUNT.swap(0, Ordering::SeqCst);
                  if skipped > 0 {
-                     liblogger::log_info!(
+                     #log_macro!(
                          &format!("Throttled logs for {}: skipped {} logs in previous minute", 
                              #fn_name, skipped),
                          None
                      );
                  }
-                 COUNTER.store(1, Ordering::SeqCst);
+                 COUNTER.store(0, Ordering::SeqCst);
+             }
+ 
+             if #sampled && #within_rate {
                  true
              } else {
-                 // Same minute, check counter
-                 let count = COUNTER.fetch_add(1, Ordering::SeqCst) + 1;
-                 if count <= #rate as usize {
-                     true
-                 } else {
-                     SKIPPED_COUNT.fetch_add(1, Ordering::SeqCst);
-                     false
-                 }
+                 SKIPPED_COUNT.fetch_add(1, Ordering::SeqCst);
+                 false
              }
          };
          
@@ -406,8 +528,7 @@
          
          // Only log if within rate limits
          if should_log {
-             // Simple logging without trying to match on the result type
-             liblogger::log_info!(&format!("{} executed", #fn_name), None);
+             #log_macro!(#message, None);
          }
          
          result
@@ -744,25 +865,58 @@
  }
  
  /// Catch and log panics but don't crash
+ /// 
+ /// After a panic, functions returning `Result` return `Err` and others
+ /// `Default::default()`. `#[catch_panic(fallback = expr)]` returns `expr` instead,
+ /// and is required for return types that can't be `Default`, such as raw pointers:
+ /// 
+ /// ```ignore
+ /// #[catch_panic(fallback = std::ptr::null_mut())]
+ /// extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+ ///     // Implementation
+ /// }
+ /// ```
  #[proc_macro_attribute]
- pub fn catch_panic(_args: TokenStream, input: TokenStream) -> TokenStream {
+ pub fn catch_panic(args: TokenStream, input: TokenStream) -> TokenStream {
+     let args = parse_macro_input!(args as MacroArgs);
      let mut input_fn = parse_macro_input!(input as ItemFn);
      let fn_name = get_fn_name(&input_fn);
      let orig_block = input_fn.block.clone();
      
-     // Determine return type
-     let returns_result = if let syn::ReturnType::Type(_, ty) = &input_fn.sig.output {
-         if let syn::Type::Path(type_path) = ty.as_ref() {
-             let last_segment = type_path.path.segments.last().unwrap();
-             last_segment.ident.to_string() == "Result"
-         } else {
-             false
+     if let Some(fallback) = &args.fallback {
+         *input_fn.block = parse_quote!({
+             use std::panic::{catch_unwind, AssertUnwindSafe};
+             
+             match catch_unwind(AssertUnwindSafe(|| #orig_block)) {
+                 Ok(result) => result,
+                 Err(panic_err) => {
+                     let panic_msg = if let Some(s) = panic_err.downcast_ref::<&str>() {
+                         s.to_string()
+                     } else if let Some(s) = panic_err.downcast_ref::<String>() {
+                         s.clone()
+                     } else {
+                         "Unknown panic ".to_string()
+                     };
+                     
+                     liblogger::log_error!(&format!("{} caught panic: {}", #fn_name, panic_msg), None);
+                     #fallback
+                 }
+             }
+         });
+         return TokenStream::from(quote!(#input_fn));
+     }
+     
+     if !returns_result(&input_fn) {
+         if let Some(kind) = non_default_return(&input_fn) {
+             let message = format!(
+                 "catch_panic: {} returns {}, which has no Default; add #[catch_panic(fallback = ...)]",
+                 fn_name, kind
+             );
+             return syn::Error::new_spanned(&input_fn.sig.output, message).to_compile_error().into();
          }
-     } else {
-         false
-     };
+     }
      
-     input_fn.block = if returns_result {
+     input_fn.block = if returns_result(&input_fn) {
          Box::new(parse_quote!({
              use std::panic::{catch_unwind, AssertUnwindSafe};
              
@@ -808,6 +962,67 @@
      TokenStream::from(quote!(#input_fn))
  }
  
+ /// Log an error and return early when a function runs past its time budget
+ /// 
+ /// `#[deadline(ms = 500)]` runs the body on a new thread and waits at most `ms`
+ /// milliseconds for it. On timeout, or if the body panics, it logs an error and returns
+ /// `Err` for functions returning `Result` (the error must convert from `String`) and
+ /// `Default::default()` otherwise, like `catch_panic`.
+ /// 
+ /// Synchronous code can't be cancelled, so the body keeps running after the deadline
+ /// and its result is dropped; only the caller is unblocked. Because the body moves
+ /// to another thread, arguments must be owned and `Send + 'static` (no `&self` or
+ /// borrowed parameters), as must the return value.
+ #[proc_macro_attribute]
+ pub fn deadline(args: TokenStream, input: TokenStream) -> TokenStream {
+     let args = parse_macro_input!(args as MacroArgs);
+     let Some(ms) = args.ms else {
+         return syn::Error::new(proc_macro2::Span::call_site(), "deadline requires a budget, e.g. #[deadline(ms = 500)]")
+             .to_compile_error()
+             .into();
+     };
+     
+     let mut input_fn = parse_macro_input!(input as ItemFn);
+     let fn_name = get_fn_name(&input_fn);
+     let orig_block = input_fn.block.clone();
+     let return_type = match &input_fn.sig.output {
+         syn::ReturnType::Type(_, ty) => quote! { #ty },
+         syn::ReturnType::Default => quote! { () },
+     };
+     
+     let fallback = if returns_result(&input_fn) {
+         quote! { Err(reason.into()) }
+     } else {
+         quote! { Default::default() }
+     };
+     
+     input_fn.block = Box::new(parse_quote!({
+         use std::sync::mpsc::{channel, RecvTimeoutError};
+         use std::time::Duration;
+         
+         let (result_tx, result_rx) = channel();
+         std::thread::spawn(move || {
+             let result = (move || -> #return_type #orig_block)();
+             // The caller may have stopped waiting; the result is dropped then
+             let _ = result_tx.send(result);
+         });
+         
+         match result_rx.recv_timeout(Duration::from_millis(#ms)) {
+             Ok(result) => result,
+             Err(wait_err) => {
+                 let reason = match wait_err {
+                     RecvTimeoutError::Timeout => format!("{} exceeded its deadline of {} ms", #fn_name, #ms),
+                     RecvTimeoutError::Disconnected => format!("{} panicked before its deadline", #fn_name),
+                 };
+                 liblogger::log_error!(&reason, None);
+                 #fallback
+             }
+         }
+     }));
+     
+     TokenStream::from(quote!(#input_fn))
+ }
+ 
  /// Log health check results
  #[proc_macro_attribute]
  pub fn health_check(_args: TokenStream, input: TokenStream) -> TokenStream {
@@ -846,6 +1061,11 @@
  }
  
  /// Log function result with different levels for success/error
+ /// 
+ /// The levels given to the macro can be overridden at runtime without recompiling.
+ /// A thread's `log_result_success_level` / `log_result_error_level` context value
+ /// wins, then the `LOG_RESULT_SUCCESS_LEVEL` / `LOG_RESULT_ERROR_LEVEL` environment
+ /// variables, then the macro arguments.
  #[proc_macro_attribute] 
  pub fn log_result(args: TokenStream, input: TokenStream) -> TokenStream {
      let args = parse_macro_input!(args as MacroArgs);
@@ -867,7 +1087,11 @@
          match &result {
              Ok(val) => {
                  // Success case with different log levels
-                 let level = #success_level_str;
+                 let level = liblogger::result_level(
+                     liblogger::RESULT_SUCCESS_LEVEL_KEY,
+                     liblogger::RESULT_SUCCESS_LEVEL_ENV,
+                     #success_level_str
+                 );
                  if level == "debug" {
                      liblogger::log_debug!(&format!("{} succeeded with result: {:?}", #fn_name, val), None);
                  } else if level == "warn" {
@@ -880,7 +1104,11 @@
              },
              Err(err) => {
                  // Error case with different log levels
-                 let level = #error_level_str;
+                 let level = liblogger::result_level(
+                     liblogger::RESULT_ERROR_LEVEL_KEY,
+                     liblogger::RESULT_ERROR_LEVEL_ENV,
+                     #error_level_str
+                 );
                  if level == "debug" {
                      liblogger::log_debug!(&format!("{} failed with error: {:?}", #fn_name, err), None);
                  } else if level == "info" {
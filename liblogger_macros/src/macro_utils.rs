@@ -10,6 +10,47 @@ pub fn get_fn_name(func: &ItemFn) -> String {
     func.sig.ident.to_string()
 }
 
+/// Returns true if the function is declared to return a `Result`
+pub fn returns_result(func: &ItemFn) -> bool {
+    if let syn::ReturnType::Type(_, ty) = &func.sig.output {
+        if let syn::Type::Path(type_path) = ty.as_ref() {
+            let last_segment = type_path.path.segments.last().unwrap();
+            last_segment.ident == "Result"
+        } else {
+            false
+        }
+    } else {
+        false
+    }
+}
+
+/// Returns the liblogger macro for a level name, falling back to `log_info` for
+/// anything other than "debug", "warn" or "error"
+pub fn log_macro_for(level: Option<&str>) -> TokenStream2 {
+    match level.map(str::to_ascii_lowercase).as_deref() {
+        Some("debug") => quote! { liblogger::log_debug },
+        Some("warn") => quote! { liblogger::log_warn },
+        Some("error") => quote! { liblogger::log_error },
+        _ => quote! { liblogger::log_info },
+    }
+}
+
+/// Returns a description of the return type if it can never implement `Default`
+/// (raw pointers, references, function pointers, `impl Trait` and `!`)
+pub fn non_default_return(func: &ItemFn) -> Option<&'static str> {
+    let syn::ReturnType::Type(_, ty) = &func.sig.output else {
+        return None;
+    };
+    match ty.as_ref() {
+        syn::Type::Ptr(_) => Some("a raw pointer"),
+        syn::Type::Reference(_) => Some("a reference"),
+        syn::Type::BareFn(_) => Some("a function pointer"),
+        syn::Type::ImplTrait(_) => Some("an `impl Trait` type"),
+        syn::Type::Never(_) => Some("`!`"),
+        _ => None,
+    }
+}
+
 /// Parse a list of identifiers from attribute args
 pub struct IdList {
     pub ids: Vec<Ident>,
@@ -28,7 +69,15 @@ impl Parse for IdList {
 #[derive(Default)]
 pub struct MacroArgs {
     pub max_attempts: Option<u32>,
+    pub ms: Option<u64>,
     pub rate: Option<u32>,
+    pub sample_pct: Option<f64>,
+    pub level: Option<String>,
+    pub message: Option<String>,
+    /// Bare identifiers, in order, e.g. `timing` and `errors` in `#[instrument(timing, errors)]`
+    pub flags: Vec<String>,
+    /// Expression returned instead of the function's result, e.g. `catch_panic(fallback = ...)`
+    pub fallback: Option<Expr>,
     pub failure_threshold: Option<u32>,
     pub category: Option<String>,
     pub flag_name: Option<String>,
@@ -103,6 +152,13 @@ impl Parse for MacroArgs {
                                     }
                                 }
                             },
+                            "ms" => {
+                                if let Expr::Lit(expr_lit) = &nv.value {
+                                    if let Lit::Int(lit) = &expr_lit.lit {
+                                        args.ms = lit.base10_parse().ok();
+                                    }
+                                }
+                            },
                             "rate" => {
                                 if let Expr::Lit(expr_lit) = &nv.value {
                                     if let Lit::Int(lit) = &expr_lit.lit {
@@ -110,6 +166,32 @@ impl Parse for MacroArgs {
                                     }
                                 }
                             },
+                            "sample_pct" => {
+                                if let Expr::Lit(expr_lit) = &nv.value {
+                                    args.sample_pct = match &expr_lit.lit {
+                                        Lit::Float(lit) => lit.base10_parse().ok(),
+                                        Lit::Int(lit) => lit.base10_parse::<u32>().ok().map(f64::from),
+                                        _ => None,
+                                    };
+                                }
+                            },
+                            "level" => {
+                                if let Expr::Lit(expr_lit) = &nv.value {
+                                    if let Lit::Str(lit) = &expr_lit.lit {
+                                        args.level = Some(lit.value());
+                                    }
+                                }
+                            },
+                            "message" => {
+                                if let Expr::Lit(expr_lit) = &nv.value {
+                                    if let Lit::Str(lit) = &expr_lit.lit {
+                                        args.message = Some(lit.value());
+                                    }
+                                }
+                            },
+                            "fallback" => {
+                                args.fallback = Some(nv.value.clone());
+                            },
                             "failure_threshold" => {
                                 if let Expr::Lit(expr_lit) = &nv.value {
                                     if let Lit::Int(lit) = &expr_lit.lit {
@@ -126,8 +208,9 @@ impl Parse for MacroArgs {
                         if let Some(ident) = path.get_ident() {
                             let name = ident.to_string();
                             if args.category.is_none() {
-                                args.category = Some(name);
+                                args.category = Some(name.clone());
                             }
+                            args.flags.push(name);
                         }
                     },
                     Meta::List(list) => {
@@ -179,15 +262,10 @@ pub fn define_helper_functions() -> TokenStream2 {
             }
         }
         
-        // Placeholder for thread local context values
+        // Thread local context values, set with liblogger::set_context_value
+        // (the engine sets "request_id" for each HTTP request)
         fn get_thread_local_value(key: &str) -> Option<String> {
-            // In a real application, this would retrieve values from thread local storage
-            match key {
-                "user_id" => Some("12345".to_string()),
-                "session_id" => Some("abcd-1234-xyz".to_string()),
-                "request_id" => Some("req-789".to_string()),
-                _ => None,
-            }
+            liblogger::context_value(key)
         }
     )
 }
@@ -0,0 +1,93 @@
+//! `#[log_result]` levels and their runtime overrides, read back from a log file.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+
+use liblogger::{
+    set_context_value, Logger, RESULT_ERROR_LEVEL_KEY, RESULT_SUCCESS_LEVEL_KEY, RESULT_SUCCESS_LEVEL_ENV,
+};
+use liblogger_macros::log_result;
+
+/// Sends this test's logs to a file of its own, written synchronously.
+fn log_file() -> PathBuf {
+    static INIT: Once = Once::new();
+    let dir = std::env::temp_dir().join(format!("log_result_{}", std::process::id()));
+    INIT.call_once(|| {
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("logging.toml");
+        fs::write(
+            &config,
+            format!(
+                "[logging]\ntype = \"file\"\nthreshold = \"debug\"\nlog_folder = {:?}\nfile_path = \"test.log\"\nasync_logging = false\nforce_flush = true\n",
+                dir.display().to_string()
+            ),
+        )
+        .unwrap();
+        Logger::init_with_config_file(config.to_str().unwrap()).unwrap();
+    });
+    dir.join("test.log")
+}
+
+/// Level of the log line containing `message`.
+fn level_of(message: &str) -> &'static str {
+    let log = fs::read_to_string(log_file()).unwrap();
+    let line = log
+        .lines()
+        .find(|line| line.contains(message))
+        .unwrap_or_else(|| panic!("no line with {:?} in:\n{}", message, log));
+    ["DEBUG", "INFO", "WARN", "ERROR"]
+        .into_iter()
+        .find(|level| line.contains(&format!("[{}]", level)))
+        .unwrap_or_else(|| panic!("no level in {:?}", line))
+}
+
+#[log_result("info", "error")]
+fn checked(value: i32) -> Result<i32, i32> {
+    if value >= 0 {
+        Ok(value)
+    } else {
+        Err(value)
+    }
+}
+
+fn success(value: i32) -> &'static str {
+    let _ = checked(value);
+    level_of(&format!("checked succeeded with result: {}", value))
+}
+
+fn failure(value: i32) -> &'static str {
+    let _ = checked(value);
+    level_of(&format!("checked failed with error: {}", value))
+}
+
+// One test, since the environment override is shared by the whole process
+#[test]
+fn runtime_overrides_take_precedence_over_the_macro_levels() {
+    log_file();
+    assert_eq!(success(11), "INFO");
+    assert_eq!(failure(-11), "ERROR");
+
+    set_context_value(RESULT_SUCCESS_LEVEL_KEY, Some("debug"));
+    set_context_value(RESULT_ERROR_LEVEL_KEY, Some(" WARN "));
+    assert_eq!(success(12), "DEBUG");
+    assert_eq!(failure(-12), "WARN");
+
+    // The override belongs to this thread only
+    let other_thread = std::thread::spawn(|| success(13)).join().unwrap();
+    assert_eq!(other_thread, "INFO");
+
+    set_context_value(RESULT_SUCCESS_LEVEL_KEY, None);
+    set_context_value(RESULT_ERROR_LEVEL_KEY, None);
+    assert_eq!(success(14), "INFO");
+    assert_eq!(failure(-14), "ERROR");
+
+    // The environment applies when the thread sets nothing, and loses to the thread
+    std::env::set_var(RESULT_SUCCESS_LEVEL_ENV, "warn");
+    assert_eq!(success(15), "WARN");
+    set_context_value(RESULT_SUCCESS_LEVEL_KEY, Some("debug"));
+    assert_eq!(success(16), "DEBUG");
+    set_context_value(RESULT_SUCCESS_LEVEL_KEY, None);
+    std::env::remove_var(RESULT_SUCCESS_LEVEL_ENV);
+    assert_eq!(success(17), "INFO");
+}
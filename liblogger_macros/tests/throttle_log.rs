@@ -0,0 +1,103 @@
+//! What `#[throttle_log]` writes, read back from a log file.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use liblogger::Logger;
+use liblogger_macros::throttle_log;
+
+/// Sends this test's logs to a file of its own, written synchronously.
+fn log_file() -> PathBuf {
+    static INIT: Once = Once::new();
+    let dir = std::env::temp_dir().join(format!("throttle_log_{}", std::process::id()));
+    INIT.call_once(|| {
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("logging.toml");
+        fs::write(
+            &config,
+            format!(
+                "[logging]\ntype = \"file\"\nthreshold = \"debug\"\nlog_folder = {:?}\nfile_path = \"test.log\"\nasync_logging = false\nforce_flush = true\n",
+                dir.display().to_string()
+            ),
+        )
+        .unwrap();
+        Logger::init_with_config_file(config.to_str().unwrap()).unwrap();
+    });
+    dir.join("test.log")
+}
+
+/// Log lines containing `message`.
+fn lines_with(message: &str) -> Vec<String> {
+    let log = fs::read_to_string(log_file()).unwrap();
+    log.lines().filter(|line| line.contains(message)).map(str::to_string).collect()
+}
+
+/// The throttle window is the wall-clock minute; leaves at least `room` before it ends.
+fn wait_for_room_in_this_minute(room: Duration) {
+    let into_minute = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() % 60_000;
+    let left = Duration::from_millis(60_000 - into_minute as u64);
+    if left < room {
+        std::thread::sleep(left + Duration::from_millis(50));
+    }
+}
+
+/// Sleeps until the next wall-clock minute, where the throttle window rolls over.
+fn sleep_into_next_minute() {
+    let into_minute = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() % 60_000;
+    std::thread::sleep(Duration::from_millis(60_000 - into_minute as u64 + 50));
+}
+
+#[throttle_log(sample_pct = 10, message = "sampled call")]
+fn sampled() {}
+
+#[throttle_log(rate = 3, message = "rate limited call")]
+fn rate_limited() {}
+
+#[throttle_log(rate = 2, level = "warn", message = "{fn} saw a flaky peer")]
+fn flaky_peer() {}
+
+#[test]
+fn sample_pct_logs_about_that_fraction_of_calls() {
+    log_file();
+    const CALLS: usize = 10_000;
+    for _ in 0..CALLS {
+        sampled();
+    }
+
+    // 10% of 10,000 is 1,000, with a standard deviation of 30
+    let logged = lines_with("sampled call").len();
+    assert!((850..=1150).contains(&logged), "logged {} of {} calls", logged, CALLS);
+}
+
+#[test]
+fn without_sample_pct_the_rate_caps_each_minute() {
+    log_file();
+    wait_for_room_in_this_minute(Duration::from_secs(2));
+    for _ in 0..10 {
+        rate_limited();
+    }
+
+    assert_eq!(lines_with("rate limited call").len(), 3);
+}
+
+// Waits for the next minute, up to a minute, so the skipped-count summary is written
+#[test]
+fn chosen_level_covers_logged_calls_and_the_skipped_summary() {
+    log_file();
+    wait_for_room_in_this_minute(Duration::from_secs(2));
+    for _ in 0..5 {
+        flaky_peer();
+    }
+    sleep_into_next_minute();
+    flaky_peer();
+
+    let logged = lines_with("flaky_peer saw a flaky peer");
+    assert_eq!(logged.len(), 3, "two calls in the first minute, one in the next");
+    let summary = lines_with("Throttled logs for flaky_peer: skipped 3 logs in previous minute");
+    assert_eq!(summary.len(), 1);
+    for line in logged.iter().chain(&summary) {
+        assert!(line.contains("[WARN]"), "not logged at warn: {}", line);
+    }
+}
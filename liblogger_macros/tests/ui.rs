@@ -0,0 +1,18 @@
+//! Compile tests for the attribute macros, run with trybuild.
+//!
+//! Regenerate the expected `.stderr` files after changing an error message with
+//! `TRYBUILD=overwrite cargo test -p liblogger_macros --test ui`.
+
+#[test]
+fn catch_panic() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/catch_panic/pass_*.rs");
+    t.compile_fail("tests/ui/catch_panic/fail_*.rs");
+}
+
+#[test]
+fn instrument() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/instrument/pass_*.rs");
+    t.compile_fail("tests/ui/instrument/fail_*.rs");
+}
@@ -0,0 +1,11 @@
+// A named type without `Default` is left to the compiler to reject
+use liblogger_macros::catch_panic;
+
+struct Handle(u32);
+
+#[catch_panic]
+fn open() -> Handle {
+    Handle(7)
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use liblogger_macros::catch_panic;
+
+#[catch_panic]
+fn allocate() -> *mut u8 {
+    Box::into_raw(Box::new(1u8))
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use liblogger_macros::catch_panic;
+
+#[catch_panic]
+fn label() -> &'static str {
+    "wifi"
+}
+
+fn main() {}
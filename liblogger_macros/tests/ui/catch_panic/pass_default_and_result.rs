@@ -0,0 +1,24 @@
+// Without a fallback, `Result` functions return `Err` and others `Default::default()`
+use liblogger_macros::catch_panic;
+
+#[catch_panic]
+fn parse(input: &str) -> Result<u32, String> {
+    Ok(input.parse().unwrap())
+}
+
+#[catch_panic]
+fn count(items: &[u32]) -> usize {
+    if items.is_empty() {
+        panic!("nothing to count");
+    }
+    items.len()
+}
+
+fn main() {
+    assert_eq!(parse("42"), Ok(42));
+    let err = parse("forty-two").unwrap_err();
+    assert!(err.starts_with("Panic in parse: "), "{}", err);
+
+    assert_eq!(count(&[1, 2]), 2);
+    assert_eq!(count(&[]), 0);
+}
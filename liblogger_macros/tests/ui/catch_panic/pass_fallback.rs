@@ -0,0 +1,41 @@
+// Return types without `Default` compile once a fallback is given
+use liblogger_macros::catch_panic;
+
+struct Handle(u32);
+
+#[catch_panic(fallback = Handle(0))]
+fn open(fail: bool) -> Handle {
+    if fail {
+        panic!("open failed");
+    }
+    Handle(7)
+}
+
+#[catch_panic(fallback = std::ptr::null_mut())]
+fn allocate(fail: bool) -> *mut u8 {
+    if fail {
+        panic!("allocation failed");
+    }
+    Box::into_raw(Box::new(1u8))
+}
+
+#[catch_panic(fallback = "unknown")]
+fn label(fail: bool) -> &'static str {
+    if fail {
+        panic!("no label");
+    }
+    "wifi"
+}
+
+fn main() {
+    assert_eq!(open(false).0, 7);
+    assert_eq!(open(true).0, 0);
+
+    let ptr = allocate(false);
+    assert!(!ptr.is_null());
+    drop(unsafe { Box::from_raw(ptr) });
+    assert!(allocate(true).is_null());
+
+    assert_eq!(label(false), "wifi");
+    assert_eq!(label(true), "unknown");
+}
@@ -0,0 +1,8 @@
+use liblogger_macros::instrument;
+
+#[instrument(errors)]
+fn count() -> usize {
+    3
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use liblogger_macros::instrument;
+
+#[instrument(timing, retries)]
+fn connect() {}
+
+fn main() {}
@@ -0,0 +1,42 @@
+// Each flag combination keeps the function's signature and return value
+use liblogger_macros::instrument;
+
+#[instrument]
+fn plain(x: u32) -> u32 {
+    x + 1
+}
+
+#[instrument(timing)]
+fn timed(x: u32) -> u32 {
+    std::thread::sleep(std::time::Duration::from_millis(1));
+    x * 2
+}
+
+#[instrument(errors)]
+fn checked(x: u32) -> Result<u32, String> {
+    if x == 0 { Err("zero".to_string()) } else { Ok(x) }
+}
+
+#[instrument(timing, errors, level = "debug")]
+fn everything(x: u32) -> Result<u32, String> {
+    checked(x).map(|x| x * 10)
+}
+
+#[instrument(level = "warn")]
+fn no_return() {}
+
+#[instrument(errors, timing, level = "error")]
+fn reordered(x: u32) -> Result<(), String> {
+    checked(x).map(|_| ())
+}
+
+fn main() {
+    assert_eq!(plain(1), 2);
+    assert_eq!(timed(2), 4);
+    assert_eq!(checked(3), Ok(3));
+    assert_eq!(checked(0), Err("zero".to_string()));
+    assert_eq!(everything(4), Ok(40));
+    assert_eq!(everything(0), Err("zero".to_string()));
+    no_return();
+    assert_eq!(reordered(0), Err("zero".to_string()));
+}
@@ -0,0 +1,12 @@
+// A panic is logged and then resumed, so callers still see it
+use liblogger_macros::instrument;
+
+#[instrument(timing, errors)]
+fn explode() -> Result<(), String> {
+    panic!("boom");
+}
+
+fn main() {
+    let caught = std::panic::catch_unwind(explode).unwrap_err();
+    assert_eq!(caught.downcast_ref::<&str>(), Some(&"boom"));
+}
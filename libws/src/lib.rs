@@ -1,62 +1,86 @@
 // Public module for WebSocket client functionality
 pub mod ws_client;
+pub mod subscribers;
+
+pub use subscribers::{
+    OverflowPolicy, QueueConfig, SubscriberReceiver, SubscriberSender, SubscriberTable,
+    subscriber_channel,
+};
 
 use axum::{
-    extract::ws::{ Message, WebSocket, WebSocketUpgrade },
-    extract::ConnectInfo,
-    response::IntoResponse,
+    extract::ws::{ close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade },
+    extract::{ ConnectInfo, Query },
+    response::{ IntoResponse, Response },
 };
+use serde::Deserialize;
 use futures_util::{ SinkExt, StreamExt };
 use serde_json::{ json, Value };
 use std::{ collections::HashMap, net::SocketAddr, sync::{ Arc, Mutex } };
-use tokio::sync::mpsc::{ self, UnboundedSender };
 
 use libjwt::validate_jwt;
 
 // Type aliases for topic names and subscriber management
 pub type Topic = String;
-pub type Subscribers = Arc<Mutex<HashMap<Topic, Vec<UnboundedSender<String>>>>>;
-
-/// TODO: NOT SURE IF THIS IS STILL CORRECT -- I need to check my original code for libws 
-/// Should the JWT token is passed in the query string of the WebSocket connection request?
-/// 
-/// From lib.rs in engine, when you load the ws server call a wrapper function that will 
-/// call the handle_socket_with_jwt function
-//  .route(
-//            "/ws",
-//            get(handle_socket_adapter),
-//        )
-// 
-/// Adapter function to bridge between server and library
-/// async fn handle_socket_adapter(
-///    ws: WebSocketUpgrade,
-///    ConnectInfo(addr): ConnectInfo<SocketAddr>,
-///    State(subscribers): State<Subscribers>,
-///    query_params: Option<Query<WebSocketParams>>,  // Add query parameters
-/// ) -> impl IntoResponse {
-///    // Call the libws handler with query parameters
-///    libws::handle_socket(ws, ConnectInfo(addr), query_params, subscribers).await
-/// }
-
-/// Handles the WebSocket upgrade and initializes the connection with JWT validation.
+pub type Subscribers = Arc<Mutex<SubscriberTable>>;
+
+/// Returns the number of live subscribers per topic, with patterns listed as `prefix*`.
+///
+/// Closed connections are pruned first, so the counts only reflect live clients.
+pub fn subscriber_counts(subs: &Subscribers) -> HashMap<String, usize> {
+    let mut table = subs.lock().unwrap();
+    table.prune_closed();
+    table.topics().collect()
+}
+
+/// Query parameters accepted on the WebSocket upgrade request, e.g. `/ws?token=<jwt>`.
+#[derive(Debug, Default, Deserialize)]
+pub struct WebSocketParams {
+    pub token: Option<String>,
+}
+
+/// Checks the token supplied on the upgrade request.
+fn authorize_upgrade(token: Option<&str>) -> Result<(), String> {
+    match token {
+        Some(token) if !token.is_empty() => validate_jwt(token)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid token: {}", e)),
+        _ => Err("Missing token".to_string()),
+    }
+}
+
+/// Handles the WebSocket upgrade, requiring a valid JWT in the `token` query parameter.
+///
+/// Connections with a missing, invalid or expired token are upgraded and then closed
+/// right away with a policy-violation close frame carrying the reason.
+///
+/// Wire it up in place of `handle_socket`:
+///
+/// ```ignore
+/// .route("/ws", get(move |ws, ConnectInfo(addr), query| {
+///     libws::handle_socket_with_jwt(ws, ConnectInfo(addr), query, subs.clone())
+/// }))
+/// ```
 pub async fn handle_socket_with_jwt(
     ws: WebSocketUpgrade,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(params): Query<WebSocketParams>,
     subscribers: Subscribers,
-    token: String,
-) -> impl IntoResponse {
+) -> Response {
     println!("[handle_socket_with_jwt] WS connection from {}", addr);
 
-    // Convert both branches to Response<Body> using into_response()
-    if validate_jwt(&token).is_ok() {
-        println!("[handle_socket_with_jwt] JWT token is valid");
-        handle_socket(ws, ConnectInfo(addr), subscribers).await.into_response()
-    } else {
-        println!("[handle_socket_with_jwt] JWT token is invalid");
-        ws.on_upgrade(|socket| async move {
-            let _ = socket.close().await;
-            println!("[handle_socket_with_jwt] Connection closed due to invalid JWT");
-        }).into_response()
+    match authorize_upgrade(params.token.as_deref()) {
+        Ok(()) => {
+            println!("[handle_socket_with_jwt] JWT token is valid");
+            handle_socket(ws, ConnectInfo(addr), subscribers).await.into_response()
+        }
+        Err(reason) => {
+            println!("[handle_socket_with_jwt] Rejecting {}: {}", addr, reason);
+            ws.on_upgrade(move |mut socket| async move {
+                let frame = CloseFrame { code: close_code::POLICY, reason: reason.into() };
+                let _ = socket.send(Message::Close(Some(frame))).await;
+                println!("[handle_socket_with_jwt] Connection closed due to invalid JWT");
+            })
+        }
     }
 }
 
@@ -88,8 +112,9 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
     // Track topics the client is subscribed to
     let my_topics = Arc::new(Mutex::new(Vec::<String>::new()));
 
-    // Create a channel for sending messages to the client
-    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    // Create a bounded queue for sending messages to the client
+    let queue_config = subscribers.lock().unwrap().queue_config();
+    let (tx, mut rx) = subscriber_channel(queue_config);
     let tx_clone = tx.clone();
     let subscribers_inner = subscribers.clone();
     let topics_inner = my_topics.clone();
@@ -101,6 +126,8 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
                 break;
             }
         }
+        // The queue closes when the client is too slow under the Disconnect policy
+        let _ = ws_sender.close().await;
     });
 
     // Task for receiving messages from the client
@@ -119,12 +146,15 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
                         let topic = rest.trim().to_string();
                         println!("[subscribe] subscriber_name={}, topic={}", client_name, topic);
 
-                        subscribers_inner
-                            .lock()
-                            .unwrap()
-                            .entry(topic.clone())
-                            .or_default()
-                            .push(tx.clone());
+                        {
+                            let mut subs = subscribers_inner.lock().unwrap();
+                            subs.subscribe(&topic, tx.clone());
+
+                            // Bring the new subscriber up to date with any retained state
+                            for retained in subs.retained_for(&topic) {
+                                let _ = tx.send(retained);
+                            }
+                        }
 
                         topics_inner.lock().unwrap().push(topic);
 
@@ -133,15 +163,27 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
                         let topic = rest.trim().to_string();
                         println!("[unsubscribe] {} unsubscribing from {}", client_name, topic);
 
-                        let mut subs = subscribers_inner.lock().unwrap();
-                        if let Some(vec) = subs.get_mut(&topic) {
-                            vec.retain(|s| !same_channel(s, &tx));
-                        }
+                        subscribers_inner
+                            .lock()
+                            .unwrap()
+                            .unsubscribe(&topic, |s| s.same_channel(&tx));
                         topics_inner
                             .lock()
                             .unwrap()
                             .retain(|t| t != &topic);
 
+                        // Forward a subscriber's acknowledgement to the original publisher
+                    } else if let Some(rest) = text.strip_prefix("ack:") {
+                        let msg_id = rest.trim();
+                        let publisher = subscribers_inner.lock().unwrap().take_ack(msg_id);
+                        match publisher {
+                            Some(publisher) => {
+                                println!("[ack] {} acknowledged {}", client_name, msg_id);
+                                let _ = publisher.send(format!("ack:{}", msg_id));
+                            }
+                            None => println!("[ack] {} sent ack for unknown or already acknowledged {}", client_name, msg_id),
+                        }
+
                         // Handle JSON message publishing
                     } else if let Some(rest) = text.strip_prefix("publish-json:") {
                         match serde_json::from_str::<Value>(rest) {
@@ -150,7 +192,11 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
                                     .as_str()
                                     .unwrap_or("<none>")
                                     .to_string();
-                                let payload = parsed["payload"].as_str().unwrap_or("").to_string();
+                                // Any JSON value is forwarded as-is; older publishers send strings
+                                let payload = match &parsed["payload"] {
+                                    Value::Null => Value::String(String::new()),
+                                    value => value.clone(),
+                                };
                                 let publisher = parsed["publisher_name"]
                                     .as_str()
                                     .unwrap_or("<unknown>")
@@ -159,6 +205,8 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
                                     .as_str()
                                     .unwrap_or("")
                                     .to_string();
+                                let retain = parsed["retain"].as_bool().unwrap_or(false);
+                                let msg_id = parsed["msg_id"].as_str().map(str::to_string);
 
                                 println!(
                                     "[publish-json] publisher_name={}, topic={}, payload={}, timestamp={}",
@@ -168,45 +216,52 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
                                     timestamp
                                 );
 
-                                let json_payload =
+                                let mut forwarded =
                                     json!({
                                     "publisher_name": publisher,
                                     "topic": topic,
                                     "payload": payload,
                                     "timestamp": timestamp
-                                }).to_string();
+                                });
+                                // Late subscribers get the retained copy, which nobody is waiting to have acked
+                                let retained_payload = forwarded.to_string();
+                                if let Some(msg_id) = &msg_id {
+                                    // Subscribers reply with `ack:<msg_id>` once they've handled it
+                                    forwarded["msg_id"] = json!(msg_id);
+                                }
+                                if let Some(correlation_id) = parsed["correlation_id"].as_str() {
+                                    // Requests and their replies, matched up by `WsClient::request`
+                                    forwarded["correlation_id"] = json!(correlation_id);
+                                }
+                                let json_payload = forwarded.to_string();
 
                                 let mut subs = subscribers_inner.lock().unwrap();
-                                for (topic, sinks) in subs.iter() {
+                                if retain {
+                                    // Retaining an empty payload clears the topic's retained value
+                                    let cleared = payload.as_str().is_some_and(str::is_empty);
+                                    let retained = (!cleared).then_some(retained_payload);
+                                    subs.retain_message(&topic, retained);
+                                }
+
+                                for (topic, count) in subs.topics() {
                                     println!(
                                         "[DEBUG] Topic '{}' has {} subscribers",
                                         topic,
-                                        sinks.len()
+                                        count
                                     );
                                 }
 
-                                if let Some(sinks) = subs.get_mut(&topic) {
+                                // Delivers to exact subscribers and matching `prefix*` patterns
+                                let delivered = subs.publish(&topic, &json_payload);
+                                if let (Some(msg_id), true) = (&msg_id, delivered > 0) {
+                                    subs.expect_ack(msg_id, tx.clone());
+                                }
+                                if delivered > 0 {
                                     println!(
-                                        "[publish-json] Subscribers for topic '{}': {}",
+                                        "[publish-json] Sent to topic '{}' ({} subscribers)",
                                         topic,
-                                        sinks.len()
+                                        delivered
                                     );
-
-                                    let mut to_remove = Vec::new();
-                                    for (i, s) in sinks.iter().enumerate() {
-                                        if s.send(json_payload.clone()).is_err() {
-                                            eprintln!(
-                                                "[publish-json] Failed to send to subscriber."
-                                            );
-                                            to_remove.push(i);
-                                        } else {
-                                            println!("[publish-json] Sent to topic '{}'", topic);
-                                        }
-                                    }
-                                    // Remove dead senders (in reverse order to avoid shifting indices)
-                                    for i in to_remove.into_iter().rev() {
-                                        sinks.remove(i);
-                                    }
                                 } else {
                                     println!("[publish-json] No subscribers for topic '{}'", topic);
                                 }
@@ -224,6 +279,8 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
                 }
             }
         }
+        // Client is gone, so stop the send task as well
+        tx.close();
     });
 
     // Wait for both tasks to complete
@@ -238,16 +295,180 @@ async fn run_connection(socket: WebSocket, subscribers: Subscribers) -> Result<(
     // Cleanup subscriptions on client disconnect
     let mut subs = subscribers.lock().unwrap();
     for topic in my_topics.lock().unwrap().iter() {
-        if let Some(vec) = subs.get_mut(topic) {
-            vec.retain(|s| !same_channel(s, &tx_clone));
-        }
+        subs.unsubscribe(topic, |s| s.same_channel(&tx_clone));
     }
 
     println!("[run_connection] Cleanup complete.");
     Ok(())
 }
 
-/// Compares two channels to check if they are the same.
-fn same_channel(a: &UnboundedSender<String>, b: &UnboundedSender<String>) -> bool {
-    std::ptr::eq(a, b)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+    use axum::{ routing::get, Router };
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::{ connect_async, tungstenite };
+    use tungstenite::protocol::frame::coding::CloseCode;
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn upgrade_accepts_valid_token() {
+        let token = libjwt::generate_service_token("libws-test").unwrap();
+        assert!(authorize_upgrade(Some(&token)).is_ok());
+    }
+
+    #[test]
+    fn upgrade_rejects_missing_token() {
+        assert_eq!(authorize_upgrade(None), Err("Missing token".to_string()));
+        assert_eq!(authorize_upgrade(Some("")), Err("Missing token".to_string()));
+    }
+
+    #[test]
+    fn upgrade_rejects_expired_token() {
+        // Well past the validation leeway
+        let token = libjwt::generate_jwt("libws-test", "session", now() - 7200, now() - 3600).unwrap();
+        let reason = authorize_upgrade(Some(&token)).unwrap_err();
+        assert!(reason.starts_with("Invalid token"), "unexpected reason: {}", reason);
+    }
+
+    /// Serves `/ws` through `handle_socket_with_jwt` on a free port and returns its URL
+    /// and subscriber table.
+    async fn start_server() -> (String, Subscribers) {
+        let subs: Subscribers = Default::default();
+        let app = Router::new().route(
+            "/ws",
+            get({
+                let subs = subs.clone();
+                move |ws, ConnectInfo(addr), query| handle_socket_with_jwt(ws, ConnectInfo(addr), query, subs.clone())
+            })
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        });
+        (format!("ws://{}/ws", addr), subs)
+    }
+
+    async fn next_message(
+        socket: &mut tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>
+    ) -> tungstenite::Message {
+        tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("no message from the server")
+            .expect("connection ended")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn upgrade_with_valid_token_round_trips_a_publish() {
+        let (url, _subs) = start_server().await;
+        let token = libjwt::generate_service_token("libws-test").unwrap();
+        let (mut socket, _) = connect_async(format!("{}?token={}", url, token)).await.unwrap();
+
+        socket.send(tungstenite::Message::Text("subscribe:Echo".to_string())).await.unwrap();
+        let publish = json!({
+            "publisher_name": "libws-test",
+            "topic": "Echo",
+            "payload": "hello",
+            "timestamp": "now"
+        });
+        socket.send(tungstenite::Message::Text(format!("publish-json:{}", publish))).await.unwrap();
+
+        let tungstenite::Message::Text(text) = next_message(&mut socket).await else {
+            panic!("expected a text message");
+        };
+        let forwarded: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(forwarded["topic"], "Echo");
+        assert_eq!(forwarded["payload"], "hello");
+    }
+
+    #[tokio::test]
+    async fn upgrade_without_token_is_closed_with_policy_violation() {
+        let (url, _subs) = start_server().await;
+        let (mut socket, _) = connect_async(url).await.unwrap();
+
+        match next_message(&mut socket).await {
+            tungstenite::Message::Close(Some(frame)) => {
+                assert_eq!(frame.code, CloseCode::Policy);
+                assert_eq!(frame.reason, "Missing token");
+            }
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn abrupt_disconnect_is_not_counted_after_a_publish() {
+        let (url, subs) = start_server().await;
+        let token = libjwt::generate_service_token("libws-test").unwrap();
+        let url = format!("{}?token={}", url, token);
+        let (mut stays, _) = connect_async(&url).await.unwrap();
+        let (mut drops, _) = connect_async(&url).await.unwrap();
+
+        for socket in [&mut stays, &mut drops] {
+            socket.send(tungstenite::Message::Text("subscribe:Echo".to_string())).await.unwrap();
+        }
+        // Subscribing sends no reply, so poll until the server has registered both
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while subscriber_counts(&subs).get("Echo") != Some(&2) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.expect("both clients subscribed");
+
+        // No close frame, no unsubscribe: the TCP connection just goes away
+        drop(drops);
+
+        let publish = json!({ "publisher_name": "libws-test", "topic": "Echo", "payload": "still here" });
+        stays.send(tungstenite::Message::Text(format!("publish-json:{}", publish))).await.unwrap();
+        let tungstenite::Message::Text(text) = next_message(&mut stays).await else {
+            panic!("expected a text message");
+        };
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap()["payload"], "still here");
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while subscriber_counts(&subs).get("Echo") != Some(&1) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.expect("dead subscriber still counted in /ws/stats");
+    }
+
+    #[tokio::test]
+    async fn nested_json_payload_round_trips_through_a_subscription() {
+        let (url, subs) = start_server().await;
+        let mut subscriber = ws_client::WsClient::connect_as_service("libws-sub", &url).await.unwrap();
+        let mut publisher = ws_client::WsClient::connect_as_service("libws-pub", &url).await.unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        subscriber.on_message("NetworkState", move |payload| {
+            let _ = tx.send(payload);
+        });
+        subscriber.subscribe("libws-sub", "NetworkState", "").await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while subscriber_counts(&subs).get("NetworkState") != Some(&1) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.expect("subscriber registered");
+
+        let payload = json!({
+            "network": {
+                "ssid": "Home",
+                "bands": [2.4, 5],
+                "secure": true,
+                "neighbours": [{ "ssid": "Cafe", "signal": -70 }, { "ssid": null, "signal": -85 }]
+            },
+            "connected": false
+        });
+        publisher.publish("libws-pub", "NetworkState", payload.clone(), "now", false).await.unwrap();
+
+        // Structured payloads reach the handler as their JSON text
+        let received = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("no message for the subscriber")
+            .unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&received).unwrap(), payload);
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::{ HashMap, VecDeque };
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use tokio::sync::Notify;
+
+use crate::Topic;
+
+/// Default number of messages buffered per connection before the overflow policy applies.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 256;
+
+/// What to do when a subscriber's queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued message to make room for the new one.
+    DropOldest,
+    /// Close the subscriber's connection; it is treated as stalled.
+    Disconnect,
+}
+
+/// Bounds applied to each connection's outgoing message queue.
+#[derive(Debug, Clone, Copy)]
+pub struct QueueConfig {
+    pub capacity: usize,
+    pub policy: OverflowPolicy,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self { capacity: DEFAULT_QUEUE_CAPACITY, policy: OverflowPolicy::DropOldest }
+    }
+}
+
+/// Source of the per-connection ids handed out by `subscriber_channel`.
+static NEXT_SUBSCRIBER_ID: AtomicU64 = AtomicU64::new(1);
+
+struct QueueInner {
+    id: u64,
+    messages: Mutex<VecDeque<String>>,
+    notify: Notify,
+    closed: AtomicBool,
+    config: QueueConfig,
+}
+
+/// Sending half of a connection's bounded message queue. Cheap to clone.
+#[derive(Clone)]
+pub struct SubscriberSender {
+    inner: Arc<QueueInner>,
+}
+
+/// Receiving half of a connection's bounded message queue, drained by the send task.
+pub struct SubscriberReceiver {
+    inner: Arc<QueueInner>,
+}
+
+/// Creates a bounded queue for one WebSocket connection.
+pub fn subscriber_channel(config: QueueConfig) -> (SubscriberSender, SubscriberReceiver) {
+    let inner = Arc::new(QueueInner {
+        id: NEXT_SUBSCRIBER_ID.fetch_add(1, Ordering::Relaxed),
+        messages: Mutex::new(VecDeque::with_capacity(config.capacity.min(DEFAULT_QUEUE_CAPACITY))),
+        notify: Notify::new(),
+        closed: AtomicBool::new(false),
+        config,
+    });
+    (SubscriberSender { inner: inner.clone() }, SubscriberReceiver { inner })
+}
+
+impl SubscriberSender {
+    /// Queues a message, applying the overflow policy if the queue is full.
+    ///
+    /// Fails once the connection is gone or was disconnected for being too slow.
+    pub fn send(&self, message: String) -> Result<(), String> {
+        if self.is_closed() {
+            return Err("Subscriber is closed".to_string());
+        }
+
+        let mut messages = self.inner.messages.lock().unwrap();
+        if messages.len() >= self.inner.config.capacity.max(1) {
+            match self.inner.config.policy {
+                OverflowPolicy::DropOldest => {
+                    messages.pop_front();
+                }
+                OverflowPolicy::Disconnect => {
+                    drop(messages);
+                    self.close();
+                    return Err("Subscriber queue is full".to_string());
+                }
+            }
+        }
+        messages.push_back(message);
+        drop(messages);
+
+        self.inner.notify.notify_one();
+        Ok(())
+    }
+
+    /// Unique id of the connection this sender feeds, shared by all of its clones.
+    pub fn id(&self) -> u64 {
+        self.inner.id
+    }
+
+    /// Returns true if both senders feed the same connection.
+    pub fn same_channel(&self, other: &SubscriberSender) -> bool {
+        self.id() == other.id()
+    }
+
+    /// Returns true once the receiver is gone or the subscriber was disconnected.
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::SeqCst)
+    }
+
+    /// Closes the queue; the receiver stops after this.
+    pub fn close(&self) {
+        self.inner.closed.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_one();
+    }
+}
+
+impl SubscriberReceiver {
+    /// Waits for the next message. Returns `None` once the queue has been closed.
+    pub async fn recv(&mut self) -> Option<String> {
+        loop {
+            if self.inner.closed.load(Ordering::SeqCst) {
+                return None;
+            }
+            if let Some(message) = self.inner.messages.lock().unwrap().pop_front() {
+                return Some(message);
+            }
+            self.inner.notify.notified().await;
+        }
+    }
+}
+
+impl Drop for SubscriberReceiver {
+    fn drop(&mut self) {
+        self.inner.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Subscriber channels held by the WebSocket server.
+///
+/// Exact topics are looked up directly. Wildcard subscriptions (`plugin.*`, or `*`
+/// for everything) are kept separately as prefixes, so an exact-topic publish
+/// stays a single hash lookup plus a scan of the, usually few, patterns.
+#[derive(Default)]
+pub struct SubscriberTable {
+    exact: HashMap<Topic, Vec<SubscriberSender>>,
+    patterns: HashMap<String, Vec<SubscriberSender>>,
+    /// Last retained message per topic, replayed to new subscribers
+    retained: HashMap<Topic, String>,
+    /// Publishers waiting for an `ack:<msg_id>`, keyed by message id
+    pending_acks: HashMap<String, SubscriberSender>,
+    queue_config: QueueConfig,
+}
+
+/// Returns the prefix of a wildcard subscription (`"plugin.*"` -> `"plugin."`),
+/// or `None` for an exact topic.
+fn pattern_prefix(topic: &str) -> Option<&str> {
+    topic.strip_suffix('*')
+}
+
+impl SubscriberTable {
+    /// Creates an empty table whose connections use the given queue bounds.
+    pub fn with_queue_config(queue_config: QueueConfig) -> Self {
+        Self { queue_config, ..Self::default() }
+    }
+
+    /// Queue bounds for new connections.
+    pub fn queue_config(&self) -> QueueConfig {
+        self.queue_config
+    }
+
+    /// Changes the queue bounds for connections opened from now on; existing
+    /// connections keep the bounds they were created with.
+    pub fn set_queue_config(&mut self, queue_config: QueueConfig) {
+        self.queue_config = queue_config;
+    }
+
+    /// Adds a subscriber channel for an exact topic or a `prefix*` pattern.
+    ///
+    /// Senders of connections that have since closed are swept out first, so quiet
+    /// topics don't keep collecting dead entries from clients that dropped without
+    /// unsubscribing.
+    pub fn subscribe(&mut self, topic: &str, sender: SubscriberSender) {
+        self.prune_closed();
+        match pattern_prefix(topic) {
+            Some(prefix) => self.patterns.entry(prefix.to_string()).or_default().push(sender),
+            None => self.exact.entry(topic.to_string()).or_default().push(sender),
+        }
+    }
+
+    /// Stores `message` as the retained value for `topic`, replacing any previous one.
+    /// An empty message clears the retained value.
+    pub fn retain_message(&mut self, topic: &str, message: Option<String>) {
+        match message {
+            Some(message) => {
+                self.retained.insert(topic.to_string(), message);
+            }
+            None => {
+                self.retained.remove(topic);
+            }
+        }
+    }
+
+    /// Returns the retained messages a new subscription to `topic` should receive
+    /// right away: the topic's own value, or every match for a `prefix*` pattern.
+    pub fn retained_for(&self, topic: &str) -> Vec<String> {
+        match pattern_prefix(topic) {
+            Some(prefix) => self.retained
+                .iter()
+                .filter(|(retained_topic, _)| retained_topic.starts_with(prefix))
+                .map(|(_, message)| message.clone())
+                .collect(),
+            None => self.retained.get(topic).cloned().into_iter().collect(),
+        }
+    }
+
+    /// Remembers that `publisher` wants to hear about the first acknowledgement of `msg_id`.
+    pub fn expect_ack(&mut self, msg_id: &str, publisher: SubscriberSender) {
+        // Publishers that went away never collect their acks
+        self.pending_acks.retain(|_, p| !p.is_closed());
+        self.pending_acks.insert(msg_id.to_string(), publisher);
+    }
+
+    /// Takes the publisher waiting on `msg_id`. Later acks for the same id return `None`.
+    pub fn take_ack(&mut self, msg_id: &str) -> Option<SubscriberSender> {
+        self.pending_acks.remove(msg_id)
+    }
+
+    /// Removes every subscription for `topic` whose sender matches `is_sender`.
+    pub fn unsubscribe<F>(&mut self, topic: &str, is_sender: F)
+    where
+        F: Fn(&SubscriberSender) -> bool,
+    {
+        let sinks = match pattern_prefix(topic) {
+            Some(prefix) => self.patterns.get_mut(prefix),
+            None => self.exact.get_mut(topic),
+        };
+        if let Some(sinks) = sinks {
+            sinks.retain(|s| !is_sender(s));
+        }
+    }
+
+    /// Drops senders whose connection has closed, and topics left without subscribers.
+    /// Returns the number of senders removed.
+    pub fn prune_closed(&mut self) -> usize {
+        let mut removed = 0;
+        for sinks in self.exact.values_mut().chain(self.patterns.values_mut()) {
+            let before = sinks.len();
+            sinks.retain(|s| !s.is_closed());
+            removed += before - sinks.len();
+        }
+        self.exact.retain(|_, sinks| !sinks.is_empty());
+        self.patterns.retain(|_, sinks| !sinks.is_empty());
+        removed
+    }
+
+    /// Closes every subscriber and pending-ack queue and empties the table, so each
+    /// connection's send task sends a close frame and ends. Used on server shutdown.
+    /// Returns the number of distinct connections closed.
+    pub fn close_all(&mut self) -> usize {
+        let mut closed: Vec<u64> = Vec::new();
+        let senders = self.exact
+            .drain()
+            .chain(self.patterns.drain())
+            .flat_map(|(_, sinks)| sinks)
+            .chain(self.pending_acks.drain().map(|(_, publisher)| publisher));
+        for sender in senders {
+            if !closed.contains(&sender.id()) {
+                closed.push(sender.id());
+                sender.close();
+            }
+        }
+        closed.len()
+    }
+
+    /// Returns the exact topics and patterns (with their trailing `*`) and how many
+    /// senders each currently holds.
+    pub fn topics(&self) -> impl Iterator<Item = (String, usize)> + '_ {
+        self.exact
+            .iter()
+            .map(|(topic, sinks)| (topic.clone(), sinks.len()))
+            .chain(self.patterns.iter().map(|(prefix, sinks)| (format!("{}*", prefix), sinks.len())))
+    }
+
+    /// Sends `message` to every subscriber of `topic`, including matching patterns.
+    ///
+    /// A channel subscribed both exactly and through a pattern receives the message once.
+    /// Senders whose connection has gone away, or that were disconnected by the
+    /// overflow policy, are removed. Returns the number of deliveries.
+    pub fn publish(&mut self, topic: &str, message: &str) -> usize {
+        let mut delivered: Vec<SubscriberSender> = Vec::new();
+
+        let matching_patterns = self.patterns
+            .iter_mut()
+            .filter(|(prefix, _)| topic.starts_with(prefix.as_str()))
+            .map(|(_, sinks)| sinks);
+
+        for sinks in self.exact.get_mut(topic).into_iter().chain(matching_patterns) {
+            sinks.retain(|s| {
+                if delivered.iter().any(|d| d.same_channel(s)) {
+                    return true;
+                }
+                if let Err(e) = s.send(message.to_string()) {
+                    eprintln!("[publish-json] Failed to send to subscriber: {}", e);
+                    return false;
+                }
+                delivered.push(s.clone());
+                true
+            });
+        }
+
+        delivered.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    fn small_queue(policy: OverflowPolicy) -> QueueConfig {
+        QueueConfig { capacity: 2, policy }
+    }
+
+    /// Messages the receiver can take right now, without waiting.
+    fn drain(rx: &mut SubscriberReceiver) -> Vec<String> {
+        let mut messages = Vec::new();
+        while let Some(Some(message)) = rx.recv().now_or_never() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[test]
+    fn full_queue_drops_oldest_message() {
+        let (tx, mut rx) = subscriber_channel(small_queue(OverflowPolicy::DropOldest));
+        for message in ["one", "two", "three"] {
+            assert!(tx.send(message.to_string()).is_ok());
+        }
+
+        assert!(!tx.is_closed());
+        assert_eq!(drain(&mut rx), ["two", "three"]);
+    }
+
+    #[test]
+    fn full_queue_disconnects_subscriber() {
+        let (tx, mut rx) = subscriber_channel(small_queue(OverflowPolicy::Disconnect));
+        assert!(tx.send("one".to_string()).is_ok());
+        assert!(tx.send("two".to_string()).is_ok());
+
+        assert!(tx.send("three".to_string()).is_err());
+        assert!(tx.is_closed());
+        assert!(tx.send("four".to_string()).is_err());
+        assert_eq!(rx.recv().now_or_never(), Some(None));
+    }
+
+    #[test]
+    fn publish_removes_subscriber_disconnected_for_overflow() {
+        let mut table = SubscriberTable::with_queue_config(small_queue(OverflowPolicy::Disconnect));
+        let (slow, _slow_rx) = subscriber_channel(table.queue_config());
+        let (fast, mut fast_rx) = subscriber_channel(table.queue_config());
+        table.subscribe("Topic", slow);
+        table.subscribe("Topic", fast);
+
+        assert_eq!(table.publish("Topic", "one"), 2);
+        assert_eq!(table.publish("Topic", "two"), 2);
+        drain(&mut fast_rx);
+
+        // The slow client never read, so its third message overflows
+        assert_eq!(table.publish("Topic", "three"), 1);
+        assert_eq!(table.topics().collect::<Vec<_>>(), [("Topic".to_string(), 1)]);
+        assert_eq!(drain(&mut fast_rx), ["three"]);
+    }
+
+    #[test]
+    fn pattern_receives_topics_with_its_prefix() {
+        let mut table = SubscriberTable::default();
+        let (tx, mut rx) = subscriber_channel(table.queue_config());
+        table.subscribe("plugin.*", tx);
+
+        assert_eq!(table.publish("plugin.wifi", "wifi"), 1);
+        assert_eq!(table.publish("plugin.", "bare"), 1);
+        assert_eq!(table.publish("pluginwifi", "no dot"), 0);
+        assert_eq!(table.publish("Other", "other"), 0);
+        assert_eq!(drain(&mut rx), ["wifi", "bare"]);
+    }
+
+    #[test]
+    fn star_receives_every_topic() {
+        let mut table = SubscriberTable::default();
+        let (tx, mut rx) = subscriber_channel(table.queue_config());
+        table.subscribe("*", tx);
+
+        assert_eq!(table.publish("SwitchRoute", "a"), 1);
+        assert_eq!(table.publish("plugin.wifi", "b"), 1);
+        assert_eq!(drain(&mut rx), ["a", "b"]);
+    }
+
+    #[test]
+    fn publish_without_matching_subscriber_delivers_nothing() {
+        let mut table = SubscriberTable::default();
+        let (tx, mut rx) = subscriber_channel(table.queue_config());
+        table.subscribe("NetworkConnected", tx);
+
+        assert_eq!(table.publish("Network", "prefix of an exact topic"), 0);
+        assert_eq!(table.publish("NetworkConnected.extra", "longer"), 0);
+        assert_eq!(table.publish("Unrelated", "nothing"), 0);
+        assert!(drain(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn exact_and_pattern_subscription_is_delivered_once() {
+        let mut table = SubscriberTable::default();
+        let (both, mut both_rx) = subscriber_channel(table.queue_config());
+        let (exact_only, mut exact_rx) = subscriber_channel(table.queue_config());
+        table.subscribe("plugin.wifi", both.clone());
+        table.subscribe("plugin.*", both.clone());
+        table.subscribe("*", both);
+        table.subscribe("plugin.wifi", exact_only);
+
+        assert_eq!(table.publish("plugin.wifi", "once"), 2);
+        assert_eq!(drain(&mut both_rx), ["once"]);
+        assert_eq!(drain(&mut exact_rx), ["once"]);
+
+        // Only the patterns match here, and they still deliver once
+        assert_eq!(table.publish("plugin.mock", "patterns"), 1);
+        assert_eq!(drain(&mut both_rx), ["patterns"]);
+        assert!(drain(&mut exact_rx).is_empty());
+    }
+
+    #[test]
+    fn clones_share_a_subscriber_id() {
+        let (tx, _rx) = subscriber_channel(QueueConfig::default());
+        let (other, _other_rx) = subscriber_channel(QueueConfig::default());
+
+        assert_eq!(tx.clone().id(), tx.id());
+        assert!(tx.same_channel(&tx.clone()));
+        assert!(!tx.same_channel(&other));
+    }
+
+    #[test]
+    fn abruptly_disconnected_subscriber_is_pruned_on_publish() {
+        let mut table = SubscriberTable::default();
+        let (gone, gone_rx) = subscriber_channel(table.queue_config());
+        let (live, mut live_rx) = subscriber_channel(table.queue_config());
+        table.subscribe("Topic", gone);
+        table.subscribe("plugin.*", live.clone());
+        table.subscribe("Topic", live);
+
+        // The connection dies without unsubscribing
+        drop(gone_rx);
+
+        assert_eq!(table.publish("Topic", "hello"), 1);
+        assert_eq!(drain(&mut live_rx), ["hello"]);
+        let mut counts = table.topics().collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, [("Topic".to_string(), 1), ("plugin.*".to_string(), 1)]);
+    }
+
+    #[test]
+    fn dead_subscribers_on_quiet_topics_are_swept() {
+        let mut table = SubscriberTable::default();
+        let (gone, gone_rx) = subscriber_channel(table.queue_config());
+        table.subscribe("Quiet", gone);
+        drop(gone_rx);
+
+        // Nothing is ever published to "Quiet"; the next subscribe sweeps it
+        let (live, _live_rx) = subscriber_channel(table.queue_config());
+        table.subscribe("Other", live);
+        assert_eq!(table.topics().collect::<Vec<_>>(), [("Other".to_string(), 1)]);
+
+        let (gone, gone_rx) = subscriber_channel(table.queue_config());
+        table.subscribe("Quiet", gone);
+        drop(gone_rx);
+        assert_eq!(table.prune_closed(), 1);
+        assert_eq!(table.prune_closed(), 0);
+    }
+}
@@ -1,5 +1,5 @@
 // src/ws_client.rs
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message, tungstenite::error::ProtocolError};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message, tungstenite::error::{ProtocolError, UrlError}};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use futures_util::{SinkExt, StreamExt};
 use tokio::task::JoinHandle;
@@ -8,96 +8,229 @@ use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use futures_util::stream::{SplitSink, SplitStream};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use serde_json::json;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
 
 type Callback = Box<dyn Fn(String) + Send + Sync>;
+type RequestCallback = Box<dyn Fn(String, String) + Send + Sync>;
+type TokenProvider = Arc<dyn Fn() -> Result<String, String> + Send + Sync>;
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type WsStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// Controls how a client created with `connect_with_reconnect` retries a lost connection.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Attempts per outage before giving up. `None` retries forever.
+    pub max_attempts: Option<u32>,
+    /// Delay before the first retry; doubled after every failed attempt.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: None,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Attempts `WsClient::connect_as_service` makes per connection before giving up.
+pub const SERVICE_CONNECT_ATTEMPTS: u32 = 5;
+
+/// Cached service tokens are replaced once they get this close to expiring.
+const TOKEN_REFRESH_MARGIN_SECS: u64 = 300;
+
+/// Connection state shared between the client and its background receive task.
+struct Shared {
+    name: String,
+    ws_url: String,
+    token_provider: Option<TokenProvider>, // Asked for a fresh JWT before every (re)connect
+    ws_channel: tokio::sync::Mutex<WsSink>, // WebSocket channel for sending messages, replaced on reconnect
+    on_message_handlers: Mutex<HashMap<String, Callback>>, // Handlers for incoming messages by topic
+    subscriptions: Mutex<Vec<String>>, // Topics replayed after a reconnect
+    pending_acks: Mutex<HashMap<String, oneshot::Sender<()>>>, // Acked publishes waiting on `ack:<msg_id>`
+    on_request_handlers: Mutex<HashMap<String, RequestCallback>>, // Handlers for `request` calls by topic
+    reply_topic: String, // Private topic that replies to this client's requests arrive on
+    pending_requests: Mutex<HashMap<String, oneshot::Sender<String>>>, // Requests waiting on a reply, by correlation id
+    is_connected: Mutex<bool>,
+}
 
 /// Represents a WebSocket client with per-topic message handlers.
 pub struct WsClient {
     pub name: String, // The name of the client
-    pub ws_channel: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>, // WebSocket channel for sending messages
-    on_message_handlers: Arc<Mutex<HashMap<String, Callback>>>, // Handlers for incoming messages by topic
+    shared: Arc<Shared>,
     _async_task_handler: JoinHandle<()>, // Background task for receiving messages
-    is_connected: Arc<Mutex<bool>>, // Add connection state
 }
 
 impl WsClient {
     /// Connects to a WebSocket server and registers the client name.
     pub async fn connect(client_name: &str, ws_url: &str) -> tokio_tungstenite::tungstenite::Result<Self> {
-        println!("[connect] client_name={}, ws_url={} -- executing", client_name, ws_url);
-
-        // Create request and add Authorization header if JWT is provided
-        let request = ws_url.into_client_request()?;
+        Self::connect_inner(client_name, ws_url, None, None).await
+    }
 
-        // Establish the WebSocket connection with the request
-        let (stream, _) = connect_async(request).await?;
-        let (mut ws_channel, mut ws_receiver): (SplitSink<_, _>, SplitStream<_>) = stream.split();
+    /// Connects like `connect`, but keeps the connection alive.
+    ///
+    /// The initial connection is retried according to `policy`. If the connection drops
+    /// later, the client reconnects with backoff, registers its name again and replays
+    /// every active `subscribe`. Handlers registered with `on_message` stay in place.
+    pub async fn connect_with_reconnect(
+        client_name: &str,
+        ws_url: &str,
+        policy: ReconnectPolicy,
+    ) -> tokio_tungstenite::tungstenite::Result<Self> {
+        Self::connect_retrying(client_name, ws_url, None, policy).await
+    }
 
-        // Register the client name with the server
-        let register_msg = format!("register-name:{}", client_name);
-        ws_channel.send(Message::Text(register_msg)).await?;
+    /// Connects with a JWT from `token_provider` and reconnects with the default
+    /// `ReconnectPolicy` when the connection drops.
+    ///
+    /// The provider is called before every connection attempt and its token appended
+    /// to `ws_url` as the `token` query parameter, so a reconnect never presents a token
+    /// that expired while the old connection was up. Use `service_token_provider` for the
+    /// engine and its plugins.
+    pub async fn connect_authenticated<F>(
+        client_name: &str,
+        ws_url: &str,
+        token_provider: F,
+    ) -> tokio_tungstenite::tungstenite::Result<Self>
+    where
+        F: Fn() -> Result<String, String> + Send + Sync + 'static,
+    {
+        Self::connect_authenticated_with_policy(client_name, ws_url, token_provider, ReconnectPolicy::default()).await
+    }
 
-        let name_clone = client_name.to_string();
-        let handlers = Arc::new(Mutex::new(HashMap::<String, Callback>::new()));
-        let handlers_clone = handlers.clone();
+    /// Like `connect_authenticated`, retrying according to `policy`.
+    pub async fn connect_authenticated_with_policy<F>(
+        client_name: &str,
+        ws_url: &str,
+        token_provider: F,
+        policy: ReconnectPolicy,
+    ) -> tokio_tungstenite::tungstenite::Result<Self>
+    where
+        F: Fn() -> Result<String, String> + Send + Sync + 'static,
+    {
+        Self::connect_retrying(client_name, ws_url, Some(Arc::new(token_provider)), policy).await
+    }
 
-        // Spawn a task to handle incoming messages
-        let task = tokio::spawn(async move {
-            while let Some(Ok(msg)) = ws_receiver.next().await {
-                if let Message::Text(txt) = msg {
-                    match serde_json::from_str::<serde_json::Value>(&txt) {
-                        Ok(parsed) => {
-                            let topic = parsed.get("topic").and_then(|t| t.as_str()).unwrap_or("<unknown>");
-                            let payload = parsed.get("payload").and_then(|m| m.as_str()).unwrap_or("<no message>");
-                            let publisher = parsed.get("publisher_name").and_then(|p| p.as_str()).unwrap_or("<unknown>");
-                            let timestamp = parsed.get("timestamp").and_then(|t| t.as_str()).unwrap_or("???");
-
-                            println!(
-                                "[on_message] {} <- topic={}, payload={}, publisher={}, timestamp={}",
-                                name_clone, topic, payload, publisher, timestamp
-                            );
-
-                            // Invoke the callback for the topic if it exists
-                            if let Some(callback) = handlers_clone.lock().unwrap().get(topic) {
-                                callback(payload.to_string());
-                            }
-                        }
-                        Err(_) => {
-                            println!("[on_message] {} received malformed text: {}", name_clone, txt);
-                        }
+    async fn connect_retrying(
+        client_name: &str,
+        ws_url: &str,
+        token_provider: Option<TokenProvider>,
+        policy: ReconnectPolicy,
+    ) -> tokio_tungstenite::tungstenite::Result<Self> {
+        let mut attempt: u32 = 0;
+        let mut backoff = policy.initial_backoff;
+        loop {
+            match Self::connect_inner(client_name, ws_url, token_provider.clone(), Some(policy.clone())).await {
+                Ok(client) => return Ok(client),
+                Err(e) => {
+                    attempt += 1;
+                    if policy.max_attempts.is_some_and(|max| attempt >= max) {
+                        return Err(e);
                     }
+                    println!("[connect] client_name={} attempt {} failed: {} -- retrying", client_name, attempt, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(policy.max_backoff);
                 }
             }
+        }
+    }
+
+    async fn connect_inner(
+        client_name: &str,
+        ws_url: &str,
+        token_provider: Option<TokenProvider>,
+        policy: Option<ReconnectPolicy>,
+    ) -> tokio_tungstenite::tungstenite::Result<Self> {
+        // Keep tokens in the query string out of the log
+        let display_url = ws_url.split('?').next().unwrap_or(ws_url);
+        println!("[connect] client_name={}, ws_url={} -- executing", client_name, display_url);
+
+        let url = connect_url(ws_url, token_provider.as_ref()).map_err(url_error)?;
+        let (ws_channel, ws_receiver) = open_channel(client_name, &url, &[]).await?;
+
+        let shared = Arc::new(Shared {
+            name: client_name.to_string(),
+            ws_url: ws_url.to_string(),
+            token_provider,
+            ws_channel: tokio::sync::Mutex::new(ws_channel),
+            on_message_handlers: Mutex::new(HashMap::new()),
+            subscriptions: Mutex::new(Vec::new()),
+            pending_acks: Mutex::new(HashMap::new()),
+            on_request_handlers: Mutex::new(HashMap::new()),
+            reply_topic: format!("reply.{}.{:08x}", client_name, rand::random::<u32>()),
+            pending_requests: Mutex::new(HashMap::new()),
+            is_connected: Mutex::new(true),
         });
 
+        // Spawn a task to handle incoming messages
+        let task = tokio::spawn(receive_loop(shared.clone(), ws_receiver, policy));
+
         println!("[connect] client_name={} -- complete", client_name);
 
         Ok(Self {
             name: client_name.to_string(),
-            ws_channel,
-            on_message_handlers: handlers,
+            shared,
             _async_task_handler: task,
-            is_connected: Arc::new(Mutex::new(true)),
         })
     }
 
     /// Connects to a WebSocket server with JWT authentication and registers the client name.
+    ///
+    /// The token is sent as the `token` query parameter of the upgrade request.
     pub async fn connect_with_jwt(client_name: &str, ws_url: &str, jwt_token: &str) -> tokio_tungstenite::tungstenite::Result<Self> {
-        println!("[connect_with_jwt] client_name={}, ws_url={} -- executing", client_name, ws_url);
+        println!("[connect_with_jwt] client_name={} -- executing", client_name);
 
         match libjwt::validate_jwt(jwt_token) {
-            Ok(_) => Self::connect(client_name, ws_url).await,
+            Ok(_) => {
+                let url = authenticated_url(ws_url, jwt_token).map_err(url_error)?;
+                Self::connect(client_name, &url).await
+            }
             Err(_e) => Err(tokio_tungstenite::tungstenite::Error::Protocol(
                 ProtocolError::ResetWithoutClosingHandshake
             ))
         }
     }
 
+    /// Connects with a service token, for the engine and its plugins.
+    ///
+    /// Tokens come from `service_token_provider`, so a reconnect after the connection
+    /// drops presents a token that is still valid. Each connection, the first included,
+    /// is given `SERVICE_CONNECT_ATTEMPTS` attempts.
+    pub async fn connect_as_service(client_name: &str, ws_url: &str) -> tokio_tungstenite::tungstenite::Result<Self> {
+        let policy = ReconnectPolicy {
+            max_attempts: Some(SERVICE_CONNECT_ATTEMPTS),
+            ..ReconnectPolicy::default()
+        };
+        Self::connect_authenticated_with_policy(client_name, ws_url, service_token_provider(client_name), policy).await
+    }
+
+    /// Sends a raw protocol frame, marking the client disconnected if the send fails.
+    async fn send_text(&self, text: String) -> Result<(), String> {
+        let mut ws_channel = self.shared.ws_channel.lock().await;
+        ws_channel.send(Message::Text(text)).await.map_err(|e| {
+            *self.shared.is_connected.lock().unwrap() = false;
+            format!("Failed to send message: {}", e)
+        })
+    }
+
     /// Subscribes the client to a specific topic.
     pub async fn subscribe(&mut self, subscriber_name: &str, topic: &str, payload: &str) {
         println!("[subscribe] subscriber_name={}, topic={}, payload={}", subscriber_name, topic, payload);
+        {
+            let mut subscriptions = self.shared.subscriptions.lock().unwrap();
+            if !subscriptions.iter().any(|t| t == topic) {
+                subscriptions.push(topic.to_string());
+            }
+        }
+
         let cmd = format!("subscribe:{}", topic);
-        if let Err(e) = self.ws_channel.send(Message::Text(cmd)).await {
+        if let Err(e) = self.send_text(cmd).await {
             println!("[subscribe] Error: {:?}", e);
         }
     }
@@ -105,38 +238,152 @@ impl WsClient {
     /// Unsubscribes the client from a specific topic.
     pub async fn unsubscribe(&mut self, topic: &str) {
         println!("[unsubscribe] topic={}", topic);
+        self.shared.subscriptions.lock().unwrap().retain(|t| t != topic);
+
         let cmd = format!("unsubscribe:{}", topic);
-        if let Err(e) = self.ws_channel.send(Message::Text(cmd)).await {
+        if let Err(e) = self.send_text(cmd).await {
             println!("[unsubscribe] Error: {:?}", e);
         }
     }
 
-    /// Publishes a message to a specific topic.
-    pub async fn publish(&mut self, publisher_name: &str, topic: &str, payload: &str, timestamp: &str) -> Result<(), String> {
+    /// Publishes a message to a specific topic without waiting for delivery.
+    ///
+    /// `payload` can be any JSON value, so structured events don't need to be encoded
+    /// into a string first; plain `&str` payloads still work as before.
+    ///
+    /// With `retain` set, the server keeps this message as the topic's current value and
+    /// sends it to clients that subscribe later. Publishing an empty retained payload clears it.
+    pub async fn publish(&mut self, publisher_name: &str, topic: &str, payload: impl Into<Value>, timestamp: &str, retain: bool) -> Result<(), String> {
+        let payload = payload.into();
+
         // Check connection state first
-        if !*self.is_connected.lock().unwrap() {
+        if !self.is_connected() {
             return Err("WebSocket is not connected".to_string());
         }
 
-        println!("[publish] publisher_name={}, topic={}, payload={}, timestamp={}", 
+        println!("[publish] publisher_name={}, topic={}, payload={}, timestamp={}",
             publisher_name, topic, payload, timestamp);
-        
+
         let msg = json!({
             "publisher_name": publisher_name,
             "topic": topic,
             "payload": payload,
-            "timestamp": timestamp
+            "timestamp": timestamp,
+            "retain": retain
         });
-        let cmd = format!("publish-json:{}", msg.to_string());
+        let cmd = format!("publish-json:{}", msg);
 
-        match self.ws_channel.send(Message::Text(cmd)).await {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                // Mark as disconnected on error
-                *self.is_connected.lock().unwrap() = false;
-                Err(format!("Failed to send message: {}", e))
-            }
+        self.send_text(cmd).await
+    }
+
+    /// Publishes a message and waits until at least one subscriber acknowledges it.
+    ///
+    /// The message carries a `msg_id`; subscribers using `WsClient` acknowledge it
+    /// automatically after their `on_message` handler has run. Fails if nobody
+    /// acknowledges within `timeout`, including when the topic has no subscribers.
+    pub async fn publish_with_ack(
+        &mut self,
+        publisher_name: &str,
+        topic: &str,
+        payload: impl Into<Value>,
+        timestamp: &str,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let payload = payload.into();
+        if !self.is_connected() {
+            return Err("WebSocket is not connected".to_string());
+        }
+
+        let msg_id = format!("{}-{:016x}", self.name, rand::random::<u64>());
+        println!("[publish_with_ack] publisher_name={}, topic={}, payload={}, msg_id={}",
+            publisher_name, topic, payload, msg_id);
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.shared.pending_acks.lock().unwrap().insert(msg_id.clone(), ack_tx);
+
+        let msg = json!({
+            "publisher_name": publisher_name,
+            "topic": topic,
+            "payload": payload,
+            "timestamp": timestamp,
+            "msg_id": msg_id
+        });
+        let cmd = format!("publish-json:{}", msg);
+
+        let result = match self.send_text(cmd).await {
+            Ok(()) => match tokio::time::timeout(timeout, ack_rx).await {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(_)) => Err(format!("Connection lost before {} was acknowledged", msg_id)),
+                Err(_) => Err(format!("No acknowledgement for {} within {:?}", msg_id, timeout)),
+            },
+            Err(e) => Err(e),
+        };
+
+        self.shared.pending_acks.lock().unwrap().remove(&msg_id);
+        result
+    }
+
+    /// Sends a request on `topic` and waits for a subscriber to answer it with `reply`.
+    ///
+    /// The message carries a `correlation_id` naming this client's private reply topic,
+    /// which the client subscribes to on first use. Fails if no reply arrives within `timeout`.
+    pub async fn request(&mut self, topic: &str, payload: impl Into<Value>, timeout: Duration) -> Result<String, String> {
+        if !self.is_connected() {
+            return Err("WebSocket is not connected".to_string());
+        }
+
+        let reply_topic = self.shared.reply_topic.clone();
+        let subscribed = self.shared.subscriptions.lock().unwrap().contains(&reply_topic);
+        if !subscribed {
+            let name = self.name.clone();
+            self.subscribe(&name, &reply_topic, "").await;
         }
+
+        let correlation_id = format!("{}#{:016x}", reply_topic, rand::random::<u64>());
+        println!("[request] topic={}, correlation_id={}", topic, correlation_id);
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.shared.pending_requests.lock().unwrap().insert(correlation_id.clone(), reply_tx);
+
+        let msg = json!({
+            "publisher_name": self.name,
+            "topic": topic,
+            "payload": payload.into(),
+            "correlation_id": correlation_id
+        });
+        let cmd = format!("publish-json:{}", msg);
+
+        let result = match self.send_text(cmd).await {
+            Ok(()) => match tokio::time::timeout(timeout, reply_rx).await {
+                Ok(Ok(reply)) => Ok(reply),
+                Ok(Err(_)) => Err(format!("Connection lost before {} was answered", correlation_id)),
+                Err(_) => Err(format!("No reply to {} within {:?}", correlation_id, timeout)),
+            },
+            Err(e) => Err(e),
+        };
+
+        self.shared.pending_requests.lock().unwrap().remove(&correlation_id);
+        result
+    }
+
+    /// Answers a request received by an `on_request` handler.
+    pub async fn reply(&mut self, correlation_id: &str, payload: impl Into<Value>) -> Result<(), String> {
+        // The correlation id starts with the requester's reply topic
+        let (reply_topic, _) = correlation_id
+            .split_once('#')
+            .ok_or_else(|| format!("Malformed correlation_id: {}", correlation_id))?;
+
+        println!("[reply] topic={}, correlation_id={}", reply_topic, correlation_id);
+
+        let msg = json!({
+            "publisher_name": self.name,
+            "topic": reply_topic,
+            "payload": payload.into(),
+            "correlation_id": correlation_id
+        });
+        let cmd = format!("publish-json:{}", msg);
+
+        self.send_text(cmd).await
     }
 
     /// Registers a callback to handle messages for a specific topic.
@@ -145,7 +392,25 @@ impl WsClient {
         F: Fn(String) + Send + Sync + 'static,
     {
         println!("[on_message] registering handler for topic: {}", topic);
-        self.on_message_handlers
+        self.shared
+            .on_message_handlers
+            .lock()
+            .unwrap()
+            .insert(topic.to_string(), Box::new(callback));
+    }
+
+    /// Registers a callback to handle requests sent with `request` on a specific topic.
+    ///
+    /// The callback gets the correlation id and the payload, and answers by passing
+    /// that id to `reply`. Plain publishes on the topic still go to `on_message`.
+    /// As with `on_message`, the client must also `subscribe` to the topic.
+    pub fn on_request<F>(&mut self, topic: &str, callback: F)
+    where
+        F: Fn(String, String) + Send + Sync + 'static,
+    {
+        println!("[on_request] registering handler for topic: {}", topic);
+        self.shared
+            .on_request_handlers
             .lock()
             .unwrap()
             .insert(topic.to_string(), Box::new(callback));
@@ -153,6 +418,339 @@ impl WsClient {
 
     /// Checks if the WebSocket connection is active.
     pub fn is_connected(&self) -> bool {
-        *self.is_connected.lock().unwrap()
+        *self.shared.is_connected.lock().unwrap()
+    }
+}
+
+fn url_error(message: String) -> tokio_tungstenite::tungstenite::Error {
+    tokio_tungstenite::tungstenite::Error::Url(UrlError::UnableToConnect(message))
+}
+
+/// Returns `ws_url` with `token` added as the `token` query parameter.
+pub fn authenticated_url(ws_url: &str, token: &str) -> Result<String, String> {
+    let mut url = url::Url::parse(ws_url).map_err(|e| format!("Invalid WebSocket URL {}: {}", ws_url, e))?;
+    url.query_pairs_mut().append_pair("token", token);
+    Ok(url.to_string())
+}
+
+/// Returns the URL for the next connection attempt, with a fresh token if the client has a provider.
+fn connect_url(ws_url: &str, token_provider: Option<&TokenProvider>) -> Result<String, String> {
+    match token_provider {
+        Some(provider) => authenticated_url(ws_url, &provider()?),
+        None => Ok(ws_url.to_string()),
+    }
+}
+
+/// Returns a token provider for `connect_authenticated` that mints service tokens
+/// for `client_name`.
+///
+/// The last token is cached and handed out again until it is within a few minutes
+/// of expiring, so frequent reconnects don't mint a new token each time.
+pub fn service_token_provider(client_name: &str) -> impl Fn() -> Result<String, String> + Send + Sync + 'static {
+    let client_name = client_name.to_string();
+    let cached: Mutex<Option<(String, u64)>> = Mutex::new(None);
+    move || {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_secs();
+
+        let mut cached = cached.lock().unwrap();
+        if let Some((token, exp)) = cached.as_ref() {
+            if *exp > now + TOKEN_REFRESH_MARGIN_SECS {
+                return Ok(token.clone());
+            }
+        }
+
+        let token = libjwt::generate_service_token(&client_name)
+            .map_err(|e| format!("Failed to create service token: {}", e))?;
+        let exp = libjwt::validate_jwt(&token)
+            .map_err(|e| format!("Failed to read service token: {}", e))?
+            .exp as u64;
+        *cached = Some((token.clone(), exp));
+        Ok(token)
+    }
+}
+
+/// Returns `ws_url` carrying a service token for `client_name`.
+///
+/// The token is fixed into the URL, so a client that reconnects with it fails once the
+/// token expires; long-lived clients should use `WsClient::connect_as_service` instead.
+pub fn service_url(client_name: &str, ws_url: &str) -> Result<String, String> {
+    let token = libjwt::generate_service_token(client_name)
+        .map_err(|e| format!("Failed to create service token: {}", e))?;
+    authenticated_url(ws_url, &token)
+}
+
+/// Opens a connection, registers the client name and subscribes to `topics`.
+async fn open_channel(
+    client_name: &str,
+    ws_url: &str,
+    topics: &[String],
+) -> tokio_tungstenite::tungstenite::Result<(WsSink, WsStream)> {
+    let request = ws_url.into_client_request()?;
+
+    // Establish the WebSocket connection with the request
+    let (stream, _) = connect_async(request).await?;
+    let (mut ws_channel, ws_receiver) = stream.split();
+
+    // Register the client name with the server
+    let register_msg = format!("register-name:{}", client_name);
+    ws_channel.send(Message::Text(register_msg)).await?;
+
+    for topic in topics {
+        ws_channel.send(Message::Text(format!("subscribe:{}", topic))).await?;
+    }
+
+    Ok((ws_channel, ws_receiver))
+}
+
+/// Reads incoming messages, reconnecting according to `policy` when the connection drops.
+async fn receive_loop(shared: Arc<Shared>, mut ws_receiver: WsStream, policy: Option<ReconnectPolicy>) {
+    loop {
+        while let Some(Ok(msg)) = ws_receiver.next().await {
+            if let Message::Text(txt) = msg {
+                if let Some(msg_id) = txt.strip_prefix("ack:") {
+                    if let Some(ack_tx) = shared.pending_acks.lock().unwrap().remove(msg_id.trim()) {
+                        let _ = ack_tx.send(());
+                    }
+                } else if let Some(msg_id) = dispatch_message(&shared, &txt) {
+                    let ack = Message::Text(format!("ack:{}", msg_id));
+                    if let Err(e) = shared.ws_channel.lock().await.send(ack).await {
+                        println!("[on_message] {} failed to ack {}: {}", shared.name, msg_id, e);
+                    }
+                }
+            }
+        }
+
+        *shared.is_connected.lock().unwrap() = false;
+        // Acks for messages sent on the old connection will never arrive
+        shared.pending_acks.lock().unwrap().clear();
+        shared.pending_requests.lock().unwrap().clear();
+        println!("[on_message] {} connection closed", shared.name);
+
+        let Some(policy) = &policy else {
+            return;
+        };
+        match reconnect(&shared, policy).await {
+            Some(receiver) => ws_receiver = receiver,
+            None => return,
+        }
+    }
+}
+
+/// Retries the connection with backoff, swapping in the new sink and replaying subscriptions.
+async fn reconnect(shared: &Shared, policy: &ReconnectPolicy) -> Option<WsStream> {
+    let mut attempt: u32 = 0;
+    let mut backoff = policy.initial_backoff;
+
+    while policy.max_attempts.is_none_or(|max| attempt < max) {
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+
+        let topics = shared.subscriptions.lock().unwrap().clone();
+        let url = match connect_url(&shared.ws_url, shared.token_provider.as_ref()) {
+            Ok(url) => url,
+            Err(e) => {
+                println!("[reconnect] {} attempt {} could not get a token: {}", shared.name, attempt, e);
+                backoff = (backoff * 2).min(policy.max_backoff);
+                continue;
+            }
+        };
+        match open_channel(&shared.name, &url, &topics).await {
+            Ok((ws_channel, ws_receiver)) => {
+                *shared.ws_channel.lock().await = ws_channel;
+                *shared.is_connected.lock().unwrap() = true;
+                println!("[reconnect] {} reconnected after {} attempt(s), resubscribed to {} topic(s)",
+                    shared.name, attempt, topics.len());
+                return Some(ws_receiver);
+            }
+            Err(e) => {
+                println!("[reconnect] {} attempt {} failed: {}", shared.name, attempt, e);
+                backoff = (backoff * 2).min(policy.max_backoff);
+            }
+        }
+    }
+
+    println!("[reconnect] {} giving up after {} attempt(s)", shared.name, attempt);
+    None
+}
+
+/// Invokes the handler registered for the message's topic, if any.
+///
+/// Returns the message's `msg_id` when a handler ran and the publisher asked for an ack.
+fn dispatch_message(shared: &Shared, txt: &str) -> Option<String> {
+    match serde_json::from_str::<Value>(txt) {
+        Ok(parsed) => {
+            let topic = parsed.get("topic").and_then(|t| t.as_str()).unwrap_or("<unknown>");
+            // String payloads are passed through; structured ones as their JSON text
+            let payload = match parsed.get("payload") {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => "<no message>".to_string(),
+                Some(value) => value.to_string(),
+            };
+            let publisher = parsed.get("publisher_name").and_then(|p| p.as_str()).unwrap_or("<unknown>");
+            let timestamp = parsed.get("timestamp").and_then(|t| t.as_str()).unwrap_or("???");
+
+            println!(
+                "[on_message] {} <- topic={}, payload={}, publisher={}, timestamp={}",
+                shared.name, topic, payload, publisher, timestamp
+            );
+
+            let correlation_id = parsed.get("correlation_id").and_then(|c| c.as_str());
+            if let (Some(correlation_id), true) = (correlation_id, topic == shared.reply_topic) {
+                // A reply to one of this client's own requests
+                if let Some(reply_tx) = shared.pending_requests.lock().unwrap().remove(correlation_id) {
+                    let _ = reply_tx.send(payload);
+                }
+                return None;
+            }
+
+            // Requests go to a request handler if there is one, everything else to the topic's callback
+            let request_handlers = shared.on_request_handlers.lock().unwrap();
+            let handled = match (correlation_id, request_handlers.get(topic)) {
+                (Some(correlation_id), Some(callback)) => {
+                    callback(correlation_id.to_string(), payload);
+                    true
+                }
+                _ => match shared.on_message_handlers.lock().unwrap().get(topic) {
+                    Some(callback) => {
+                        callback(payload);
+                        true
+                    }
+                    None => false,
+                },
+            };
+
+            let msg_id = parsed.get("msg_id").and_then(|m| m.as_str());
+            msg_id.filter(|_| handled).map(str::to_string)
+        }
+        Err(_) => {
+            println!("[on_message] {} received malformed text: {}", shared.name, txt);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+    /// Accepts WebSocket upgrades on a free port and reports the `token` each one carried.
+    ///
+    /// With `drop_first` set, the first connection is closed right after the client has
+    /// registered its name, forcing a reconnect.
+    #[allow(clippy::result_large_err)] // the handshake callback's error type is fixed by tungstenite
+    async fn start_token_recorder(drop_first: bool) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut first = true;
+            while let Ok((stream, _)) = listener.accept().await {
+                let tx = tx.clone();
+                let record = move |request: &Request, response: Response| {
+                    let url = url::Url::parse(&format!("ws://localhost{}", request.uri())).unwrap();
+                    let token = url.query_pairs().find(|(k, _)| k == "token").map(|(_, v)| v.into_owned());
+                    let _ = tx.send(token.unwrap_or_default());
+                    Ok(response)
+                };
+                let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, record).await else {
+                    continue;
+                };
+                let drop_this = drop_first && first;
+                first = false;
+                tokio::spawn(async move {
+                    if drop_this {
+                        let _ = socket.next().await;
+                        return;
+                    }
+                    while let Some(Ok(_)) = socket.next().await {}
+                });
+            }
+        });
+        (format!("ws://{}/ws", addr), rx)
+    }
+
+    /// A provider that hands out `stub-token-1`, `stub-token-2`, ... and counts its calls.
+    fn counting_provider(calls: Arc<AtomicU32>) -> impl Fn() -> Result<String, String> + Send + Sync + 'static {
+        move || Ok(format!("stub-token-{}", calls.fetch_add(1, Ordering::SeqCst) + 1))
+    }
+
+    async fn next_token(tokens: &mut mpsc::UnboundedReceiver<String>) -> String {
+        tokio::time::timeout(Duration::from_secs(5), tokens.recv())
+            .await
+            .expect("no connection attempt reached the server")
+            .unwrap()
     }
-}
\ No newline at end of file
+
+    fn quick_policy(max_attempts: Option<u32>) -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_authenticated_presents_the_provider_token() {
+        let (url, mut tokens) = start_token_recorder(false).await;
+        let calls = Arc::new(AtomicU32::new(0));
+
+        let client = WsClient::connect_authenticated("stub", &url, counting_provider(calls.clone())).await.unwrap();
+
+        assert!(client.is_connected());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(next_token(&mut tokens).await, "stub-token-1");
+    }
+
+    #[tokio::test]
+    async fn reconnect_asks_the_provider_for_a_fresh_token() {
+        let (url, mut tokens) = start_token_recorder(true).await;
+        let calls = Arc::new(AtomicU32::new(0));
+
+        let _client = WsClient::connect_authenticated_with_policy("stub", &url, counting_provider(calls.clone()), quick_policy(None))
+            .await
+            .unwrap();
+
+        assert_eq!(next_token(&mut tokens).await, "stub-token-1");
+        assert_eq!(next_token(&mut tokens).await, "stub-token-2");
+    }
+
+    #[tokio::test]
+    async fn service_clients_reconnect_with_a_valid_service_token() {
+        let (url, mut tokens) = start_token_recorder(true).await;
+
+        let client = WsClient::connect_as_service("stub_service", &url).await.unwrap();
+        assert!(client.is_connected());
+
+        for _ in 0..2 {
+            let claims = libjwt::validate_jwt(&next_token(&mut tokens).await).unwrap();
+            assert_eq!(claims.sub, "stub_service");
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_authenticated_fails_when_the_provider_has_no_token() {
+        let (url, mut tokens) = start_token_recorder(false).await;
+        let calls = Arc::new(AtomicU32::new(0));
+        let provider = {
+            let calls = calls.clone();
+            move || {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err("token service unavailable".to_string())
+            }
+        };
+
+        let result = WsClient::connect_authenticated_with_policy("stub", &url, provider, quick_policy(Some(2))).await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        // Without a token the client never opens a connection
+        assert!(tokens.try_recv().is_err());
+    }
+}
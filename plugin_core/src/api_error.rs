@@ -0,0 +1,63 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// A plugin API error that clients can tell apart by `kind` rather than by message text.
+///
+/// `typed_error_response` sends it as `{"error":{"kind":"validation","message":"..."}}`,
+/// with `details` added when set. `code` is the HTTP status and is not serialized.
+///
+/// ```
+/// use plugin_core::ApiError;
+///
+/// let error = ApiError::validation("Invalid data").with_details(serde_json::json!({ "field": "ssid" }));
+/// assert_eq!((error.code, error.kind), (400, "validation"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub code: u16,
+    /// Stable, machine-readable error kind, e.g. `validation` or `not_found`.
+    pub kind: &'static str,
+    /// Human-readable description, safe to show to the user.
+    pub message: String,
+    /// Optional structured context, such as the offending field.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn new(code: u16, kind: &'static str, message: impl Into<String>) -> Self {
+        Self { code, kind, message: message.into(), details: None }
+    }
+
+    /// 400: the request body or parameters are invalid.
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(400, "validation", message)
+    }
+
+    /// 404: the resource does not exist.
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(404, "not_found", message)
+    }
+
+    /// 409: the request conflicts with the resource's current state.
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(409, "conflict", message)
+    }
+
+    /// 415: the body is not in a format the plugin accepts.
+    pub fn unsupported_media_type(message: impl Into<String>) -> Self {
+        Self::new(415, "unsupported_media_type", message)
+    }
+
+    /// 500: the plugin failed while handling the request.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(500, "internal", message)
+    }
+
+    /// Attaches structured context to the error.
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
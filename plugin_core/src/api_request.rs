@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
 use std::os::raw::c_char;
 use crate::HttpMethod;
 use crate::ApiHeader;
@@ -44,4 +46,236 @@ pub struct ApiRequest {
 
     /// Length of the body in bytes. Zero indicates an empty body.
     pub body_len: usize,
-}
\ No newline at end of file
+}
+
+impl ApiRequest {
+    /// Parses the `query` string into an owned map of URL-decoded key/value pairs.
+    ///
+    /// Returns an empty map when `query` is null or empty. Keys without a value
+    /// (`?verbose`) map to an empty string; for repeated keys the last value wins.
+    pub fn query_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        if self.query.is_null() {
+            return params;
+        }
+
+        let query = unsafe { CStr::from_ptr(self.query) }.to_string_lossy();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            params.insert(url_decode(key), url_decode(value));
+        }
+        params
+    }
+
+    /// Returns the request's content type, or `None` when it is null or empty.
+    pub fn content_type(&self) -> Option<&str> {
+        if self.content_type.is_null() {
+            return None;
+        }
+        unsafe { CStr::from_ptr(self.content_type) }.to_str().ok().filter(|ct| !ct.trim().is_empty())
+    }
+
+    /// Returns true if the content type is JSON: `application/json` or a `+json`
+    /// suffix type such as `application/merge-patch+json`, with any parameters.
+    ///
+    /// A missing content type is not JSON; handlers that accept bodies without one
+    /// should check [`content_type`](Self::content_type) first.
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    /// use plugin_core::{ ApiRequest, HttpMethod };
+    ///
+    /// fn request(content_type: Option<&CString>) -> ApiRequest {
+    ///     ApiRequest {
+    ///         path: std::ptr::null(),
+    ///         method: HttpMethod::Post,
+    ///         headers: std::ptr::null(),
+    ///         content_type: content_type.map_or(std::ptr::null(), |c| c.as_ptr()),
+    ///         header_count: 0,
+    ///         query: std::ptr::null(),
+    ///         body_ptr: std::ptr::null(),
+    ///         body_len: 0,
+    ///     }
+    /// }
+    ///
+    /// let json = CString::new("application/json; charset=utf-8").unwrap();
+    /// let text = CString::new("text/plain").unwrap();
+    /// assert!(request(Some(&json)).is_json());
+    /// assert!(!request(Some(&text)).is_json());
+    /// assert!(!request(None).is_json());
+    /// assert_eq!(request(None).content_type(), None);
+    /// ```
+    pub fn is_json(&self) -> bool {
+        let Some(content_type) = self.content_type() else {
+            return false;
+        };
+        let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+    }
+
+    /// Returns the value of the first header named `name` (case-insensitive).
+    ///
+    /// Returns `None` when `headers` is null, the header is absent, or its
+    /// key or value is a null pointer.
+    pub fn header(&self, name: &str) -> Option<String> {
+        self.raw_headers().iter().find_map(|h| {
+            let (key, value) = header_pair(h)?;
+            if key.eq_ignore_ascii_case(name) {
+                Some(value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the `X-Request-Id` the engine tagged this request with, if any.
+    pub fn request_id(&self) -> Option<String> {
+        self.header("x-request-id")
+    }
+
+    /// Returns all headers as an owned map keyed by lower-cased header name.
+    pub fn headers_map(&self) -> HashMap<String, String> {
+        self.raw_headers()
+            .iter()
+            .filter_map(header_pair)
+            .map(|(key, value)| (key.to_ascii_lowercase(), value))
+            .collect()
+    }
+
+    fn raw_headers(&self) -> &[ApiHeader] {
+        if self.headers.is_null() || self.header_count == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.headers, self.header_count) }
+    }
+}
+
+fn header_pair(header: &ApiHeader) -> Option<(String, String)> {
+    if header.key.is_null() || header.value.is_null() {
+        return None;
+    }
+    let key = unsafe { CStr::from_ptr(header.key) }.to_string_lossy().into_owned();
+    let value = unsafe { CStr::from_ptr(header.value) }.to_string_lossy().into_owned();
+    Some((key, value))
+}
+
+/// Decodes `application/x-www-form-urlencoded` text: `+` becomes a space and
+/// `%XX` escapes are replaced by their byte. Malformed escapes are kept verbatim.
+fn url_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    fn request(query: Option<&CString>) -> ApiRequest {
+        ApiRequest {
+            path: std::ptr::null(),
+            method: HttpMethod::Get,
+            headers: std::ptr::null(),
+            content_type: std::ptr::null(),
+            header_count: 0,
+            query: query.map_or(std::ptr::null(), |q| q.as_ptr()),
+            body_ptr: std::ptr::null(),
+            body_len: 0,
+        }
+    }
+
+    // Returns `content_type()` and `is_json()` for a request with the given content type
+    fn content_type_checks(content_type: Option<&str>) -> (Option<String>, bool) {
+        let content_type = content_type.map(|ct| CString::new(ct).unwrap());
+        let mut request = request(None);
+        request.content_type = content_type.as_ref().map_or(std::ptr::null(), |ct| ct.as_ptr());
+        (request.content_type().map(str::to_string), request.is_json())
+    }
+
+    fn params(query: &str) -> HashMap<String, String> {
+        let query = CString::new(query).unwrap();
+        request(Some(&query)).query_params()
+    }
+
+    #[test]
+    fn empty_or_missing_query_has_no_params() {
+        assert!(request(None).query_params().is_empty());
+        assert!(params("").is_empty());
+        assert!(params("&&").is_empty());
+    }
+
+    #[test]
+    fn single_param() {
+        assert_eq!(params("limit=10"), HashMap::from([("limit".to_string(), "10".to_string())]));
+        assert_eq!(params("verbose"), HashMap::from([("verbose".to_string(), String::new())]));
+    }
+
+    #[test]
+    fn multiple_params_last_value_wins() {
+        let parsed = params("limit=10&offset=20&limit=5");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed["limit"], "5");
+        assert_eq!(parsed["offset"], "20");
+    }
+
+    #[test]
+    fn percent_encoded_keys_and_values_are_decoded() {
+        let parsed = params("ssid=Home%20Net%2B5G&security%5B%5D=WPA2+Personal&caf%C3%A9=%E2%9C%93");
+        assert_eq!(parsed["ssid"], "Home Net+5G");
+        assert_eq!(parsed["security[]"], "WPA2 Personal");
+        assert_eq!(parsed["caf\u{e9}"], "\u{2713}");
+    }
+
+    #[test]
+    fn malformed_escapes_are_kept_verbatim() {
+        assert_eq!(params("q=100%")["q"], "100%");
+        assert_eq!(params("r=%zz")["r"], "%zz");
+        assert_eq!(params("s=%4")["s"], "%4");
+    }
+
+    #[test]
+    fn missing_content_type_is_not_json() {
+        assert_eq!(content_type_checks(None), (None, false));
+        assert_eq!(content_type_checks(Some("")), (None, false));
+        assert_eq!(content_type_checks(Some("  ")), (None, false));
+    }
+
+    #[test]
+    fn json_content_types_are_json() {
+        for content_type in [
+            "application/json",
+            "application/json; charset=utf-8",
+            "Application/JSON",
+            "application/merge-patch+json",
+        ] {
+            assert_eq!(content_type_checks(Some(content_type)), (Some(content_type.to_string()), true), "{}", content_type);
+        }
+    }
+
+    #[test]
+    fn other_content_types_are_not_json() {
+        for content_type in ["text/plain", "text/plain; charset=utf-8", "application/xml", "text/json+plain"] {
+            assert_eq!(content_type_checks(Some(content_type)), (Some(content_type.to_string()), false), "{}", content_type);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! In-memory CRUD resource handling shared by plugins built with `declare_crud_plugin!`.
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{ de::DeserializeOwned, Serialize };
+
+use crate::error_response;
+use crate::jwt_utils::validate_jwt_token;
+use crate::log_debug;
+use crate::resource_utils::parse_resource_path;
+use crate::response_utils::{ health_response, json_response, method_not_allowed_response };
+use crate::ws_utils::{ publish_event, PluginWsClient };
+use crate::{ ApiRequest, ApiResponse, HttpMethod };
+
+/// A resource stored by a [`CrudPlugin`], addressed by a string id.
+///
+/// `declare_crud_plugin!` implements this for types with an `id: String` field.
+pub trait CrudItem: Serialize + DeserializeOwned + Clone + Send + 'static {
+    fn id(&self) -> &str;
+    fn set_id(&mut self, id: String);
+}
+
+/// State and request handling of a HashMap-backed CRUD plugin.
+///
+/// Serves `GET <resource>[/id]`, `POST <resource>`, `PUT <resource>/id` and
+/// `DELETE <resource>[/id]`, and publishes every change on `topic`: the item itself
+/// for creates and updates, `{"id": "...", "deleted": true}` for deletes.
+pub struct CrudPlugin<T> {
+    name: &'static str,
+    resource: &'static str,
+    topic: &'static str,
+    items: Lazy<Mutex<HashMap<String, T>>>,
+    ws_client: PluginWsClient,
+    run_completed: AtomicBool,
+}
+
+impl<T: CrudItem> CrudPlugin<T> {
+    /// Creates an empty plugin state; usable in a `static` initializer.
+    pub const fn new(name: &'static str, resource: &'static str, topic: &'static str) -> Self {
+        Self {
+            name,
+            resource,
+            topic,
+            items: Lazy::new(|| Mutex::new(HashMap::new())),
+            ws_client: PluginWsClient::new(),
+            run_completed: AtomicBool::new(false),
+        }
+    }
+
+    /// Connects to the engine's WebSocket bus and subscribes to `topic`. Call from `run()`.
+    pub fn run(&self, ws_url: &str) {
+        println!("[{}] - run", self.name);
+        self.ws_client.connect(self.name, ws_url, &[self.topic]);
+        self.run_completed.store(true, Ordering::SeqCst);
+    }
+
+    /// Reports whether `run()` finished and the WebSocket client is connected.
+    pub fn health(&self) -> *mut ApiResponse {
+        health_response(self.run_completed.load(Ordering::SeqCst), self.ws_client.is_connected())
+    }
+
+    /// Validates the caller's JWT and dispatches the request to the matching operation.
+    pub fn handle(&self, request: &ApiRequest) -> *mut ApiResponse {
+        if let Err(response) = validate_jwt_token(request) {
+            return response;
+        }
+
+        let path = if request.path.is_null() {
+            "<null>"
+        } else {
+            unsafe { CStr::from_ptr(request.path) }.to_str().unwrap_or("<invalid>")
+        };
+
+        // Extract ID from path if present (format: "<resource>/{id}")
+        let id_opt = match parse_resource_path(path).as_slice() {
+            [resource] if *resource == self.resource => None,
+            [resource, id] if *resource == self.resource => Some(*id),
+            [resource, ..] if *resource == self.resource => return error_response(404, "Resource not found"),
+            _ => return method_not_allowed_response(request.method, request.path),
+        };
+
+        match request.method {
+            HttpMethod::Get => self.get(id_opt),
+            HttpMethod::Post => self.create(request_body(request)),
+            HttpMethod::Put => match id_opt {
+                Some(id) => self.update(id, request_body(request)),
+                None => error_response(400, "Resource ID required"),
+            },
+            HttpMethod::Delete => self.delete(id_opt),
+            _ => method_not_allowed_response(request.method, request.path),
+        }
+    }
+
+    // Returns one item, or every item keyed by id
+    fn get(&self, id: Option<&str>) -> *mut ApiResponse {
+        let items = self.items.lock().unwrap();
+        match id {
+            Some(id) => match items.get(id) {
+                Some(item) => json_response(200, &serde_json::to_string(item).unwrap()),
+                None => error_response(404, "Resource not found"),
+            },
+            None => json_response(200, &serde_json::to_string(&*items).unwrap()),
+        }
+    }
+
+    // Stores a new item, generating an id if the client sent none
+    fn create(&self, body: &[u8]) -> *mut ApiResponse {
+        let Ok(mut item) = serde_json::from_slice::<T>(body) else {
+            return error_response(400, "Invalid data");
+        };
+        if item.id().is_empty() {
+            item.set_id(format!("{:x}", rand::random::<u64>()));
+        }
+
+        let id = item.id().to_string();
+        self.items.lock().unwrap().insert(id.clone(), item.clone());
+        self.publish(&serde_json::to_string(&item).unwrap_or_default());
+
+        let response = serde_json::json!({
+            "message": "Resource created",
+            "id": id
+        });
+        log_debug!(format!("[{}] Saved resource {}", self.name, id).as_str());
+        json_response(201, &response.to_string())
+    }
+
+    // Replaces an existing item; the id in the URL wins over the body's
+    fn update(&self, id: &str, body: &[u8]) -> *mut ApiResponse {
+        let Ok(mut item) = serde_json::from_slice::<T>(body) else {
+            return error_response(400, "Invalid data");
+        };
+        item.set_id(id.to_string());
+
+        {
+            let mut items = self.items.lock().unwrap();
+            if !items.contains_key(id) {
+                return error_response(404, "Resource not found");
+            }
+            items.insert(id.to_string(), item.clone());
+        }
+
+        self.publish(&serde_json::to_string(&item).unwrap_or_default());
+        json_response(200, r#"{"message": "Resource updated"}"#)
+    }
+
+    // Removes one item, or all of them when no id is given
+    fn delete(&self, id: Option<&str>) -> *mut ApiResponse {
+        let removed: Vec<String> = {
+            let mut items = self.items.lock().unwrap();
+            match id {
+                Some(id) => items.remove(id).map(|_| id.to_string()).into_iter().collect(),
+                None => items.drain().map(|(id, _)| id).collect(),
+            }
+        };
+
+        if id.is_some() && removed.is_empty() {
+            return error_response(404, "Resource not found");
+        }
+
+        for id in &removed {
+            self.publish(&serde_json::json!({ "id": id, "deleted": true }).to_string());
+        }
+
+        if id.is_some() {
+            json_response(200, r#"{"message": "Resource deleted"}"#)
+        } else {
+            json_response(200, r#"{"message": "All resources deleted"}"#)
+        }
+    }
+
+    // Publishes a change event if the WebSocket client is connected. Never called with the lock held.
+    fn publish(&self, payload: &str) {
+        if let Some(client) = self.ws_client.get() {
+            publish_event(&client, self.name, self.topic, payload);
+        }
+    }
+}
+
+// Returns the request body, treating a null pointer as empty
+fn request_body(request: &ApiRequest) -> &[u8] {
+    if request.body_ptr.is_null() {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(request.body_ptr, request.body_len) }
+    }
+}
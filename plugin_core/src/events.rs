@@ -0,0 +1,150 @@
+use serde::{ de::DeserializeOwned, Deserialize, Serialize };
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// Declares the `EngineEvent` enum together with its topic strings, so the two
+/// can never drift apart.
+macro_rules! engine_events {
+    ($( $(#[$meta:meta])* $variant:ident => $topic:literal ),* $(,)?) => {
+        /// Well-known topics on the engine's WebSocket bus.
+        ///
+        /// Using these instead of string literals turns a misspelled topic, which would
+        /// otherwise silently deliver to nobody, into a compile error. The raw string
+        /// API keeps working for plugin-specific topics.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum EngineEvent {
+            $( $(#[$meta])* $variant ),*
+        }
+
+        impl EngineEvent {
+            /// Every known event, in declaration order.
+            pub const ALL: &'static [EngineEvent] = &[$( EngineEvent::$variant ),*];
+
+            /// Returns the topic string used on the wire.
+            pub const fn to_topic(self) -> &'static str {
+                match self {
+                    $( EngineEvent::$variant => $topic ),*
+                }
+            }
+
+            /// Looks up the event for a topic string. Returns `None` for topics that
+            /// aren't engine events, such as plugin-specific ones.
+            pub fn from_topic(topic: &str) -> Option<Self> {
+                match topic {
+                    $( $topic => Some(EngineEvent::$variant), )*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+engine_events! {
+    /// Tells the webapp to navigate; the payload is a `SwitchRoutePayload`.
+    SwitchRoute => "SwitchRoute",
+    /// The device joined a network; the payload is a `NetworkStatePayload`.
+    NetworkConnected => "NetworkConnected",
+    /// A connection attempt failed or the connected network was forgotten; the
+    /// payload is a `NetworkStatePayload` with a `reason`.
+    NetworkDisconnected => "NetworkDisconnected",
+    /// The welcome step finished; the payload is a `StepCompletedPayload`.
+    WelcomeCompleted => "WelcomeCompleted",
+    /// The WiFi step finished; the payload is a `WifiCompletedPayload`.
+    WifiCompleted => "WifiCompleted",
+    /// The execution plan step finished; the payload is a `StepCompletedPayload`.
+    ExecutionPlanCompleted => "ExecutionPlanCompleted",
+    /// The login step finished; the payload is a `StepCompletedPayload`.
+    LoginCompleted => "LoginCompleted",
+    /// The provisioning step finished; the payload is a `StepCompletedPayload`.
+    ProvisionCompleted => "ProvisionCompleted",
+    /// A long-running job reported progress; the payload is a `StatusMessagePayload`.
+    StatusMessageChanged => "StatusMessageChanged",
+}
+
+impl fmt::Display for EngineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_topic())
+    }
+}
+
+impl FromStr for EngineEvent {
+    type Err = String;
+
+    fn from_str(topic: &str) -> Result<Self, Self::Err> {
+        EngineEvent::from_topic(topic).ok_or_else(|| format!("Unknown engine event: {}", topic))
+    }
+}
+
+/// Payload of `SwitchRoute`: the webapp route to show, e.g. `/settings/web`.
+/// Travels as a bare string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SwitchRoutePayload {
+    pub route: String,
+}
+
+/// Payload of `StatusMessageChanged`: the current progress message. Travels as a bare string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StatusMessagePayload {
+    pub message: String,
+}
+
+/// Payload the web steps publish when they finish, e.g. `{"status": "completed"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepCompletedPayload {
+    pub status: String,
+}
+
+/// Payload of `WifiCompleted`, e.g. `{"status": "connected", "ssid": "MyNetwork"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WifiCompletedPayload {
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssid: Option<String>,
+}
+
+/// Payload of `NetworkConnected` and `NetworkDisconnected`, e.g.
+/// `{"ssid": "MyNetwork"}` or `{"ssid": "MyNetwork", "reason": "auth_failed"}`.
+///
+/// `reason` is only set on `NetworkDisconnected`: `auth_failed`, `timeout`,
+/// `connect_failed` or `forgotten`.
+///
+/// ```
+/// use plugin_core::events::{ decode_payload, NetworkStatePayload };
+///
+/// let state: NetworkStatePayload = decode_payload(r#"{"ssid":"Home","reason":"timeout"}"#).unwrap();
+/// assert_eq!(state.reason.as_deref(), Some("timeout"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkStatePayload {
+    pub ssid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Converts a typed payload into the value passed to `WsClient::publish`.
+pub fn encode_payload<T: Serialize>(payload: &T) -> Result<Value, String> {
+    serde_json::to_value(payload).map_err(|e| format!("Failed to encode event payload: {}", e))
+}
+
+/// Parses the payload string handed to an `on_message` handler.
+///
+/// Accepts JSON text, which covers structured payloads and ones published as
+/// JSON-encoded strings, and falls back to the bare text for string payloads.
+///
+/// ```
+/// use plugin_core::events::{ decode_payload, SwitchRoutePayload, WifiCompletedPayload };
+///
+/// let wifi: WifiCompletedPayload = decode_payload(r#"{"status":"connected","ssid":"Home"}"#).unwrap();
+/// assert_eq!(wifi.ssid.as_deref(), Some("Home"));
+///
+/// let switch: SwitchRoutePayload = decode_payload("/settings/web").unwrap();
+/// assert_eq!(switch.route, "/settings/web");
+/// ```
+pub fn decode_payload<T: DeserializeOwned>(payload: &str) -> Result<T, String> {
+    serde_json::from_str(payload)
+        .or_else(|_| serde_json::from_value(Value::String(payload.to_string())))
+        .map_err(|e| format!("Invalid event payload: {}", e))
+}
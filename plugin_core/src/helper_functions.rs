@@ -2,8 +2,12 @@ use std::ffi::{ CString, c_char };
 use std::ptr;
 use crate::{ApiHeader, ApiResponse, HttpMethod};
 
+/// Returns `{"message": msg}` with the given status.
+///
+/// Kept for existing plugins; new error paths should use
+/// `response_utils::typed_error_response`, whose `kind` clients can match on.
 pub fn error_response(code: u16, msg: &str) -> *mut ApiResponse {
-    let json = format!(r#"{{"message":"{}"}}"#, msg);
+    let json = serde_json::json!({ "message": msg }).to_string();
     let body = json.into_bytes();
     let body_len = body.len();
     let body_ptr = Box::into_raw(body.into_boxed_slice()) as *const u8;
@@ -22,6 +26,23 @@ pub fn error_response(code: u16, msg: &str) -> *mut ApiResponse {
     Box::into_raw(response)
 }
 
+/// Runs a plugin request handler, converting a panic into a 500 response.
+///
+/// A panic that unwinds out of an `extern "C"` function aborts the whole engine,
+/// so plugins should route `handle_request` through this instead of letting it escape.
+pub fn guard_handle_request<F>(plugin_name: &str, handler: F) -> *mut ApiResponse
+where
+    F: FnOnce() -> *mut ApiResponse,
+{
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(handler)) {
+        Ok(response) => response,
+        Err(_) => {
+            eprintln!("[{}] handle_request panicked", plugin_name);
+            error_response(500, "Internal plugin error")
+        }
+    }
+}
+
 pub fn success_response(body_json: &str) -> *mut ApiResponse {
     error_response(200, body_json)
 }
@@ -32,6 +53,7 @@ pub fn method_not_allowed(method: HttpMethod, resource: *const c_char) -> *const
         HttpMethod::Post => "POST",
         HttpMethod::Put => "PUT",
         HttpMethod::Delete => "DELETE",
+        HttpMethod::Patch => "PATCH",
     };
 
     let res_str = unsafe {
@@ -76,4 +98,4 @@ pub fn cleanup_response(response: *mut ApiResponse) {
             let _ = Box::from_raw(headers_slice as *mut [ApiHeader]);
         }
     }
-}
\ No newline at end of file
+}
@@ -12,6 +12,7 @@
 /// - POST   → 0b0010 = 2
 /// - PUT    → 0b0100 = 4
 /// - DELETE → 0b1000 = 8
+/// - PATCH  → 0b10000 = 16
 ///
 /// So a plugin that supports GET and POST on a resource would declare:
 /// `supported_methods = HttpMethod::Get as u8 | HttpMethod::Post as u8`
@@ -36,6 +37,9 @@ pub enum HttpMethod {
 
     /// HTTP DELETE: used to remove a resource.
     Delete = 3,
+
+    /// HTTP PATCH: used to partially update a resource.
+    Patch = 4,
 }
 
 impl PartialEq for HttpMethod {
@@ -46,6 +50,7 @@ impl PartialEq for HttpMethod {
                 | (HttpMethod::Post, HttpMethod::Post)
                 | (HttpMethod::Put, HttpMethod::Put)
                 | (HttpMethod::Delete, HttpMethod::Delete)
+                | (HttpMethod::Patch, HttpMethod::Patch)
         )
     }
 }
\ No newline at end of file
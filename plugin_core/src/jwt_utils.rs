@@ -2,10 +2,16 @@
 //! 
 //! This module provides JWT validation utilities for plugin authentication.
 
-use std::ffi::CStr;
-use crate::{ApiRequest, ApiResponse, error_response};
+use crate::{ApiRequest, ApiResponse};
+use crate::response_utils::{forbidden_response, unauthorized_response};
 use crate::log_debug;
 use crate::log_warn;
+use libjwt::Claims;
+
+/// Token accepted without verification when `plugin_core` is built with the
+/// `dev_auth_bypass` feature. It grants every scope.
+#[cfg(feature = "dev_auth_bypass")]
+pub const DEV_BYPASS_TOKEN: &str = "dev";
 
 /// Validates JWT token from an API request
 ///
@@ -17,48 +23,194 @@ use crate::log_warn;
 ///
 /// # Returns
 /// * `Ok(())` - If token is valid
-/// * `Err(*mut ApiResponse)` - If token is invalid or missing, contains error response
+/// * `Err(*mut ApiResponse)` - 401 with a `WWW-Authenticate: Bearer` challenge if the
+///   token is invalid or missing
 pub fn validate_jwt_token(request: &ApiRequest) -> Result<(), *mut ApiResponse> {
-    // Extract authorization header from request if it exists
-    let auth_header = if !request.headers.is_null() && request.header_count > 0 {
-        let headers = unsafe { std::slice::from_raw_parts(request.headers, request.header_count as usize) };
-        
-        // Search for Authorization header
-        headers.iter().find_map(|header| {
-            let name = unsafe { CStr::from_ptr(header.key).to_str().ok()? };
-            if name.eq_ignore_ascii_case("Authorization") {
-                unsafe { CStr::from_ptr(header.value).to_str().ok() }
-            } else {
-                None
-            }
-        })
+    authenticate(request).map(|_| ())
+}
+
+/// Validates JWT token from an API request and checks that it grants `scope`
+///
+/// # Arguments
+/// * `request` - API request containing headers
+/// * `scope` - Scope the operation requires, e.g. `wifi:connect`
+///
+/// # Returns
+/// * `Ok(())` - If token is valid and grants the scope
+/// * `Err(*mut ApiResponse)` - 401 if the token is invalid or missing, 403 if it lacks the scope
+pub fn validate_jwt_token_with_scope(request: &ApiRequest, scope: &str) -> Result<(), *mut ApiResponse> {
+    let claims = authenticate(request)?;
+    if claims.has_scope(scope) {
+        Ok(())
     } else {
-        None
-    };
+        log_warn!(format!("JWT token for {} lacks required scope {}", claims.sub, scope).as_str());
+        Err(forbidden_response(&format!("Missing required scope: {}", scope), scope))
+    }
+}
+
+/// Extracts and validates the bearer token, returning its claims
+fn authenticate(request: &ApiRequest) -> Result<Claims, *mut ApiResponse> {
+    // Extract authorization header from request if it exists
+    let auth_header = request.header("Authorization");
     
     // Validate JWT token if authorization header exists
-    if let Some(auth) = auth_header {
+    if let Some(auth) = auth_header.as_deref() {
         if !auth.starts_with("Bearer ") {
             log_warn!("Invalid Authorization format, expected Bearer token");
-            return Err(error_response(401, "Invalid Authorization format, expected Bearer token"));
+            return Err(unauthorized_response("Invalid Authorization format, expected Bearer token", false));
         }
         
         let token = &auth[7..]; // Skip "Bearer " prefix
+
+        #[cfg(feature = "dev_auth_bypass")]
+        if token == DEV_BYPASS_TOKEN {
+            return Ok(dev_bypass_claims());
+        }
         
         match libjwt::validate_jwt(token) {
             Ok(claims) => {
                 // Token is valid, continue with request processing
                 log_debug!("JWT token validation successful");
                 log_debug!(format!("Claims: {:?}", claims).as_str());
-                Ok(())
+                Ok(claims)
             }
             Err(e) => {
                 log_warn!(format!("JWT validation failed: {}", e).as_str());
-                Err(error_response(401, "Invalid or expired token"))
+                Err(unauthorized_response("Invalid or expired token", true))
             }
         }
     } else {
         log_warn!("No Authorization header found");
-        Err(error_response(401, "Authentication required"))
+        Err(unauthorized_response("Authentication required", false))
+    }
+}
+
+/// Claims handed out for `DEV_BYPASS_TOKEN`, announced loudly on every use
+#[cfg(feature = "dev_auth_bypass")]
+fn dev_bypass_claims() -> Claims {
+    let message = "!!! DEV AUTH BYPASS: accepted `Bearer dev` without verification. Never ship a build with the dev_auth_bypass feature !!!";
+    eprintln!("{}", message);
+    log_warn!(message);
+    Claims {
+        sub: "dev".to_string(),
+        session_id: "dev".to_string(),
+        exp: usize::MAX,
+        iat: 0,
+        scopes: vec![libjwt::FULL_ACCESS_SCOPE.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ApiHeader, HttpMethod};
+    use std::ffi::CString;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const SCOPE: &str = "wifi:connect";
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn token_with_scopes(scopes: &[&str]) -> String {
+        libjwt::generate_jwt_with_scopes("test-key", "session", scopes, now(), now() + 3600).unwrap()
+    }
+
+    /// Runs `check` on a GET request carrying `authorization` (if any) and returns the
+    /// status of the error response, or `None` if the request was accepted.
+    fn status_for<F>(authorization: Option<&str>, check: F) -> Option<u16>
+    where
+        F: Fn(&ApiRequest) -> Result<(), *mut ApiResponse>,
+    {
+        let key = CString::new("Authorization").unwrap();
+        let value = CString::new(authorization.unwrap_or("")).unwrap();
+        let headers = [ApiHeader { key: key.as_ptr(), value: value.as_ptr() }];
+        let path = CString::new("network").unwrap();
+        let request = ApiRequest {
+            path: path.as_ptr(),
+            method: HttpMethod::Get,
+            headers: headers.as_ptr(),
+            header_count: if authorization.is_some() { 1 } else { 0 },
+            content_type: std::ptr::null(),
+            query: std::ptr::null(),
+            body_ptr: std::ptr::null(),
+            body_len: 0,
+        };
+
+        check(&request).err().map(|response| {
+            let status = unsafe { (*response).status };
+            crate::cleanup_response(response);
+            status
+        })
+    }
+
+    fn with_scope(request: &ApiRequest) -> Result<(), *mut ApiResponse> {
+        validate_jwt_token_with_scope(request, SCOPE)
+    }
+
+    #[test]
+    fn token_with_the_scope_is_accepted() {
+        let bearer = format!("Bearer {}", token_with_scopes(&[SCOPE]));
+        assert_eq!(status_for(Some(&bearer), with_scope), None);
+    }
+
+    #[test]
+    fn wildcard_scopes_are_accepted() {
+        for granted in ["wifi:*", libjwt::FULL_ACCESS_SCOPE] {
+            let bearer = format!("Bearer {}", token_with_scopes(&[granted]));
+            assert_eq!(status_for(Some(&bearer), with_scope), None, "granted {}", granted);
+        }
+    }
+
+    #[test]
+    fn token_missing_the_scope_is_forbidden() {
+        let bearer = format!("Bearer {}", token_with_scopes(&["settings:write", "wifi:scan"]));
+        assert_eq!(status_for(Some(&bearer), with_scope), Some(403));
+        // The same token still passes the plain validity check
+        assert_eq!(status_for(Some(&bearer), validate_jwt_token), None);
+    }
+
+    #[test]
+    fn token_without_a_scopes_claim_is_forbidden() {
+        // Tokens issued before scopes existed carry no `scopes` claim at all
+        let claims = serde_json::json!({
+            "sub": "test-key",
+            "session_id": "session",
+            "iat": now(),
+            "exp": now() + 3600,
+        });
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"jwt_secret_do_not_use_in_production"),
+        ).unwrap();
+        let bearer = format!("Bearer {}", token);
+
+        assert_eq!(status_for(Some(&bearer), validate_jwt_token), None);
+        assert_eq!(status_for(Some(&bearer), with_scope), Some(403));
+    }
+
+    #[test]
+    fn missing_or_invalid_token_is_unauthorized() {
+        assert_eq!(status_for(None, with_scope), Some(401));
+        assert_eq!(status_for(Some("Bearer not-a-jwt"), with_scope), Some(401));
+        assert_eq!(status_for(Some("Basic dXNlcjpwYXNz"), with_scope), Some(401));
+    }
+
+    // Runs under `cargo test -p plugin_core --features dev_auth_bypass`
+    #[test]
+    #[cfg(feature = "dev_auth_bypass")]
+    fn dev_token_is_accepted_with_every_scope_when_the_bypass_is_enabled() {
+        let bearer = format!("Bearer {}", DEV_BYPASS_TOKEN);
+        assert_eq!(status_for(Some(&bearer), validate_jwt_token), None);
+        assert_eq!(status_for(Some(&bearer), with_scope), None);
+    }
+
+    #[test]
+    #[cfg(not(feature = "dev_auth_bypass"))]
+    fn dev_token_is_unauthorized_without_the_bypass() {
+        assert_eq!(status_for(Some("Bearer dev"), validate_jwt_token), Some(401));
+        assert_eq!(status_for(Some("Bearer dev"), with_scope), Some(401));
     }
 }
@@ -1,5 +1,6 @@
 pub mod api_request;
 pub mod api_response;
+pub mod api_error;
 pub mod api_header;
 pub mod http_method;
 pub mod plugin_context;
@@ -11,22 +12,28 @@ pub mod logging;
 pub mod resource_utils;
 pub mod response_utils;
 pub mod jwt_utils; // Add this line to expose the jwt_utils module
+pub mod events;
+pub mod ws_utils;
+pub mod crud_utils;
 
 #[macro_use]
 mod plugin_macros;
 
 pub use api_request::ApiRequest;
 pub use api_response::ApiResponse;
+pub use api_error::ApiError;
 pub use api_header::ApiHeader;
 pub use http_method::HttpMethod;
-pub use plugin_context::PluginContext;
-pub use plugin::Plugin;
+pub use plugin_context::{ PluginContext, ws_url_from_context };
+pub use plugin::{ Plugin, PLUGIN_ABI_VERSION };
 pub use resource::Resource;
+pub use events::EngineEvent;
 
 pub use helper_functions::error_response;
 pub use helper_functions::success_response;
 pub use helper_functions::method_not_allowed;
 pub use helper_functions::cleanup_response;
+pub use helper_functions::guard_handle_request;
 
 // When logging feature is enabled, re-export from liblogger
 #[cfg(feature = "logging")]
@@ -41,6 +48,7 @@ pub use liblogger_macros::initialize_logger_attributes;
 
 // Always export the init_logger function
 pub use logging::init_logger;
+pub use logging::with_request_context;
 
 // Add a convenience function to check if logging is enabled
 /// Returns true if the logging feature is enabled
@@ -55,6 +55,26 @@ pub fn init_logger(plugin_name: &str) -> Result<(), String> {
     }
 }
 
+/// Runs `f` with the request's `X-Request-Id` set as the logger's `request_id`
+///
+/// The engine tags each request with an id and logs it; plugin logs written inside
+/// `f` carry the same id, so a request can be followed from the engine into the
+/// plugin. The previous value is restored afterwards. Without the `logging`
+/// feature this just calls `f`.
+#[cfg(feature = "logging")]
+pub fn with_request_context<R>(request: &crate::ApiRequest, f: impl FnOnce() -> R) -> R {
+    let key = liblogger::REQUEST_ID_KEY;
+    let previous = liblogger::set_context_value(key, request.request_id().as_deref());
+    let result = f();
+    liblogger::set_context_value(key, previous.as_deref());
+    result
+}
+
+#[cfg(not(feature = "logging"))]
+pub fn with_request_context<R>(_request: &crate::ApiRequest, f: impl FnOnce() -> R) -> R {
+    f()
+}
+
 // Create no-op versions of the macros when logging is disabled
 // These will be available at crate root level due to #[macro_export]
 #[cfg(not(feature = "logging"))]
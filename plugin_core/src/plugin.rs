@@ -98,4 +98,19 @@ pub struct Plugin {
     pub run_workflow: Option<extern "C" fn(input: *const ApiRequest) -> *mut ApiResponse>,
     pub on_progress: Option<extern "C" fn() -> *mut ApiResponse>,
     pub on_complete: Option<extern "C" fn() -> *mut ApiResponse>,
-}
\ No newline at end of file
+
+    /// Optional health probe used by the engine's `/api/health` endpoint.
+    ///
+    /// Should return a JSON response such as `{"run_completed":true,"ws_connected":true}`
+    /// (see `response_utils::health_response`). A 2xx status means healthy. The engine
+    /// frees the response with `cleanup()`. When `None`, the plugin is reported as "ok".
+    pub health_check: Option<extern "C" fn() -> *mut ApiResponse>,
+}
+
+/// Version of the `Plugin` layout and calling contract shared by engine and plugins.
+///
+/// `declare_plugin!` exports it as `plugin_abi_version()`, and the engine refuses
+/// plugins reporting a different value. Bump it whenever a field of `Plugin`,
+/// `ApiRequest`, `ApiResponse`, `ApiHeader`, `Resource` or `PluginContext` is
+/// added, removed or reordered.
+pub const PLUGIN_ABI_VERSION: u32 = 2;
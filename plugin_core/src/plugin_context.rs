@@ -1,5 +1,14 @@
+use std::ffi::CStr;
 use std::os::raw::c_char;
 
+use serde::de::DeserializeOwned;
+
+/// WebSocket URL plugins fall back to when the engine doesn't pass one.
+pub const DEFAULT_WS_URL: &str = "ws://127.0.0.1:8081/ws";
+
+/// Configuration key under which the engine passes its WebSocket URL.
+pub const WS_URL_CONFIG_KEY: &str = "ws_url";
+
 /// Represents runtime context passed from the engine to a plugin at initialization.
 ///
 /// This structure allows the engine to provide optional configuration data to the plugin
@@ -15,14 +24,93 @@ use std::os::raw::c_char;
 /// - JSON strings: `"{\"scan\":true,\"timeout\":3000}"`
 /// - Plugin-specific syntax
 ///
+/// The engine also appends `ws_url=<url>` (as `;`-separated pair) for plugins built
+/// before the `ws_url` field existed; see [`ws_url_from_context`].
+///
 /// It is up to the plugin to parse this string appropriately.
 ///
+/// ### Structured configuration
+/// Plugins loaded from the execution plan also receive their `[plugins.config]`
+/// table as a JSON object in `config_json`; read it with [`PluginContext::config_json`].
+///
+/// ### ABI
+/// Fields are only ever appended, so a plugin built against an older layout still
+/// reads the fields it knows about. The reverse does not hold: an engine built
+/// before `ws_url` was added passes a shorter struct, so plugins must be rebuilt
+/// alongside the engine (which is already the case for the rest of the ABI).
+///
 /// ### Safety
-/// - The `config` pointer must be either null or point to a valid null-terminated C string.
-/// - The plugin must not modify or deallocate the memory behind `config`.
+/// - The `config`, `ws_url` and `config_json` pointers must be either null or point
+///   to a valid null-terminated C string.
+/// - The plugin must not modify or deallocate the memory behind them.
 #[repr(C)]
 pub struct PluginContext {
     /// Optional configuration string passed to the plugin at startup.
     /// This is a null-terminated UTF-8 C string. May be null.
     pub config: *const c_char,
-}
\ No newline at end of file
+    /// URL of the engine's WebSocket server, e.g. `ws://127.0.0.1:8081/ws`.
+    /// This is a null-terminated UTF-8 C string. May be null.
+    pub ws_url: *const c_char,
+    /// The plugin's `[plugins.config]` table from the execution plan, as a JSON object.
+    /// This is a null-terminated UTF-8 C string. Null when the plan has no such table.
+    pub config_json: *const c_char,
+}
+
+impl PluginContext {
+    /// Returns the configuration string, or `None` if it is null or not valid UTF-8.
+    pub fn config_str(&self) -> Option<&str> {
+        if self.config.is_null() {
+            return None;
+        }
+        unsafe { CStr::from_ptr(self.config) }.to_str().ok()
+    }
+
+    /// Returns the engine's WebSocket URL, or `None` if it is null, empty or not valid UTF-8.
+    pub fn ws_url(&self) -> Option<String> {
+        if self.ws_url.is_null() {
+            return None;
+        }
+        unsafe { CStr::from_ptr(self.ws_url) }
+            .to_str()
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Looks up `key` in a `key=value;key=value` configuration string.
+    pub fn config_value(&self, key: &str) -> Option<&str> {
+        self.config_str()?
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| k.trim() == key)
+            .map(|(_, v)| v.trim())
+    }
+
+    /// Deserializes the plugin's structured configuration into `T`.
+    ///
+    /// A missing table is read as `{}`, so plugins whose config type has
+    /// `#[serde(default)]` fields get the defaults. Fails if the JSON is not valid
+    /// UTF-8 or does not match `T`.
+    pub fn config_json<T: DeserializeOwned>(&self) -> Result<T, String> {
+        let json = if self.config_json.is_null() {
+            "{}"
+        } else {
+            unsafe { CStr::from_ptr(self.config_json) }
+                .to_str()
+                .map_err(|e| format!("Plugin config is not valid UTF-8: {}", e))?
+        };
+        serde_json::from_str(json).map_err(|e| format!("Invalid plugin config: {}", e))
+    }
+}
+
+/// Returns the engine's WebSocket URL from the plugin's context.
+///
+/// Prefers the `ws_url` field, then a `ws_url=` entry in `config`, and falls back to
+/// [`DEFAULT_WS_URL`] if the engine passed neither.
+///
+/// Plugins call this from `run()` with `unsafe { ctx.as_ref() }`.
+pub fn ws_url_from_context(ctx: Option<&PluginContext>) -> String {
+    ctx.and_then(|ctx| {
+        ctx.ws_url().or_else(|| ctx.config_value(WS_URL_CONFIG_KEY).map(str::to_string))
+    }).unwrap_or_else(|| DEFAULT_WS_URL.to_string())
+}
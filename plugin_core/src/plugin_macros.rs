@@ -1,15 +1,55 @@
+/// Exports a plugin's `create_plugin`, `name`, `plugin_route` and `plugin_abi_version`
+/// symbols.
+///
+/// Every plugin exports the same [`Plugin`](crate::Plugin) table. The optional
+/// callbacks `run_workflow`, `on_progress`, `on_complete` and `health_check` are
+/// `None` unless named, so plugins don't need stub functions. Optional callbacks go
+/// after `cleanup` in the order shown:
+///
+/// ```ignore
+/// declare_plugin! {
+///     name: "plugin_wifi",
+///     route: "wifi",
+///     run: run,
+///     static_content_path: get_static_content_path,
+///     api_resources: get_api_resources,
+///     handle_request: handle_request,
+///     cleanup: cleanup,
+///     on_complete: on_complete,
+/// }
+/// ```
+///
+/// `handle_request` is a plain Rust `fn(*const ApiRequest) -> *mut ApiResponse`, not
+/// `extern "C"`: the macro exports it through a generated `extern "C"` wrapper that runs
+/// it inside [`guard_handle_request`](crate::guard_handle_request). A panic that reaches
+/// an `extern "C"` frame aborts the process before anything can catch it, so the guard
+/// has to sit inside the only such frame; a panicking handler answers 500 instead.
+///
+/// The older positional forms are still accepted and expand to the same table:
+/// seven functions, or ten with `run_workflow`, `on_progress` and `on_complete`,
+/// either optionally followed by `health_check = fn`.
 #[macro_export]
 macro_rules! declare_plugin {
-    // 1. UI or minimal plugin (6 args, no workflow support)
+    // Named form; the positional forms below expand to this
     (
-        $name:expr,
-        $route:expr,
-        $run_fn:ident,
-        $static_fn:ident,
-        $resources_fn:ident,
-        $handle_fn:ident,
-        $cleanup_fn:ident
+        name: $name:expr,
+        route: $route:expr,
+        run: $run_fn:ident,
+        static_content_path: $static_fn:ident,
+        api_resources: $resources_fn:ident,
+        handle_request: $handle_fn:ident,
+        cleanup: $cleanup_fn:ident
+        $(, run_workflow: $run_workflow_fn:ident)?
+        $(, on_progress: $on_progress_fn:ident)?
+        $(, on_complete: $on_complete_fn:ident)?
+        $(, health_check: $health_fn:ident)?
+        $(,)?
     ) => {
+        #[no_mangle]
+        pub extern "C" fn plugin_abi_version() -> u32 {
+            $crate::PLUGIN_ABI_VERSION
+        }
+
         #[no_mangle]
         pub extern "C" fn name() -> *const ::std::os::raw::c_char {
             ::std::ffi::CString::new($name).unwrap().into_raw()
@@ -22,22 +62,65 @@ macro_rules! declare_plugin {
 
         #[no_mangle]
         pub extern "C" fn create_plugin() -> *const $crate::Plugin {
+            extern "C" fn guarded_handle_request(
+                request: *const $crate::ApiRequest,
+            ) -> *mut $crate::ApiResponse {
+                let handler: fn(*const $crate::ApiRequest) -> *mut $crate::ApiResponse = $handle_fn;
+                $crate::guard_handle_request($name, || handler(request))
+            }
+
             &$crate::Plugin {
                 name,
                 plugin_route,
                 run: $run_fn,
                 get_static_content_path: $static_fn,
                 get_api_resources: $resources_fn,
-                handle_request: $handle_fn,
+                handle_request: guarded_handle_request,
                 cleanup: $cleanup_fn,
-                run_workflow: None,
-                on_progress: None,
-                on_complete: None,
+                run_workflow: $crate::declare_plugin!(@request_fn $($run_workflow_fn)?),
+                on_progress: $crate::declare_plugin!(@response_fn $($on_progress_fn)?),
+                on_complete: $crate::declare_plugin!(@response_fn $($on_complete_fn)?),
+                health_check: $crate::declare_plugin!(@response_fn $($health_fn)?),
             }
         }
     };
 
-    // 2. Headless plugin with workflow support (9 args)
+    // Internal: optional callback taking a request
+    (@request_fn) => { None };
+    (@request_fn $f:ident) => {
+        Some($f as extern "C" fn(*const $crate::ApiRequest) -> *mut $crate::ApiResponse)
+    };
+
+    // Internal: optional callback without arguments
+    (@response_fn) => { None };
+    (@response_fn $f:ident) => {
+        Some($f as extern "C" fn() -> *mut $crate::ApiResponse)
+    };
+
+    // 1. UI or minimal plugin (6 args, no workflow support), optionally followed by `health_check = fn`
+    (
+        $name:expr,
+        $route:expr,
+        $run_fn:ident,
+        $static_fn:ident,
+        $resources_fn:ident,
+        $handle_fn:ident,
+        $cleanup_fn:ident
+        $(, health_check = $health_fn:ident)?
+    ) => {
+        $crate::declare_plugin! {
+            name: $name,
+            route: $route,
+            run: $run_fn,
+            static_content_path: $static_fn,
+            api_resources: $resources_fn,
+            handle_request: $handle_fn,
+            cleanup: $cleanup_fn
+            $(, health_check: $health_fn)?
+        }
+    };
+
+    // 2. Headless plugin with workflow support (9 args), optionally followed by `health_check = fn`
     (
         $name:expr,
         $route:expr,
@@ -49,31 +132,117 @@ macro_rules! declare_plugin {
         $run_workflow_fn:ident,
         $on_progress_fn:ident,
         $on_complete_fn:ident
+        $(, health_check = $health_fn:ident)?
     ) => {
-        #[no_mangle]
-        pub extern "C" fn name() -> *const ::std::os::raw::c_char {
-            ::std::ffi::CString::new($name).unwrap().into_raw()
+        $crate::declare_plugin! {
+            name: $name,
+            route: $route,
+            run: $run_fn,
+            static_content_path: $static_fn,
+            api_resources: $resources_fn,
+            handle_request: $handle_fn,
+            cleanup: $cleanup_fn,
+            run_workflow: $run_workflow_fn,
+            on_progress: $on_progress_fn,
+            on_complete: $on_complete_fn
+            $(, health_check: $health_fn)?
+        }
+    };
+}
+
+/// Declares a complete HashMap-backed CRUD plugin for one resource type.
+///
+/// Generates `run`, `get_static_content_path`, `get_api_resources`, `handle_request`,
+/// `health_check` and `cleanup` on top of a [`CrudPlugin`](crate::crud_utils::CrudPlugin)
+/// static named `CRUD_PLUGIN`, then registers them with `declare_plugin!`. The item
+/// type needs `Serialize`, `Deserialize`, `Clone` and an `id: String` field. Plugins
+/// with custom endpoints should keep using `declare_plugin!`.
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize, Clone, Default)]
+/// struct Summary {
+///     #[serde(default)]
+///     id: String,
+///     field1: String,
+/// }
+///
+/// declare_crud_plugin! {
+///     name: "plugin_finish",
+///     route: "finish",
+///     static_path: "finish/web",
+///     resource: "summary",
+///     item: Summary,
+///     topic: "SummaryUpdated",
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_crud_plugin {
+    (
+        name: $name:expr,
+        route: $route:expr,
+        static_path: $static_path:expr,
+        resource: $resource:expr,
+        item: $item:ty,
+        topic: $topic:expr $(,)?
+    ) => {
+        impl $crate::crud_utils::CrudItem for $item {
+            fn id(&self) -> &str {
+                &self.id
+            }
+
+            fn set_id(&mut self, id: String) {
+                self.id = id;
+            }
         }
 
-        #[no_mangle]
-        pub extern "C" fn plugin_route() -> *const ::std::os::raw::c_char {
-            ::std::ffi::CString::new($route).unwrap().into_raw()
+        static CRUD_PLUGIN: $crate::crud_utils::CrudPlugin<$item> =
+            $crate::crud_utils::CrudPlugin::new($name, $resource, $topic);
+
+        extern "C" fn run(ctx: *const $crate::PluginContext) {
+            let ws_url = $crate::ws_url_from_context(unsafe { ctx.as_ref() });
+            CRUD_PLUGIN.run(&ws_url);
         }
 
-        #[no_mangle]
-        pub extern "C" fn create_plugin() -> *const $crate::Plugin {
-            &$crate::Plugin {
-                name,
-                plugin_route,
-                run: $run_fn,
-                get_static_content_path: $static_fn,
-                get_api_resources: $resources_fn,
-                handle_request: $handle_fn,
-                cleanup: $cleanup_fn,
-                run_workflow: Some($run_workflow_fn as extern "C" fn(*const $crate::ApiRequest) -> *mut $crate::ApiResponse),
-                on_progress: Some($on_progress_fn as extern "C" fn() -> *mut $crate::ApiResponse),
-                on_complete: Some($on_complete_fn as extern "C" fn() -> *mut $crate::ApiResponse),
+        extern "C" fn get_static_content_path() -> *const ::std::os::raw::c_char {
+            ::std::ffi::CString::new($static_path).unwrap().into_raw()
+        }
+
+        extern "C" fn get_api_resources(out_len: *mut usize) -> *const $crate::Resource {
+            static METHODS: [$crate::HttpMethod; 4] = [
+                $crate::HttpMethod::Get,
+                $crate::HttpMethod::Post,
+                $crate::HttpMethod::Put,
+                $crate::HttpMethod::Delete,
+            ];
+            let slice = $crate::resource_utils::static_resource($resource, &METHODS);
+            unsafe { *out_len = slice.len(); }
+            slice.as_ptr()
+        }
+
+        fn handle_request(req: *const $crate::ApiRequest) -> *mut $crate::ApiResponse {
+            match unsafe { req.as_ref() } {
+                Some(request) => $crate::with_request_context(request, || CRUD_PLUGIN.handle(request)),
+                None => ::std::ptr::null_mut(),
             }
         }
+
+        extern "C" fn health_check() -> *mut $crate::ApiResponse {
+            CRUD_PLUGIN.health()
+        }
+
+        extern "C" fn cleanup(resp: *mut $crate::ApiResponse) {
+            $crate::cleanup_response(resp);
+        }
+
+        $crate::declare_plugin! {
+            name: $name,
+            route: $route,
+            run: run,
+            static_content_path: get_static_content_path,
+            api_resources: get_api_resources,
+            handle_request: handle_request,
+            cleanup: cleanup,
+            health_check: health_check,
+        }
     };
 }
@@ -18,18 +18,30 @@ pub struct Resource {
     /// A pointer to a list of supported HTTP methods for this resource.
     pub supported_methods: *const HttpMethod,
 
+    /// Number of entries in the `supported_methods` list.
+    pub method_count: usize,
+
     /// Marker to indicate raw pointers are not Send
     _marker: PhantomData<*const ()>,
 }
 
 impl Resource {
-    pub fn new(path: *const c_char, supported_methods: *const HttpMethod) -> Self {
+    pub fn new(path: *const c_char, supported_methods: *const HttpMethod, method_count: usize) -> Self {
         Self {
             path,
             supported_methods,
+            method_count,
             _marker: PhantomData,
         }
     }
+
+    /// Returns the supported methods as a slice. Empty if the list pointer is null.
+    pub fn methods(&self) -> &[HttpMethod] {
+        if self.supported_methods.is_null() || self.method_count == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.supported_methods, self.method_count) }
+    }
 }
 
 // Manually implement Send for Resource
@@ -40,6 +52,7 @@ impl Clone for Resource {
         Self {
             path: self.path,
             supported_methods: self.supported_methods,
+            method_count: self.method_count,
             _marker: PhantomData,
         }
     }
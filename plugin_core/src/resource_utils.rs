@@ -10,7 +10,7 @@ pub fn static_resource(path: &str, methods: &'static [HttpMethod]) -> &'static [
         let c_path = CString::new(path).unwrap();
         let path_ptr = Box::leak(c_path.into_boxed_c_str()).as_ptr();
 
-        let resource = Resource::new(path_ptr, methods.as_ptr());
+        let resource = Resource::new(path_ptr, methods.as_ptr(), methods.len());
         let boxed = vec![resource].into_boxed_slice();
         unsafe {
             STATIC_SLICE = Some(Box::leak(boxed));
@@ -18,4 +18,23 @@ pub fn static_resource(path: &str, methods: &'static [HttpMethod]) -> &'static [
     });
 
     unsafe { STATIC_SLICE.unwrap_or_else(|| &[]) }
-}
\ No newline at end of file
+}
+
+/// Splits a plugin resource path into every non-empty segment, in order.
+///
+/// The engine forwards the whole sub-path, so nested routes such as
+/// `user/123/sessions` reach the plugin intact. Empty segments (trailing or
+/// doubled slashes) are dropped.
+///
+/// ```
+/// use plugin_core::resource_utils::parse_resource_path;
+///
+/// assert_eq!(parse_resource_path("user"), vec!["user"]);
+/// assert_eq!(parse_resource_path("user/"), vec!["user"]);
+/// assert_eq!(parse_resource_path("user/123"), vec!["user", "123"]);
+/// assert_eq!(parse_resource_path("user/123/sessions"), vec!["user", "123", "sessions"]);
+/// assert_eq!(parse_resource_path("user//123/"), vec!["user", "123"]);
+/// ```
+pub fn parse_resource_path(path: &str) -> Vec<&str> {
+    path.split('/').filter(|segment| !segment.is_empty()).collect()
+}
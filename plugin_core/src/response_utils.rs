@@ -1,41 +1,178 @@
 use std::ffi::CString;
 use std::os::raw::c_char;
 use std::ptr;
-use crate::ApiResponse;
+use crate::{ApiError, ApiHeader, ApiRequest, ApiResponse};
 use crate::HttpMethod;
 
-pub fn json_response(status: u16, body: &str) -> *mut ApiResponse {
-    let bytes = body.as_bytes().to_vec();
+/// Allocates an `ApiResponse` with the given headers, laid out so that
+/// `cleanup_response` can free every part of it.
+fn allocate_response(status: u16, content_type: &str, body: &[u8], headers: &[(&str, &str)]) -> *mut ApiResponse {
+    let bytes = body.to_vec();
     let len = bytes.len();
-    let ptr = Box::into_raw(bytes.into_boxed_slice()) as *const u8;
-    let content_type = CString::new("application/json").unwrap().into_raw();
+    let body_ptr = Box::into_raw(bytes.into_boxed_slice()) as *const u8;
+    let content_type = CString::new(content_type).unwrap_or_default().into_raw();
+
+    let (headers_ptr, header_count) = if headers.is_empty() {
+        (ptr::null(), 0)
+    } else {
+        let raw: Vec<ApiHeader> = headers
+            .iter()
+            .map(|(key, value)| ApiHeader {
+                key: CString::new(*key).unwrap_or_default().into_raw(),
+                value: CString::new(*value).unwrap_or_default().into_raw(),
+            })
+            .collect();
+        let count = raw.len();
+        (Box::into_raw(raw.into_boxed_slice()) as *const ApiHeader, count)
+    };
 
     let response = Box::new(ApiResponse {
         status,
-        headers: ptr::null(),
-        header_count: 0,
+        headers: headers_ptr,
+        header_count,
         content_type,
-        body_ptr: ptr,
+        body_ptr,
         body_len: len,
     });
     Box::into_raw(response)
 }
 
+/// Builds an `ApiResponse` with custom status, headers, content type and body.
+///
+/// Defaults to `200` with an empty `application/json` body. The built response is
+/// allocated like `json_response`, so `cleanup_response` frees it.
+///
+/// ```
+/// use plugin_core::response_utils::ResponseBuilder;
+/// use plugin_core::cleanup_response;
+///
+/// let response = ResponseBuilder::new()
+///     .status(200)
+///     .content_type("text/csv")
+///     .header("Cache-Control", "no-store")
+///     .body("ssid,signal\nHome,-40\n")
+///     .build();
+/// cleanup_response(response);
+/// ```
+pub struct ResponseBuilder {
+    status: u16,
+    content_type: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Default for ResponseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseBuilder {
+    pub fn new() -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json".to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Sets the HTTP status code.
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Appends a response header. May be called repeatedly for the same name.
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the `Content-Type` of the body.
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = content_type.to_string();
+        self
+    }
+
+    /// Sets the raw body bytes.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Allocates the response for return across the FFI boundary.
+    pub fn build(self) -> *mut ApiResponse {
+        let headers: Vec<(&str, &str)> = self.headers
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        allocate_response(self.status, &self.content_type, &self.body, &headers)
+    }
+}
+
+pub fn json_response(status: u16, body: &str) -> *mut ApiResponse {
+    allocate_response(status, "application/json", body.as_bytes(), &[])
+}
+
+/// Sends an `ApiError` with its status as `{"error":{"kind":...,"message":...}}`.
+pub fn typed_error_response(error: ApiError) -> *mut ApiResponse {
+    let body = serde_json::json!({ "error": error });
+    allocate_response(error.code, "application/json", body.to_string().as_bytes(), &[])
+}
+
 pub fn text_response(status: u16, body: &str) -> *mut ApiResponse {
-    let bytes = body.as_bytes().to_vec();
-    let len = bytes.len();
-    let ptr = Box::into_raw(bytes.into_boxed_slice()) as *const u8;
-    let content_type = CString::new("text/plain").unwrap().into_raw();
+    allocate_response(status, "text/plain", body.as_bytes(), &[])
+}
 
-    let response = Box::new(ApiResponse {
-        status,
-        headers: ptr::null(),
-        header_count: 0,
-        content_type,
-        body_ptr: ptr,
-        body_len: len,
-    });
-    Box::into_raw(response)
+/// Returns a redirect with a `Location` header.
+///
+/// `status` should be a 3xx code such as 302 or 307; anything else falls back to 302.
+pub fn redirect_response(status: u16, location: &str) -> *mut ApiResponse {
+    let status = if (300..400).contains(&status) { status } else { 302 };
+    ResponseBuilder::new()
+        .status(status)
+        .content_type("text/plain")
+        .header("Location", location)
+        .build()
+}
+
+/// Builds the response expected from a plugin's `health_check` function.
+///
+/// Healthy (200) only when `run()` has finished and the WebSocket client is connected;
+/// otherwise 503 with the same JSON body so the engine can report which part failed.
+pub fn health_response(run_completed: bool, ws_connected: bool) -> *mut ApiResponse {
+    let status = if run_completed && ws_connected { 200 } else { 503 };
+    let body = format!(
+        r#"{{"run_completed":{},"ws_connected":{}}}"#,
+        run_completed, ws_connected
+    );
+    json_response(status, &body)
+}
+
+/// Returns `401 Unauthorized` with a `WWW-Authenticate: Bearer` challenge.
+///
+/// Use it when the token is missing or invalid, so clients know to authenticate again.
+/// `invalid_token` adds `error="invalid_token"` for tokens that were sent but rejected.
+pub fn unauthorized_response(message: &str, invalid_token: bool) -> *mut ApiResponse {
+    let challenge = if invalid_token { r#"Bearer error="invalid_token""# } else { "Bearer" };
+    ResponseBuilder::new()
+        .status(401)
+        .header("WWW-Authenticate", challenge)
+        .body(serde_json::json!({ "message": message }).to_string())
+        .build()
+}
+
+/// Returns `403 Forbidden` for a valid token that lacks `scope`.
+///
+/// Re-authenticating won't help here, so clients should give up rather than retry.
+pub fn forbidden_response(message: &str, scope: &str) -> *mut ApiResponse {
+    let challenge = format!(r#"Bearer error="insufficient_scope", scope="{}""#, scope);
+    ResponseBuilder::new()
+        .status(403)
+        .header("WWW-Authenticate", &challenge)
+        .body(serde_json::json!({ "message": message }).to_string())
+        .build()
 }
 
 pub fn not_found_response() -> *mut ApiResponse {
@@ -48,4 +185,58 @@ pub fn method_not_allowed_response(method: HttpMethod, path: *const c_char) -> *
     let path_str = unsafe { CStr::from_ptr(path).to_string_lossy() };
     let message = format!("Method {} not allowed on path '{}'", method_str, path_str);
     text_response(405, &message)
-}
\ No newline at end of file
+}
+/// Returns a JSON response carrying an `ETag` header.
+///
+/// `etag` may be given bare or already quoted; it is sent quoted.
+pub fn etag_response(status: u16, body: &str, etag: &str) -> *mut ApiResponse {
+    let quoted = quote_etag(etag);
+    allocate_response(status, "application/json", body.as_bytes(), &[("ETag", &quoted)])
+}
+
+/// Returns an empty `304 Not Modified` response.
+pub fn not_modified_response() -> *mut ApiResponse {
+    allocate_response(304, "application/json", &[], &[])
+}
+
+/// Computes a stable strong ETag (FNV-1a, hex encoded) over a response body.
+pub fn compute_etag(body: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in body {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("\"{:016x}\"", hash)
+}
+
+/// Returns true if the request's `If-None-Match` header matches `etag`.
+///
+/// Handles `*`, comma-separated lists and weak (`W/`) validators.
+pub fn etag_matches(request: &ApiRequest, etag: &str) -> bool {
+    let Some(header) = request.header("If-None-Match") else {
+        return false;
+    };
+    let expected = quote_etag(etag);
+    header.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.trim_start_matches("W/") == expected
+    })
+}
+
+/// Compares the request's `If-Match` header with `etag`, returning `None` when the
+/// header is absent.
+///
+/// Handles `*` and comma-separated lists. Weak (`W/`) validators never match, since
+/// `If-Match` uses strong comparison.
+pub fn if_match(request: &ApiRequest, etag: &str) -> Option<bool> {
+    let header = request.header("If-Match")?;
+    let expected = quote_etag(etag);
+    Some(header.split(',').map(str::trim).any(|candidate| candidate == "*" || candidate == expected))
+}
+
+fn quote_etag(etag: &str) -> String {
+    if etag.starts_with('"') && etag.ends_with('"') && etag.len() >= 2 {
+        etag.to_string()
+    } else {
+        format!("\"{}\"", etag)
+    }
+}
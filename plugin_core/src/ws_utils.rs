@@ -0,0 +1,121 @@
+//! Helpers for plugins that talk to the engine's WebSocket bus.
+
+use std::sync::{ Arc, Mutex };
+
+use libws::ws_client::WsClient;
+use once_cell::sync::{ Lazy, OnceCell };
+use serde_json::Value;
+use tokio::runtime::Runtime;
+
+use crate::log_error;
+use crate::log_warn;
+
+/// Runtime hosting `publish_event`, so callers don't need one of their own.
+static PUBLISH_RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("ws-publish")
+        .enable_all()
+        .build()
+        .expect("Failed to create the WebSocket publish runtime")
+});
+
+/// A plugin's WebSocket client, stored once after `run()` connects.
+///
+/// Declared as a plain `static`, so reading it needs no `unsafe` and no
+/// `static mut` raw-pointer access.
+///
+/// ```
+/// use plugin_core::ws_utils::PluginWsClient;
+///
+/// static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
+///
+/// assert!(!PLUGIN_WS_CLIENT.is_connected());
+/// assert!(PLUGIN_WS_CLIENT.get().is_none());
+/// ```
+pub struct PluginWsClient {
+    cell: OnceCell<Arc<Mutex<WsClient>>>,
+}
+
+impl PluginWsClient {
+    /// Creates an empty slot; usable in a `static` initializer.
+    pub const fn new() -> Self {
+        Self { cell: OnceCell::new() }
+    }
+
+    /// Stores the connected client. Fails, handing the client back, if one is already stored.
+    pub fn set(&self, client: Arc<Mutex<WsClient>>) -> Result<(), Arc<Mutex<WsClient>>> {
+        self.cell.set(client)
+    }
+
+    /// Returns a handle to the client, or `None` before `set` succeeded.
+    pub fn get(&self) -> Option<Arc<Mutex<WsClient>>> {
+        self.cell.get().map(Arc::clone)
+    }
+
+    /// Returns true once a client has been stored.
+    pub fn is_connected(&self) -> bool {
+        self.cell.get().is_some()
+    }
+
+    /// Connects to the engine as the service `name`, subscribes to `topics` and stores
+    /// the client.
+    ///
+    /// Blocks until done, so call it from `run()`. The connection lives on the same
+    /// runtime as `publish_event`, so clients published to with it should be
+    /// created here rather than on a plugin's own runtime. Returns false if the
+    /// engine couldn't be reached.
+    pub fn connect(&self, name: &str, ws_url: &str, topics: &[&str]) -> bool {
+        PUBLISH_RUNTIME.block_on(async {
+            let mut client = match WsClient::connect_as_service(name, ws_url).await {
+                Ok(client) => client,
+                Err(_e) => {
+                    log_error!(format!("[{}] Failed to connect to {}: {}", name, ws_url, _e).as_str());
+                    return false;
+                }
+            };
+
+            for topic in topics {
+                client.subscribe(name, topic, "").await;
+            }
+
+            if self.set(Arc::new(Mutex::new(client))).is_err() {
+                log_warn!(format!("[{}] WebSocket client already initialized", name).as_str());
+            }
+            true
+        })
+    }
+}
+
+impl Default for PluginWsClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Publishes `payload` on `topic` in the background, stamped with the current time.
+///
+/// Returns immediately, so it can be called from a synchronous `handle_request`.
+/// `payload` is sent as JSON when it parses as JSON and as a plain string otherwise.
+/// A poisoned client mutex is recovered rather than panicking, and a failed
+/// publish is logged.
+pub fn publish_event(client: &Arc<Mutex<WsClient>>, publisher: &str, topic: &str, payload: &str) {
+    let client = Arc::clone(client);
+    let publisher = publisher.to_string();
+    let topic = topic.to_string();
+    let payload = serde_json::from_str::<Value>(payload).unwrap_or_else(|_| Value::String(payload.to_string()));
+    let timestamp = chrono::Utc::now().to_rfc3339();
+
+    // The std MutexGuard isn't Send, so the publish runs on a blocking thread
+    PUBLISH_RUNTIME.spawn_blocking(move || {
+        let mut ws_client = client.lock().unwrap_or_else(|poisoned| {
+            log_warn!(format!("[{}] WebSocket client mutex was poisoned; publishing anyway", publisher).as_str());
+            poisoned.into_inner()
+        });
+
+        let handle = tokio::runtime::Handle::current();
+        if let Err(_e) = handle.block_on(ws_client.publish(&publisher, &topic, payload, &timestamp, false)) {
+            log_error!(format!("[{}] Failed to publish {}: {}", publisher, topic, _e).as_str());
+        }
+    });
+}
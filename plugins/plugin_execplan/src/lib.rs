@@ -5,6 +5,7 @@ use plugin_core::{
     ApiResponse,
     HttpMethod,
     PluginContext,
+    ws_url_from_context,
     Resource,
     declare_plugin,
     error_response,
@@ -16,6 +17,7 @@ use plugin_core::jwt_utils::validate_jwt_token;
 
 use std::sync::{ Arc, Mutex };
 use libws::ws_client::WsClient;
+use plugin_core::ws_utils::PluginWsClient;
 use tokio::runtime::Runtime;
 use once_cell::sync::Lazy;
 use std::os::raw::c_char;
@@ -27,7 +29,7 @@ use serde::{ Serialize, Deserialize };
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
 // Shared WebSocket client
-static mut PLUGIN_WS_CLIENT: Option<Arc<Mutex<WsClient>>> = None;
+static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
 
 // Define your data structure
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -45,8 +47,8 @@ fn on_load() {
     println!("[plugin_execplan] >>> LOADED");
 }
 
-pub async fn create_ws_plugin_client() {
-    if let Ok(client) = WsClient::connect("plugin_execplan", "ws://127.0.0.1:8081/ws").await {
+pub async fn create_ws_plugin_client(ws_url: &str) {
+    if let Ok(client) = WsClient::connect_as_service("plugin_execplan", ws_url).await {
         let client = Arc::new(Mutex::new(client));
         /*
         if let Ok(mut ws_client) = client.lock() {
@@ -54,16 +56,17 @@ pub async fn create_ws_plugin_client() {
             //println!("[plugin_execplan] Subscribed to BluePrintUpdated");
         }
         */
-        unsafe {
-            PLUGIN_WS_CLIENT = Some(client);
+        if PLUGIN_WS_CLIENT.set(client).is_err() {
+            eprintln!("[plugin_execplan] WebSocket client already initialized");
         }
     }
 }
 
-extern "C" fn run(_ctx: *const PluginContext) {
+extern "C" fn run(ctx: *const PluginContext) {
     println!("[plugin_execplan] - run");
+    let ws_url = ws_url_from_context(unsafe { ctx.as_ref() });
     RUNTIME.block_on(async {
-        create_ws_plugin_client().await;
+        create_ws_plugin_client(&ws_url).await;
     });
 }
 
@@ -85,7 +88,7 @@ extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
     slice.as_ptr()
 }
 
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -8,6 +8,7 @@ use plugin_core::{
     log_info,
     declare_plugin,
     PluginContext,
+    ws_url_from_context,
     Resource,
     HttpMethod,
     ApiRequest,
@@ -15,7 +16,7 @@ use plugin_core::{
     error_response,
     cleanup_response,
     response_utils::{ json_response, method_not_allowed_response },
-    resource_utils::static_resource,
+    resource_utils::{static_resource, parse_resource_path},
 };
 use plugin_core::jwt_utils::validate_jwt_token;
 
@@ -26,11 +27,12 @@ use std::ptr;
 use std::sync::{ Arc, Mutex };
 
 // External dependencies
-use liblogger_macros::{ log_entry_exit, measure_time, initialize_logger_attributes };
+use liblogger_macros::{ instrument, initialize_logger_attributes };
 use once_cell::sync::Lazy;
 use serde::{ Serialize, Deserialize };
 use tokio::runtime::Runtime;
 use libws::ws_client::WsClient;
+use plugin_core::ws_utils::PluginWsClient;
 
 // Initialize logger attributes
 initialize_logger_attributes!();
@@ -39,7 +41,7 @@ initialize_logger_attributes!();
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
 // Global WebSocket client instance for event-driven communication
-static mut PLUGIN_WS_CLIENT: Option<Arc<Mutex<WsClient>>> = None;
+static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
 
 // Resource data model definition
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -68,8 +70,8 @@ fn on_load() {
 
 // Establishes WebSocket connection for real-time event publishing/subscribing
 // Automatically subscribes to the resource update event channel
-pub async fn create_ws_plugin_client() {
-    if let Ok(client) = WsClient::connect("plugin_howto", "ws://127.0.0.1:8081/ws").await {
+pub async fn create_ws_plugin_client(ws_url: &str) {
+    if let Ok(client) = WsClient::connect_as_service("plugin_howto", ws_url).await {
         let client = Arc::new(Mutex::new(client));
 
         if let Ok(mut ws_client) = client.lock() {
@@ -77,18 +79,19 @@ pub async fn create_ws_plugin_client() {
             log_debug!("[plugin_howto] Subscribed to TodoitemsUpdated");
         }
 
-        unsafe {
-            PLUGIN_WS_CLIENT = Some(client);
+        if PLUGIN_WS_CLIENT.set(client).is_err() {
+            eprintln!("[plugin_howto] WebSocket client already initialized");
         }
     }
 }
 
 // Entry point called by the plugin engine on startup
 // Initializes WebSocket connection and other required resources
-extern "C" fn run(_ctx: *const PluginContext) {
+extern "C" fn run(ctx: *const PluginContext) {
     println!("[plugin_howto] - run");
+    let ws_url = ws_url_from_context(unsafe { ctx.as_ref() });
     RUNTIME.block_on(async {
-        create_ws_plugin_client().await;
+        create_ws_plugin_client(&ws_url).await;
     });
 }
 
@@ -116,9 +119,8 @@ extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
 
 // Main request handler implementing RESTful API operations
 // Processes GET, POST, PUT, and DELETE requests for the resource
-#[log_entry_exit]
-#[measure_time]
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+#[instrument(timing)]
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -137,12 +139,11 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
             CStr::from_ptr(request.path).to_str().unwrap_or("<invalid>")
         };
 
-        // Extract ID from path if present (format: "todoitems/{id}")
-        let path_parts: Vec<&str> = path.split('/').collect();
-        let (resource_path, id_opt) = if path_parts.len() >= 2 {
-            (path_parts[0], Some(path_parts[1]))
-        } else {
-            (path, None)
+        // Extract ID from path if present (format: "todoitems/{id}"); deeper paths are not served
+        let (resource_path, id_opt) = match parse_resource_path(path).as_slice() {
+            [resource] => (*resource, None),
+            [resource, id] => (*resource, Some(*id)),
+            _ => return error_response(404, "Resource not found"),
         };
 
         match request.method {
@@ -1,16 +1,18 @@
 extern crate plugin_core;
 
 use plugin_core::{
-    ApiRequest, ApiResponse, HttpMethod, PluginContext, Resource,
+    ApiRequest, ApiResponse, HttpMethod, PluginContext, ws_url_from_context, Resource,
     declare_plugin,
     error_response,
-    response_utils::{json_response, method_not_allowed_response},
-    resource_utils::static_resource,
+    response_utils::{json_response, method_not_allowed_response, health_response, etag_response, if_match},
+    resource_utils::{static_resource, parse_resource_path},
     cleanup_response,
 };
 use plugin_core::jwt_utils::validate_jwt_token;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use libws::ws_client::WsClient;
+use plugin_core::ws_utils::{PluginWsClient, publish_event};
 use tokio::runtime::Runtime;
 use once_cell::sync::Lazy;
 use std::os::raw::c_char;
@@ -22,7 +24,10 @@ use serde::{Serialize, Deserialize};
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
 // Shared WebSocket client
-static mut PLUGIN_WS_CLIENT: Option<Arc<Mutex<WsClient>>> = None;
+static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
+
+// Set once run() has finished, reported through health_check
+static RUN_COMPLETED: AtomicBool = AtomicBool::new(false);
 
 // Define your data structure - using CamelCase for type name
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -32,6 +37,90 @@ struct Userprofile {
     username: String,
     password: String,
     remember_me: bool,
+    // Bumped on every update; 0 in a request body means "not sent"
+    #[serde(default)]
+    version: u64,
+}
+
+// Length limits for Userprofile fields, counted in characters
+const MAX_ID_LEN: usize = 64;
+const MAX_USERNAME_LEN: usize = 64;
+const MAX_PASSWORD_LEN: usize = 128;
+
+// A validation failure for one field, returned to the client in the 422 body
+#[derive(Serialize)]
+struct FieldError {
+    field: &'static str,
+    message: String,
+}
+
+impl FieldError {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self { field, message: message.into() }
+    }
+}
+
+impl Userprofile {
+    // Checks required fields and length limits, returning every failure at once.
+    // An empty id is allowed since POST generates one.
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if self.id.chars().count() > MAX_ID_LEN {
+            errors.push(FieldError::new("id", format!("must be at most {} characters", MAX_ID_LEN)));
+        }
+        if self.id.contains('/') {
+            errors.push(FieldError::new("id", "must not contain '/'"));
+        }
+
+        if self.username.trim().is_empty() {
+            errors.push(FieldError::new("username", "is required"));
+        } else if self.username.chars().count() > MAX_USERNAME_LEN {
+            errors.push(FieldError::new("username", format!("must be at most {} characters", MAX_USERNAME_LEN)));
+        }
+
+        if self.password.is_empty() {
+            errors.push(FieldError::new("password", "is required"));
+        } else if self.password.chars().count() > MAX_PASSWORD_LEN {
+            errors.push(FieldError::new("password", format!("must be at most {} characters", MAX_PASSWORD_LEN)));
+        }
+
+        errors
+    }
+}
+
+// Strong ETag for a profile version, e.g. `"3"`
+fn version_etag(version: u64) -> String {
+    format!("\"{}\"", version)
+}
+
+// Checks the client's expected version for a PUT, from `If-Match` or the body's `version`.
+// Returns the response to send when the update must not go ahead.
+fn check_expected_version(request: &ApiRequest, sent_version: u64, current: u64) -> Result<(), *mut ApiResponse> {
+    let matches = match if_match(request, &version_etag(current)) {
+        Some(matches) => matches,
+        None if sent_version != 0 => sent_version == current,
+        None => return Err(error_response(428, "Send If-Match or the expected version")),
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        let body = serde_json::json!({
+            "message": "Resource was modified by someone else",
+            "version": current,
+        });
+        Err(json_response(409, &body.to_string()))
+    }
+}
+
+// Builds the 422 response listing each invalid field
+fn validation_failed_response(errors: &[FieldError]) -> *mut ApiResponse {
+    let body = serde_json::json!({
+        "message": "Validation failed",
+        "errors": errors,
+    });
+    json_response(422, &body.to_string())
 }
 
 // Shared state - using a HashMap to store multiple items by ID
@@ -44,8 +133,8 @@ fn on_load() {
     println!("[plugin_login] >>> LOADED");
 }
 
-pub async fn create_ws_plugin_client() {
-    if let Ok(client) = WsClient::connect("plugin_login", "ws://127.0.0.1:8081/ws").await {
+pub async fn create_ws_plugin_client(ws_url: &str) {
+    if let Ok(client) = WsClient::connect_as_service("plugin_login", ws_url).await {
         let client = Arc::new(Mutex::new(client));
         
         if let Ok(mut ws_client) = client.lock() {
@@ -53,17 +142,24 @@ pub async fn create_ws_plugin_client() {
             println!("[plugin_login] Subscribed to UserprofileUpdated");
         }
         
-        unsafe {
-            PLUGIN_WS_CLIENT = Some(client);
+        if PLUGIN_WS_CLIENT.set(client).is_err() {
+            eprintln!("[plugin_login] WebSocket client already initialized");
         }
     }
 }
 
-extern "C" fn run(_ctx: *const PluginContext) {
+extern "C" fn run(ctx: *const PluginContext) {
     println!("[plugin_login] - run");
+    let ws_url = ws_url_from_context(unsafe { ctx.as_ref() });
     RUNTIME.block_on(async {
-        create_ws_plugin_client().await;
+        create_ws_plugin_client(&ws_url).await;
     });
+    RUN_COMPLETED.store(true, Ordering::SeqCst);
+}
+
+extern "C" fn health_check() -> *mut ApiResponse {
+    let ws_connected = PLUGIN_WS_CLIENT.is_connected();
+    health_response(RUN_COMPLETED.load(Ordering::SeqCst), ws_connected)
 }
 
 extern "C" fn get_static_content_path() -> *const c_char {
@@ -82,7 +178,7 @@ extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
     slice.as_ptr()
 }
 
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -101,12 +197,11 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
             CStr::from_ptr(request.path).to_str().unwrap_or("<invalid>")
         };
 
-        // Extract ID from path if present (format: "userprofile/ID")
-        let path_parts: Vec<&str> = path.split('/').collect();
-        let (resource_path, id_opt) = if path_parts.len() >= 2 {
-            (path_parts[0], Some(path_parts[1]))
-        } else {
-            (path, None)
+        // Extract ID from path if present (format: "userprofile/ID"); deeper paths are not served
+        let (resource_path, id_opt) = match parse_resource_path(path).as_slice() {
+            [resource] => (*resource, None),
+            [resource, id] => (*resource, Some(*id)),
+            _ => return error_response(404, "Resource not found"),
         };
 
         match request.method {
@@ -118,7 +213,7 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                 if let Some(id) = id_opt {
                     if let Some(item) = state.get(id) {
                         let json = serde_json::to_string(&item).unwrap();
-                        json_response(200, &json)
+                        etag_response(200, &json, &version_etag(item.version))
                     } else {
                         error_response(404, "Resource not found")
                     }
@@ -137,56 +232,37 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                 println!("[plugin_login] Received request body: {:?}", String::from_utf8_lossy(body));
                 
                 if let Ok(mut data) = serde_json::from_slice::<Userprofile>(body) {
+                    let errors = data.validate();
+                    if !errors.is_empty() {
+                        return validation_failed_response(&errors);
+                    }
+
                     let mut state = STATE.lock().unwrap();
                     
-                    // If ID is empty, generate one
+                    // If ID is empty, generate one; a client-supplied ID must be new
                     if data.id.is_empty() {
                         data.id = format!("{:x}", rand::random::<u64>());
+                    } else if state.contains_key(&data.id) {
+                        return error_response(409, "Resource already exists");
                     }
+                    data.version = 1;
                     
                     // Clone the ID for use in the response
                     let resource_id = data.id.clone();
                     
-                    // Insert into state
+                    // Insert into state, releasing the lock before publishing
                     state.insert(data.id.clone(), data.clone());
-                    
-                    // Get WebSocket client safely without creating a shared reference to static
-                    let client_clone = {
-                        // Get a raw pointer to the static
-                        let ws_client_ptr = ptr::addr_of!(PLUGIN_WS_CLIENT);
-                        // Dereference the raw pointer to get Option<Arc<Mutex<WsClient>>>
-                        (*ws_client_ptr).as_ref().map(Arc::clone)
-                    };
-                    
-                    if let Some(client) = client_clone {
-                        let timestamp = chrono::Utc::now().to_rfc3339();
-                        let data_clone = data.clone(); // Clone data before moving
-                        
-                        // Use spawn_blocking to handle the non-Send MutexGuard
-                        RUNTIME.spawn(async move {
-                            let timestamp_clone = timestamp.clone();
-                            let payload = serde_json::to_string(&data_clone).unwrap();
-                            
-                            tokio::task::spawn_blocking(move || {
-                                if let Ok(mut ws_client) = client.lock() {
-                                    let rt = tokio::runtime::Handle::current();
-                                    let _ = rt.block_on(ws_client.publish(
-                                        "plugin_login", 
-                                        "UserprofileUpdated", 
-                                        &payload,
-                                        &timestamp_clone
-                                    ));
-                                }
-                            }).await.ok();
-                        });
-                    }
+                    drop(state);
+
+                    publish_userprofile_updated(&serde_json::to_string(&data).unwrap_or_default());
                     
                     // Create response with the saved resource_id
                     let response = serde_json::json!({
                         "message": "Resource created",
-                        "id": resource_id
+                        "id": resource_id,
+                        "version": data.version
                     });
-                    json_response(201, &serde_json::to_string(&response).unwrap())
+                    etag_response(201, &serde_json::to_string(&response).unwrap(), &version_etag(data.version))
                 } else {
                     error_response(400, "Invalid data")
                 }
@@ -197,45 +273,33 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                 if let Some(id) = id_opt {
                     let body = std::slice::from_raw_parts(request.body_ptr, request.body_len);
                     if let Ok(mut data) = serde_json::from_slice::<Userprofile>(body) {
-                        let mut state = STATE.lock().unwrap();
-                        
                         // Ensure the ID in the URL matches the resource
                         data.id = id.to_string();
+
+                        let errors = data.validate();
+                        if !errors.is_empty() {
+                            return validation_failed_response(&errors);
+                        }
+
+                        let mut state = STATE.lock().unwrap();
                         
-                        if state.contains_key(id) {
-                            state.insert(id.to_string(), data.clone());
-                            
-                            // Get WebSocket client safely without creating a shared reference to static
-                            let client_clone = {
-                                // Get a raw pointer to the static
-                                let ws_client_ptr = ptr::addr_of!(PLUGIN_WS_CLIENT);
-                                // Dereference the raw pointer to get Option<Arc<Mutex<WsClient>>>
-                                (*ws_client_ptr).as_ref().map(Arc::clone)
-                            };
-                            
-                            if let Some(client) = client_clone {
-                                let timestamp = chrono::Utc::now().to_rfc3339();
-                                let data_clone = data.clone();
-                                
-                                RUNTIME.spawn(async move {
-                                    let timestamp_clone = timestamp.clone();
-                                    let payload = serde_json::to_string(&data_clone).unwrap();
-                                    
-                                    tokio::task::spawn_blocking(move || {
-                                        if let Ok(mut ws_client) = client.lock() {
-                                            let rt = tokio::runtime::Handle::current();
-                                            let _ = rt.block_on(ws_client.publish(
-                                                "plugin_login", 
-                                                "UserprofileUpdated", 
-                                                &payload,
-                                                &timestamp_clone
-                                            ));
-                                        }
-                                    }).await.ok();
-                                });
+                        if let Some(current) = state.get(id).map(|item| item.version) {
+                            // Reject updates based on a version that is no longer current
+                            if let Err(response) = check_expected_version(request, data.version, current) {
+                                return response;
                             }
+
+                            data.version = current + 1;
+                            state.insert(id.to_string(), data.clone());
+                            drop(state);
+
+                            publish_userprofile_updated(&serde_json::to_string(&data).unwrap_or_default());
                             
-                            json_response(200, r#"{"message": "Resource updated"}"#)
+                            let response = serde_json::json!({
+                                "message": "Resource updated",
+                                "version": data.version
+                            });
+                            etag_response(200, &response.to_string(), &version_etag(data.version))
                         } else {
                             error_response(404, "Resource not found")
                         }
@@ -249,49 +313,29 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
 
             // DELETE: Remove a resource
             HttpMethod::Delete if resource_path == "userprofile" => {
-                let mut state = STATE.lock().unwrap();
-                
-                if let Some(id) = id_opt {
-                    let id_string = id.to_string(); // Clone ID for use in async block
-                    
-                    if state.remove(id).is_some() {
-                        // Get WebSocket client safely without creating a shared reference to static
-                        let client_clone = {
-                            // Get a raw pointer to the static
-                            let ws_client_ptr = ptr::addr_of!(PLUGIN_WS_CLIENT);
-                            // Dereference the raw pointer to get Option<Arc<Mutex<WsClient>>>
-                            (*ws_client_ptr).as_ref().map(Arc::clone)
-                        };
-                        
-                        if let Some(client) = client_clone {
-                            let timestamp = chrono::Utc::now().to_rfc3339();
-                            
-                            RUNTIME.spawn(async move {
-                                let timestamp_clone = timestamp.clone();
-                                let event_data = serde_json::json!({ "id": id_string, "deleted": true });
-                                let payload = serde_json::to_string(&event_data).unwrap();
-                                
-                                tokio::task::spawn_blocking(move || {
-                                    if let Ok(mut ws_client) = client.lock() {
-                                        let rt = tokio::runtime::Handle::current();
-                                        let _ = rt.block_on(ws_client.publish(
-                                            "plugin_login", 
-                                            "UserprofileUpdated", 
-                                            &payload,
-                                            &timestamp_clone
-                                        ));
-                                    }
-                                }).await.ok();
-                            });
-                        }
-                        
-                        json_response(200, r#"{"message": "Resource deleted"}"#)
-                    } else {
-                        error_response(404, "Resource not found")
+                // Remove under the lock, then publish once it has been released
+                let removed: Vec<String> = {
+                    let mut state = STATE.lock().unwrap();
+                    match id_opt {
+                        Some(id) => state.remove(id).map(|_| id.to_string()).into_iter().collect(),
+                        // Clear all resources
+                        None => state.drain().map(|(id, _)| id).collect(),
                     }
+                };
+
+                if id_opt.is_some() && removed.is_empty() {
+                    return error_response(404, "Resource not found");
+                }
+
+                // Clearing everything sends the same event as each single delete would
+                for id in &removed {
+                    let payload = serde_json::json!({ "id": id, "deleted": true }).to_string();
+                    publish_userprofile_updated(&payload);
+                }
+
+                if id_opt.is_some() {
+                    json_response(200, r#"{"message": "Resource deleted"}"#)
                 } else {
-                    // Clear all resources
-                    state.clear();
                     json_response(200, r#"{"message": "All resources deleted"}"#)
                 }
             }
@@ -301,6 +345,17 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     }
 }
 
+// Publishes a UserprofileUpdated event if the WebSocket client is connected.
+// Callers must not hold the STATE lock.
+fn publish_userprofile_updated(payload: &str) {
+    #[cfg(test)]
+    tests::record_event(payload);
+
+    if let Some(client) = PLUGIN_WS_CLIENT.get() {
+        publish_event(&client, "plugin_login", "UserprofileUpdated", payload);
+    }
+}
+
 extern "C" fn cleanup(resp: *mut ApiResponse) {
     cleanup_response(resp);
 }
@@ -312,5 +367,270 @@ declare_plugin!(
     get_static_content_path,
     get_api_resources,
     handle_request,
-    cleanup
+    cleanup,
+    health_check = health_check
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin_core::ApiHeader;
+
+    // STATE is shared and DELETE clears it, so tests take turns
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    // Events passed to publish_userprofile_updated, with whether STATE was free at the time
+    static EVENTS: Mutex<Vec<(String, bool)>> = Mutex::new(Vec::new());
+
+    pub(super) fn record_event(payload: &str) {
+        let state_free = STATE.try_lock().is_ok();
+        EVENTS.lock().unwrap().push((payload.to_string(), state_free));
+    }
+
+    fn take_turn() -> std::sync::MutexGuard<'static, ()> {
+        SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    const PROFILE: &str = r#"{"username": "alice", "password": "secret", "remember_me": false}"#;
+
+    fn bearer() -> String {
+        let now = chrono::Utc::now().timestamp() as u64;
+        format!("Bearer {}", libjwt::generate_jwt("test-key", "session", now, now + 3600).unwrap())
+    }
+
+    // Calls handle_request and returns the response status and body
+    fn send(method: HttpMethod, path: &str, authorization: Option<&str>, body: &str) -> (u16, String) {
+        let headers: Vec<(&str, &str)> = authorization.map(|auth| ("Authorization", auth)).into_iter().collect();
+        send_with_headers(method, path, &headers, body)
+    }
+
+    fn send_with_headers(method: HttpMethod, path: &str, headers: &[(&str, &str)], body: &str) -> (u16, String) {
+        let owned: Vec<(CString, CString)> = headers
+            .iter()
+            .map(|(key, value)| (CString::new(*key).unwrap(), CString::new(*value).unwrap()))
+            .collect();
+        let headers: Vec<ApiHeader> = owned
+            .iter()
+            .map(|(key, value)| ApiHeader { key: key.as_ptr(), value: value.as_ptr() })
+            .collect();
+        let path = CString::new(path).unwrap();
+        let request = ApiRequest {
+            path: path.as_ptr(),
+            method,
+            headers: headers.as_ptr(),
+            header_count: headers.len(),
+            content_type: ptr::null(),
+            query: ptr::null(),
+            body_ptr: body.as_ptr(),
+            body_len: body.len(),
+        };
+
+        let response = handle_request(&request);
+        let (status, body) = unsafe {
+            let body = std::slice::from_raw_parts((*response).body_ptr, (*response).body_len);
+            ((*response).status, String::from_utf8_lossy(body).into_owned())
+        };
+        cleanup(response);
+        (status, body)
+    }
+
+    // Sends an authenticated request
+    fn call(method: HttpMethod, path: &str, body: &str) -> (u16, String) {
+        send(method, path, Some(&bearer()), body)
+    }
+
+    #[test]
+    fn request_without_a_token_is_unauthorized() {
+        let _turn = take_turn();
+        let intruder = r#"{"username": "intruder", "password": "secret", "remember_me": false}"#;
+        assert_eq!(send(HttpMethod::Get, "userprofile", None, "").0, 401);
+        assert_eq!(send(HttpMethod::Post, "userprofile", None, intruder).0, 401);
+        assert_eq!(send(HttpMethod::Get, "userprofile", Some("Bearer not-a-jwt"), "").0, 401);
+        assert!(
+            STATE.lock().unwrap().values().all(|profile| profile.username != "intruder"),
+            "unauthenticated POST created a profile"
+        );
+    }
+
+    #[test]
+    fn request_with_a_token_is_served() {
+        let _turn = take_turn();
+        assert_eq!(call(HttpMethod::Get, "userprofile", "").0, 200);
+    }
+
+    #[test]
+    fn profile_paths_resolve_and_deeper_paths_are_not_found() {
+        let _turn = take_turn();
+        let (status, created) = call(HttpMethod::Post, "userprofile", PROFILE);
+        assert_eq!(status, 201, "{}", created);
+        let created: serde_json::Value = serde_json::from_str(&created).unwrap();
+        let id = created["id"].as_str().unwrap();
+
+        assert_eq!(call(HttpMethod::Get, "userprofile", "").0, 200);
+        assert_eq!(call(HttpMethod::Get, &format!("userprofile/{}", id), "").0, 200);
+        // Sub-resources reach the plugin whole and are not mistaken for the profile
+        let (status, body) = call(HttpMethod::Get, &format!("userprofile/{}/sessions", id), "");
+        assert_eq!(status, 404, "{}", body);
+    }
+
+    // POSTs `profile` and returns the status and the fields named in a 422 body
+    fn rejected_fields(profile: serde_json::Value) -> (u16, Vec<String>) {
+        let (status, body) = call(HttpMethod::Post, "userprofile", &profile.to_string());
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+        let fields = body["errors"]
+            .as_array()
+            .map(|errors| errors.iter().map(|e| e["field"].as_str().unwrap().to_string()).collect())
+            .unwrap_or_default();
+        (status, fields)
+    }
+
+    fn profile(id: &str, username: &str, password: &str) -> serde_json::Value {
+        serde_json::json!({ "id": id, "username": username, "password": password, "remember_me": false })
+    }
+
+    #[test]
+    fn each_invalid_field_is_reported() {
+        let _turn = take_turn();
+        let long = |len: usize| "x".repeat(len);
+        let cases = [
+            (profile(&long(MAX_ID_LEN + 1), "alice", "secret"), "id"),
+            (profile("a/b", "alice", "secret"), "id"),
+            (profile("", "", "secret"), "username"),
+            (profile("", "   ", "secret"), "username"),
+            (profile("", &long(MAX_USERNAME_LEN + 1), "secret"), "username"),
+            (profile("", "alice", ""), "password"),
+            (profile("", "alice", &long(MAX_PASSWORD_LEN + 1)), "password"),
+        ];
+        for (body, field) in cases {
+            assert_eq!(rejected_fields(body.clone()), (422, vec![field.to_string()]), "{}", body);
+        }
+
+        // Limits are inclusive and counted in characters, not bytes
+        let at_limit = profile(&long(MAX_ID_LEN), &"é".repeat(MAX_USERNAME_LEN), &long(MAX_PASSWORD_LEN));
+        assert_eq!(rejected_fields(at_limit).0, 201);
+    }
+
+    #[test]
+    fn every_failure_is_listed_at_once() {
+        let _turn = take_turn();
+        let (status, fields) = rejected_fields(profile("a/b", "", ""));
+        assert_eq!(status, 422);
+        assert_eq!(fields, ["id", "username", "password"]);
+    }
+
+    #[test]
+    fn missing_required_keys_are_invalid_data() {
+        let _turn = take_turn();
+        let (status, _) = call(HttpMethod::Post, "userprofile", r#"{"username": "alice"}"#);
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn client_supplied_id_must_be_new() {
+        let _turn = take_turn();
+        let id = format!("collide-{:x}", rand::random::<u64>());
+        assert_eq!(rejected_fields(profile(&id, "alice", "secret")).0, 201);
+
+        let (status, body) = call(HttpMethod::Post, "userprofile", &profile(&id, "mallory", "secret").to_string());
+        assert_eq!(status, 409, "{}", body);
+        assert_eq!(STATE.lock().unwrap()[&id].username, "alice", "the existing profile was overwritten");
+    }
+
+    #[test]
+    fn delete_all_announces_every_removed_profile() {
+        let _turn = take_turn();
+        let mut created = Vec::new();
+        for name in ["alice", "bob", "carol"] {
+            let (status, body) = call(HttpMethod::Post, "userprofile", &profile("", name, "secret").to_string());
+            assert_eq!(status, 201, "{}", body);
+            created.push(serde_json::from_str::<serde_json::Value>(&body).unwrap()["id"].as_str().unwrap().to_string());
+        }
+        let mut expected: Vec<String> = STATE.lock().unwrap().keys().cloned().collect();
+        EVENTS.lock().unwrap().clear();
+
+        assert_eq!(call(HttpMethod::Delete, "userprofile", "").0, 200);
+        assert!(STATE.lock().unwrap().is_empty());
+
+        let events = std::mem::take(&mut *EVENTS.lock().unwrap());
+        assert!(events.iter().all(|(_, state_free)| *state_free), "published while holding STATE");
+        let mut deleted: Vec<String> = events
+            .iter()
+            .map(|(payload, _)| serde_json::from_str::<serde_json::Value>(payload).unwrap())
+            .inspect(|event| assert_eq!(event["deleted"], true))
+            .map(|event| event["id"].as_str().unwrap().to_string())
+            .collect();
+        deleted.sort();
+        expected.sort();
+        assert_eq!(deleted, expected);
+        assert!(created.iter().all(|id| deleted.contains(id)));
+
+        // Clearing an empty store announces nothing
+        assert_eq!(call(HttpMethod::Delete, "userprofile", "").0, 200);
+        assert!(EVENTS.lock().unwrap().is_empty());
+    }
+
+    // Creates a profile and returns its id
+    fn create_profile(username: &str) -> String {
+        let (status, body) = call(HttpMethod::Post, "userprofile", &profile("", username, "secret").to_string());
+        assert_eq!(status, 201, "{}", body);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["version"], 1);
+        body["id"].as_str().unwrap().to_string()
+    }
+
+    fn put_profile(id: &str, username: &str, version: Option<u64>, if_match: Option<&str>) -> (u16, serde_json::Value) {
+        let mut body = profile("", username, "secret");
+        if let Some(version) = version {
+            body["version"] = version.into();
+        }
+        let authorization = bearer();
+        let mut headers = vec![("Authorization", authorization.as_str())];
+        headers.extend(if_match.map(|etag| ("If-Match", etag)));
+
+        let (status, body) = send_with_headers(HttpMethod::Put, &format!("userprofile/{}", id), &headers, &body.to_string());
+        (status, serde_json::from_str(&body).unwrap_or_default())
+    }
+
+    #[test]
+    fn put_with_the_current_version_is_applied() {
+        let _turn = take_turn();
+        let id = create_profile("alice");
+
+        let (status, body) = put_profile(&id, "alice2", Some(1), None);
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["version"], 2);
+
+        // The ETag from a GET works as well
+        let (status, body) = put_profile(&id, "alice3", None, Some("\"2\""));
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["version"], 3);
+
+        let stored = STATE.lock().unwrap()[&id].clone();
+        assert_eq!((stored.username.as_str(), stored.version), ("alice3", 3));
+    }
+
+    #[test]
+    fn put_with_a_stale_version_is_a_conflict() {
+        let _turn = take_turn();
+        let id = create_profile("bob");
+        assert_eq!(put_profile(&id, "bob-first", Some(1), None).0, 200);
+
+        // A second client still editing version 1 loses, through either mechanism
+        for (version, if_match) in [(Some(1), None), (None, Some("\"1\""))] {
+            let (status, body) = put_profile(&id, "bob-second", version, if_match);
+            assert_eq!(status, 409, "{}", body);
+            assert_eq!(body["version"], 2, "the conflict names the current version");
+        }
+
+        let stored = STATE.lock().unwrap()[&id].clone();
+        assert_eq!((stored.username.as_str(), stored.version), ("bob-first", 2));
+    }
+
+    #[test]
+    fn put_without_an_expected_version_is_refused() {
+        let _turn = take_turn();
+        let id = create_profile("carol");
+        assert_eq!(put_profile(&id, "carol2", None, None).0, 428);
+        assert_eq!(STATE.lock().unwrap()[&id].version, 1);
+    }
+}
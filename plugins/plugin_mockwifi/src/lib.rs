@@ -5,10 +5,10 @@ extern crate liblogger_macros;
 // Plugin core imports
 use plugin_core::{
     log_debug, log_info, 
-    declare_plugin, PluginContext, Resource, HttpMethod,
-    ApiRequest, ApiResponse, error_response, cleanup_response,
-    response_utils::{json_response, method_not_allowed_response},
-    resource_utils::static_resource,
+    declare_plugin, PluginContext, ws_url_from_context, Resource, HttpMethod,
+    ApiRequest, ApiResponse, ApiError, cleanup_response, with_request_context,
+    response_utils::{json_response, method_not_allowed_response, typed_error_response},
+    resource_utils::{static_resource, parse_resource_path},
     jwt_utils::validate_jwt_token,
 };
 
@@ -20,12 +20,13 @@ use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 
 // External dependencies
-use liblogger_macros::{log_entry_exit, measure_time, initialize_logger_attributes};
+use liblogger_macros::{instrument, initialize_logger_attributes};
 use once_cell::sync::Lazy;
 use serde::{Serialize, Deserialize};
 use tokio::runtime::Runtime;
 use libws::ws_client::WsClient;
-use rand;
+use plugin_core::ws_utils::{PluginWsClient, publish_event};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 mod network_info;
 
@@ -62,7 +63,7 @@ initialize_logger_attributes!();
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
 // Global WebSocket client instance for event-driven communication
-static mut PLUGIN_WS_CLIENT: Option<Arc<Mutex<WsClient>>> = None;
+static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
 
 // Plugin initialization hook that runs when the plugin is first loaded
 #[ctor::ctor]
@@ -77,8 +78,8 @@ fn on_load() {
 
 // Establishes WebSocket connection for real-time event publishing/subscribing
 // Automatically subscribes to the resource update event channel
-pub async fn create_ws_plugin_client() {
-    if let Ok(client) = WsClient::connect("plugin_mockwifi", "ws://127.0.0.1:8081/ws").await {
+pub async fn create_ws_plugin_client(ws_url: &str) {
+    if let Ok(client) = WsClient::connect_as_service("plugin_mockwifi", ws_url).await {
         let client = Arc::new(Mutex::new(client));
         
         if let Ok(mut ws_client) = client.lock() {
@@ -86,22 +87,57 @@ pub async fn create_ws_plugin_client() {
             log_debug!("[plugin_mockwifi] Subscribed to NetworkUpdated");
         }
         
-        unsafe {
-            PLUGIN_WS_CLIENT = Some(client);
+        if PLUGIN_WS_CLIENT.set(client).is_err() {
+            eprintln!("[plugin_mockwifi] WebSocket client already initialized");
         }
     }
 }
 
 // Entry point called by the plugin engine on startup
 // Initializes WebSocket connection and other required resources
-extern "C" fn run(_ctx: *const PluginContext) {
+extern "C" fn run(ctx: *const PluginContext) {
     println!("[plugin_mockwifi] - run");
+    let ws_url = ws_url_from_context(unsafe { ctx.as_ref() });
     RUNTIME.block_on(async {
-        create_ws_plugin_client().await;
+        create_ws_plugin_client(&ws_url).await;
     });
     
-    // Initialize some mock networks for testing
-    initialize_mock_networks();
+    // Initialize some mock networks for testing; `count=50;seed=42` in the config
+    // generates a reproducible set instead of the three defaults
+    let ctx = unsafe { ctx.as_ref() };
+    let count = ctx.and_then(|c| c.config_value("count")).and_then(|v| v.parse::<usize>().ok());
+    let seed = ctx.and_then(|c| c.config_value("seed")).and_then(|v| v.parse::<u64>().ok());
+    match count {
+        Some(count) => generate_mock_networks(count, seed.unwrap_or(0)),
+        None => initialize_mock_networks(),
+    }
+}
+
+// Generates `count` mock networks from a seeded RNG
+// The same count and seed always produce the same networks with the same IDs.
+fn generate_mock_networks(count: usize, seed: u64) {
+    const SSID_PREFIXES: [&str; 6] = ["Home", "Office", "Cafe", "Guest", "Lab", "Library"];
+    const SECURITY: [&str; 4] = ["Open", "WPA2", "WPA3", "WPA2-Enterprise"];
+    const CHANNELS: [i32; 11] = [1, 6, 11, 36, 40, 44, 48, 149, 153, 157, 161];
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut networks = MOCK_NETWORKS.lock().unwrap();
+    log_debug!(format!("Generating {} mock networks with seed {}", count, seed).as_str());
+
+    for i in 0..count {
+        let channel = CHANNELS[rng.gen_range(0..CHANNELS.len())];
+        let frequency = if channel <= 13 { 2407 + channel * 5 } else { 5000 + channel * 5 };
+        let mac: [u8; 6] = rng.gen();
+        let network = MockNetworkInfo {
+            ssid: format!("{}_{:03}", SSID_PREFIXES[rng.gen_range(0..SSID_PREFIXES.len())], i + 1),
+            bssid: mac.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":"),
+            signal: rng.gen_range(-90..=-30),
+            channel,
+            security: SECURITY[rng.gen_range(0..SECURITY.len())].to_string(),
+            frequency: frequency as f32,
+        };
+        networks.insert(format!("{:016x}", rng.gen::<u64>()), network);
+    }
 }
 
 // Initialize some mock network data
@@ -147,6 +183,19 @@ fn initialize_mock_networks() {
     }
 }
 
+// Publishes a `NetworkUpdated` event for a mutation, the way plugin_login announces its changes
+// Payload: `{"action": "created" | "updated" | "deleted", "id": "<id>", "network": {...} | null}`
+fn publish_network_updated(action: &str, id: &str, network: Option<&MockNetworkInfo>) {
+    if let Some(client) = PLUGIN_WS_CLIENT.get() {
+        let payload = serde_json::json!({
+            "action": action,
+            "id": id,
+            "network": network,
+        });
+        publish_event(&client, "plugin_mockwifi", "NetworkUpdated", &payload.to_string());
+    }
+}
+
 // Convert a NetworkInfoInput to MockNetworkInfo
 fn from_input_to_json(input: &NetworkInfoInput) -> MockNetworkInfo {
     MockNetworkInfo {
@@ -159,6 +208,119 @@ fn from_input_to_json(input: &NetworkInfoInput) -> MockNetworkInfo {
     }
 }
 
+// Longest SSID allowed by 802.11, in bytes
+const MAX_SSID_BYTES: usize = 32;
+
+// Checks one element of a bulk create, returning why it was rejected
+fn validate_input(input: &NetworkInfoInput) -> Result<(), String> {
+    if input.ssid.trim().is_empty() {
+        return Err("ssid is required".to_string());
+    }
+    if input.ssid.len() > MAX_SSID_BYTES {
+        return Err(format!("ssid must be at most {} bytes", MAX_SSID_BYTES));
+    }
+    if let Some(signal) = input.signal.filter(|s| !(-100..=0).contains(s)) {
+        return Err(format!("signal must be between -100 and 0 dBm, got {}", signal));
+    }
+    Ok(())
+}
+
+// Creates every valid network in a `POST network/bulk` array
+// Invalid elements don't stop the batch; each one gets its own result, in input order:
+// `{"index": 0, "id": "<id>"}` or `{"index": 1, "error": "<reason>"}`.
+// Answers 201 when everything was created, 207 on partial success and 422 when nothing was.
+fn bulk_create_networks(items: Vec<serde_json::Value>) -> *mut ApiResponse {
+    let mut results = Vec::with_capacity(items.len());
+    let mut created = Vec::new();
+
+    {
+        let mut networks = MOCK_NETWORKS.lock().unwrap();
+        for (index, item) in items.into_iter().enumerate() {
+            let input = serde_json::from_value::<NetworkInfoInput>(item)
+                .map_err(|e| format!("Invalid data: {}", e))
+                .and_then(|input| validate_input(&input).map(|_| input));
+
+            match input {
+                Ok(input) => {
+                    let network = from_input_to_json(&input);
+                    let resource_id = format!("{:x}", rand::random::<u64>());
+                    networks.insert(resource_id.clone(), network.clone());
+                    results.push(serde_json::json!({ "index": index, "id": resource_id }));
+                    created.push((resource_id, network));
+                }
+                Err(error) => results.push(serde_json::json!({ "index": index, "error": error })),
+            }
+        }
+    }
+
+    for (id, network) in &created {
+        publish_network_updated("created", id, Some(network));
+    }
+
+    let failed = results.len() - created.len();
+    let status = match (created.len(), failed) {
+        (_, 0) => 201,
+        (0, _) => 422,
+        _ => 207,
+    };
+    log_debug!(format!("Bulk create: {} created, {} failed", created.len(), failed).as_str());
+
+    let response = serde_json::json!({
+        "created": created.len(),
+        "failed": failed,
+        "results": results,
+    });
+    json_response(status, &response.to_string())
+}
+
+// Parses an optional numeric query parameter, naming the parameter when it's malformed
+fn parse_param<T: std::str::FromStr>(params: &HashMap<String, String>, name: &str) -> Result<Option<T>, String> {
+    params
+        .get(name)
+        .map(|value| value.parse::<T>().map_err(|_| format!("Invalid '{}' parameter: {}", name, value)))
+        .transpose()
+}
+
+// Filters, sorts and pages the mock networks for `GET network?limit=&offset=&min_signal=&security=`
+// Networks are sorted by signal, strongest first; `security` matches case-insensitively.
+// An offset past the end yields an empty page; `total` always counts every match.
+fn query_networks(
+    networks: &HashMap<String, MockNetworkInfo>,
+    params: &HashMap<String, String>
+) -> Result<serde_json::Value, String> {
+    let limit = parse_param::<usize>(params, "limit")?;
+    let offset = parse_param::<usize>(params, "offset")?.unwrap_or(0);
+    let min_signal = parse_param::<i32>(params, "min_signal")?;
+    let security = params.get("security").filter(|s| !s.is_empty());
+
+    let mut matches: Vec<(&String, &MockNetworkInfo)> = networks
+        .iter()
+        .filter(|(_, n)| min_signal.is_none_or(|min| n.signal >= min))
+        .filter(|(_, n)| security.is_none_or(|s| n.security.eq_ignore_ascii_case(s)))
+        .collect();
+    // Ties are broken by id so pages stay stable between requests
+    matches.sort_by(|(a_id, a), (b_id, b)| b.signal.cmp(&a.signal).then_with(|| a_id.cmp(b_id)));
+
+    let total = matches.len();
+    let page: Vec<serde_json::Value> = matches
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|(id, network)| {
+            let mut item = serde_json::to_value(network).unwrap_or_default();
+            item["id"] = serde_json::Value::String(id.clone());
+            item
+        })
+        .collect();
+
+    Ok(serde_json::json!({
+        "total": total,
+        "offset": offset,
+        "limit": limit,
+        "networks": page,
+    }))
+}
+
 // Defines the path where static web content (HTML, CSS, JS) can be served from
 // This content will be available at /mwifi/web/ in the application
 extern "C" fn get_static_content_path() -> *const c_char {
@@ -179,11 +341,20 @@ extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
     slice.as_ptr()
 }
 
+// Entry point called by the engine; this plugin's logs for the request carry
+// the engine's X-Request-Id
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+    if req.is_null() {
+        return ptr::null_mut();
+    }
+    let request = unsafe { &*req };
+    with_request_context(request, || handle_api_request(req))
+}
+
 // Main request handler implementing RESTful API operations
 // Processes GET, POST, PUT, and DELETE requests for the resource
-#[log_entry_exit]
-#[measure_time]
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+#[instrument(timing)]
+fn handle_api_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -202,12 +373,17 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
             CStr::from_ptr(request.path).to_str().unwrap_or("<invalid>")
         };
 
-        // Extract ID from path if present (format: "network/{id}")
-        let path_parts: Vec<&str> = path.split('/').collect();
-        let (resource_path, id_opt) = if path_parts.len() >= 2 {
-            (path_parts[0], Some(path_parts[1]))
-        } else {
-            (path, None)
+        // Bodies must be JSON; requests without a Content-Type are still accepted
+        if request.body_len > 0 && request.content_type().is_some() && !request.is_json() {
+            return typed_error_response(ApiError::unsupported_media_type("Content-Type must be application/json"));
+        }
+
+        // Extract ID from path if present (format: "network/{id}"); deeper paths are not served
+        let segments = parse_resource_path(path);
+        let (resource_path, id_opt) = match segments.as_slice() {
+            [resource] => (*resource, None),
+            [resource, id] => (*resource, Some(*id)),
+            _ => return typed_error_response(ApiError::not_found("Resource not found")),
         };
 
         match request.method {
@@ -222,16 +398,30 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                         let json = serde_json::to_string(&item).unwrap();
                         json_response(200, &json)
                     } else {
-                        error_response(404, "Resource not found")
+                        typed_error_response(ApiError::not_found("Resource not found"))
                     }
-                } else {
+                } else if request.query_params().is_empty() {
                     // Return all mock networks
                     let json = serde_json::to_string(&*networks).unwrap();
                     log_debug!(format!("Returning all networks: {} - Context: {}", json, "plugin_mockwifi").as_str());
                     json_response(200, &json)
+                } else {
+                    match query_networks(&networks, &request.query_params()) {
+                        Ok(page) => json_response(200, &page.to_string()),
+                        Err(message) => typed_error_response(ApiError::validation(message)),
+                    }
                 }
             }
             
+            // POST network/bulk: Create every network in a JSON array
+            HttpMethod::Post if segments == ["network", "bulk"] => {
+                let body = std::slice::from_raw_parts(request.body_ptr, request.body_len);
+                match serde_json::from_slice::<Vec<serde_json::Value>>(body) {
+                    Ok(items) => bulk_create_networks(items),
+                    Err(_) => typed_error_response(ApiError::validation("Expected a JSON array of networks")),
+                }
+            }
+
             // POST: Create a new resource
             HttpMethod::Post if resource_path == "network" => {
                 let body = std::slice::from_raw_parts(request.body_ptr, request.body_len);
@@ -245,7 +435,8 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                     let resource_id = format!("{:x}", rand::random::<u64>());
                     
                     // Insert into networks with the generated ID as key
-                    networks.insert(resource_id.clone(), network_json);
+                    networks.insert(resource_id.clone(), network_json.clone());
+                    publish_network_updated("created", &resource_id, Some(&network_json));
 
                     // Create response with the saved resource_id
                     let response = serde_json::json!({
@@ -255,7 +446,7 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                     log_debug!(format!("Saving a resource: {}, Context: {}", response, "plugin_mockwifi").as_str());
                     json_response(201, &serde_json::to_string(&response).unwrap())
                 } else {
-                    error_response(400, "Invalid data")
+                    typed_error_response(ApiError::validation("Invalid data"))
                 }
             }
             
@@ -270,16 +461,17 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                         let network_json = from_input_to_json(&network_input);
 
                         if networks.contains_key(id) {
-                            networks.insert(id.to_string(), network_json);
+                            networks.insert(id.to_string(), network_json.clone());
+                            publish_network_updated("updated", id, Some(&network_json));
                             json_response(200, r#"{"message": "Resource updated"}"#)
                         } else {
-                            error_response(404, "Resource not found")
+                            typed_error_response(ApiError::not_found("Resource not found"))
                         }
                     } else {
-                        error_response(400, "Invalid data")
+                        typed_error_response(ApiError::validation("Invalid data"))
                     }
                 } else {
-                    error_response(400, "Resource ID required")
+                    typed_error_response(ApiError::validation("Resource ID required"))
                 }
             }
 
@@ -289,12 +481,16 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
 
                 if let Some(id) = id_opt {
                     if networks.remove(id).is_some() {
+                        publish_network_updated("deleted", id, None);
                         json_response(200, r#"{"message": "Resource deleted"}"#)
                     } else {
-                        error_response(404, "Resource not found")
+                        typed_error_response(ApiError::not_found("Resource not found"))
                     }
                 } else {
-                    // Clear all resources
+                    // Clear all resources, announcing each removal like a single delete
+                    for id in networks.keys() {
+                        publish_network_updated("deleted", id, None);
+                    }
                     networks.clear();
                     json_response(200, r#"{"message": "All resources deleted"}"#)
                 }
@@ -322,3 +518,202 @@ declare_plugin!(
     handle_request,
     cleanup
 );
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(ssid: &str, signal: i32, security: &str) -> MockNetworkInfo {
+        MockNetworkInfo {
+            ssid: ssid.to_string(),
+            bssid: "00:11:22:33:44:55".to_string(),
+            signal,
+            channel: 6,
+            security: security.to_string(),
+            frequency: 2437.0,
+        }
+    }
+
+    // Five networks keyed by id; "c" and "d" tie on signal
+    fn sample_networks() -> HashMap<String, MockNetworkInfo> {
+        [
+            ("a", network("Weak", -85, "Open")),
+            ("b", network("Strong", -40, "WPA2")),
+            ("c", network("MidWpa3", -65, "WPA3")),
+            ("d", network("MidWpa2", -65, "wpa2")),
+            ("e", network("Far", -75, "WPA2")),
+        ]
+        .into_iter()
+        .map(|(id, n)| (id.to_string(), n))
+        .collect()
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> serde_json::Value {
+        let params = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        query_networks(&sample_networks(), &params).unwrap()
+    }
+
+    // Sends an authenticated request to the plugin and returns the status and body
+    fn send(method: HttpMethod, path: &str, content_type: Option<&str>, body: &str) -> (u16, String) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let token = libjwt::generate_jwt("test-key", "session", now, now + 3600).unwrap();
+        let key = CString::new("Authorization").unwrap();
+        let value = CString::new(format!("Bearer {}", token)).unwrap();
+        let headers = [plugin_core::ApiHeader { key: key.as_ptr(), value: value.as_ptr() }];
+        let path = CString::new(path).unwrap();
+        let content_type = content_type.map(|ct| CString::new(ct).unwrap());
+        let request = ApiRequest {
+            path: path.as_ptr(),
+            method,
+            headers: headers.as_ptr(),
+            header_count: 1,
+            content_type: content_type.as_ref().map_or(ptr::null(), |ct| ct.as_ptr()),
+            query: ptr::null(),
+            body_ptr: body.as_ptr(),
+            body_len: body.len(),
+        };
+
+        let response = handle_request(&request);
+        let (status, body) = unsafe {
+            let body = std::slice::from_raw_parts((*response).body_ptr, (*response).body_len);
+            ((*response).status, String::from_utf8_lossy(body).into_owned())
+        };
+        cleanup(response);
+        (status, body)
+    }
+
+    fn post_bulk(items: &str) -> (u16, serde_json::Value) {
+        let (status, body) = send(HttpMethod::Post, "network/bulk", Some("application/json"), items);
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    fn ids(page: &serde_json::Value) -> Vec<&str> {
+        page["networks"].as_array().unwrap().iter().map(|n| n["id"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn results_are_sorted_strongest_first() {
+        let page = query(&[]);
+        assert_eq!(ids(&page), ["b", "c", "d", "e", "a"]);
+        assert_eq!(page["total"], 5);
+        assert_eq!(page["networks"][0]["ssid"], "Strong");
+    }
+
+    #[test]
+    fn limit_and_offset_select_a_page() {
+        let page = query(&[("limit", "2"), ("offset", "1")]);
+        assert_eq!(ids(&page), ["c", "d"]);
+        assert_eq!(page["total"], 5);
+        assert_eq!(page["offset"], 1);
+        assert_eq!(page["limit"], 2);
+
+        assert_eq!(ids(&query(&[("limit", "2")])), ["b", "c"]);
+        assert_eq!(ids(&query(&[("offset", "3")])), ["e", "a"]);
+    }
+
+    #[test]
+    fn offset_past_the_end_yields_an_empty_page() {
+        for offset in ["5", "500"] {
+            let page = query(&[("offset", offset), ("limit", "10")]);
+            assert!(ids(&page).is_empty(), "offset {}", offset);
+            assert_eq!(page["total"], 5);
+        }
+    }
+
+    #[test]
+    fn min_signal_keeps_networks_at_or_above_it() {
+        let page = query(&[("min_signal", "-65")]);
+        assert_eq!(ids(&page), ["b", "c", "d"]);
+        assert_eq!(page["total"], 3);
+    }
+
+    #[test]
+    fn security_matches_case_insensitively() {
+        let page = query(&[("security", "WPA2")]);
+        assert_eq!(ids(&page), ["b", "d", "e"]);
+        assert_eq!(page["total"], 3);
+
+        // An empty value doesn't filter
+        assert_eq!(query(&[("security", "")])["total"], 5);
+    }
+
+    #[test]
+    fn filters_combine_before_paging() {
+        let page = query(&[("security", "wpa2"), ("min_signal", "-70"), ("limit", "1"), ("offset", "1")]);
+        assert_eq!(ids(&page), ["d"]);
+        assert_eq!(page["total"], 2);
+    }
+
+    #[test]
+    fn malformed_numbers_name_the_parameter() {
+        for (name, value) in [("limit", "ten"), ("offset", "-1"), ("min_signal", "loud")] {
+            let params = HashMap::from([(name.to_string(), value.to_string())]);
+            let err = query_networks(&sample_networks(), &params).unwrap_err();
+            assert_eq!(err, format!("Invalid '{}' parameter: {}", name, value));
+        }
+    }
+
+    #[test]
+    fn bulk_create_reports_each_item() {
+        let long_ssid = "x".repeat(MAX_SSID_BYTES + 1);
+        let batch = serde_json::json!([
+            { "ssid": "BulkOne", "signal": -50 },
+            { "signal": -60 },
+            { "ssid": "   " },
+            { "ssid": long_ssid },
+            { "ssid": "BulkTwo", "signal": 5 },
+            "not a network",
+            { "ssid": "BulkThree", "security": "WPA3" },
+        ]);
+        let (status, body) = post_bulk(&batch.to_string());
+
+        assert_eq!(status, 207, "{}", body);
+        assert_eq!(body["created"], 2);
+        assert_eq!(body["failed"], 5);
+
+        let results = body["results"].as_array().unwrap();
+        let indexes: Vec<u64> = results.iter().map(|r| r["index"].as_u64().unwrap()).collect();
+        assert_eq!(indexes, [0, 1, 2, 3, 4, 5, 6], "results follow the input order");
+
+        let error = |i: usize| results[i]["error"].as_str().unwrap_or_default().to_string();
+        assert!(error(1).starts_with("Invalid data: missing field `ssid`"), "{}", error(1));
+        assert_eq!(error(2), "ssid is required");
+        assert_eq!(error(3), format!("ssid must be at most {} bytes", MAX_SSID_BYTES));
+        assert_eq!(error(4), "signal must be between -100 and 0 dBm, got 5");
+        assert!(error(5).starts_with("Invalid data"), "{}", error(5));
+
+        let networks = MOCK_NETWORKS.lock().unwrap();
+        for (index, ssid) in [(0, "BulkOne"), (6, "BulkThree")] {
+            assert!(results[index].get("error").is_none());
+            let id = results[index]["id"].as_str().unwrap();
+            assert_eq!(networks[id].ssid, ssid);
+        }
+        assert!(networks.values().all(|n| n.ssid != "BulkTwo"), "an invalid item was stored");
+    }
+
+    #[test]
+    fn bulk_status_reflects_the_outcome() {
+        assert_eq!(post_bulk(r#"[{"ssid": "BulkAllGood"}]"#).0, 201);
+        assert_eq!(post_bulk(r#"[{"ssid": ""}, {"signal": -40}]"#).0, 422);
+        assert_eq!(post_bulk(r#"{"ssid": "NotAnArray"}"#).0, 400);
+    }
+
+    #[test]
+    fn post_body_content_type_is_checked() {
+        let network = r#"{"ssid": "ContentTypeNet"}"#;
+
+        // Clients that send no Content-Type keep working
+        assert_eq!(send(HttpMethod::Post, "network", None, network).0, 201);
+        assert_eq!(send(HttpMethod::Post, "network", Some("application/json"), network).0, 201);
+        assert_eq!(send(HttpMethod::Post, "network", Some("application/json; charset=utf-8"), network).0, 201);
+
+        let (status, body) = send(HttpMethod::Post, "network", Some("text/plain"), network);
+        assert_eq!(status, 415, "{}", body);
+        assert!(body.contains("Content-Type must be application/json"), "{}", body);
+        assert_eq!(send(HttpMethod::Post, "network/bulk", Some("text/plain"), &format!("[{}]", network)).0, 415);
+
+        // Without a body there is nothing to parse, whatever the content type
+        assert_eq!(send(HttpMethod::Get, "network", Some("text/plain"), "").0, 200);
+    }
+}
@@ -188,7 +188,7 @@ export async function activate(container, appManager, jwtManager) {
                 */
                 window.dispatchEvent(new PopStateEvent("popstate"));
             } else {
-                resultBox.innerHTML = `<div class="alert alert-danger">${json.message || "Connection failed"}</div>`;
+                resultBox.innerHTML = `<div class="alert alert-danger">${json.error?.message || json.message || "Connection failed"}</div>`;
             }
         } catch (err) {
             resultBox.innerHTML = `<div class="alert alert-danger">${err.message}</div>`;
@@ -1,16 +1,17 @@
 extern crate plugin_core;
 
 use plugin_core::{
-    ApiRequest, ApiResponse, HttpMethod, PluginContext, Resource,
+    ApiRequest, ApiResponse, HttpMethod, PluginContext, ws_url_from_context, Resource,
     declare_plugin,
     error_response,
     response_utils::{json_response, method_not_allowed_response},
-    resource_utils::static_resource,
+    resource_utils::{static_resource, parse_resource_path},
     cleanup_response,
 };
 use plugin_core::jwt_utils::validate_jwt_token;
 use std::sync::{Arc, Mutex};
 use libws::ws_client::WsClient;
+use plugin_core::ws_utils::PluginWsClient;
 use tokio::runtime::Runtime;
 use once_cell::sync::Lazy;
 use std::os::raw::c_char;
@@ -22,7 +23,7 @@ use serde::{Serialize, Deserialize};
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
 // Shared WebSocket client
-static mut PLUGIN_WS_CLIENT: Option<Arc<Mutex<WsClient>>> = None;
+static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
 
 // Define your data structure - using CamelCase for type name
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -42,8 +43,8 @@ fn on_load() {
     println!("[plugin_provisioning] >>> LOADED");
 }
 
-pub async fn create_ws_plugin_client() {
-    if let Ok(client) = WsClient::connect("plugin_provisioning", "ws://127.0.0.1:8081/ws").await {
+pub async fn create_ws_plugin_client(ws_url: &str) {
+    if let Ok(client) = WsClient::connect_as_service("plugin_provisioning", ws_url).await {
         let client = Arc::new(Mutex::new(client));
         /*
         if let Ok(mut ws_client) = client.lock() {
@@ -51,16 +52,17 @@ pub async fn create_ws_plugin_client() {
             println!("[plugin_provisioning] Subscribed to DeviceUpdated");
         }
         */
-        unsafe {
-            PLUGIN_WS_CLIENT = Some(client);
+        if PLUGIN_WS_CLIENT.set(client).is_err() {
+            eprintln!("[plugin_provisioning] WebSocket client already initialized");
         }
     }
 }
 
-extern "C" fn run(_ctx: *const PluginContext) {
+extern "C" fn run(ctx: *const PluginContext) {
     println!("[plugin_provisioning] - run");
+    let ws_url = ws_url_from_context(unsafe { ctx.as_ref() });
     RUNTIME.block_on(async {
-        create_ws_plugin_client().await;
+        create_ws_plugin_client(&ws_url).await;
     });
 }
 
@@ -80,7 +82,7 @@ extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
     slice.as_ptr()
 }
 
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -99,12 +101,11 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
             CStr::from_ptr(request.path).to_str().unwrap_or("<invalid>")
         };
 
-        // Extract ID from path if present (format: "device/ID")
-        let path_parts: Vec<&str> = path.split('/').collect();
-        let (resource_path, id_opt) = if path_parts.len() >= 2 {
-            (path_parts[0], Some(path_parts[1]))
-        } else {
-            (path, None)
+        // Extract ID from path if present (format: "device/ID"); deeper paths are not served
+        let (resource_path, id_opt) = match parse_resource_path(path).as_slice() {
+            [resource] => (*resource, None),
+            [resource, id] => (*resource, Some(*id)),
+            _ => return error_response(404, "Resource not found"),
         };
 
         match request.method {
@@ -144,13 +145,8 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                     // Insert into state
                     state.insert(data.id.clone(), data.clone());
                     
-                    // Get WebSocket client safely without creating a shared reference to static
-                    let client_clone = {
-                        // Get a raw pointer to the static
-                        let ws_client_ptr = ptr::addr_of!(PLUGIN_WS_CLIENT);
-                        // Dereference the raw pointer to get Option<Arc<Mutex<WsClient>>>
-                        (*ws_client_ptr).as_ref().map(Arc::clone)
-                    };
+                    // Get a handle to the WebSocket client, if run() connected one
+                    let client_clone = PLUGIN_WS_CLIENT.get();
                     
                     if let Some(client) = client_clone {
                         let timestamp = chrono::Utc::now().to_rfc3339();
@@ -159,7 +155,7 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                         // Use spawn_blocking to handle the non-Send MutexGuard
                         RUNTIME.spawn(async move {
                             let timestamp_clone = timestamp.clone();
-                            let payload = serde_json::to_string(&data_clone).unwrap();
+                            let payload = serde_json::to_value(&data_clone).unwrap();
                             
                             tokio::task::spawn_blocking(move || {
                                 if let Ok(mut ws_client) = client.lock() {
@@ -167,8 +163,9 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                                     let _ = rt.block_on(ws_client.publish(
                                         "plugin_provisioning", 
                                         "DeviceUpdated", 
-                                        &payload,
-                                        &timestamp_clone
+                                        payload,
+                                        &timestamp_clone,
+                                        false
                                     ));
                                 }
                             }).await.ok();
@@ -199,13 +196,8 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                         if state.contains_key(id) {
                             state.insert(id.to_string(), data.clone());
                             
-                            // Get WebSocket client safely without creating a shared reference to static
-                            let client_clone = {
-                                // Get a raw pointer to the static
-                                let ws_client_ptr = ptr::addr_of!(PLUGIN_WS_CLIENT);
-                                // Dereference the raw pointer to get Option<Arc<Mutex<WsClient>>>
-                                (*ws_client_ptr).as_ref().map(Arc::clone)
-                            };
+                            // Get a handle to the WebSocket client, if run() connected one
+                            let client_clone = PLUGIN_WS_CLIENT.get();
                             
                             if let Some(client) = client_clone {
                                 let timestamp = chrono::Utc::now().to_rfc3339();
@@ -213,7 +205,7 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                                 
                                 RUNTIME.spawn(async move {
                                     let timestamp_clone = timestamp.clone();
-                                    let payload = serde_json::to_string(&data_clone).unwrap();
+                                    let payload = serde_json::to_value(&data_clone).unwrap();
                                     
                                     tokio::task::spawn_blocking(move || {
                                         if let Ok(mut ws_client) = client.lock() {
@@ -221,8 +213,9 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                                             let _ = rt.block_on(ws_client.publish(
                                                 "plugin_provisioning", 
                                                 "DeviceUpdated", 
-                                                &payload,
-                                                &timestamp_clone
+                                                payload,
+                                                &timestamp_clone,
+                                                false
                                             ));
                                         }
                                     }).await.ok();
@@ -249,21 +242,15 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                     let id_string = id.to_string(); // Clone ID for use in async block
                     
                     if state.remove(id).is_some() {
-                        // Get WebSocket client safely without creating a shared reference to static
-                        let client_clone = {
-                            // Get a raw pointer to the static
-                            let ws_client_ptr = ptr::addr_of!(PLUGIN_WS_CLIENT);
-                            // Dereference the raw pointer to get Option<Arc<Mutex<WsClient>>>
-                            (*ws_client_ptr).as_ref().map(Arc::clone)
-                        };
+                        // Get a handle to the WebSocket client, if run() connected one
+                        let client_clone = PLUGIN_WS_CLIENT.get();
                         
                         if let Some(client) = client_clone {
                             let timestamp = chrono::Utc::now().to_rfc3339();
                             
                             RUNTIME.spawn(async move {
                                 let timestamp_clone = timestamp.clone();
-                                let event_data = serde_json::json!({ "id": id_string, "deleted": true });
-                                let payload = serde_json::to_string(&event_data).unwrap();
+                                let payload = serde_json::json!({ "id": id_string, "deleted": true });
                                 
                                 tokio::task::spawn_blocking(move || {
                                     if let Ok(mut ws_client) = client.lock() {
@@ -271,8 +258,9 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                                         let _ = rt.block_on(ws_client.publish(
                                             "plugin_provisioning", 
                                             "DeviceUpdated", 
-                                            &payload,
-                                            &timestamp_clone
+                                            payload,
+                                            &timestamp_clone,
+                                            false
                                         ));
                                     }
                                 }).await.ok();
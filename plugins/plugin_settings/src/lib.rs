@@ -1,17 +1,21 @@
 use plugin_core::{
-    ApiRequest, ApiResponse, HttpMethod, PluginContext, Resource,
+    ApiRequest, ApiResponse, HttpMethod, PluginContext, ws_url_from_context, Resource,
     declare_plugin,
     error_response,
     response_utils::{json_response, method_not_allowed_response},
     resource_utils::static_resource,
     cleanup_response,
+    log_info, log_warn, log_error,
 };
 use plugin_core::jwt_utils::validate_jwt_token;
 
 use std::sync::{Arc, Mutex};
 use libws::ws_client::WsClient;
+use plugin_core::ws_utils::PluginWsClient;
 use tokio::runtime::Runtime;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::os::raw::c_char;
 use std::ffi::CString;
 use std::ffi::CStr;
@@ -23,11 +27,23 @@ use serde::Deserialize;
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
 // Shared WebSocket client
-static mut PLUGIN_WS_CLIENT: Option<Arc<Mutex<WsClient>>> = None;
+static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
+
+// Config key overriding where the settings are stored, e.g. `settings_path=/data/settings.json`
+const SETTINGS_PATH_CONFIG_KEY: &str = "settings_path";
+
+// File name used next to the executable when no path is configured
+const DEFAULT_SETTINGS_FILE: &str = "device_settings.json";
+
+// File the settings are persisted to, resolved once in `run`
+static SETTINGS_PATH: OnceCell<PathBuf> = OnceCell::new();
 
 // DeviceSettings is the top-level struct holding all plugin settings.
 // It contains three sections: general, echo, and automation.
+// Every settings struct denies unknown fields, so a misspelled key in a POST, PUT
+// or PATCH is rejected instead of being silently dropped.
 #[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
 struct DeviceSettings {
     general: GeneralSettings,
     echo: EchoSettings,
@@ -36,6 +52,7 @@ struct DeviceSettings {
 
 // GeneralSettings holds general device configuration such as name, language, region, etc.
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 struct GeneralSettings {
     #[serde(rename = "deviceName")]
     device_name: String,      // Device name as shown to the user
@@ -68,6 +85,7 @@ impl Default for GeneralSettings {
 
 // EchoSettings holds configuration specific to Echo device features.
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 struct EchoSettings {
     #[serde(rename = "wakeWord")]
     wake_word: String,            // Wake word for the device (e.g., "Alexa")
@@ -93,6 +111,7 @@ impl Default for EchoSettings {
 
 // AutomationSettings holds automation-related configuration.
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 struct AutomationSettings {
     #[serde(rename = "frustrationFreeAutomation")]
     frustration_free_automation: bool, // Whether Frustration Free Automation is enabled
@@ -116,14 +135,73 @@ static STATE: Lazy<Mutex<DeviceSettings>> = Lazy::new(|| {
     })
 });
 
+// Returns the default settings file in the exe directory, or the working directory
+// if the exe path can't be resolved.
+fn default_settings_path() -> PathBuf {
+    match std::env::current_exe() {
+        Ok(mut exe_path) => {
+            exe_path.pop();
+            exe_path.join(DEFAULT_SETTINGS_FILE)
+        }
+        Err(_) => PathBuf::from(DEFAULT_SETTINGS_FILE),
+    }
+}
+
+// Loads the settings stored at `path`. A missing or unreadable file falls back to
+// the defaults so the plugin always starts.
+fn load_settings(path: &Path) -> DeviceSettings {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            log_info!(format!("No settings file at {}, using defaults", path.display()).as_str());
+            return DeviceSettings::default();
+        }
+        Err(_e) => {
+            log_warn!(format!("Failed to read settings file {}: {}, using defaults", path.display(), _e).as_str());
+            return DeviceSettings::default();
+        }
+    };
+
+    serde_json::from_str(&content).unwrap_or_else(|_e| {
+        log_warn!(format!("Corrupt settings file {}: {}, using defaults", path.display(), _e).as_str());
+        DeviceSettings::default()
+    })
+}
+
+// Writes the settings to the configured file. The JSON goes to a temporary file
+// first and is renamed over the old one, so a crash never leaves a half-written file.
+fn save_settings(settings: &DeviceSettings) -> Result<(), String> {
+    let path = SETTINGS_PATH.get_or_init(default_settings_path);
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, json)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+// Persists `settings` and, only if that succeeded, makes them the current state.
+fn store_settings(state: &mut DeviceSettings, settings: DeviceSettings) -> Result<(), String> {
+    save_settings(&settings)?;
+    *state = settings;
+    Ok(())
+}
+
 #[ctor::ctor]
 fn on_load() {
     println!("[plugin_settings] >>> LOADED");
 }
 
 // Create WebSocket client
-pub async fn create_ws_plugin_client() {
-    if let Ok(client) = WsClient::connect("plugin_settings", "ws://127.0.0.1:8081/ws").await {
+pub async fn create_ws_plugin_client(ws_url: &str) {
+    if let Ok(client) = WsClient::connect_as_service("plugin_settings", ws_url).await {
         let client = Arc::new(Mutex::new(client));
         
         if let Ok(mut ws_client) = client.lock() {
@@ -131,16 +209,27 @@ pub async fn create_ws_plugin_client() {
             println!("[plugin_settings] Subscribed to SettingUpdateCompleted");
         }
         
-        unsafe {
-            PLUGIN_WS_CLIENT = Some(client);
+        if PLUGIN_WS_CLIENT.set(client).is_err() {
+            eprintln!("[plugin_settings] WebSocket client already initialized");
         }
     }
 }
 
-extern "C" fn run(_ctx: *const PluginContext) {
+extern "C" fn run(ctx: *const PluginContext) {
     println!("[plugin_settings] - run");
+    let ctx = unsafe { ctx.as_ref() };
+
+    // Restore the settings saved by a previous run
+    let path = ctx
+        .and_then(|c| c.config_value(SETTINGS_PATH_CONFIG_KEY))
+        .map(PathBuf::from)
+        .unwrap_or_else(default_settings_path);
+    *STATE.lock().unwrap() = load_settings(&path);
+    let _ = SETTINGS_PATH.set(path);
+
+    let ws_url = ws_url_from_context(ctx);
     RUNTIME.block_on(async {
-        create_ws_plugin_client().await;
+        create_ws_plugin_client(&ws_url).await;
     });
 }
 
@@ -149,18 +238,19 @@ extern "C" fn get_static_content_path() -> *const c_char {
 }
 
 extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
-    static METHODS: [HttpMethod; 4] = [
+    static METHODS: [HttpMethod; 5] = [
         HttpMethod::Get, 
         HttpMethod::Post,
         HttpMethod::Put,
         HttpMethod::Delete,
+        HttpMethod::Patch,
     ];
     let slice = static_resource("devicesettings", &METHODS);
     unsafe { *out_len = slice.len(); }
     slice.as_ptr()
 }
 
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -188,30 +278,57 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
 
             HttpMethod::Post if path == "devicesettings" => {
                 let body = std::slice::from_raw_parts(request.body_ptr, request.body_len);
-                if let Ok(settings) = serde_json::from_slice::<DeviceSettings>(body) {
-                    let mut state = STATE.lock().unwrap();
-                    *state = settings;
-                    json_response(201, r#"{"message": "Settings created"}"#)
-                } else {
-                    error_response(400, "Invalid settings data")
+                match serde_json::from_slice::<DeviceSettings>(body) {
+                    Ok(settings) => {
+                        let mut state = STATE.lock().unwrap();
+                        match store_settings(&mut state, settings) {
+                            Ok(()) => json_response(201, r#"{"message": "Settings created"}"#),
+                            Err(e) => save_failed_response(&e),
+                        }
+                    }
+                    Err(e) => invalid_settings_response(&e),
                 }
             }
 
             HttpMethod::Put if path == "devicesettings" => {
                 let body = std::slice::from_raw_parts(request.body_ptr, request.body_len);
-                if let Ok(settings) = serde_json::from_slice::<DeviceSettings>(body) {
-                    let mut state = STATE.lock().unwrap();
-                    *state = settings;
-                    json_response(200, r#"{"message": "Settings updated"}"#)
-                } else {
-                    error_response(400, "Invalid settings data")
+                match serde_json::from_slice::<DeviceSettings>(body) {
+                    Ok(settings) => {
+                        let mut state = STATE.lock().unwrap();
+                        match store_settings(&mut state, settings) {
+                            Ok(()) => json_response(200, r#"{"message": "Settings updated"}"#),
+                            Err(e) => save_failed_response(&e),
+                        }
+                    }
+                    Err(e) => invalid_settings_response(&e),
+                }
+            }
+
+            HttpMethod::Patch if path == "devicesettings" => {
+                let body = std::slice::from_raw_parts(request.body_ptr, request.body_len);
+                let Ok(patch) = serde_json::from_slice::<serde_json::Value>(body) else {
+                    return error_response(400, "Invalid settings data");
+                };
+
+                let mut state = STATE.lock().unwrap();
+                let mut merged = serde_json::to_value(&*state).unwrap();
+                merge_json(&mut merged, &patch);
+
+                match serde_json::from_value::<DeviceSettings>(merged) {
+                    Ok(settings) => match store_settings(&mut state, settings) {
+                        Ok(()) => json_response(200, r#"{"message": "Settings patched"}"#),
+                        Err(e) => save_failed_response(&e),
+                    },
+                    Err(e) => invalid_settings_response(&e),
                 }
             }
 
             HttpMethod::Delete if path == "devicesettings" => {
                 let mut state = STATE.lock().unwrap();
-                *state = DeviceSettings::default();
-                json_response(200, r#"{"message": "Settings reset to defaults"}"#)
+                match store_settings(&mut state, DeviceSettings::default()) {
+                    Ok(()) => json_response(200, r#"{"message": "Settings reset to defaults"}"#),
+                    Err(e) => save_failed_response(&e),
+                }
             }
 
             _ => method_not_allowed_response(request.method, request.path),
@@ -219,6 +336,40 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     }
 }
 
+// Rejects a body that doesn't describe valid settings, naming the offending field.
+fn invalid_settings_response(error: &serde_json::Error) -> *mut ApiResponse {
+    let body = serde_json::json!({ "message": format!("Invalid settings data: {}", error) });
+    json_response(400, &body.to_string())
+}
+
+// Logs a failed write and reports it; the in-memory settings are left unchanged.
+fn save_failed_response(_error: &str) -> *mut ApiResponse {
+    log_error!(format!("Failed to save settings: {}", _error).as_str());
+    error_response(500, "Failed to save settings")
+}
+
+// Recursively merges `patch` into `target`: objects are merged key by key, any
+// other value replaces the existing one. Null values are ignored since every
+// settings field is required.
+fn merge_json(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    match (target, patch) {
+        (serde_json::Value::Object(target_map), serde_json::Value::Object(patch_map)) => {
+            for (key, value) in patch_map {
+                if value.is_null() {
+                    continue;
+                }
+                match target_map.get_mut(key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target_map.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
 extern "C" fn cleanup(resp: *mut ApiResponse) {
     cleanup_response(resp);
 }
@@ -231,4 +382,126 @@ declare_plugin!(
     get_api_resources,
     handle_request,
     cleanup
-);
\ No newline at end of file
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // STATE and the settings file are shared, so tests that touch them take turns
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn take_turn() -> std::sync::MutexGuard<'static, ()> {
+        SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Points the plugin's settings file at a temp file and returns its path
+    fn settings_path() -> &'static Path {
+        SETTINGS_PATH.get_or_init(|| {
+            std::env::temp_dir().join(format!("plugin_settings_test-{}.json", std::process::id()))
+        })
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("plugin_settings_{}-{}.json", name, std::process::id()))
+    }
+
+    fn as_json(settings: &DeviceSettings) -> serde_json::Value {
+        serde_json::to_value(settings).unwrap()
+    }
+
+    // Sends an authenticated request for `devicesettings` and returns the response status
+    fn send(method: HttpMethod, body: &str) -> u16 {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let token = libjwt::generate_jwt("test-key", "session", now, now + 3600).unwrap();
+        let key = CString::new("Authorization").unwrap();
+        let value = CString::new(format!("Bearer {}", token)).unwrap();
+        let headers = [plugin_core::ApiHeader { key: key.as_ptr(), value: value.as_ptr() }];
+        let path = CString::new("devicesettings").unwrap();
+        let request = ApiRequest {
+            path: path.as_ptr(),
+            method,
+            headers: headers.as_ptr(),
+            header_count: 1,
+            content_type: ptr::null(),
+            query: ptr::null(),
+            body_ptr: body.as_ptr(),
+            body_len: body.len(),
+        };
+
+        let response = handle_request(&request);
+        let status = unsafe { (*response).status };
+        cleanup(response);
+        status
+    }
+
+    #[test]
+    fn saved_settings_load_back_unchanged() {
+        let _turn = take_turn();
+        let mut settings = DeviceSettings::default();
+        settings.general.device_name = "Kitchen Echo".to_string();
+        settings.general.time_zone = "GMT+1".to_string();
+        settings.echo.wake_word = "Computer".to_string();
+        settings.echo.mic_enabled = false;
+        settings.automation.frustration_free_automation = false;
+
+        save_settings(&settings).unwrap();
+        let loaded = load_settings(settings_path());
+        let _ = fs::remove_file(settings_path());
+
+        assert_eq!(as_json(&loaded), as_json(&settings));
+        assert!(!settings_path().with_extension("json.tmp").exists(), "temporary file left behind");
+    }
+
+    #[test]
+    fn missing_or_corrupt_file_loads_the_defaults() {
+        let defaults = as_json(&DeviceSettings::default());
+        assert_eq!(as_json(&load_settings(&temp_file("missing"))), defaults);
+
+        let corrupt = temp_file("corrupt");
+        fs::write(&corrupt, r#"{"general": {"deviceName": "#).unwrap();
+        let loaded = load_settings(&corrupt);
+        let _ = fs::remove_file(&corrupt);
+        assert_eq!(as_json(&loaded), defaults);
+    }
+
+    #[test]
+    fn patch_changes_one_nested_field_and_keeps_its_siblings() {
+        let _turn = take_turn();
+        settings_path();
+        *STATE.lock().unwrap() = DeviceSettings::default();
+        let before = as_json(&STATE.lock().unwrap());
+
+        assert_eq!(send(HttpMethod::Patch, r#"{"echo": {"wakeWord": "Computer"}}"#), 200);
+
+        let after = as_json(&STATE.lock().unwrap());
+        assert_eq!(after["echo"]["wakeWord"], "Computer");
+        for sibling in ["micEnabled", "dropInCalling", "displaySettings"] {
+            assert_eq!(after["echo"][sibling], before["echo"][sibling], "echo.{} changed", sibling);
+        }
+        assert_eq!(after["general"], before["general"]);
+        assert_eq!(after["automation"], before["automation"]);
+
+        // The patched settings were persisted as well
+        let saved = as_json(&load_settings(settings_path()));
+        let _ = fs::remove_file(settings_path());
+        assert_eq!(saved, after);
+    }
+
+    #[test]
+    fn patch_with_unknown_fields_is_rejected() {
+        let _turn = take_turn();
+        settings_path();
+        *STATE.lock().unwrap() = DeviceSettings::default();
+        let before = as_json(&STATE.lock().unwrap());
+
+        for patch in [
+            r#"{"echo": {"wakWord": "Computer"}}"#,
+            r#"{"colour": "blue"}"#,
+            r#"{"general": {"deviceName": "Den", "nickname": "Dave"}}"#,
+        ] {
+            assert_eq!(send(HttpMethod::Patch, patch), 400, "{}", patch);
+        }
+        assert_eq!(as_json(&STATE.lock().unwrap()), before, "a rejected patch was partly applied");
+    }
+}
@@ -9,26 +9,89 @@ use std::ffi::{ CString, CStr };
 use std::os::raw::c_char;
 use std::ptr;
 use std::sync::{ Mutex, Arc };
+use std::sync::atomic::{ AtomicBool, Ordering };
 use std::thread;
-use std::time::Duration;
+use std::time::{ Duration, Instant };
 
 use once_cell::sync::{ Lazy, OnceCell };
-use libws::ws_client::WsClient;
+use libws::ws_client::{ ReconnectPolicy, WsClient, service_token_provider };
 
 #[ctor::ctor]
 fn on_load() {
     println!("[plugin_task_agent_headless] >>> LOADED");
 }
 
-static PROGRESS_STATE: once_cell::sync::Lazy<Arc<Mutex<String>>> = once_cell::sync::Lazy::new(||
-    Arc::new(Mutex::new("Waiting for job...".to_string()))
+/// Workflow steps, published as "Step <n>: <label>".
+const WORKFLOW_STEPS: [&str; 4] = ["Initializing..", "Processing..", "Finalizing..", "Completed"];
+
+/// Pause before the first step.
+#[cfg(not(test))]
+const START_DELAY: Duration = Duration::from_secs(3);
+/// Pause after every step.
+#[cfg(not(test))]
+const STEP_DELAY: Duration = Duration::from_secs(2);
+
+// Tests run the same workflow without the long waits
+#[cfg(test)]
+const START_DELAY: Duration = Duration::from_millis(150);
+#[cfg(test)]
+const STEP_DELAY: Duration = Duration::from_millis(50);
+
+/// Progress of the current job, as returned by `on_progress`.
+#[derive(Debug, Clone, serde::Serialize)]
+struct JobProgress {
+    /// Step being worked on, starting at 1; 0 before a job starts
+    step: usize,
+    total: usize,
+    label: String,
+    /// Set once every step has run
+    done: bool,
+    /// Set when the job was stopped through `DELETE jobs`
+    cancelled: bool,
+}
+
+impl JobProgress {
+    fn waiting() -> Self {
+        Self {
+            step: 0,
+            total: WORKFLOW_STEPS.len(),
+            label: "Waiting for job...".to_string(),
+            done: false,
+            cancelled: false,
+        }
+    }
+}
+
+static PROGRESS_STATE: once_cell::sync::Lazy<Arc<Mutex<JobProgress>>> = once_cell::sync::Lazy::new(||
+    Arc::new(Mutex::new(JobProgress::waiting()))
 );
 
+/// Asks the running job to stop; checked between steps and while the worker waits.
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Set while a worker thread exists, so a second `POST jobs` can't start an overlapping one.
+static JOB_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Clears `JOB_RUNNING` when the worker ends, however it ends.
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        JOB_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Status published when a job is cancelled.
+const CANCELLED_STATUS: &str = "cancelled";
+
 /// Topic for receiving status change messages.
-pub static STATUS_CHANGED: &str = "StatusMessageChanged";
+pub static STATUS_CHANGED: &str = EngineEvent::StatusMessageChanged.to_topic();
 
 /// Topic for route switching
-pub static SWITCH_ROUTE: &str = "SwitchRoute";
+pub static SWITCH_ROUTE: &str = EngineEvent::SwitchRoute.to_topic();
+
+// Request topic answered with the job's progress, an alternative to polling `on_complete`
+pub static STATUS_REQUEST: &str = "TaskAgentStatusRequest";
 
 /// WebSocket client for the plugin.
 pub static PLUGIN_WS_CLIENT: OnceCell<Arc<Mutex<WsClient>>> = OnceCell::new();
@@ -40,12 +103,14 @@ pub static RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
 
 // WebSocket Client Initialization
 /// Creates and initializes the WebSocket client for the plugin.
-pub async fn create_ws_plugin_client() {
+pub async fn create_ws_plugin_client(url: &str) {
     println!("Creating ws client for the plugin");
-    let url = "ws://127.0.0.1:8081/ws";
 
-    // Connect to the WebSocket server.
-    let client = WsClient::connect("plugin_task_agent", url)
+    // Connect to the WebSocket server; dropped connections are re-established
+    // and the subscriptions below replayed by the client itself.
+    let policy = ReconnectPolicy { max_attempts: Some(5), ..ReconnectPolicy::default() };
+    let token_provider = service_token_provider("plugin_task_agent");
+    let client = WsClient::connect_authenticated_with_policy("plugin_task_agent", url, token_provider, policy)
         .await
         .expect("Failed to connect WsClient");
 
@@ -68,6 +133,29 @@ pub async fn create_ws_plugin_client() {
         client.on_message(STATUS_CHANGED, |msg| {
             println!("[plugin_task_agent_headless] => STATUS_CHANGED: {}", msg);
         });
+
+        // Answer status requests from the engine or other plugins
+        client.subscribe("plugin_task_agent", STATUS_REQUEST, "").await;
+        client.on_request(STATUS_REQUEST, |correlation_id, _payload| {
+            let current = PROGRESS_STATE.lock().unwrap().clone();
+            let reply = serde_json::json!({
+                "status": current.label,
+                "completed": current.done,
+                "progress": current
+            });
+
+            // The handler runs on the client's receive task, so reply from elsewhere
+            RUNTIME.spawn_blocking(move || {
+                if let Some(client_arc) = PLUGIN_WS_CLIENT.get() {
+                    if let Ok(mut client) = client_arc.lock() {
+                        let rt = tokio::runtime::Handle::current();
+                        if let Err(e) = rt.block_on(client.reply(&correlation_id, reply)) {
+                            eprintln!("[plugin_task_agent_headless] Failed to answer status request: {}", e);
+                        }
+                    }
+                }
+            });
+        });
     }
 }
 
@@ -79,9 +167,11 @@ extern "C" fn run(ctx: *const PluginContext) {
         return;
     }
 
+    let ws_url = ws_url_from_context(unsafe { ctx.as_ref() });
+
     // Use shared runtime instead of creating new one
     RUNTIME.block_on(async {
-        create_ws_plugin_client().await;
+        create_ws_plugin_client(&ws_url).await;
     });
 }
 
@@ -90,7 +180,7 @@ extern "C" fn get_static_content_path() -> *const c_char {
 }
 
 extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
-    static METHODS: [HttpMethod; 1] = [HttpMethod::Post];
+    static METHODS: [HttpMethod; 2] = [HttpMethod::Post, HttpMethod::Delete];
     let slice = static_resource("jobs", &METHODS);
     unsafe {
         *out_len = slice.len();
@@ -98,7 +188,7 @@ extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
     slice.as_ptr()
 }
 
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -129,78 +219,128 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
                 run_workflow(req)
             }
 
+            HttpMethod::Delete if path == "jobs" => cancel_workflow(),
+
             _ => method_not_allowed_response(request.method, request.path),
         }
     }
 }
 
+/// Publishes a status message as retained, giving the client time to reconnect between attempts.
+fn publish_status(status: &str) {
+    let Some(client_arc) = PLUGIN_WS_CLIENT.get() else {
+        return;
+    };
+    let client_arc = client_arc.clone();
+    let timestamp = chrono::Utc::now().to_rfc3339();
+
+    // Use shared runtime
+    RUNTIME.block_on(async {
+        let mut retries = 3;
+        while retries > 0 {
+            let result = match client_arc.lock() {
+                Ok(mut client) => client.publish("plugin_task_agent", STATUS_CHANGED, status, &timestamp, true).await,
+                Err(_) => break,
+            };
+            match result {
+                Ok(_) => {
+                    println!("[plugin_task_agent_headless] Successfully published status update");
+                    break;
+                }
+                Err(e) => {
+                    eprintln!("[plugin_task_agent_headless] Failed to publish status: {}", e);
+                    retries -= 1;
+                    if retries > 0 {
+                        tokio::time::sleep(Duration::from_millis(500)).await;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Sleeps for `duration`, waking early if the job is cancelled. Returns true if it was.
+fn sleep_unless_cancelled(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while !CANCEL_REQUESTED.load(Ordering::SeqCst) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return false;
+        }
+        thread::sleep(remaining.min(Duration::from_millis(100)));
+    }
+    true
+}
+
+/// Records and announces that the job stopped early.
+fn finish_cancelled() {
+    println!("[plugin_task_agent_headless] Job cancelled");
+    {
+        let mut lock = PROGRESS_STATE.lock().unwrap();
+        lock.label = "Job cancelled".to_string();
+        lock.cancelled = true;
+    }
+    publish_status(CANCELLED_STATUS);
+}
+
 extern "C" fn run_workflow(_req: *const ApiRequest) -> *mut ApiResponse {
     println!("[plugin_task_agent_headless] - run_workflow");
 
+    if JOB_RUNNING.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        println!("[plugin_task_agent_headless] Job already running; not starting another");
+        return error_response(409, "Job already running");
+    }
+
+    CANCEL_REQUESTED.store(false, Ordering::SeqCst);
+    *PROGRESS_STATE.lock().unwrap() = JobProgress::waiting();
+
     thread::spawn(|| {
-        // Wait for 3 seconds before starting the workflow
-        thread::sleep(Duration::from_secs(3));
+        let _running = RunningGuard;
 
-        let steps = vec![
-            "Step 1: Initializing..",
-            "Step 2: Processing..",
-            "Step 3: Finalizing..",
-            "Step 4: Completed",
-        ];
+        // Wait a moment before starting the workflow
+        if sleep_unless_cancelled(START_DELAY) {
+            finish_cancelled();
+            return;
+        }
+
+        for (index, label) in WORKFLOW_STEPS.iter().enumerate() {
+            if CANCEL_REQUESTED.load(Ordering::SeqCst) {
+                finish_cancelled();
+                return;
+            }
 
-        for step in steps {
+            let step = format!("Step {}: {}", index + 1, label);
             {
                 let mut lock = PROGRESS_STATE.lock().unwrap();
-                *lock = step.to_string();
+                *lock = JobProgress {
+                    step: index + 1,
+                    label: step.clone(),
+                    ..JobProgress::waiting()
+                };
             }
 
-            // Try to publish with reconnection attempts
-            if let Some(client_arc) = PLUGIN_WS_CLIENT.get() {
-                let client_arc = client_arc.clone();
-                let step = step.to_string();
-                let timestamp = chrono::Utc::now().to_rfc3339();
-
-                // Use shared runtime
-                RUNTIME.block_on(async {
-                    let mut retries = 3;
-                    while retries > 0 {
-                        if let Ok(mut client) = client_arc.lock() {
-                            match client.publish("plugin_task_agent", STATUS_CHANGED, &step, &timestamp).await {
-                                Ok(_) => {
-                                    println!("[plugin_task_agent_headless] Successfully published status update");
-                                    break;
-                                }
-                                Err(e) => {
-                                    eprintln!("[plugin_task_agent_headless] Failed to publish status: {}", e);
-                                    retries -= 1;
-                                    if retries > 0 {
-                                        tokio::time::sleep(Duration::from_millis(500)).await;
-                                        // Try to reconnect
-                                        if let Ok(new_client) = WsClient::connect("plugin_task_agent", "ws://127.0.0.1:8081/ws").await {
-                                            *client = new_client;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                });
-            }
+            publish_status(&step);
 
-            thread::sleep(Duration::from_secs(2));
+            if sleep_unless_cancelled(STEP_DELAY) {
+                finish_cancelled();
+                return;
+            }
         }
 
-        // After completing all steps, publish route switch
+        PROGRESS_STATE.lock().unwrap().done = true;
+
+        // After completing all steps, publish route switch and wait for the engine to confirm it
         if let Some(client_arc) = PLUGIN_WS_CLIENT.get() {
             let client_arc = client_arc.clone();
             let timestamp = chrono::Utc::now().to_rfc3339();
             
             RUNTIME.block_on(async {
                 if let Ok(mut client) = client_arc.lock() {
-                    if let Err(e) = client.publish("plugin_task_agent", SWITCH_ROUTE, "/settings/web", &timestamp).await {
-                        eprintln!("[plugin_task_agent_headless] Failed to publish route switch: {}", e);
+                    let ack_timeout = Duration::from_secs(5);
+                    if let Err(e) = client.publish_with_ack("plugin_task_agent", SWITCH_ROUTE, "/settings/web", &timestamp, ack_timeout).await {
+                        eprintln!("[plugin_task_agent_headless] Route switch not confirmed: {}", e);
                     } else {
-                        println!("[plugin_task_agent_headless] Successfully published route switch to /settings/web");
+                        println!("[plugin_task_agent_headless] Route switch to /settings/web acknowledged");
                     }
                 }
             });
@@ -210,16 +350,28 @@ extern "C" fn run_workflow(_req: *const ApiRequest) -> *mut ApiResponse {
     json_response(202, r#"{ "message": "Job started" }"#)
 }
 
+/// Stops the running job before its next step; the worker then publishes `cancelled`.
+/// The flag is cleared when the next job starts.
+fn cancel_workflow() -> *mut ApiResponse {
+    if !JOB_RUNNING.load(Ordering::SeqCst) {
+        return error_response(404, "No job running");
+    }
+
+    CANCEL_REQUESTED.store(true, Ordering::SeqCst);
+    json_response(202, r#"{ "message": "Cancelling job" }"#)
+}
+
+/// Returns `{ "step": 2, "total": 4, "label": "Step 2: Processing..", "done": false, "cancelled": false }`.
 extern "C" fn on_progress() -> *mut ApiResponse {
     let current = PROGRESS_STATE.lock().unwrap().clone();
-    println!("[plugin_task_agent_headless] on_progress = {}", current);
-    let msg = format!(r#"{{ "status": "{}" }}"#, current);
+    println!("[plugin_task_agent_headless] on_progress = {:?}", current);
+    let msg = serde_json::to_string(&current).unwrap_or_else(|_| "{}".to_string());
     json_response(200, &msg)
 }
 
 extern "C" fn on_complete() -> *mut ApiResponse {
-    let current = PROGRESS_STATE.lock().unwrap().clone();
-    if current == "Job completed" {
+    let done = PROGRESS_STATE.lock().unwrap().done;
+    if done {
         json_response(200, r#"{ "message": "Job finished" }"#)
     } else {
         json_response(204, r#"{ "message": "Still running" }"#)
@@ -242,3 +394,113 @@ declare_plugin!(
     on_progress,
     on_complete
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The job state is global, so tests take turns.
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// Waits for the previous test's job to end and returns the turn.
+    fn take_turn() -> std::sync::MutexGuard<'static, ()> {
+        let turn = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        wait_until("the previous job ends", || !JOB_RUNNING.load(Ordering::SeqCst));
+        turn
+    }
+
+    fn wait_until(what: &str, condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out waiting until {}", what);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn bearer() -> CString {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let token = libjwt::generate_jwt("test-key", "session", now, now + 3600).unwrap();
+        CString::new(format!("Bearer {}", token)).unwrap()
+    }
+
+    /// Sends an authenticated request for `jobs` and returns the response status.
+    fn send(method: HttpMethod) -> u16 {
+        let key = CString::new("Authorization").unwrap();
+        let value = bearer();
+        let headers = [ApiHeader { key: key.as_ptr(), value: value.as_ptr() }];
+        let path = CString::new("jobs").unwrap();
+        let request = ApiRequest {
+            path: path.as_ptr(),
+            method,
+            headers: headers.as_ptr(),
+            header_count: 1,
+            content_type: ptr::null(),
+            query: ptr::null(),
+            body_ptr: ptr::null(),
+            body_len: 0,
+        };
+        status_of(handle_request(&request))
+    }
+
+    fn status_of(response: *mut ApiResponse) -> u16 {
+        let status = unsafe { (*response).status };
+        cleanup(response);
+        status
+    }
+
+    fn progress() -> serde_json::Value {
+        let response = on_progress();
+        let body = unsafe { std::slice::from_raw_parts((*response).body_ptr, (*response).body_len).to_vec() };
+        cleanup(response);
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn workflow_runs_to_completion() {
+        let _turn = take_turn();
+        assert_eq!(send(HttpMethod::Post), 202);
+        assert_eq!(status_of(on_complete()), 204, "a fresh job can't be finished yet");
+
+        wait_until("on_complete answers 200", || status_of(on_complete()) == 200);
+
+        let progress = progress();
+        assert_eq!(progress["step"], WORKFLOW_STEPS.len());
+        assert_eq!(progress["total"], WORKFLOW_STEPS.len());
+        assert_eq!(progress["label"], "Step 4: Completed");
+        assert_eq!(progress["done"], true);
+        assert_eq!(progress["cancelled"], false);
+    }
+
+    #[test]
+    fn started_job_can_be_cancelled() {
+        let _turn = take_turn();
+        assert_eq!(send(HttpMethod::Delete), 404, "nothing to cancel yet");
+        assert_eq!(send(HttpMethod::Post), 202);
+
+        let started = Instant::now();
+        assert_eq!(send(HttpMethod::Delete), 202);
+        wait_until("the cancelled worker exits", || !JOB_RUNNING.load(Ordering::SeqCst));
+        let full_run = START_DELAY + STEP_DELAY * WORKFLOW_STEPS.len() as u32;
+        assert!(started.elapsed() < full_run, "worker kept sleeping for {:?}", started.elapsed());
+
+        let progress = progress();
+        assert_eq!(progress["cancelled"], true);
+        assert_eq!(progress["done"], false);
+        assert_eq!(progress["label"], "Job cancelled");
+        assert_eq!(status_of(on_complete()), 204);
+    }
+
+    #[test]
+    fn second_start_while_running_is_a_conflict() {
+        let _turn = take_turn();
+        assert_eq!(send(HttpMethod::Post), 202);
+        assert_eq!(send(HttpMethod::Post), 409);
+        assert_eq!(status_of(run_workflow(ptr::null())), 409, "the engine's entry point is guarded too");
+
+        // Once the job is over a new one may start
+        assert_eq!(send(HttpMethod::Delete), 202);
+        wait_until("the cancelled worker exits", || !JOB_RUNNING.load(Ordering::SeqCst));
+        assert_eq!(send(HttpMethod::Post), 202);
+        assert_eq!(send(HttpMethod::Delete), 202);
+    }
+}
@@ -9,6 +9,9 @@ use plugin_core::resource_utils::static_resource;
 use plugin_core::response_utils::*;
 use plugin_core::jwt_utils::validate_jwt_token;
 
+// Terms text never changes at runtime, so clients can revalidate it with ETags
+const TERMS_BODY: &str = r#"{ "terms": "Lorem empsum yada yada" }"#;
+
 #[ctor::ctor]
 fn on_load() {
     println!("[plugin_terms] >>> LOADED");
@@ -40,7 +43,7 @@ extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
     slice.as_ptr()
 }
 
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -63,7 +66,12 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
 
         match request.method {
             HttpMethod::Get if path == "userterms" => {
-                return json_response(200, r#"{ "terms": "Lorem empsum yada yada" }"#);
+                let body = TERMS_BODY;
+                let etag = compute_etag(body.as_bytes());
+                if etag_matches(request, &etag) {
+                    return not_modified_response();
+                }
+                return etag_response(200, body, &etag);
             }
 
             HttpMethod::Post if path == "userterms" => {
@@ -1,7 +1,7 @@
 extern crate plugin_core;
 
 use plugin_core::{
-    ApiRequest, ApiResponse, HttpMethod, PluginContext, Resource,
+    ApiRequest, ApiResponse, HttpMethod, PluginContext, ws_url_from_context, Resource,
     declare_plugin,
     response_utils::{json_response, method_not_allowed_response},
     resource_utils::static_resource,
@@ -10,6 +10,7 @@ use plugin_core::{
 use plugin_core::jwt_utils::validate_jwt_token;
 use std::sync::{Arc, Mutex};
 use libws::ws_client::WsClient;
+use plugin_core::ws_utils::PluginWsClient;
 use tokio::runtime::Runtime;
 use once_cell::sync::Lazy;
 use std::os::raw::c_char;
@@ -21,7 +22,7 @@ use serde::{Serialize, Deserialize};
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
 // Shared WebSocket client
-static mut PLUGIN_WS_CLIENT: Option<Arc<Mutex<WsClient>>> = None;
+static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
 
 // Define your data structure
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -34,8 +35,8 @@ fn on_load() {
     println!("[plugin_welcome] >>> LOADED");
 }
 
-pub async fn create_ws_plugin_client() {
-    if let Ok(client) = WsClient::connect("plugin_welcome", "ws://127.0.0.1:8081/ws").await {
+pub async fn create_ws_plugin_client(ws_url: &str) {
+    if let Ok(client) = WsClient::connect_as_service("plugin_welcome", ws_url).await {
         let client = Arc::new(Mutex::new(client));
         
         if let Ok(mut ws_client) = client.lock() {
@@ -43,16 +44,17 @@ pub async fn create_ws_plugin_client() {
             println!("[plugin_welcome] Subscribed to WelcomeMessageUpdated");
         }
         
-        unsafe {
-            PLUGIN_WS_CLIENT = Some(client);
+        if PLUGIN_WS_CLIENT.set(client).is_err() {
+            eprintln!("[plugin_welcome] WebSocket client already initialized");
         }
     }
 }
 
-extern "C" fn run(_ctx: *const PluginContext) {
+extern "C" fn run(ctx: *const PluginContext) {
     println!("[plugin_welcome] - run");
+    let ws_url = ws_url_from_context(unsafe { ctx.as_ref() });
     RUNTIME.block_on(async {
-        create_ws_plugin_client().await;
+        create_ws_plugin_client(&ws_url).await;
     });
 }
 
@@ -72,7 +74,7 @@ extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
     slice.as_ptr()
 }
 
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         return ptr::null_mut();
     }
@@ -12,33 +12,95 @@ extern crate libjwt;
 
 use liblogger_macros::{log_entry_exit, measure_time};
 use once_cell::sync::Lazy;
+use serde::Deserialize;
+use tokio::runtime::Runtime;
+use zeroize::Zeroizing;
 
 use plugin_core::{
     log_debug, log_info, log_warn, log_error,
-    declare_plugin, PluginContext, Resource, HttpMethod,
+    declare_plugin, PluginContext, ws_url_from_context, Resource, HttpMethod,
     ApiRequest, ApiResponse, error_response, cleanup_response
 };
 use plugin_core::resource_utils::static_resource;
 use plugin_core::response_utils::*;
-use plugin_core::jwt_utils::validate_jwt_token;
+use plugin_core::jwt_utils::{validate_jwt_token, validate_jwt_token_with_scope};
+use plugin_core::events::{encode_payload, EngineEvent, NetworkStatePayload};
+use plugin_core::ws_utils::{PluginWsClient, publish_event};
 
 // Standard library
 use std::ffi::{CString, CStr};
 use std::os::raw::c_char;
 use std::ptr;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 // Internal modules
-mod network_info;
+pub mod network_info;
 pub mod wifi_manager_cp;
-use network_info::{NetworkInfo, to_json};
+use network_info::OwnedNetwork;
+use wifi_manager_cp::{
+    ConnectError, ConnectOptions, ScanOptions, MAX_CONNECT_TIMEOUT_SECS,
+    DEFAULT_SCAN_ATTEMPTS, DEFAULT_SCAN_RETRY_DELAY_MS
+};
 
 // Initialize logger attributes
 liblogger_macros::initialize_logger_attributes!();
 
+/// Scope a token needs to make the device join a network
+const WIFI_CONNECT_SCOPE: &str = "wifi:connect";
+
+/// Shared runtime driving scans, so a request never builds a runtime of its own
+static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
+
+/// WebSocket client used to announce connection changes
+static PLUGIN_WS_CLIENT: PluginWsClient = PluginWsClient::new();
+
 /// Global flag to track WiFi connection status
 static WIFI_CONNECTED: Lazy<Arc<Mutex<bool>>> = Lazy::new(|| Arc::new(Mutex::new(false)));
 
+/// SSID of the last successful connection, so forgetting it clears `WIFI_CONNECTED`
+static CONNECTED_SSID: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// Default time a scan result is reused before `GET network` scans again
+const DEFAULT_SCAN_CACHE_TTL_SECS: u64 = 10;
+
+/// Last non-empty scan result and when it was taken
+static SCAN_CACHE: Lazy<Mutex<Option<(Instant, Vec<OwnedNetwork>)>>> = Lazy::new(|| Mutex::new(None));
+
+/// Settings read from the plugin's `[plugins.config]` table in the execution plan
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct WifiConfig {
+    /// How many times a scan is attempted before giving up
+    scan_retries: u32,
+    /// Pause between scan attempts, in milliseconds
+    scan_retry_delay_ms: u64,
+    /// How long a scan result is served from the cache, in seconds; 0 disables the cache
+    scan_cache_ttl_secs: u64,
+}
+
+impl Default for WifiConfig {
+    fn default() -> Self {
+        Self {
+            scan_retries: DEFAULT_SCAN_ATTEMPTS,
+            scan_retry_delay_ms: DEFAULT_SCAN_RETRY_DELAY_MS,
+            scan_cache_ttl_secs: DEFAULT_SCAN_CACHE_TTL_SECS,
+        }
+    }
+}
+
+impl WifiConfig {
+    fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            attempts: self.scan_retries,
+            retry_delay: Duration::from_millis(self.scan_retry_delay_ms),
+        }
+    }
+}
+
+/// Configuration the plugin was run with
+static CONFIG: Lazy<Mutex<WifiConfig>> = Lazy::new(|| Mutex::new(WifiConfig::default()));
+
 /// Plugin initialization handler
 /// Called when the plugin is first loaded
 #[ctor::ctor]
@@ -64,25 +126,52 @@ extern "C" fn run(ctx: *const PluginContext) {
         return;
     }
 
-    unsafe {
-        let config_cstr = CStr::from_ptr((*ctx).config);
-        log_debug!(format!("WiFi Plugin running with config: {}", config_cstr.to_string_lossy()).as_str());
+    let ctx = unsafe { &*ctx };
+    let config = ctx.config_json::<WifiConfig>().unwrap_or_else(|e| {
+        log_warn!(format!("Using default WiFi config: {}", e).as_str());
+        WifiConfig::default()
+    });
+    log_debug!(format!("WiFi Plugin running with config: {:?}", config).as_str());
+    *CONFIG.lock().unwrap() = config;
+
+    let ws_url = ws_url_from_context(Some(ctx));
+    // Lets the plugin publish `NetworkConnected` and `NetworkDisconnected`
+    if !PLUGIN_WS_CLIENT.connect("plugin_wifi", &ws_url, &[]) {
+        log_warn!("WebSocket connection failed, connection events are disabled");
+    }
+}
+
+/// Publishes `NetworkConnected` (no `reason`) or `NetworkDisconnected` for `ssid`
+fn publish_network_state(ssid: &str, reason: Option<&str>) {
+    let Some(client) = PLUGIN_WS_CLIENT.get() else {
+        return;
+    };
+    match network_state_event(ssid, reason) {
+        Ok((event, payload)) => publish_event(&client, "plugin_wifi", event.to_topic(), &payload.to_string()),
+        Err(_e) => log_error!(_e.as_str()),
     }
 }
 
+/// Event and encoded `NetworkStatePayload` announcing a connection change for `ssid`
+fn network_state_event(ssid: &str, reason: Option<&str>) -> Result<(EngineEvent, serde_json::Value), String> {
+    let event = if reason.is_some() { EngineEvent::NetworkDisconnected } else { EngineEvent::NetworkConnected };
+    let payload = NetworkStatePayload { ssid: ssid.to_string(), reason: reason.map(str::to_string) };
+    encode_payload(&payload).map(|payload| (event, payload))
+}
+
 extern "C" fn get_static_content_path() -> *const c_char {
     CString::new("wifi/web").unwrap().into_raw()
 }
 
 extern "C" fn get_api_resources(out_len: *mut usize) -> *const Resource {
-    static METHODS: [HttpMethod; 2] = [HttpMethod::Get, HttpMethod::Post];
+    static METHODS: [HttpMethod; 3] = [HttpMethod::Get, HttpMethod::Post, HttpMethod::Delete];
     let slice = static_resource("network", &METHODS);
     unsafe { *out_len = slice.len(); }
     slice.as_ptr()
 }
 
 #[measure_time]
-extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
+fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     if req.is_null() {
         log_warn!("Received null request pointer");
         return ptr::null_mut();
@@ -91,8 +180,13 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     unsafe {
         let request = &*req;
         
-        // Validate JWT token using the shared utility function
-        if let Err(response) = validate_jwt_token(request) {
+        // Validate JWT token using the shared utility function; joining or forgetting
+        // a network additionally needs the connect scope
+        let auth = match request.method {
+            HttpMethod::Post | HttpMethod::Delete => validate_jwt_token_with_scope(request, WIFI_CONNECT_SCOPE),
+            _ => validate_jwt_token(request),
+        };
+        if let Err(response) = auth {
             return response;
         }
         
@@ -106,36 +200,49 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
 
         match request.method {
             HttpMethod::Get if path == "network" => {
-                log_info!("Processing network scan request");
-                let mut count: usize = 0;
-                let result_ptr = scan(&mut count);
-
-                let json = if result_ptr.is_null() || count == 0 {
-                    log_warn!("Scan returned no networks");
-                    "[]".to_string()
-                } else {
-                    let results = std::slice::from_raw_parts(result_ptr, count);
-                    let objects: Vec<_> = results.iter().map(to_json).collect();
-                    log_info!(format!("Scan completed successfully, found_networks={}", count).as_str());
-                    serde_json::to_string(&objects).unwrap_or("[]".into())
-                };
+                let params = request.query_params();
+                let flag = |name: &str| params.get(name).map(|v| v == "true" || v == "1");
+                return network_list(flag("fresh").unwrap_or(false), flag("hidden").unwrap_or(true));
+            }
 
-                return json_response(200, &json);
+            HttpMethod::Get if path == "network/current" => {
+                return current_connection();
             }
 
             HttpMethod::Post if path == "network" => {
                 let body = std::slice::from_raw_parts(request.body_ptr, request.body_len);
                 let body_str = std::str::from_utf8(body).unwrap_or("");
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(body_str) {
+                if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(body_str) {
+                    // Move the password out of the parsed body so the plugin's only copy is wiped on drop.
+                    // The request body itself belongs to the engine and is freed by it.
+                    let password = match json.get_mut("password").map(serde_json::Value::take) {
+                        Some(serde_json::Value::String(password)) => Zeroizing::new(password),
+                        _ => Zeroizing::new(String::new()),
+                    };
                     let ssid = json.get("ssid").and_then(|v| v.as_str()).unwrap_or("");
-                    let password = json.get("password").and_then(|v| v.as_str()).unwrap_or("");
+                    let options = connect_options(&json);
                     log_info!(format!("Processing connection request for ssid={}", ssid).as_str());
-                    return connect_to_network(ssid, password);
+                    return connect_to_network(ssid, &password, &options);
                 }
                 log_error!("Invalid JSON in connection request");
                 return error_response(400, "Invalid JSON payload");
             }
 
+            HttpMethod::Delete if path == "network" => {
+                let body = std::slice::from_raw_parts(request.body_ptr, request.body_len);
+                let body_str = std::str::from_utf8(body).unwrap_or("");
+                let Ok(json) = serde_json::from_str::<serde_json::Value>(body_str) else {
+                    log_error!("Invalid JSON in forget request");
+                    return error_response(400, "Invalid JSON payload");
+                };
+                let ssid = json.get("ssid").and_then(|v| v.as_str()).unwrap_or("");
+                if ssid.is_empty() {
+                    return error_response(400, "Missing ssid");
+                }
+                let interface = json.get("interface").and_then(|v| v.as_str()).filter(|i| !i.is_empty());
+                return forget_network(ssid, interface);
+            }
+
             _ => {
                 log_warn!(format!("Method not allowed: method={:?}, path={}", request.method, path).as_str());
                 method_not_allowed_response(request.method, request.path)
@@ -144,30 +251,144 @@ extern "C" fn handle_request(req: *const ApiRequest) -> *mut ApiResponse {
     }
 }
 
-extern "C" fn scan(out_count: *mut usize) -> *mut NetworkInfo {
+/// Answers `GET network` with `{"networks": [...], "cached": bool, "age_secs": n}`.
+///
+/// A result younger than `scan_cache_ttl_secs` is served from the cache unless
+/// `fresh` is set. Empty scans are not cached, so the next request scans again.
+/// Hidden networks, listed last, are left out when `include_hidden` is false
+/// (`?hidden=false`).
+fn network_list(fresh: bool, include_hidden: bool) -> *mut ApiResponse {
+    let ttl = Duration::from_secs(CONFIG.lock().unwrap().scan_cache_ttl_secs);
+
+    if !fresh {
+        if let Some((scanned_at, networks)) = SCAN_CACHE.lock().unwrap().as_ref() {
+            let age = scanned_at.elapsed();
+            if age < ttl {
+                log_info!(format!("Serving cached scan, age_secs={}", age.as_secs()).as_str());
+                return scan_response(networks, include_hidden, true, age);
+            }
+        }
+    }
+
+    log_info!("Processing network scan request");
+    let networks = scan();
+    if networks.is_empty() {
+        log_warn!("Scan returned no networks");
+    } else {
+        log_info!(format!("Scan completed successfully, found_networks={}", networks.len()).as_str());
+    }
+
+    if !networks.is_empty() && !ttl.is_zero() {
+        *SCAN_CACHE.lock().unwrap() = Some((Instant::now(), networks.clone()));
+    }
+    scan_response(&networks, include_hidden, false, Duration::ZERO)
+}
+
+fn scan_response(networks: &[OwnedNetwork], include_hidden: bool, cached: bool, age: Duration) -> *mut ApiResponse {
+    let entries: Vec<_> = networks
+        .iter()
+        .filter(|n| include_hidden || !n.hidden)
+        .map(OwnedNetwork::to_json)
+        .collect();
+    let body = serde_json::json!({
+        "networks": entries,
+        "cached": cached,
+        "age_secs": age.as_secs(),
+    });
+    json_response(200, &body.to_string())
+}
+
+fn scan() -> Vec<OwnedNetwork> {
     log_info!("Starting WiFi network scan");
-    wifi_manager_cp::scan(out_count)
+    let options = CONFIG.lock().unwrap().scan_options();
+    RUNTIME.block_on(wifi_manager_cp::scan(&options))
+}
+
+/// Reads the optional `timeout_secs` and `interface` fields of a connect request.
+/// Omitted fields keep the defaults; the timeout is clamped to 1..=MAX_CONNECT_TIMEOUT_SECS.
+fn connect_options(json: &serde_json::Value) -> ConnectOptions {
+    let mut options = ConnectOptions::default();
+    if let Some(secs) = json.get("timeout_secs").and_then(|v| v.as_u64()) {
+        options.timeout = Duration::from_secs(secs.clamp(1, MAX_CONNECT_TIMEOUT_SECS));
+    }
+    options.interface = json
+        .get("interface")
+        .and_then(|v| v.as_str())
+        .filter(|interface| !interface.is_empty())
+        .map(str::to_string);
+    options
 }
 
 #[measure_time]
-fn connect_to_network(ssid: &str, password: &str) -> *mut ApiResponse {
+fn connect_to_network(ssid: &str, password: &str, options: &ConnectOptions) -> *mut ApiResponse {
     log_info!(format!("Attempting to connect to network ssid={}", ssid).as_str());
     
-    let success = wifi_manager_cp::connect_wifi(ssid, password);
+    let result = wifi_manager_cp::connect_wifi(ssid, password, options);
     
     {
         let mut flag = WIFI_CONNECTED.lock().unwrap();
-        *flag = success;
+        *flag = result.is_ok();
+        *CONNECTED_SSID.lock().unwrap() = result.is_ok().then(|| ssid.to_string());
     }
 
-    if success {
-        log_info!(format!("Successfully connected to WiFi network ssid={}", ssid).as_str());
-        let msg = format!(r#"{{ "message": "Connected to {}" }}"#, ssid);
-        json_response(200, &msg)
-    } else {
-        log_error!(format!("Failed to connect to WiFi network ssid={}", ssid).as_str());
-        let msg = format!(r#"{{ "message": "Failed to connect to {}" }}"#, ssid);
-        json_response(500, &msg)
+    // `error` lets the UI tell a wrong password apart from a network that never answered.
+    // A rejected password is 422 rather than 401, which the webapp treats as an expired token.
+    let (status, error, message) = match result {
+        Ok(()) => {
+            log_info!(format!("Successfully connected to WiFi network ssid={}", ssid).as_str());
+            publish_network_state(ssid, None);
+            let msg = serde_json::json!({ "message": format!("Connected to {}", ssid) });
+            return json_response(200, &msg.to_string());
+        }
+        Err(ConnectError::AuthFailed) => (422, "auth_failed", format!("Wrong password for {}", ssid)),
+        Err(ConnectError::Timeout) => (504, "timeout", format!("Timed out connecting to {}", ssid)),
+        Err(ConnectError::Failed(_)) => (500, "connect_failed", format!("Failed to connect to {}", ssid)),
+    };
+
+    log_error!(format!("Failed to connect to WiFi network ssid={}: {}", ssid, error).as_str());
+    publish_network_state(ssid, Some(error));
+    let msg = serde_json::json!({ "error": error, "message": message });
+    json_response(status, &msg.to_string())
+}
+
+/// Reports the network the device is associated with, or `{"connected": false}` when there is none.
+fn current_connection() -> *mut ApiResponse {
+    match wifi_manager_cp::current_connection() {
+        Ok(Some(connection)) => {
+            log_debug!(format!("Current connection ssid={}", connection.ssid).as_str());
+            let mut json = serde_json::to_value(&connection).unwrap_or_default();
+            json["connected"] = serde_json::Value::Bool(true);
+            json_response(200, &json.to_string())
+        }
+        Ok(None) => json_response(200, r#"{ "connected": false }"#),
+        Err(_e) => {
+            log_error!(format!("Failed to read current connection: {}", _e).as_str());
+            error_response(500, "Failed to read current connection")
+        }
+    }
+}
+
+/// Disconnects from and removes the saved profile for `ssid`, so it can be joined
+/// again with a different password.
+fn forget_network(ssid: &str, interface: Option<&str>) -> *mut ApiResponse {
+    log_info!(format!("Forgetting network ssid={}", ssid).as_str());
+
+    match wifi_manager_cp::forget_network(ssid, interface) {
+        Ok(()) => {
+            let mut connected_ssid = CONNECTED_SSID.lock().unwrap();
+            if connected_ssid.as_deref() == Some(ssid) {
+                *connected_ssid = None;
+                *WIFI_CONNECTED.lock().unwrap() = false;
+                publish_network_state(ssid, Some("forgotten"));
+            }
+            let msg = serde_json::json!({ "message": format!("Forgot {}", ssid) });
+            json_response(200, &msg.to_string())
+        }
+        Err(_e) => {
+            log_error!(format!("Failed to forget network ssid={}: {}", ssid, _e).as_str());
+            let msg = serde_json::json!({ "error": "forget_failed", "message": format!("Failed to forget {}", ssid) });
+            json_response(500, &msg.to_string())
+        }
     }
 }
 
@@ -186,23 +407,125 @@ extern "C" fn cleanup(resp: *mut ApiResponse) {
     cleanup_response(resp);
 }
 
-extern "C" fn null_workflow(_req: *const ApiRequest) -> *mut ApiResponse {
-    std::ptr::null_mut()
+declare_plugin! {
+    name: "plugin_wifi",
+    route: "wifi",
+    run: run,
+    static_content_path: get_static_content_path,
+    api_resources: get_api_resources,
+    handle_request: handle_request,
+    cleanup: cleanup,
+    on_complete: on_complete,
 }
 
-extern "C" fn null_progress() -> *mut ApiResponse {
-    std::ptr::null_mut()
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plugin_core::ApiHeader;
 
-declare_plugin!(
-    "plugin_wifi",
-    "wifi",
-    run,
-    get_static_content_path,
-    get_api_resources,
-    handle_request,
-    cleanup,
-    null_workflow,
-    null_progress,
-    on_complete
-);
+    fn bearer() -> CString {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let token = libjwt::generate_jwt("test-key", "session", now, now + 3600).unwrap();
+        CString::new(format!("Bearer {}", token)).unwrap()
+    }
+
+    /// Sends an authenticated `GET network?<query>` and returns the status and JSON body.
+    fn get_network(query: &str) -> (u16, serde_json::Value) {
+        let key = CString::new("Authorization").unwrap();
+        let value = bearer();
+        let headers = [ApiHeader { key: key.as_ptr(), value: value.as_ptr() }];
+        let path = CString::new("network").unwrap();
+        let query = CString::new(query).unwrap();
+        let request = ApiRequest {
+            path: path.as_ptr(),
+            method: HttpMethod::Get,
+            headers: headers.as_ptr(),
+            header_count: 1,
+            content_type: ptr::null(),
+            query: query.as_ptr(),
+            body_ptr: ptr::null(),
+            body_len: 0,
+        };
+
+        let response = handle_request(&request);
+        let (status, body) = unsafe {
+            ((*response).status, std::slice::from_raw_parts((*response).body_ptr, (*response).body_len).to_vec())
+        };
+        cleanup(response);
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn scanning_twice_reuses_the_shared_runtime() {
+        // One quick attempt per scan; the sandbox may have no WiFi hardware at all
+        *CONFIG.lock().unwrap() = WifiConfig { scan_retries: 1, scan_retry_delay_ms: 0, scan_cache_ttl_secs: 0 };
+
+        for _ in 0..2 {
+            let (status, body) = get_network("fresh=true");
+            assert_eq!(status, 200);
+            assert!(body["networks"].is_array(), "{}", body);
+            assert_eq!(body["cached"], false);
+        }
+
+        // The engine calls handle_request from a blocking thread of its own runtime
+        let engine = Runtime::new().unwrap();
+        for _ in 0..2 {
+            let (status, _) = engine.block_on(async { tokio::task::spawn_blocking(|| get_network("fresh=true")).await }).unwrap();
+            assert_eq!(status, 200);
+        }
+    }
+
+    #[test]
+    fn hidden_networks_can_be_left_out_of_the_list() {
+        let named = network_info::OwnedNetwork {
+            ssid: "HomeNet".to_string(),
+            bssid: "aa:bb:cc:dd:ee:01".to_string(),
+            signal: -50,
+            channel: 6,
+            security: "WPA2-Personal".to_string(),
+            frequency: 2437.0,
+            reported_unit: network_info::SignalUnit::Dbm,
+            hidden: false,
+        };
+        let unnamed = network_info::OwnedNetwork {
+            ssid: "Hidden Network 2".to_string(),
+            bssid: "aa:bb:cc:dd:ee:02".to_string(),
+            hidden: true,
+            ..named.clone()
+        };
+        let networks = [named, unnamed];
+
+        let listed = |include_hidden: bool| {
+            let response = scan_response(&networks, include_hidden, false, Duration::ZERO);
+            let body = unsafe { std::slice::from_raw_parts((*response).body_ptr, (*response).body_len).to_vec() };
+            cleanup(response);
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            body["networks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|n| format!("{} hidden={}", n["ssid"].as_str().unwrap(), n["hidden"]))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(listed(true), ["HomeNet hidden=false", "Hidden Network 2 hidden=true"]);
+        assert_eq!(listed(false), ["HomeNet hidden=false"]);
+    }
+
+    #[test]
+    fn connection_changes_are_announced_with_the_network_state_payload() {
+        let (event, payload) = network_state_event("HomeNet", None).unwrap();
+        assert_eq!(event.to_topic(), "NetworkConnected");
+        assert_eq!(payload, serde_json::json!({ "ssid": "HomeNet" }), "no reason on a connect");
+
+        for reason in ["auth_failed", "timeout", "connect_failed", "forgotten"] {
+            let (event, payload) = network_state_event("HomeNet", Some(reason)).unwrap();
+            assert_eq!(event.to_topic(), "NetworkDisconnected");
+            assert_eq!(payload, serde_json::json!({ "ssid": "HomeNet", "reason": reason }));
+
+            // Subscribers read it back with the shared payload type
+            let decoded: NetworkStatePayload = plugin_core::events::decode_payload(&payload.to_string()).unwrap();
+            assert_eq!(decoded.reason.as_deref(), Some(reason));
+        }
+    }
+}
@@ -1,5 +1,13 @@
+//! Network types for the WiFi plugin
+//!
+//! Inside the plugin networks are `OwnedNetwork`s. `NetworkInfo` is the C layout and
+//! exists only to hand networks across the ABI. Whoever builds a `NetworkInfo`
+//! (`OwnedNetwork::to_ffi`) or an array of them (`networks_to_ffi`) owns the memory
+//! until it is given back to `free_network_info` or `free_networks`. Readers such as
+//! `OwnedNetwork::from` only borrow it.
+
 use std::os::raw::{c_char, c_int, c_float};
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 
 #[repr(C)]
 #[derive(Debug, Clone)]
@@ -10,7 +18,7 @@ pub struct NetworkInfo {
     /// BSSID (MAC address) of the access point.
     pub bssid: *const c_char,
 
-    /// Signal strength in percentage or dBm, depending on platform.
+    /// Signal strength in dBm; percentage readings are converted by the scanner.
     pub signal: c_int,
 
     /// Wi-Fi channel number (e.g., 6 for 2.4GHz, 36 for 5GHz).
@@ -21,27 +29,370 @@ pub struct NetworkInfo {
 
     /// Operating frequency in MHz (e.g., 2412.0 for 2.4GHz).
     pub frequency: c_float,
+
+    /// Unit the platform reported the signal in, before conversion to dBm.
+    pub reported_unit: SignalUnit,
+}
+
+/// Unit of a raw signal reading.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum SignalUnit {
+    /// Received signal strength in dBm, e.g. -65.
+    #[serde(rename = "dBm")]
+    Dbm = 0,
+    /// Link quality from 0 to 100, as reported by `netsh`.
+    #[serde(rename = "percent")]
+    Percent = 1,
 }
 
-/// We need this structure to serialize the data on axum route handler
-#[derive(serde::Serialize)]
-pub struct NetworkInfoJson {
-    ssid: String,
-    bssid: String,
-    signal: i32,
-    channel: i32,
-    security: String,
-    frequency: f32,
-}
-
-// This is for the NetworkInfo struct
-pub fn to_json(net: &NetworkInfo) -> NetworkInfoJson {
-    NetworkInfoJson {
-        ssid: unsafe { CStr::from_ptr(net.ssid).to_string_lossy().into_owned() },
-        bssid: unsafe { CStr::from_ptr(net.bssid).to_string_lossy().into_owned() },
-        signal: net.signal,
-        channel: net.channel,
-        security: unsafe { CStr::from_ptr(net.security).to_string_lossy().into_owned() },
-        frequency: net.frequency,
-    }
-}
\ No newline at end of file
+/// A scanned network with owned strings, used everywhere inside the plugin.
+///
+/// `to_json` builds the entries of `GET network`. Only code handing networks across
+/// the C ABI converts to `NetworkInfo`, with `to_ffi`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct OwnedNetwork {
+    pub ssid: String,
+    pub bssid: String,
+    /// Signal strength in dBm
+    pub signal: i32,
+    pub channel: i32,
+    pub security: String,
+    /// Operating frequency in MHz
+    pub frequency: f32,
+    pub reported_unit: SignalUnit,
+    /// The network doesn't broadcast its SSID; `ssid` holds a "Hidden Network N" label
+    pub hidden: bool,
+}
+
+impl From<&NetworkInfo> for OwnedNetwork {
+    /// Copies the strings out of a `NetworkInfo`. Invalid UTF-8 is replaced with
+    /// U+FFFD and null pointers become empty strings, so this never fails.
+    fn from(net: &NetworkInfo) -> Self {
+        Self {
+            ssid: owned_string(net.ssid),
+            bssid: owned_string(net.bssid),
+            signal: net.signal,
+            channel: net.channel,
+            security: owned_string(net.security),
+            frequency: net.frequency,
+            reported_unit: net.reported_unit,
+            // NetworkInfo has no such field; an empty SSID is the only hint
+            hidden: net.ssid.is_null() || unsafe { CStr::from_ptr(net.ssid) }.is_empty(),
+        }
+    }
+}
+
+impl OwnedNetwork {
+    /// Signal as a 0-100 quality percentage, the inverse of the scanner's
+    /// percent-to-dBm mapping: -100 dBm or weaker is 0%, -50 dBm or stronger is 100%.
+    pub fn signal_percent(&self) -> u8 {
+        ((self.signal + 100) * 2).clamp(0, 100) as u8
+    }
+
+    /// JSON entry for `GET network`: `ssid`, `bssid`, `signal` (dBm), `channel`,
+    /// `security`, `frequency`, `reported_unit`, `hidden` and the derived `signal_percent`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "ssid": self.ssid,
+            "bssid": self.bssid,
+            "signal": self.signal,
+            "channel": self.channel,
+            "security": self.security,
+            "frequency": self.frequency,
+            "reported_unit": self.reported_unit,
+            "hidden": self.hidden,
+            "signal_percent": self.signal_percent(),
+        })
+    }
+
+    /// Builds the FFI form, allocating its strings.
+    ///
+    /// Interior NUL bytes cannot be represented and yield an empty string. The
+    /// result must be released with `free_network_info`.
+    pub fn to_ffi(&self) -> NetworkInfo {
+        NetworkInfo {
+            ssid: CString::new(self.ssid.as_str()).unwrap_or_default().into_raw(),
+            bssid: CString::new(self.bssid.as_str()).unwrap_or_default().into_raw(),
+            signal: self.signal,
+            channel: self.channel,
+            security: CString::new(self.security.as_str()).unwrap_or_default().into_raw(),
+            frequency: self.frequency,
+            reported_unit: self.reported_unit,
+        }
+    }
+}
+
+/// Orders networks for the network picker: named networks before hidden ones, each
+/// group strongest first. `signal` is already normalized to dBm, so readings from
+/// different platforms compare correctly. Equal signals keep their scan order.
+pub fn sort_networks(networks: &mut [OwnedNetwork]) {
+    networks.sort_by(|a, b| a.hidden.cmp(&b.hidden).then(b.signal.cmp(&a.signal)));
+}
+
+/// Frees the strings of a `NetworkInfo` built by `OwnedNetwork::to_ffi`.
+///
+/// # Safety
+/// `net` must come from `to_ffi`, and no copy of it may be used afterwards.
+pub unsafe fn free_network_info(net: NetworkInfo) {
+    for ptr in [net.ssid, net.bssid, net.security] {
+        if !ptr.is_null() {
+            drop(CString::from_raw(ptr as *mut c_char));
+        }
+    }
+}
+
+/// Converts networks to a heap-allocated `NetworkInfo` array for the C ABI and
+/// stores its length in `out_count`. An empty list returns null.
+///
+/// The array must be released with `free_networks`.
+pub fn networks_to_ffi(networks: &[OwnedNetwork], out_count: &mut usize) -> *mut NetworkInfo {
+    *out_count = networks.len();
+    if networks.is_empty() {
+        return std::ptr::null_mut();
+    }
+    let boxed: Box<[NetworkInfo]> = networks.iter().map(OwnedNetwork::to_ffi).collect();
+    Box::into_raw(boxed) as *mut NetworkInfo
+}
+
+/// Frees an array from `networks_to_ffi` along with every string in it.
+///
+/// # Safety
+/// `ptr` and `count` must be exactly what `networks_to_ffi` returned, and the array
+/// must not be used afterwards. A null `ptr` is ignored.
+pub unsafe fn free_networks(ptr: *mut NetworkInfo, count: usize) {
+    if ptr.is_null() {
+        return;
+    }
+    let boxed = Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, count));
+    for net in boxed.iter() {
+        free_network_info(net.clone());
+    }
+}
+
+fn owned_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+}
+
+/// The network the device is currently associated with, as reported by the platform.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConnectionInfo {
+    pub ssid: String,
+    pub bssid: Option<String>,
+    /// Signal quality from 0 to 100 percent.
+    pub signal: Option<u8>,
+    /// "strong", "good" or "weak", derived from `signal`, for display.
+    pub strength: Option<&'static str>,
+    /// IPv4 address of the wireless interface, without the prefix length.
+    pub ip: Option<String>,
+}
+
+impl ConnectionInfo {
+    pub fn new(ssid: String, bssid: Option<String>, signal: Option<u8>, ip: Option<String>) -> Self {
+        let strength = signal.map(|percent| match percent {
+            70..=u8::MAX => "strong",
+            40..=69 => "good",
+            _ => "weak",
+        });
+        Self { ssid, bssid, signal, strength, ip }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts the bytes each thread has allocated and not yet freed, so a test can
+    /// check its own allocations without seeing other tests running in parallel.
+    struct CountingAllocator;
+
+    thread_local! {
+        static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+    }
+
+    fn track(delta: isize) {
+        // Allocations made while the thread shuts down have nowhere to be counted
+        let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + delta));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            track(layout.size() as isize);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            track(-(layout.size() as isize));
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn live_bytes() -> isize {
+        LIVE_BYTES.with(Cell::get)
+    }
+
+    fn network(ssid: &str, signal: i32) -> OwnedNetwork {
+        OwnedNetwork {
+            ssid: ssid.to_string(),
+            bssid: "aa:bb:cc:dd:ee:ff".to_string(),
+            signal,
+            channel: 36,
+            security: "WPA2-Personal".to_string(),
+            frequency: 5180.0,
+            reported_unit: SignalUnit::Percent,
+            hidden: false,
+        }
+    }
+
+    #[test]
+    fn owned_network_round_trips_through_ffi() {
+        let original = network("HomeNet", -55);
+
+        let ffi = original.to_ffi();
+        let back = OwnedNetwork::from(&ffi);
+        unsafe { free_network_info(ffi) };
+
+        assert_eq!(back, original);
+    }
+
+    #[test]
+    fn network_array_round_trips_through_ffi() {
+        let networks = vec![network("HomeNet", -55), network("Office", -70)];
+
+        let mut count = 0;
+        let ptr = networks_to_ffi(&networks, &mut count);
+        let back: Vec<OwnedNetwork> = unsafe { std::slice::from_raw_parts(ptr, count) }
+            .iter()
+            .map(OwnedNetwork::from)
+            .collect();
+        unsafe { free_networks(ptr, count) };
+
+        assert_eq!(back, networks);
+        assert!(networks_to_ffi(&[], &mut count).is_null());
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn clones_sort_independently_of_the_original() {
+        let scanned = vec![network("Weak", -80), network("Strong", -50)];
+
+        let mut sorted = scanned.clone();
+        sort_networks(&mut sorted);
+        sorted[0].ssid.push_str(" (5G)");
+
+        assert_eq!(sorted[0].ssid, "Strong (5G)");
+        assert_eq!(scanned[0].ssid, "Weak");
+        assert_eq!(scanned[1].ssid, "Strong");
+    }
+
+    #[test]
+    fn null_and_unrepresentable_strings_convert_to_empty() {
+        let ffi = NetworkInfo {
+            ssid: std::ptr::null(),
+            bssid: std::ptr::null(),
+            signal: -60,
+            channel: 6,
+            security: std::ptr::null(),
+            frequency: 2437.0,
+            reported_unit: SignalUnit::Dbm,
+        };
+        let owned = OwnedNetwork::from(&ffi);
+        assert_eq!((owned.ssid.as_str(), owned.bssid.as_str(), owned.security.as_str()), ("", "", ""));
+        assert!(owned.hidden, "a network without an SSID is hidden");
+
+        // Interior NUL bytes can't cross the C ABI
+        let ffi = network("Home\0Net", -60).to_ffi();
+        assert_eq!(OwnedNetwork::from(&ffi).ssid, "");
+        unsafe { free_network_info(ffi) };
+    }
+
+    #[test]
+    fn repeated_scans_free_everything_they_hand_across_the_abi() {
+        let scan: Vec<OwnedNetwork> = (0..8).map(|i| network(&format!("Network {}", i), -40 - i)).collect();
+
+        let before = live_bytes();
+        for _ in 0..100 {
+            let mut count = 0;
+            let ptr = networks_to_ffi(&scan, &mut count);
+            assert!(live_bytes() > before, "the array and its strings are allocated");
+            unsafe { free_networks(ptr, count) };
+        }
+        assert_eq!(live_bytes(), before, "bytes leaked across 100 scans");
+
+        let before = live_bytes();
+        for net in &scan {
+            unsafe { free_network_info(net.to_ffi()) };
+        }
+        assert_eq!(live_bytes(), before, "bytes leaked by single networks");
+    }
+
+    #[test]
+    fn non_utf8_strings_serialize_lossily() {
+        let ssid = CString::new(vec![b'C', b'a', b'f', 0xe9, 0xff]).unwrap();
+        let bssid = CString::new(vec![0xc3, 0x28]).unwrap();
+        let security = CString::new("WPA2-Personal").unwrap();
+        let ffi = NetworkInfo {
+            ssid: ssid.as_ptr(),
+            bssid: bssid.as_ptr(),
+            signal: -65,
+            channel: 6,
+            security: security.as_ptr(),
+            frequency: 2437.0,
+            reported_unit: SignalUnit::Percent,
+        };
+
+        let json = OwnedNetwork::from(&ffi).to_json();
+
+        assert_eq!(json["ssid"], "Caf\u{fffd}\u{fffd}");
+        assert_eq!(json["bssid"], "\u{fffd}(");
+        assert_eq!(json["security"], "WPA2-Personal");
+        assert_eq!(json["signal"], -65);
+        assert_eq!(json["signal_percent"], 70);
+        assert_eq!(json["reported_unit"], "percent");
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            ["bssid", "channel", "frequency", "hidden", "reported_unit", "security", "signal", "signal_percent", "ssid"]
+        );
+    }
+
+    fn hidden(label: &str, signal: i32) -> OwnedNetwork {
+        OwnedNetwork { hidden: true, ..network(label, signal) }
+    }
+
+    #[test]
+    fn named_networks_sort_strongest_first_with_hidden_ones_last() {
+        let mut networks = vec![
+            hidden("Hidden Network 1", -40),
+            network("Weak", -80),
+            network("TieA", -60),
+            hidden("Hidden Network 4", -90),
+            network("Strong", -50),
+            network("TieB", -60),
+            hidden("Hidden Network 7", -70),
+        ];
+
+        sort_networks(&mut networks);
+
+        let order: Vec<&str> = networks.iter().map(|n| n.ssid.as_str()).collect();
+        assert_eq!(
+            order,
+            ["Strong", "TieA", "TieB", "Weak", "Hidden Network 1", "Hidden Network 7", "Hidden Network 4"]
+        );
+    }
+
+    #[test]
+    fn hidden_flag_is_serialized() {
+        assert_eq!(network("HomeNet", -50).to_json()["hidden"], false);
+        assert_eq!(hidden("Hidden Network 2", -50).to_json()["hidden"], true);
+    }
+}
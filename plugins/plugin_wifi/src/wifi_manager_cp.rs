@@ -3,21 +3,19 @@
 //! Provides cross-platform WiFi operations using:
 //! - tokio-wifiscanner for WiFi scanning
 //! - wifi-rs for WiFi connections
+//! - `netsh` as a Windows scan fallback, behind the `legacy_scan` feature
 //! 
 //! Supports Windows, macOS, and Linux platforms through unified APIs
 //! For iOS, provides mock implementations
 
-use std::ffi::{CString, c_char, CStr};
-
-use crate::network_info::NetworkInfo;
+use crate::network_info::{sort_networks, ConnectionInfo, OwnedNetwork, SignalUnit};
 
 // Only include these imports when needed for supported platforms
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
-use std::ptr;
-#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
 use std::thread;
-#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
 use std::time::Duration;
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+use std::{sync::mpsc, time::Instant};
 
 // Conditionally import WiFi libraries based on platform
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
@@ -32,21 +30,18 @@ use wifi_support::*;
 
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
 #[allow(dead_code)]
-// Simplified runtime wrapper that handles async correctly
-fn run_scan() -> Result<Vec<Wifi>, Box<dyn std::error::Error>> {
-    let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(async {
-        match tokio_wifiscanner::scan().await {
-            Ok(networks) => Ok(networks),
-            Err(e) => Err(Box::<dyn std::error::Error>::from(e))
-        }
-    })
+// Single tokio-wifiscanner scan, run on the caller's runtime
+async fn run_scan() -> Result<Vec<Wifi>, Box<dyn std::error::Error>> {
+    match tokio_wifiscanner::scan().await {
+        Ok(networks) => Ok(networks),
+        Err(e) => Err(Box::<dyn std::error::Error>::from(e))
+    }
 }
 
 #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
 #[allow(dead_code)]
 // Mock implementation for unsupported platforms (like iOS)
-fn run_scan() -> Result<Vec<Wifi>, Box<dyn std::error::Error>> {
+async fn run_scan() -> Result<Vec<Wifi>, Box<dyn std::error::Error>> {
     // Return an empty list on unsupported platforms
     Ok(Vec::new())
 }
@@ -84,147 +79,213 @@ fn security_type_to_string(security: &str) -> String {
 
 #[allow(dead_code)]
 /// Calculates a frequency from a channel number
+///
+/// Every 5 GHz channel, including ones between the common UNII sub-bands
+/// (e.g. 96 or 169), maps to 5000 + 5 * channel MHz.
 fn channel_to_frequency(channel: u32) -> f32 {
-    if channel >= 1 && channel <= 14 {
-        // 2.4 GHz band
-        (2412 + (channel - 1) * 5) as f32
-    } else if channel >= 36 && channel <= 165 {
+    match channel {
+        // 2.4 GHz band; channel 14 sits apart from the 5 MHz raster
+        1..=13 => (2407 + channel * 5) as f32,
+        14 => 2484.0,
         // 5 GHz band
-        if channel <= 48 {
-            (5180 + (channel - 36) * 5) as f32
-        } else if channel <= 64 {
-            (5260 + (channel - 52) * 5) as f32
-        } else if channel <= 144 {
-            (5500 + (channel - 100) * 5) as f32
-        } else {
-            (5745 + (channel - 149) * 5) as f32
-        }
-    } else {
-        0.0 // Unknown channel
+        32..=177 => (5000 + channel * 5) as f32,
+        _ => 0.0, // Unknown channel
     }
 }
 
 /// Extract channel number from frequency - commented out since it's unused
 #[allow(dead_code)]
 fn frequency_to_channel(freq: u32) -> u32 {
-    if freq >= 2412 && freq <= 2484 {
+    match freq {
+        2484 => 14,  // Special case for channel 14
         // 2.4 GHz band
-        if freq == 2484 {
-            14  // Special case for channel 14
-        } else {
-            (freq - 2412) / 5 + 1
+        2412..=2472 => (freq - 2407) / 5,
+        // 5 GHz band, the inverse of channel_to_frequency
+        5160..=5885 => (freq - 5000) / 5,
+        _ => 0,  // Unknown frequency
+    }
+}
+
+/// Default number of scan attempts before giving up
+pub const DEFAULT_SCAN_ATTEMPTS: u32 = 3;
+
+/// Default pause between scan attempts, in milliseconds
+pub const DEFAULT_SCAN_RETRY_DELAY_MS: u64 = 2000;
+
+/// Options for `scan`
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// How many times to scan before giving up; at least one scan always runs
+    pub attempts: u32,
+    /// Pause after a failed or empty scan before the next attempt
+    pub retry_delay: Duration,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            attempts: DEFAULT_SCAN_ATTEMPTS,
+            retry_delay: Duration::from_millis(DEFAULT_SCAN_RETRY_DELAY_MS),
         }
-    } else if freq >= 5170 && freq <= 5825 {
-        // 5 GHz band
-        if freq >= 5745 {
-            // Channels 149-165
-            (freq - 5745) / 5 + 149
-        } else if freq >= 5500 {
-            // Channels 100-144
-            (freq - 5500) / 5 + 100
-        } else if freq >= 5260 {
-            // Channels 52-64
-            (freq - 5260) / 5 + 52
-        } else {
-            // Channels 36-48
-            (freq - 5180) / 5 + 36
+    }
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+/// Calls `scan_once` until it finds networks, up to `options.attempts` times with
+/// `options.retry_delay` between attempts
+///
+/// Returns `None` if every attempt failed or came back empty. The delay is a tokio
+/// sleep, so waiting between attempts doesn't hold a runtime thread.
+async fn scan_with_retries<T, E, F, Fut>(options: &ScanOptions, mut scan_once: F) -> Option<Vec<T>>
+where
+    E: std::fmt::Debug,
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<T>, E>>,
+{
+    let attempts = options.attempts.max(1);
+
+    for attempt in 1..=attempts {
+        println!("[plugin_wifi] Scan attempt {} of {}", attempt, attempts);
+
+        match scan_once().await {
+            Ok(networks) if !networks.is_empty() => return Some(networks),
+            Ok(_) => println!("[plugin_wifi] No networks found in scan attempt {}", attempt),
+            Err(e) => println!("[plugin_wifi] Scan attempt {} failed: {:?}", attempt, e),
+        }
+
+        if attempt < attempts {
+            tokio::time::sleep(options.retry_delay).await;
         }
-    } else {
-        0  // Unknown frequency
     }
+
+    None
 }
 
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
 /// Scans for available WiFi networks using tokio-wifiscanner
-pub fn scan(out_count: *mut usize) -> *mut NetworkInfo {
+///
+/// Runs on the caller's runtime; the plugin drives it with its shared `RUNTIME`
+/// rather than a runtime per scan. Returns an empty list if every attempt failed.
+pub async fn scan(options: &ScanOptions) -> Vec<OwnedNetwork> {
     println!("[plugin_wifi] Starting WiFi scan with tokio-wifiscanner");
     
-    for attempt in 1..=3 {
-        println!("[plugin_wifi] Scan attempt {} of 3", attempt);
+    if let Some(networks) = scan_with_retries(options, run_scan).await {
+        println!("[plugin_wifi] Found {} networks", networks.len());
         
-        // Use a properly configured async runtime
-        match run_scan() {
-            Ok(networks) => {
-                if networks.is_empty() {
-                    println!("[plugin_wifi] No networks found in scan attempt {}", attempt);
-                    if attempt < 3 {
-                        thread::sleep(Duration::from_secs(2));
-                        continue;
-                    }
-                } else {
-                    println!("[plugin_wifi] Found {} networks", networks.len());
-                    
-                    // Debug output to match documentation example
-                    for network in &networks {
-                        // Note: Direct field access without Option unwrapping
-                        println!(
-                            "{} {:15} {:10} {:?} {}",
-                            network.mac, network.ssid, 
-                            network.channel, network.signal_level, 
-                            network.security
-                        );
-                    }
-                    
-                    let results = process_scan_results(networks);
-                    println!("[plugin_wifi] Processed {} unique networks", results.len());
-                    
-                    let boxed_results = results.into_boxed_slice();
-                    unsafe {
-                        *out_count = boxed_results.len();
-                    }
-                    return Box::into_raw(boxed_results) as *mut NetworkInfo;
-                }
-            },
-            Err(e) => {
-                println!("[plugin_wifi] Scan attempt {} failed: {:?}", attempt, e);
-                
-                if attempt < 3 {
-                    thread::sleep(Duration::from_secs(2));
-                }
-            }
+        // Debug output to match documentation example
+        for network in &networks {
+            // Note: Direct field access without Option unwrapping
+            println!(
+                "{} {:15} {:10} {:?} {}",
+                network.mac, network.ssid, 
+                network.channel, network.signal_level, 
+                network.security
+            );
         }
+        
+        let results = process_scan_results(networks);
+        println!("[plugin_wifi] Processed {} unique networks", results.len());
+        return results;
     }
     
+    #[cfg(all(target_os = "windows", feature = "legacy_scan"))]
+    {
+        let results = legacy_scan();
+        if !results.is_empty() {
+            println!("[plugin_wifi] Legacy netsh scan found {} unique networks", results.len());
+            return results;
+        }
+    }
+
     println!("[plugin_wifi] All scan attempts failed");
-    unsafe {
-        *out_count = 0;
+    Vec::new()
+}
+
+#[cfg(all(target_os = "windows", feature = "legacy_scan"))]
+/// Fallback scan through `netsh wlan show networks mode=bssid`, for when
+/// tokio-wifiscanner comes back empty
+fn legacy_scan() -> Vec<OwnedNetwork> {
+    println!("[plugin_wifi] Falling back to netsh scan");
+    match command_output("netsh", &["wlan", "show", "networks", "mode=bssid"]) {
+        Ok(output) => process_scan_results(parse_netsh_networks(&output)),
+        Err(e) => {
+            println!("[plugin_wifi] Legacy netsh scan failed: {}", e);
+            Vec::new()
+        }
     }
-    ptr::null_mut()
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+#[allow(dead_code)]
+/// Parses `netsh wlan show networks mode=bssid` output into one entry per BSSID
+///
+/// Every BSSID line starts a new entry, the first one of an SSID included; the
+/// `Signal` and `Channel` lines that follow fill it in. Duplicate SSIDs are
+/// resolved by `process_scan_results`, which keeps the strongest. Signal stays
+/// the percentage netsh reports.
+fn parse_netsh_networks(output: &str) -> Vec<Wifi> {
+    let mut networks: Vec<Wifi> = Vec::new();
+    let mut ssid = String::new();
+    let mut security = String::new();
+
+    for line in output.lines() {
+        // Split on the first colon only; BSSIDs contain colons themselves
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim();
+
+        if key.starts_with("SSID") {
+            ssid = value.to_string();
+            security.clear();
+        } else if key == "Authentication" {
+            security = value.to_string();
+        } else if key.starts_with("BSSID") {
+            networks.push(Wifi {
+                mac: value.to_string(),
+                ssid: ssid.clone(),
+                channel: String::new(),
+                signal_level: String::new(),
+                security: security.clone(),
+            });
+        } else if let Some(network) = networks.last_mut() {
+            match key {
+                "Signal" => network.signal_level = value.trim_end_matches('%').to_string(),
+                "Channel" => network.channel = value.to_string(),
+                _ => {}
+            }
+        }
+    }
+
+    networks
 }
 
 #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
 /// Mock implementation of WiFi scanning for unsupported platforms (like iOS)
-pub fn scan(out_count: *mut usize) -> *mut NetworkInfo {
+pub async fn scan(_options: &ScanOptions) -> Vec<OwnedNetwork> {
     println!("[plugin_wifi] WiFi scanning not supported on this platform");
     println!("[plugin_wifi] Returning mock data");
     
     // Create a mock network for demo purposes
-    let mut mock_networks = Vec::new();
-    let ssid = CString::new("MockNetwork").unwrap();
-    let bssid = CString::new("00:11:22:33:44:55").unwrap();
-    let security = CString::new("WPA2 Personal").unwrap();
-    
-    mock_networks.push(NetworkInfo {
-        ssid: ssid.into_raw(),
-        bssid: bssid.into_raw(),
-        security: security.into_raw(),
+    vec![OwnedNetwork {
+        ssid: "MockNetwork".to_string(),
+        bssid: "00:11:22:33:44:55".to_string(),
+        security: "WPA2 Personal".to_string(),
         channel: 6,
         frequency: 2437.0,  // Channel 6 frequency
         signal: -65,
-    });
-    
-    let boxed_results = mock_networks.into_boxed_slice();
-    unsafe {
-        *out_count = boxed_results.len();
-    }
-    Box::into_raw(boxed_results) as *mut NetworkInfo
+        reported_unit: SignalUnit::Dbm,
+        hidden: false,
+    }]
 }
 
-/// Processes scan results from tokio-wifiscanner into NetworkInfo structures
+/// Processes scan results from tokio-wifiscanner into OwnedNetwork entries,
+/// sorted with `sort_networks`
 #[allow(dead_code)]
-fn process_scan_results(networks: Vec<Wifi>) -> Vec<NetworkInfo> {
+fn process_scan_results(networks: Vec<Wifi>) -> Vec<OwnedNetwork> {
     // Track networks by SSID to handle duplicates
-    let mut results: Vec<NetworkInfo> = Vec::with_capacity(networks.len());
+    let mut results: Vec<OwnedNetwork> = Vec::with_capacity(networks.len());
     let mut seen_ssids = std::collections::HashSet::new();
     
     for (i, network) in networks.iter().enumerate() {
@@ -239,8 +300,6 @@ fn process_scan_results(networks: Vec<Wifi>) -> Vec<NetworkInfo> {
         if seen_ssids.contains(&ssid_string) {
             // Find if we already have this network with worse signal
             if let Some(pos) = results.iter().position(|n| {
-                let existing_ssid = unsafe { CStr::from_ptr(n.ssid) }.to_string_lossy();
-                
                 // Get signal from the network (works with both structs)
                 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
                 let signal_strength = parse_signal_level(&network.signal_level);
@@ -248,15 +307,10 @@ fn process_scan_results(networks: Vec<Wifi>) -> Vec<NetworkInfo> {
                 #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
                 let signal_strength = network.signal;
                 
-                existing_ssid == ssid_string && n.signal < signal_strength
+                // Stored signals are dBm, so compare in dBm too
+                n.ssid == ssid_string && n.signal < normalize_signal(signal_strength).0
             }) {
                 // Replace the weaker network
-                unsafe { 
-                    // Fix: Properly drop the CStrings
-                    let _ = CString::from_raw(results[pos].ssid as *mut c_char);
-                    let _ = CString::from_raw(results[pos].bssid as *mut c_char);
-                    let _ = CString::from_raw(results[pos].security as *mut c_char);
-                }
                 results.remove(pos);
             } else {
                 // Skip this duplicate if it's weaker
@@ -295,6 +349,8 @@ fn process_scan_results(networks: Vec<Wifi>) -> Vec<NetworkInfo> {
         
         #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
         let signal = network.signal;
+
+        let (signal, reported_unit) = normalize_signal(signal);
         
         // Log the network details
         println!(
@@ -302,24 +358,19 @@ fn process_scan_results(networks: Vec<Wifi>) -> Vec<NetworkInfo> {
             i, ssid_string, bssid_string, signal, channel, security_string, frequency
         );
         
-        // Create CStrings for FFI
-        let ssid_cstr = CString::new(ssid_string).unwrap_or_default().into_raw();
-        let bssid_cstr = CString::new(bssid_string).unwrap_or_default().into_raw();
-        let security_cstr = CString::new(security_string).unwrap_or_default().into_raw();
-        
-        // Create NetworkInfo struct
-        let network_info = NetworkInfo {
-            ssid: ssid_cstr,
-            bssid: bssid_cstr,
+        results.push(OwnedNetwork {
+            ssid: ssid_string,
+            bssid: bssid_string,
             signal,
             channel: channel as i32,
-            security: security_cstr,
+            security: security_string,
             frequency,
-        };
-        
-        results.push(network_info);
+            reported_unit,
+            hidden: network.ssid.is_empty(),
+        });
     }
     
+    sort_networks(&mut results);
     results
 }
 
@@ -329,20 +380,117 @@ fn parse_channel(channel_str: &str) -> u32 {
     channel_str.parse::<u32>().unwrap_or(0)
 }
 
-/// Parse signal level from string
+/// Parse signal level from string, e.g. "-52", "-52.00" or "85"
 #[allow(dead_code)]
 fn parse_signal_level(signal_str: &str) -> i32 {
-    signal_str.parse::<i32>().unwrap_or(0)
+    signal_str.trim().parse::<f32>().map(|signal| signal.round() as i32).unwrap_or(0)
+}
+
+/// Converts a raw signal reading to dBm and reports the unit it came in
+///
+/// Scanners report dBm (negative) or a 0-100 quality percentage (`netsh` on
+/// Windows). Positive readings are treated as percentages and mapped with the
+/// linear approximation Windows itself uses: 0% is -100 dBm, 100% is -50 dBm,
+/// so dBm = percent / 2 - 100 (70% becomes -65 dBm).
+fn normalize_signal(raw: i32) -> (i32, SignalUnit) {
+    if raw > 0 {
+        (raw.min(100) / 2 - 100, SignalUnit::Percent)
+    } else {
+        (raw, SignalUnit::Dbm)
+    }
+}
+
+/// Time allowed for a connection when the request doesn't specify one
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 30;
+
+/// Upper bound for a requested connection timeout
+pub const MAX_CONNECT_TIMEOUT_SECS: u64 = 120;
+
+/// Options for `connect_wifi`
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// Wireless interface to use (e.g. "wlan0"); the platform default when `None`
+    pub interface: Option<String>,
+    /// Total time allowed for all connection attempts
+    pub timeout: Duration,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            interface: None,
+            timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+        }
+    }
+}
+
+/// Why `connect_wifi` did not connect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectError {
+    /// The attempts did not finish within the timeout
+    Timeout,
+    /// The network rejected the credentials, most likely a wrong password
+    AuthFailed,
+    /// Any other failure, with the platform's error text
+    Failed(String),
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectError::Timeout => write!(f, "Connection timed out"),
+            ConnectError::AuthFailed => write!(f, "Authentication failed"),
+            ConnectError::Failed(reason) => write!(f, "Connection failed: {}", reason),
+        }
+    }
 }
 
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
 /// Connects to a WiFi network using the wifi-rs crate
-pub fn connect_wifi(ssid: &str, password: &str) -> bool {
-    println!("[plugin_wifi] Attempting to connect to {} using wifi-rs", ssid);
-    
-    // Create a new WiFi instance with None config
-    // Fixed initialization without the unnecessary match
-    let mut wifi = wifi_rs::WiFi::new(None);
+///
+/// The attempts run on a worker thread so a platform call that never returns
+/// can't hold the request past `options.timeout`. Such a call is left to finish
+/// in the background; its result is discarded.
+pub fn connect_wifi(ssid: &str, password: &str, options: &ConnectOptions) -> Result<(), ConnectError> {
+    println!(
+        "[plugin_wifi] Attempting to connect to {} using wifi-rs (interface: {}, timeout: {:?})",
+        ssid,
+        options.interface.as_deref().unwrap_or("default"),
+        options.timeout
+    );
+
+    let deadline = Instant::now() + options.timeout;
+    let (tx, rx) = mpsc::channel();
+    let ssid_owned = ssid.to_string();
+    // Wiped when the worker thread is done with it, even after a timeout
+    let password_owned = zeroize::Zeroizing::new(password.to_string());
+    let interface = options.interface.clone();
+
+    thread::spawn(move || {
+        let result = connect_attempts(&ssid_owned, &password_owned, interface.as_deref(), deadline);
+        let _ = tx.send(result);
+    });
+
+    match rx.recv_timeout(options.timeout) {
+        Ok(result) => result,
+        Err(_) => {
+            println!("[plugin_wifi] Connection to {} timed out after {:?}", ssid, options.timeout);
+            Err(ConnectError::Timeout)
+        }
+    }
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+/// Runs up to three connection attempts, stopping early once `deadline` has passed
+fn connect_attempts(
+    ssid: &str,
+    password: &str,
+    interface: Option<&str>,
+    deadline: Instant
+) -> Result<(), ConnectError> {
+    let config = interface.map(|interface| Config { interface: Some(interface) });
+    let mut wifi = wifi_rs::WiFi::new(config);
+    let mut last_error = ConnectError::Failed("No connection attempt was made".to_string());
     
     for attempt in 1..=3 {
         println!("[plugin_wifi] Connection attempt {} of 3", attempt);
@@ -352,39 +500,238 @@ pub fn connect_wifi(ssid: &str, password: &str) -> bool {
             Ok(success) => {
                 if success {
                     println!("[plugin_wifi] Successfully connected to {}", ssid);
-                    return true;
+                    return Ok(());
                 } else {
+                    // The platform tool ran but the network refused the join
                     println!("[plugin_wifi] Connect call succeeded but reported failure");
+                    last_error = ConnectError::AuthFailed;
                 }
             },
             Err(e) => {
                 println!("[plugin_wifi] Connection attempt {} failed: {:?}", attempt, e);
+                last_error = ConnectError::Failed(format!("{:?}", e));
             }
         }
         
+        let retry_delay = Duration::from_secs(2);
         if attempt < 3 {
+            if Instant::now() + retry_delay >= deadline {
+                println!("[plugin_wifi] No time left for another connection attempt");
+                return Err(ConnectError::Timeout);
+            }
             println!("[plugin_wifi] Waiting before next connection attempt...");
-            thread::sleep(Duration::from_secs(2));
+            thread::sleep(retry_delay);
         }
     }
     
     println!("[plugin_wifi] All connection attempts failed");
-    false
+    Err(last_error)
 }
 
 #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
 /// Mock implementation for unsupported platforms (like iOS)
-pub fn connect_wifi(ssid: &str, password: &str) -> bool {
+pub fn connect_wifi(ssid: &str, password: &str, _options: &ConnectOptions) -> Result<(), ConnectError> {
     println!("[plugin_wifi] WiFi connections not supported on this platform");
     println!("[plugin_wifi] Mock connection to {} would use password {}", ssid, 
              if password.is_empty() { "none" } else { "provided" });
-    false
+    Err(ConnectError::Failed("WiFi connections are not supported on this platform".to_string()))
+}
+
+/// Runs a platform network tool and returns its stdout, turning a non-zero exit into an error with its output
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+fn command_output(program: &str, args: &[&str]) -> Result<String, String> {
+    println!("[plugin_wifi] Running {} {:?}", program, args);
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+        Err(format!("{} exited with {}: {}", program, output.status, detail))
+    }
+}
+
+/// Runs a platform network tool for its side effect
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+fn run_network_command(program: &str, args: &[&str]) -> Result<(), String> {
+    command_output(program, args).map(|_| ())
+}
+
+/// Returns the value of a `Key : value` line from tool output, matching the key exactly
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn field_value<'a>(output: &'a str, key: &str) -> Option<&'a str> {
+    output.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim()).filter(|value| !value.is_empty())
+    })
+}
+
+#[cfg(target_os = "windows")]
+/// Reads the current connection from `netsh wlan show interfaces`
+///
+/// The IP comes from `netsh interface ip show addresses` for the same interface.
+pub fn current_connection() -> Result<Option<ConnectionInfo>, String> {
+    let output = command_output("netsh", &["wlan", "show", "interfaces"])?;
+    if field_value(&output, "State") != Some("connected") {
+        return Ok(None);
+    }
+    let Some(ssid) = field_value(&output, "SSID") else {
+        return Ok(None);
+    };
+
+    let bssid = field_value(&output, "BSSID").map(str::to_string);
+    let signal = field_value(&output, "Signal")
+        .and_then(|value| value.trim_end_matches('%').trim().parse::<u8>().ok());
+    let ip = field_value(&output, "Name").and_then(|name| {
+        let name_arg = format!("name={}", name);
+        let addresses = command_output("netsh", &["interface", "ip", "show", "addresses", name_arg.as_str()]).ok()?;
+        field_value(&addresses, "IP Address").map(str::to_string)
+    });
+
+    Ok(Some(ConnectionInfo::new(ssid.to_string(), bssid, signal, ip)))
+}
+
+/// Splits an `nmcli -t` line on the colons that aren't escaped as `\:`
+#[cfg(target_os = "linux")]
+fn split_terse_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    fields.last_mut().unwrap().push(escaped);
+                }
+            }
+            ':' => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+#[cfg(target_os = "linux")]
+/// Reads the current connection from NetworkManager
+///
+/// The active access point comes from `nmcli -t -f ACTIVE,SSID,BSSID,SIGNAL device wifi`,
+/// the IP from the connected wifi device's `IP4.ADDRESS`.
+pub fn current_connection() -> Result<Option<ConnectionInfo>, String> {
+    let output = command_output("nmcli", &["-t", "-f", "ACTIVE,SSID,BSSID,SIGNAL", "device", "wifi"])?;
+    let Some(fields) = output
+        .lines()
+        .map(split_terse_fields)
+        .find(|fields| fields.first().map(String::as_str) == Some("yes"))
+    else {
+        return Ok(None);
+    };
+
+    let ssid = fields.get(1).cloned().unwrap_or_default();
+    let bssid = fields.get(2).cloned().filter(|bssid| !bssid.is_empty());
+    let signal = fields.get(3).and_then(|signal| signal.parse::<u8>().ok());
+
+    let devices = command_output("nmcli", &["-t", "-f", "DEVICE,TYPE,STATE", "device"]).unwrap_or_default();
+    let ip = devices
+        .lines()
+        .map(split_terse_fields)
+        .find(|fields| fields.get(1).map(String::as_str) == Some("wifi") && fields.get(2).map(String::as_str) == Some("connected"))
+        .and_then(|fields| {
+            let device = fields.first()?.clone();
+            command_output("nmcli", &["-t", "-g", "IP4.ADDRESS", "device", "show", device.as_str()]).ok()
+        })
+        .and_then(|addresses| {
+            // Several addresses are separated by " | "; report the first without its prefix length
+            let first = addresses.lines().next()?.split(" | ").next()?.trim();
+            first.split('/').next().filter(|ip| !ip.is_empty()).map(str::to_string)
+        });
+
+    Ok(Some(ConnectionInfo::new(ssid, bssid, signal, ip)))
+}
+
+#[cfg(target_os = "macos")]
+/// Path of Apple's `airport` tool, which isn't on the PATH
+const AIRPORT_PATH: &str = "/System/Library/PrivateFrameworks/Apple80211.framework/Versions/Current/Resources/airport";
+
+#[cfg(target_os = "macos")]
+/// Reads the current connection from `airport -I`; the IP comes from `ipconfig getifaddr en0`
+///
+/// `airport` reports RSSI in dBm, which is mapped to 0-100% (-100 dBm or less is 0, -50 dBm or more is 100).
+pub fn current_connection() -> Result<Option<ConnectionInfo>, String> {
+    let output = command_output(AIRPORT_PATH, &["-I"])?;
+    let Some(ssid) = field_value(&output, "SSID") else {
+        return Ok(None);
+    };
+
+    let bssid = field_value(&output, "BSSID").map(str::to_string);
+    let signal = field_value(&output, "agrCtlRSSI")
+        .and_then(|rssi| rssi.parse::<i32>().ok())
+        .map(|rssi| ((rssi + 100) * 2).clamp(0, 100) as u8);
+    let ip = command_output("ipconfig", &["getifaddr", "en0"])
+        .ok()
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty());
+
+    Ok(Some(ConnectionInfo::new(ssid.to_string(), bssid, signal, ip)))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+/// Mock implementation for unsupported platforms (like iOS)
+pub fn current_connection() -> Result<Option<ConnectionInfo>, String> {
+    Ok(Some(ConnectionInfo::new(
+        "MockNetwork".to_string(),
+        Some("00:11:22:33:44:55".to_string()),
+        Some(80),
+        Some("192.168.1.100".to_string()),
+    )))
+}
+
+#[cfg(target_os = "windows")]
+/// Disconnects from `ssid` and deletes its saved profile
+///
+/// Deleting the profile of the active network also drops the connection.
+pub fn forget_network(ssid: &str, interface: Option<&str>) -> Result<(), String> {
+    let name_arg = format!("name={}", ssid);
+    let interface_arg = interface.map(|interface| format!("interface={}", interface));
+    let mut args = vec!["wlan", "delete", "profile", name_arg.as_str()];
+    if let Some(interface_arg) = &interface_arg {
+        args.push(interface_arg.as_str());
+    }
+    run_network_command("netsh", &args)
+}
+
+#[cfg(target_os = "linux")]
+/// Disconnects from `ssid` and deletes its saved NetworkManager connection
+///
+/// NetworkManager takes an active connection down before deleting it. The
+/// interface isn't needed, connections are looked up by name.
+pub fn forget_network(ssid: &str, _interface: Option<&str>) -> Result<(), String> {
+    run_network_command("nmcli", &["connection", "delete", "id", ssid])
+}
+
+#[cfg(target_os = "macos")]
+/// Removes `ssid` from the preferred networks of `interface` (default "en0")
+///
+/// Removing the current network from the preferred list makes macOS leave it.
+pub fn forget_network(ssid: &str, interface: Option<&str>) -> Result<(), String> {
+    let device = interface.unwrap_or("en0");
+    run_network_command("networksetup", &["-removepreferredwirelessnetwork", device, ssid])
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+/// Mock implementation for unsupported platforms (like iOS)
+pub fn forget_network(ssid: &str, _interface: Option<&str>) -> Result<(), String> {
+    println!("[plugin_wifi] Mock forget of {}", ssid);
+    Err("Forgetting networks is not supported on this platform".to_string())
 }
 
 /// Legacy compatibility function for older code
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
 pub fn connect_wifi_impl(ssid: &str, password: &str) -> bool {
-    connect_wifi(ssid, password)
+    connect_wifi(ssid, password, &ConnectOptions::default()).is_ok()
 }
 
 /// Legacy compatibility function for unsupported platforms
@@ -395,3 +742,223 @@ pub fn connect_wifi_impl(_: &str, _: &str) -> bool {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_round_trip_through_frequency() {
+        let expected = [(1, 2412), (13, 2472), (14, 2484), (36, 5180), (96, 5480), (165, 5825), (177, 5885)];
+        for (channel, freq) in expected {
+            assert_eq!(channel_to_frequency(channel), freq as f32, "channel {}", channel);
+            assert_eq!(frequency_to_channel(freq), channel, "frequency {}", freq);
+        }
+    }
+
+    #[test]
+    fn unknown_channels_and_frequencies_map_to_zero() {
+        assert_eq!(channel_to_frequency(0), 0.0);
+        assert_eq!(channel_to_frequency(15), 0.0);
+        assert_eq!(channel_to_frequency(178), 0.0);
+        assert_eq!(frequency_to_channel(2400), 0);
+        assert_eq!(frequency_to_channel(5900), 0);
+    }
+
+    // `netsh wlan show networks mode=bssid` output: one SSID seen through two
+    // BSSIDs on different bands, one SSID with a single BSSID
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    const NETSH_OUTPUT: &str = "\
+Interface name : Wi-Fi
+There are 2 networks currently visible.
+
+SSID 1 : HomeNet
+    Network type            : Infrastructure
+    Authentication          : WPA2-Personal
+    Encryption              : CCMP
+    BSSID 1                 : aa:bb:cc:dd:ee:01
+         Signal             : 60%
+         Radio type         : 802.11n
+         Channel            : 6
+         Basic rates (Mbps) : 1 2 5.5 11
+    BSSID 2                 : aa:bb:cc:dd:ee:02
+         Signal             : 90%
+         Radio type         : 802.11ac
+         Channel            : 36
+
+SSID 2 : CoffeeShop
+    Network type            : Infrastructure
+    Authentication          : Open
+    Encryption              : None
+    BSSID 1                 : 11:22:33:44:55:66
+         Signal             : 40%
+         Radio type         : 802.11n
+         Channel            : 11
+";
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn netsh_parser_keeps_every_bssid() {
+        let networks = parse_netsh_networks(NETSH_OUTPUT);
+        let entries: Vec<(&str, &str, &str, &str, &str)> = networks
+            .iter()
+            .map(|n| (n.ssid.as_str(), n.mac.as_str(), n.signal_level.as_str(), n.channel.as_str(), n.security.as_str()))
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("HomeNet", "aa:bb:cc:dd:ee:01", "60", "6", "WPA2-Personal"),
+                ("HomeNet", "aa:bb:cc:dd:ee:02", "90", "36", "WPA2-Personal"),
+                ("CoffeeShop", "11:22:33:44:55:66", "40", "11", "Open"),
+            ]
+        );
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn netsh_results_keep_the_strongest_bssid_with_its_band() {
+        let mut networks = process_scan_results(parse_netsh_networks(NETSH_OUTPUT));
+        networks.sort_by(|a, b| a.ssid.cmp(&b.ssid));
+
+        assert_eq!(networks.len(), 2, "every SSID is reported once");
+
+        // The single-BSSID network is not dropped
+        let coffee = &networks[0];
+        assert_eq!(coffee.ssid, "CoffeeShop");
+        assert_eq!((coffee.channel, coffee.frequency), (11, 2462.0));
+        assert_eq!((coffee.signal, coffee.reported_unit), (-80, SignalUnit::Percent));
+
+        // The 5 GHz BSSID is stronger, so it wins over the 2.4 GHz one
+        let home = &networks[1];
+        assert_eq!(home.ssid, "HomeNet");
+        assert_eq!(home.bssid, "aa:bb:cc:dd:ee:02");
+        assert_eq!((home.channel, home.frequency), (36, 5180.0));
+        assert_eq!(home.security, "WPA2 Personal");
+    }
+
+    #[test]
+    fn percentages_and_dbm_normalize_to_dbm() {
+        assert_eq!(normalize_signal(70), (-65, SignalUnit::Percent));
+        assert_eq!(normalize_signal(100), (-50, SignalUnit::Percent));
+        assert_eq!(normalize_signal(120), (-50, SignalUnit::Percent), "quality is capped at 100%");
+        assert_eq!(normalize_signal(-65), (-65, SignalUnit::Dbm));
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    fn scanned(ssid: &str, mac: &str, signal_level: &str) -> Wifi {
+        Wifi {
+            mac: mac.to_string(),
+            ssid: ssid.to_string(),
+            channel: "6".to_string(),
+            signal_level: signal_level.to_string(),
+            security: "WPA2-Personal".to_string(),
+        }
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn windows_percentages_and_linux_dbm_sort_together() {
+        let networks = process_scan_results(vec![
+            scanned("Weak", "00:00:00:00:00:01", "-80"),
+            scanned("WindowsSeventy", "00:00:00:00:00:02", "70"),
+            scanned("Strong", "00:00:00:00:00:03", "90"),
+            scanned("LinuxSixtyFive", "00:00:00:00:00:04", "-65.00"),
+        ]);
+
+        let order: Vec<(&str, i32)> = networks.iter().map(|n| (n.ssid.as_str(), n.signal)).collect();
+        assert_eq!(order[0], ("Strong", -55));
+        assert_eq!(order[3], ("Weak", -80));
+
+        // 70% and -65 dBm are the same strength, so they sit next to each other
+        let middle: Vec<(i32, SignalUnit)> = networks[1..3].iter().map(|n| (n.signal, n.reported_unit)).collect();
+        assert!(middle.contains(&(-65, SignalUnit::Percent)), "{:?}", middle);
+        assert!(middle.contains(&(-65, SignalUnit::Dbm)), "{:?}", middle);
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn duplicate_ssids_compare_across_units() {
+        // 40% is -80 dBm, weaker than the -60 dBm reading, whichever comes first
+        for readings in [["40", "-60"], ["-60", "40"]] {
+            let networks = process_scan_results(vec![
+                scanned("Mixed", "00:00:00:00:00:01", readings[0]),
+                scanned("Mixed", "00:00:00:00:00:02", readings[1]),
+            ]);
+            assert_eq!(networks.len(), 1);
+            assert_eq!((networks[0].signal, networks[0].reported_unit), (-60, SignalUnit::Dbm), "{:?}", readings);
+        }
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    fn quick_retries(attempts: u32) -> ScanOptions {
+        ScanOptions { attempts, retry_delay: Duration::from_millis(1) }
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    type FakeScan = Result<Vec<&'static str>, &'static str>;
+
+    /// A `scan_once` replaying `results` in order and counting its calls.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    fn fake_scan<'a>(
+        results: Vec<FakeScan>,
+        calls: &'a std::cell::Cell<usize>,
+    ) -> impl FnMut() -> std::future::Ready<FakeScan> + 'a {
+        let mut results = results.into_iter();
+        move || {
+            calls.set(calls.get() + 1);
+            std::future::ready(results.next().unwrap_or(Ok(Vec::new())))
+        }
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    #[tokio::test]
+    async fn scan_retries_failed_and_empty_attempts() {
+        let calls = std::cell::Cell::new(0);
+        let scan_once = fake_scan(vec![Err("device busy"), Ok(vec![]), Ok(vec!["HomeNet"])], &calls);
+
+        let networks = scan_with_retries(&quick_retries(3), scan_once).await;
+
+        assert_eq!(networks, Some(vec!["HomeNet"]));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    #[tokio::test]
+    async fn scan_stops_at_the_first_networks_found() {
+        let calls = std::cell::Cell::new(0);
+        let scan_once = fake_scan(vec![Ok(vec!["HomeNet"]), Ok(vec!["Other"])], &calls);
+
+        let networks = scan_with_retries(&quick_retries(3), scan_once).await;
+
+        assert_eq!(networks, Some(vec!["HomeNet"]));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    #[tokio::test]
+    async fn scan_gives_up_after_the_configured_attempts() {
+        let calls = std::cell::Cell::new(0);
+        let scan_once = fake_scan(vec![Err("device busy"); 5], &calls);
+        assert_eq!(scan_with_retries(&quick_retries(2), scan_once).await, None);
+        assert_eq!(calls.get(), 2);
+
+        // Zero attempts still scans once
+        let calls = std::cell::Cell::new(0);
+        let scan_once = fake_scan(vec![Err("device busy")], &calls);
+        assert_eq!(scan_with_retries(&quick_retries(0), scan_once).await, None);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn unnamed_networks_are_labelled_hidden_and_listed_last() {
+        let networks = process_scan_results(vec![
+            scanned("", "00:00:00:00:00:01", "-40"),
+            scanned("HomeNet", "00:00:00:00:00:02", "-70"),
+        ]);
+
+        let listed: Vec<(&str, bool)> = networks.iter().map(|n| (n.ssid.as_str(), n.hidden)).collect();
+        assert_eq!(listed, [("HomeNet", false), ("Hidden Network 1", true)]);
+    }
+}
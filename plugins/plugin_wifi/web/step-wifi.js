@@ -27,18 +27,19 @@ export async function activate(container, appManager, jwtManager) {
         return "/wifi/web/icons/wifi-weak.svg";
     }
 
-    async function getNetworkList() {
+    // `fresh` skips the plugin's scan cache, for an explicit rescan
+    async function getNetworkList(fresh = false) {
         try {
             scanBtn.disabled = true;
             scanBtn.classList.add("loading");
             scanStatus.innerHTML = "Scanning networks...";
             networkList.innerHTML = "";
             
-            const response = await jwtManager.secure_request('/api/wifi/network');
+            const response = await jwtManager.secure_request(`/api/wifi/network${fresh ? '?fresh=true' : ''}`);
             
             if (!response.ok) throw new Error(`Network scan failed (${response.status})`);
             
-            const networks = await response.json();
+            const { networks } = await response.json();
             
             if (!Array.isArray(networks) || networks.length === 0) {
                 scanStatus.innerHTML = "No networks found";
@@ -105,7 +106,7 @@ export async function activate(container, appManager, jwtManager) {
     }
 
     scanBtn.addEventListener("click", async () => {
-        await getNetworkList();
+        await getNetworkList(true);
     });
     skipBtn.addEventListener("click", async () => {
         resultBox.innerHTML = "";
@@ -42,6 +42,9 @@ document.addEventListener("DOMContentLoaded", async () => {
         // Initialize JWT authentication first
         await jwtManager.initialize_with_jwt();
         console.log('JWT authentication initialized successfully');
+
+        // The WebSocket server only accepts connections carrying a valid token
+        appManager.connectWithTokenProvider(() => jwtManager.get_fresh_token());
         
         // Then proceed with initial routing after JWT is ready
         handleRouting();
@@ -7,6 +7,16 @@ class AppManager {
         this.activePlugins = new Set();
         this.ready = false;
         this.readyCallbacks = [];
+        this.tokenProvider = null;
+    }
+
+    /**
+     * Starts the WebSocket connection. The server requires a JWT on the upgrade,
+     * so the provider is asked for a current token on every (re)connect.
+     * @param {function(): Promise<string>} provider - Resolves to a JWT token
+     */
+    connectWithTokenProvider(provider) {
+        this.tokenProvider = provider;
         this.connect();
     }
 
@@ -24,17 +34,30 @@ class AppManager {
         this.readyCallbacks = [];
     }
 
-    connect() {
+    async connect() {
         if (this.isConnecting || (this.ws?.readyState === WebSocket.OPEN)) {
             console.log('[appManager] Connection already exists or in progress');
             return;
         }
+        if (!this.tokenProvider) {
+            console.warn('[appManager] No token provider set; call connectWithTokenProvider() first');
+            return;
+        }
 
         this.cleanup();
         this.isConnecting = true;
         console.log('[appManager] Initiating new connection');
 
-        this.ws = new WebSocket('ws://localhost:8081/ws');
+        let token;
+        try {
+            token = await this.tokenProvider();
+        } catch (error) {
+            console.error('[appManager] Could not get a token for the WebSocket:', error);
+            this.scheduleReconnect();
+            return;
+        }
+
+        this.ws = new WebSocket(`ws://localhost:8081/ws?token=${encodeURIComponent(token)}`);
 
         this.ws.onopen = () => {
             this.isConnecting = false;
@@ -47,8 +70,8 @@ class AppManager {
             this.setReady(); // Move setReady() here
         };
 
-        this.ws.onclose = () => {
-            console.log('[appManager] Connection closed');
+        this.ws.onclose = (event) => {
+            console.log('[appManager] Connection closed', event.reason || '');
             this.scheduleReconnect();
         };
 
@@ -90,6 +90,20 @@ export async function secure_request(url, options = {}) {
     }
 }
 
+/**
+ * Get a current token for the active session, e.g. for the WebSocket upgrade
+ * @returns {Promise<string>} - JWT token
+ */
+export async function get_fresh_token() {
+    if (!apiKey || !sessionId) {
+        throw new Error('[JWT Manager] No session available. Call initialize_with_jwt() first');
+    }
+
+    const refreshResponse = await getSessionToken(apiKey, sessionId);
+    currentToken = refreshResponse.token;
+    return currentToken;
+}
+
 /**
  * Create a new session with the API
  * @param {string} key - API key